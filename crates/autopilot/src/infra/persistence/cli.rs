@@ -32,6 +32,7 @@ impl S3 {
             Some(s3::Config {
                 bucket: self.s3_instance_upload_bucket.unwrap(),
                 filename_prefix: self.s3_instance_upload_filename_prefix.unwrap(),
+                endpoint: None,
             })
         } else {
             None
@@ -769,6 +769,26 @@ fn main() {
                 .add_contract(Contract::new("CowAmmUniswapV2PriceOracle"))
                 .add_contract(Contract::new("CowAmmFactoryGetter")),
         )
+        // <https://docs.curve.finance/references/deployed-contracts/>
+        .add_submodule(
+            Submodule::new("curve")
+                .add_contract(Contract::new("CurveMetaRegistry").with_networks(networks![
+                    MAINNET => "0xF98B45FA17DE75FB1aD0e7aFD971b0ca00e379fC",
+                ]))
+                // The blocks below are lower bounds of the factory deployments,
+                // indexing starts there.
+                .add_contract(
+                    Contract::new("CurveStableSwapNGFactory").with_networks(networks![
+                        MAINNET => ("0x6A8cbed756804B16E05E741eDaBd5cB544AE21bf", 18000000),
+                    ]),
+                )
+                .add_contract(Contract::new("CurveTwocryptoFactory").with_networks(networks![
+                    MAINNET => ("0x98EE851a00abeE0d95D08cF4CA2BdCE32aeaAF7F", 18500000),
+                ]))
+                .add_contract(Contract::new("CurveTricryptoFactory").with_networks(networks![
+                    MAINNET => ("0x0c0e5f2fF0ff18a3be9b835635039256dC4B4963", 17000000),
+                ])),
+        )
         .add_submodule(
             Submodule::new("test") // Test Contract for using up a specified amount of gas.
                 .add_contract(Contract::new("GasHog"))
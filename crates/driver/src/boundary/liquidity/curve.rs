@@ -0,0 +1,178 @@
+use {
+    crate::{
+        boundary::{self, Result},
+        domain::{
+            eth,
+            liquidity::{self, curve},
+        },
+        infra::{self, blockchain::Ethereum},
+    },
+    ethrpc::block_stream::CurrentBlockWatcher,
+    shared::{
+        http_solver::model::TokenAmount,
+        interaction::Interaction,
+        sources::curve::{
+            pool_cache::PoolCache,
+            pool_fetching::{
+                CryptoState,
+                CurvePoolFetcher,
+                PoolConfig,
+                PoolKind,
+                PoolState,
+                StableState,
+            },
+        },
+    },
+    solver::{
+        interactions::allowances::Allowances,
+        liquidity::{
+            CurvePoolOrder,
+            curve::{CurveLiquidity, SettlementHandler},
+        },
+        liquidity_collector::LiquidityCollecting,
+    },
+    std::sync::Arc,
+};
+
+/// Rough gas used by a StableSwap `exchange`.
+const GAS_PER_STABLE_SWAP: u64 = 130_000;
+
+/// Rough gas used by a CryptoSwap `exchange`, which is more expensive because
+/// of the Newton iterations and price scale updates.
+const GAS_PER_CRYPTO_SWAP: u64 = 200_000;
+
+pub fn to_domain(id: liquidity::Id, pool: CurvePoolOrder) -> Result<liquidity::Liquidity> {
+    let pool = pool.pool;
+    anyhow::ensure!(
+        pool.coins.len() == pool.balances.len(),
+        "Curve pool coins and balances mismatch",
+    );
+
+    let state = match pool.state {
+        PoolState::Stable(StableState {
+            rates,
+            amp,
+            fee,
+            offpeg_fee_multiplier,
+        }) => curve::State::Stable(curve::StableState {
+            rates,
+            amp,
+            fee,
+            offpeg_fee_multiplier,
+        }),
+        PoolState::Crypto(CryptoState {
+            precisions,
+            price_scale,
+            ann,
+            gamma,
+            mid_fee,
+            out_fee,
+            fee_gamma,
+        }) => curve::State::Crypto(curve::CryptoState {
+            precisions,
+            price_scale,
+            ann,
+            gamma,
+            mid_fee,
+            out_fee,
+            fee_gamma,
+        }),
+    };
+    let gas = match state {
+        curve::State::Stable(_) => GAS_PER_STABLE_SWAP,
+        curve::State::Crypto(_) => GAS_PER_CRYPTO_SWAP,
+    };
+
+    Ok(liquidity::Liquidity {
+        id,
+        gas: gas.into(),
+        kind: liquidity::Kind::Curve(curve::Pool {
+            address: pool.address.into(),
+            kind: match pool.kind {
+                PoolKind::StableSwap => curve::Kind::StableSwap,
+                PoolKind::StableSwapNg => curve::Kind::StableSwapNg,
+                PoolKind::TwoCrypto => curve::Kind::TwoCrypto,
+                PoolKind::TwoCryptoNg => curve::Kind::TwoCryptoNg,
+                PoolKind::TricryptoNg => curve::Kind::TricryptoNg,
+            },
+            reserves: pool
+                .coins
+                .into_iter()
+                .zip(pool.balances)
+                .map(|(token, balance)| eth::Asset {
+                    token: token.into(),
+                    amount: balance.into(),
+                })
+                .collect(),
+            state,
+        }),
+    })
+}
+
+pub fn to_interaction(
+    pool: &liquidity::curve::Pool,
+    input: &liquidity::MaxInput,
+    output: &liquidity::ExactOutput,
+    receiver: &eth::Address,
+) -> Result<eth::Interaction> {
+    let handler = SettlementHandler::new(
+        pool.address.0,
+        kind(pool.kind),
+        pool.tokens().map(Into::into).collect(),
+        // Allowances are computed separately by the driver, so the handler
+        // is only used for encoding the `exchange` call itself.
+        Allowances::empty(*receiver),
+    );
+
+    let interaction = handler.swap(
+        TokenAmount::new(input.0.token.into(), input.0.amount),
+        TokenAmount::new(output.0.token.into(), output.0.amount),
+    )?;
+
+    let (target, value, call_data) = interaction.encode();
+    Ok(eth::Interaction {
+        target,
+        value: value.into(),
+        call_data: call_data.0.to_vec().into(),
+    })
+}
+
+pub fn collector(
+    eth: &Ethereum,
+    blocks: &CurrentBlockWatcher,
+    config: &infra::liquidity::config::Curve,
+) -> Result<Box<dyn LiquidityCollecting>> {
+    let eth = eth.with_metric_label("curve".into());
+    let web3 = eth.web3().clone();
+    let pool_fetcher = CurvePoolFetcher::new(
+        web3.clone(),
+        config
+            .pools
+            .iter()
+            .map(|pool| PoolConfig {
+                address: pool.address.0,
+                kind: kind(pool.kind),
+            })
+            .collect(),
+    );
+    let pool_cache = PoolCache::new(
+        boundary::liquidity::cache_config(),
+        Arc::new(pool_fetcher),
+        blocks.clone(),
+    )?;
+    Ok(Box::new(CurveLiquidity::new(
+        web3,
+        Arc::new(pool_cache),
+        *eth.contracts().settlement().address(),
+    )))
+}
+
+fn kind(kind: curve::Kind) -> PoolKind {
+    match kind {
+        curve::Kind::StableSwap => PoolKind::StableSwap,
+        curve::Kind::StableSwapNg => PoolKind::StableSwapNg,
+        curve::Kind::TwoCrypto => PoolKind::TwoCrypto,
+        curve::Kind::TwoCryptoNg => PoolKind::TwoCryptoNg,
+        curve::Kind::TricryptoNg => PoolKind::TricryptoNg,
+    }
+}
@@ -25,6 +25,7 @@ use {
 };
 
 pub mod balancer;
+pub mod curve;
 pub mod swapr;
 pub mod uniswap;
 pub mod zeroex;
@@ -83,6 +84,12 @@ impl Fetcher {
             })
             .collect();
 
+        let curve = config
+            .curve
+            .iter()
+            .map(|config| curve::collector(eth, block_stream, config))
+            .collect::<Result<Vec<_>>>()?;
+
         let uni_v3: Vec<_> = config
             .uniswap_v3
             .iter()
@@ -112,7 +119,7 @@ impl Fetcher {
         Ok(Self {
             blocks: block_stream.clone(),
             inner: LiquidityCollector {
-                liquidity_sources: [uni_v2, swapr, bal_v2, uni_v3, zeroex]
+                liquidity_sources: [uni_v2, swapr, bal_v2, curve, uni_v3, zeroex]
                     .into_iter()
                     .flatten()
                     .collect(),
@@ -167,6 +174,7 @@ impl Fetcher {
                     Liquidity::BalancerStable(pool) => balancer::v2::stable::to_domain(id, pool),
                     Liquidity::LimitOrder(pool) => zeroex::to_domain(id, pool),
                     Liquidity::Concentrated(pool) => uniswap::v3::to_domain(id, pool),
+                    Liquidity::Curve(pool) => curve::to_domain(id, pool),
                 }
                 // Ignore "bad" liquidity - this allows the driver to continue
                 // solving with the other good stuff.
@@ -2,6 +2,7 @@ use {
     self::solution::settlement,
     super::{
         Mempools,
+        mempools,
         time::{self, Remaining},
     },
     crate::{
@@ -109,6 +110,13 @@ impl Competition {
                 .await;
         });
 
+        let accounts = competition.solver.accounts();
+        if accounts.needs_maintenance() {
+            tokio::spawn(
+                accounts.maintain(competition.solver.clone(), competition.mempools.clone()),
+            );
+        }
+
         competition
     }
 
@@ -118,6 +126,7 @@ impl Competition {
         let timer = ::observe::metrics::metrics()
             .on_auction_overhead_start("driver", "pre_processing_total");
 
+        let request = auction.clone();
         let tasks = self
             .fetcher
             .start_or_get_tasks_for_auction(auction)
@@ -127,6 +136,9 @@ impl Competition {
                 Error::MalformedRequest
             })?;
         let mut auction = Arc::unwrap_or_clone(tasks.auction.await);
+        if let Some(id) = auction.id() {
+            self.solver.persistence().archive_request(id, request);
+        }
 
         let settlement_contract = *self.eth.contracts().settlement().address();
         let solver_address = self.solver.address();
@@ -347,6 +359,11 @@ impl Competition {
             return Ok(score);
         };
         let solution_id = settlement.solution().get();
+        if let Some(solved) = &score {
+            self.solver
+                .ledger()
+                .solved(&settlement, solved.score, &auction.native_prices());
+        }
 
         {
             let mut lock = self.settlements.lock().unwrap();
@@ -549,6 +566,7 @@ impl Competition {
             .find(|s| s.solution().get() == solution_id && s.auction_id == auction_id)
             .cloned()
             .ok_or(Error::SolutionNotAvailable)?;
+        self.solver.ledger().revealed(auction_id, solution_id);
         Ok(Revealed {
             internalized_calldata: settlement
                 .transaction(settlement::Internalization::Enable)
@@ -603,55 +621,90 @@ impl Competition {
         self: Arc<Self>,
         mut settle_receiver: mpsc::Receiver<SettleRequest>,
     ) {
+        let accounts = self.solver.accounts();
         while let Some(request) = settle_receiver.recv().await {
-            let SettleRequest {
+            // Requests are settled concurrently, each from its own account
+            // that can pay for the settlement.
+            let required = self.required_balance(request.auction_id, request.solution_id);
+            let Some(account) = accounts.acquire(required).await else {
+                request.tracing_span.in_scope(|| {
+                    tracing::warn!(?required, "no solver account can pay for the settlement")
+                });
+                notify::insufficient_balance(&self.solver, required);
+                let _ = request.response_sender.send(Err(Error::SubmissionError));
+                continue;
+            };
+            tokio::spawn(Arc::clone(&self).handle_settle_request(request, account));
+        }
+    }
+
+    /// The balance the account submitting the settlement of the given
+    /// solution needs. Unknown settlements need none, as settling them fails
+    /// anyway.
+    fn required_balance(&self, auction_id: auction::Id, solution_id: u64) -> eth::Ether {
+        self.settlements
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|s| s.solution().get() == solution_id && s.auction_id == auction_id)
+            .map_or(eth::Ether(eth::U256::ZERO), |settlement| {
+                settlement.required_balance
+            })
+    }
+
+    async fn handle_settle_request(
+        self: Arc<Self>,
+        request: SettleRequest,
+        account: mempools::accounts::Lease,
+    ) {
+        let SettleRequest {
+            auction_id,
+            solution_id,
+            submission_deadline,
+            mut response_sender,
+            tracing_span,
+        } = request;
+        async {
+            if self.eth.current_block().borrow().number >= submission_deadline {
+                if let Err(err) = response_sender.send(Err(DeadlineExceeded.into())) {
+                    tracing::error!(
+                        ?err,
+                        "settle deadline exceeded. unable to return a response"
+                    );
+                }
+                return;
+            }
+
+            observe::settling();
+            let settle_fut = Box::pin(self.process_settle_request(
                 auction_id,
                 solution_id,
                 submission_deadline,
-                mut response_sender,
-                tracing_span,
-            } = request;
-            async {
-                if self.eth.current_block().borrow().number >= submission_deadline {
-                    if let Err(err) = response_sender.send(Err(DeadlineExceeded.into())) {
-                        tracing::error!(
-                            ?err,
-                            "settle deadline exceeded. unable to return a response"
-                        );
-                    }
-                    return;
+                &account,
+            ));
+            let closed_fut = Box::pin(response_sender.closed());
+            let result = match futures::future::select(closed_fut, settle_fut).await {
+                // Cancel the settlement task if the sender is closed (client likely
+                // disconnected). This is a fallback to recover from issues
+                // like a stuck driver (e.g., stalled block stream).
+                Either::Left((_closed, settle_fut)) => {
+                    tracing::debug!("autopilot terminated settle call");
+                    // Add a grace period to give driver the last chance to cancel the
+                    // tx if needed.
+                    tokio::time::timeout(Duration::from_secs(1), settle_fut)
+                        .await
+                        .unwrap_or_else(|_| {
+                            tracing::error!("didn't finish tx submission within grace period");
+                            Err(DeadlineExceeded.into())
+                        })
                 }
-
-                observe::settling();
-                let settle_fut = Box::pin(self.process_settle_request(
-                    auction_id,
-                    solution_id,
-                    submission_deadline,
-                ));
-                let closed_fut = Box::pin(response_sender.closed());
-                let result = match futures::future::select(closed_fut, settle_fut).await {
-                    // Cancel the settlement task if the sender is closed (client likely
-                    // disconnected). This is a fallback to recover from issues
-                    // like a stuck driver (e.g., stalled block stream).
-                    Either::Left((_closed, settle_fut)) => {
-                        tracing::debug!("autopilot terminated settle call");
-                        // Add a grace period to give driver the last chance to cancel the
-                        // tx if needed.
-                        tokio::time::timeout(Duration::from_secs(1), settle_fut)
-                            .await
-                            .unwrap_or_else(|_| {
-                                tracing::error!("didn't finish tx submission within grace period");
-                                Err(DeadlineExceeded.into())
-                            })
-                    }
-                    Either::Right((res, _)) => res,
-                };
-                observe::settled(self.solver.name(), &result);
-                let _ = response_sender.send(result);
-            }
-            .instrument(tracing_span)
-            .await
+                Either::Right((res, _)) => res,
+            };
+            observe::settled(self.solver.name(), &result);
+            let _ = response_sender.send(result);
         }
+        .instrument(tracing_span)
+        .await
     }
 
     async fn process_settle_request(
@@ -659,6 +712,7 @@ impl Competition {
         auction_id: auction::Id,
         solution_id: u64,
         submission_deadline: BlockNo,
+        account: &mempools::accounts::Lease,
     ) -> Result<Settled, Error> {
         let settlement = {
             let mut lock = self.settlements.lock().unwrap();
@@ -690,7 +744,7 @@ impl Competition {
 
         let executed = self
             .mempools
-            .execute(&self.solver, &settlement, submission_deadline)
+            .execute(account, &settlement, submission_deadline)
             .await;
         notify::executed(
             &self.solver,
@@ -698,6 +752,15 @@ impl Competition {
             settlement.solution(),
             &executed,
         );
+        self.solver.persistence().archive_settlement(
+            auction_id,
+            solution_id,
+            submission_deadline,
+            &executed,
+        );
+        self.solver
+            .ledger()
+            .settled(&self.eth, &settlement, &executed);
 
         match executed {
             Err(_) => Err(Error::SubmissionError),
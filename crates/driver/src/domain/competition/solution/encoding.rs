@@ -358,6 +358,7 @@ pub fn liquidity_interaction(
         liquidity::Kind::BalancerV2Weighted(pool) => {
             pool.swap(&input, &output, settlement_contract).ok()
         }
+        liquidity::Kind::Curve(pool) => pool.swap(&input, &output, settlement_contract).ok(),
         liquidity::Kind::Swapr(pool) => pool.swap(&input, &output, settlement_contract).ok(),
         liquidity::Kind::ZeroEx(limit_order) => limit_order.to_interaction(&input).ok(),
     }
@@ -30,6 +30,16 @@ impl Interaction {
         }
     }
 
+    /// The assets produced by this interaction. These assets are sent to the
+    /// settlement contract when the interaction executes, and are the minimum
+    /// amounts the interaction promises.
+    pub fn outputs(&self) -> Vec<eth::Asset> {
+        match self {
+            Interaction::Custom(custom) => custom.outputs.clone(),
+            Interaction::Liquidity(liquidity) => vec![liquidity.output],
+        }
+    }
+
     /// Returns the ERC20 approvals required for executing this interaction
     /// onchain.
     pub fn allowances(&self) -> Vec<eth::allowance::Required> {
@@ -41,6 +51,7 @@ impl Interaction {
                     liquidity::Kind::UniswapV3(pool) => pool.router,
                     liquidity::Kind::BalancerV2Stable(pool) => pool.vault,
                     liquidity::Kind::BalancerV2Weighted(pool) => pool.vault,
+                    liquidity::Kind::Curve(pool) => pool.address,
                     liquidity::Kind::Swapr(pool) => pool.base.router,
                     liquidity::Kind::ZeroEx(pool) => (*pool.zeroex.address()).into(),
                 };
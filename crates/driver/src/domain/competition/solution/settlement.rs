@@ -45,6 +45,9 @@ pub struct Settlement {
     transaction: SettlementTx,
     /// The gas parameters used by the settlement.
     pub gas: Gas,
+    /// The native token balance the submitting account needs to pay for the
+    /// settlement's gas.
+    pub required_balance: eth::Ether,
     #[debug(ignore)]
     solution: Solution,
 }
@@ -164,7 +167,13 @@ impl Settlement {
             .into_iter()
             .fold(eth::AccessList::default(), |acc, list| acc.merge(list));
 
-        // Simulate the settlement and get the access list and gas.
+        // Simulate the settlement and get the access list and gas. The settlement
+        // is simulated from the solver's main account, although any of its
+        // accounts may submit it. They are all allowed to settle and the
+        // settlement doesn't otherwise depend on the sender, so only the gas for
+        // looking up the sender in the allow list can differ, which the gas limit
+        // buffer covers. The submitting account simulates it again right before
+        // submission.
         let (access_list, gas) = Self::simulate(
             transaction.internalized.clone(),
             &partial_access_list,
@@ -176,14 +185,20 @@ impl Settlement {
         let gas = Gas::new(gas, eth.block_gas_limit())?;
 
         // Ensure that the solver has sufficient balance for the settlement to be mined
-        // even if the gas price keeps climbing during the tx submission.
-        let required_eth_balance =
+        // even if the gas price keeps climbing during the tx submission. The
+        // settlement is only submitted from an account holding that balance.
+        let required_balance =
             // Converting to U256 first avoids possible overflow
             gas.required_balance(U256::from(price.max_fee_per_gas).saturating_mul(U256::from(2)));
-        if eth.balance(solution.solver().address()).await? < required_eth_balance {
-            return Err(Error::SolverAccountInsufficientBalance(
-                required_eth_balance,
-            ));
+        let accounts = solution.solver().accounts();
+        let balances =
+            try_join_all(accounts.addresses().map(|address| eth.balance(address))).await?;
+        if balances
+            .into_iter()
+            .max()
+            .is_none_or(|balance| balance < required_balance)
+        {
+            return Err(Error::SolverAccountInsufficientBalance(required_balance));
         }
 
         // Is at least one interaction internalized?
@@ -212,6 +227,7 @@ impl Settlement {
             solution,
             transaction: transaction.with_access_list(access_list),
             gas,
+            required_balance,
         })
     }
 
@@ -331,6 +347,28 @@ impl Settlement {
         acc
     }
 
+    /// The fees charged by the trades of this settlement, in their sell
+    /// tokens.
+    pub fn fees(&self) -> Vec<eth::Asset> {
+        self.solution
+            .trades
+            .iter()
+            .map(|trade| eth::Asset {
+                token: trade.sell().token,
+                amount: trade.fee().0.into(),
+            })
+            .collect()
+    }
+
+    /// The owners of the orders traded by this settlement.
+    pub fn owners(&self) -> HashSet<eth::Address> {
+        self.solution
+            .trades
+            .iter()
+            .map(|trade| trade.uid().owner())
+            .collect()
+    }
+
     /// The uniform price vector this settlement proposes
     pub fn prices(&self) -> HashMap<eth::TokenAddress, eth::TokenAmount> {
         self.solution
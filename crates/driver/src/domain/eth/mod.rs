@@ -354,6 +354,10 @@ impl From<model::interaction::InteractionData> for Interaction {
 #[derive(Clone, Debug, From, Into)]
 pub struct TxId(pub B256);
 
+/// The hash identifying a bundle of transactions sent to block builders.
+#[derive(Clone, Copy, Debug, From, Into)]
+pub struct BundleHash(pub B256);
+
 pub enum TxStatus {
     /// The transaction has been included and executed successfully.
     Executed { block_number: BlockNo },
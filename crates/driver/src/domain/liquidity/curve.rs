@@ -0,0 +1,99 @@
+use crate::{
+    boundary,
+    domain::{eth, liquidity},
+};
+
+/// Liquidity data tied to a Curve pool.
+///
+/// Curve pools come in two flavours: StableSwap pools [^1] for pegged assets
+/// and CryptoSwap pools [^2] for volatile ones. Both are traded by calling
+/// `exchange` on the pool contract itself.
+///
+/// [^1]: <https://docs.curve.finance/stableswap-exchange/stableswap-ng/pools/overview/>
+/// [^2]: <https://docs.curve.finance/cryptoswap-exchange/overview/>
+#[derive(Clone, Debug)]
+pub struct Pool {
+    pub address: eth::ContractAddress,
+    pub kind: Kind,
+    /// The pool coins and their balances, in pool order.
+    pub reserves: Vec<eth::Asset>,
+    pub state: State,
+}
+
+impl Pool {
+    /// Encodes a pool swap as an interaction. Returns `Err` if the swap
+    /// parameters are invalid for the pool, specifically if the input and
+    /// output tokens do not belong to the pool.
+    pub fn swap(
+        &self,
+        input: &liquidity::MaxInput,
+        output: &liquidity::ExactOutput,
+        receiver: &eth::Address,
+    ) -> Result<eth::Interaction, liquidity::InvalidSwap> {
+        let tokens = self.tokens().collect::<Vec<_>>();
+        if input.0.token == output.0.token
+            || !tokens.contains(&input.0.token)
+            || !tokens.contains(&output.0.token)
+        {
+            return Err(liquidity::InvalidSwap);
+        }
+
+        boundary::liquidity::curve::to_interaction(self, input, output, receiver)
+            .map_err(|_| liquidity::InvalidSwap)
+    }
+
+    /// Returns an iterator over the pool tokens, in pool order.
+    pub fn tokens(&self) -> impl Iterator<Item = eth::TokenAddress> + '_ {
+        self.reserves.iter().map(|reserve| reserve.token)
+    }
+}
+
+/// The flavour of a Curve pool.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kind {
+    /// Plain StableSwap pools (e.g. 3pool).
+    StableSwap,
+    /// StableSwap-NG pools with dynamic off-peg fees and oracle rates.
+    StableSwapNg,
+    /// Original two-coin CryptoSwap pools.
+    TwoCrypto,
+    /// Two-coin CryptoSwap-NG pools.
+    TwoCryptoNg,
+    /// Three-coin Tricrypto-NG pools.
+    TricryptoNg,
+}
+
+/// The invariant specific state of a Curve pool.
+#[derive(Clone, Debug)]
+pub enum State {
+    Stable(StableState),
+    Crypto(CryptoState),
+}
+
+/// StableSwap invariant parameters.
+#[derive(Clone, Debug)]
+pub struct StableState {
+    /// Rate multipliers normalizing balances to 18 decimals.
+    pub rates: Vec<eth::U256>,
+    /// Amplification coefficient, including the pool's `A_PRECISION`.
+    pub amp: eth::U256,
+    /// Swap fee in `1e10` units.
+    pub fee: eth::U256,
+    /// The off-peg fee multiplier of NG pools, `None` for plain pools.
+    pub offpeg_fee_multiplier: Option<eth::U256>,
+}
+
+/// CryptoSwap invariant parameters.
+#[derive(Clone, Debug)]
+pub struct CryptoState {
+    /// Multipliers normalizing balances to 18 decimals.
+    pub precisions: Vec<eth::U256>,
+    /// Price of coins `1..N` in terms of coin 0.
+    pub price_scale: Vec<eth::U256>,
+    /// The amplification coefficient as stored on-chain.
+    pub ann: eth::U256,
+    pub gamma: eth::U256,
+    pub mid_fee: eth::U256,
+    pub out_fee: eth::U256,
+    pub fee_gamma: eth::U256,
+}
@@ -8,6 +8,7 @@ use {
 };
 
 pub mod balancer;
+pub mod curve;
 pub mod swapr;
 pub mod uniswap;
 pub mod zeroex;
@@ -49,6 +50,7 @@ pub enum Kind {
     UniswapV3(uniswap::v3::Pool),
     BalancerV2Stable(balancer::v2::stable::Pool),
     BalancerV2Weighted(balancer::v2::weighted::Pool),
+    Curve(curve::Pool),
     Swapr(swapr::Pool),
     ZeroEx(zeroex::LimitOrder),
 }
@@ -60,6 +62,7 @@ impl From<&Kind> for &'static str {
             Kind::UniswapV3(_) => "UniswapV3",
             Kind::BalancerV2Stable(_) => "BalancerV2Stable",
             Kind::BalancerV2Weighted(_) => "BalancerV2Weighted",
+            Kind::Curve(_) => "Curve",
             Kind::Swapr(_) => "Swapr",
             Kind::ZeroEx(_) => "ZeroExLimitOrder",
         }
@@ -0,0 +1,378 @@
+//! Pool of accounts a solver submits settlements from.
+//!
+//! Every account has at most one settlement in flight, so a solver with
+//! several accounts can settle several auctions at the same time and a
+//! transaction stuck in the mempool only blocks its own account. Settlements
+//! are only submitted from accounts that can pay for their gas, and accounts
+//! whose last transaction may still be pending (e.g. a cancellation) are only
+//! used when no other account is free. On every block the balances and nonces
+//! of the accounts are refreshed, and accounts running low on the native token
+//! are topped up by the others.
+
+use {
+    super::Mempools,
+    crate::{
+        domain::eth,
+        infra::{
+            notify,
+            solver::{Account, Solver},
+        },
+    },
+    alloy::network::TxSigner,
+    ethrpc::block_stream::into_stream,
+    futures::{StreamExt, future::join_all},
+    std::sync::{Arc, Mutex},
+    tokio::sync::Notify,
+};
+
+/// Keeps the native token balances of the accounts above a threshold.
+#[derive(Debug, Clone, Copy)]
+pub struct Rebalancing {
+    /// Accounts below this balance get topped up, and the solver is notified
+    /// about them.
+    pub min_balance: eth::Ether,
+    /// The balance accounts are topped up to. Accounts only send funds if
+    /// they keep at least this much.
+    pub target_balance: eth::Ether,
+}
+
+#[derive(Debug)]
+pub struct Accounts {
+    accounts: Vec<Account>,
+    rebalancing: Option<Rebalancing>,
+    state: Mutex<Vec<State>>,
+    /// Notified whenever an account is released.
+    freed: Notify,
+}
+
+#[derive(Debug, Default, Clone)]
+struct State {
+    /// Whether the account is leased.
+    busy: bool,
+    /// The nonce of the last transaction sent from the account, until it got
+    /// mined or replaced.
+    pending: Option<u64>,
+    /// The balance at the last block.
+    balance: Option<eth::Ether>,
+    /// A top up sent to the account that isn't mined yet.
+    top_up: Option<TopUp>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TopUp {
+    /// The index of the account sending the funds.
+    from: usize,
+    nonce: u64,
+}
+
+impl State {
+    fn is_low(&self, rebalancing: Option<Rebalancing>) -> bool {
+        rebalancing
+            .zip(self.balance)
+            .is_some_and(|(rebalancing, balance)| balance < rebalancing.min_balance)
+    }
+}
+
+impl Accounts {
+    /// Creates a pool of the given accounts, with the solver's main account
+    /// first.
+    pub fn new(accounts: Vec<Account>, rebalancing: Option<Rebalancing>) -> Self {
+        Self {
+            state: Mutex::new(vec![State::default(); accounts.len()]),
+            accounts,
+            rebalancing,
+            freed: Notify::new(),
+        }
+    }
+
+    /// Whether balances and nonces have to be kept track of, which is only
+    /// the case for several accounts or if balances are monitored.
+    pub fn needs_maintenance(&self) -> bool {
+        self.accounts.len() > 1 || self.rebalancing.is_some()
+    }
+
+    /// The addresses of the accounts.
+    pub fn addresses(&self) -> impl Iterator<Item = eth::Address> + '_ {
+        self.accounts.iter().map(|account| account.address())
+    }
+
+    /// Waits for an account holding at least the `required` balance to be
+    /// free and reserves it until the returned lease is dropped. Accounts
+    /// whose balance isn't known yet are assumed to hold enough. Returns
+    /// `None` if none of the accounts, free or not, holds enough.
+    pub async fn acquire(self: &Arc<Self>, required: eth::Ether) -> Option<Lease> {
+        let covers = |account: &State| account.balance.is_none_or(|balance| balance >= required);
+        loop {
+            let freed = self.freed.notified();
+            if !self.state.lock().unwrap().iter().any(covers) {
+                return None;
+            }
+            if let Some(lease) = self.try_acquire(|_, account| covers(account)) {
+                return Some(lease);
+            }
+            freed.await;
+        }
+    }
+
+    /// Reserves a free account, preferring accounts without pending
+    /// transactions and then accounts that aren't low on balance. Ties go to
+    /// the account configured first.
+    fn try_acquire(self: &Arc<Self>, eligible: impl Fn(usize, &State) -> bool) -> Option<Lease> {
+        let mut state = self.state.lock().unwrap();
+        let (index, _) = state
+            .iter()
+            .enumerate()
+            .filter(|(index, account)| !account.busy && eligible(*index, account))
+            .min_by_key(|(_, account)| {
+                (account.pending.is_some(), account.is_low(self.rebalancing))
+            })?;
+        state[index].busy = true;
+        Some(Lease {
+            accounts: self.clone(),
+            index,
+        })
+    }
+
+    /// Refreshes the balances and nonces of the accounts on every block and
+    /// keeps their balances topped up. Runs until the block stream ends.
+    pub async fn maintain(self: Arc<Self>, solver: Solver, mempools: Mempools) {
+        let mut blocks = into_stream(mempools.ethereum.current_block().clone());
+        while blocks.next().await.is_some() {
+            self.refresh(&solver, &mempools).await;
+            if let Some(rebalancing) = self.rebalancing {
+                self.rebalance(rebalancing, &mempools).await;
+            }
+        }
+    }
+
+    async fn refresh(&self, solver: &Solver, mempools: &Mempools) {
+        let updates = join_all(self.addresses().map(|address| async move {
+            let balance = mempools
+                .ethereum
+                .balance(address)
+                .await
+                .inspect_err(|err| tracing::warn!(?err, ?address, "failed to fetch balance"))
+                .ok();
+            let nonce = mempools
+                .nonce(address)
+                .await
+                .inspect_err(|err| tracing::warn!(?err, ?address, "failed to fetch nonce"))
+                .ok();
+            (balance, nonce)
+        }))
+        .await;
+
+        let mut ran_low = Vec::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            let nonces = updates.iter().map(|(_, nonce)| *nonce).collect::<Vec<_>>();
+            for (index, (balance, nonce)) in updates.into_iter().enumerate() {
+                let account = &mut state[index];
+                // Once the nonce moved past the pending transaction it was
+                // either mined or replaced.
+                if let (Some(pending), Some(nonce)) = (account.pending, nonce)
+                    && nonce > pending
+                {
+                    account.pending = None;
+                }
+                if let Some(top_up) = account.top_up
+                    && nonces[top_up.from].is_some_and(|nonce| nonce > top_up.nonce)
+                {
+                    account.top_up = None;
+                }
+                if let Some(balance) = balance {
+                    let was_low = account.is_low(self.rebalancing);
+                    account.balance = Some(balance);
+                    if !was_low && account.is_low(self.rebalancing) {
+                        ran_low.push((self.accounts[index].address(), balance));
+                    }
+                }
+            }
+        }
+
+        if let Some(rebalancing) = self.rebalancing {
+            for (address, balance) in ran_low {
+                tracing::warn!(?address, ?balance, "solver account is low on balance");
+                notify::insufficient_balance(solver, rebalancing.min_balance);
+            }
+        }
+    }
+
+    /// Tops up the account lowest on balance from the free account with the
+    /// highest balance, if it can spare the funds.
+    async fn rebalance(self: &Arc<Self>, rebalancing: Rebalancing, mempools: &Mempools) {
+        let (recipient, amount, donor) = {
+            let state = self.state.lock().unwrap();
+            let Some((recipient, balance)) = state
+                .iter()
+                .enumerate()
+                .filter(|(_, account)| account.top_up.is_none())
+                .filter_map(|(index, account)| Some((index, account.balance?)))
+                .filter(|(_, balance)| *balance < rebalancing.min_balance)
+                .min_by_key(|(_, balance)| *balance)
+            else {
+                return;
+            };
+            let amount = rebalancing.target_balance.0.saturating_sub(balance.0);
+            let donor = state
+                .iter()
+                .enumerate()
+                .filter(|(_, account)| !account.busy && account.pending.is_none())
+                .filter_map(|(index, account)| Some((index, account.balance?)))
+                .filter(|(_, balance)| {
+                    balance.0 >= rebalancing.target_balance.0.saturating_add(amount)
+                })
+                .max_by_key(|(_, balance)| *balance);
+            let Some((donor, _)) = donor else {
+                tracing::debug!(
+                    recipient = ?self.accounts[recipient].address(),
+                    "no account can spare funds to top up solver account"
+                );
+                return;
+            };
+            (recipient, eth::Ether(amount), donor)
+        };
+
+        // The donor may have been leased in the meantime.
+        let Some(lease) = self.try_acquire(|index, _| index == donor) else {
+            return;
+        };
+        let to = self.accounts[recipient].address();
+        let from = lease.address();
+        match mempools.transfer(&lease, to, amount).await {
+            Ok(hash) => {
+                tracing::info!(?from, ?to, ?amount, ?hash, "topped up solver account");
+                let mut state = self.state.lock().unwrap();
+                state[recipient].top_up = state[donor]
+                    .pending
+                    .map(|nonce| TopUp { from: donor, nonce });
+            }
+            Err(err) => tracing::warn!(?from, ?to, ?err, "failed to top up solver account"),
+        }
+    }
+}
+
+/// An account reserved for submitting transactions until dropped.
+#[derive(Debug)]
+pub struct Lease {
+    accounts: Arc<Accounts>,
+    index: usize,
+}
+
+impl Lease {
+    pub fn address(&self) -> eth::Address {
+        self.accounts.accounts[self.index].address()
+    }
+
+    /// Records that a transaction with the given nonce was sent from the
+    /// account.
+    pub fn sent(&self, nonce: u64) {
+        self.accounts.state.lock().unwrap()[self.index].pending = Some(nonce);
+    }
+
+    /// Records that the last transaction sent from the account was mined.
+    pub fn mined(&self) {
+        self.accounts.state.lock().unwrap()[self.index].pending = None;
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.accounts.state.lock().unwrap()[self.index].busy = false;
+        self.accounts.freed.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloy::primitives::Address, std::time::Duration};
+
+    fn accounts(n: u8, rebalancing: Option<Rebalancing>) -> Arc<Accounts> {
+        Arc::new(Accounts::new(
+            (1..=n)
+                .map(|i| Account::Address(Address::repeat_byte(i)))
+                .collect(),
+            rebalancing,
+        ))
+    }
+
+    #[tokio::test]
+    async fn leases_free_accounts() {
+        let accounts = accounts(2, None);
+        let first = accounts.acquire(eth::Ether::from(0)).await.unwrap();
+        let second = accounts.acquire(eth::Ether::from(0)).await.unwrap();
+        assert_eq!(first.address(), Address::repeat_byte(1));
+        assert_eq!(second.address(), Address::repeat_byte(2));
+
+        // All accounts are busy until one is released.
+        let third = tokio::spawn({
+            let accounts = accounts.clone();
+            async move {
+                accounts
+                    .acquire(eth::Ether::from(0))
+                    .await
+                    .unwrap()
+                    .address()
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!third.is_finished());
+        drop(second);
+        assert_eq!(third.await.unwrap(), Address::repeat_byte(2));
+    }
+
+    #[tokio::test]
+    async fn prefers_accounts_without_pending_transactions() {
+        let rebalancing = Rebalancing {
+            min_balance: eth::Ether::from(10),
+            target_balance: eth::Ether::from(20),
+        };
+        let accounts = accounts(3, Some(rebalancing));
+        {
+            let mut state = accounts.state.lock().unwrap();
+            state[0].pending = Some(1);
+            state[1].balance = Some(eth::Ether::from(5));
+        }
+
+        // Low balances are better than transactions that may be replaced.
+        let lease = accounts.acquire(eth::Ether::from(0)).await.unwrap();
+        assert_eq!(lease.address(), Address::repeat_byte(3));
+        let lease = accounts.acquire(eth::Ether::from(0)).await.unwrap();
+        assert_eq!(lease.address(), Address::repeat_byte(2));
+        let lease = accounts.acquire(eth::Ether::from(0)).await.unwrap();
+        assert_eq!(lease.address(), Address::repeat_byte(1));
+    }
+
+    #[tokio::test]
+    async fn only_leases_accounts_covering_the_required_balance() {
+        let accounts = accounts(3, None);
+        {
+            let mut state = accounts.state.lock().unwrap();
+            state[0].balance = Some(eth::Ether::from(5));
+            state[1].balance = Some(eth::Ether::from(20));
+        }
+
+        let lease = accounts.acquire(eth::Ether::from(10)).await.unwrap();
+        assert_eq!(lease.address(), Address::repeat_byte(2));
+        // Accounts of unknown balance may hold enough.
+        let unknown = accounts.acquire(eth::Ether::from(10)).await.unwrap();
+        assert_eq!(unknown.address(), Address::repeat_byte(3));
+        assert!(accounts.acquire(eth::Ether::from(30)).await.is_none());
+
+        // The first account is free but can't pay, so the next lease waits
+        // for one that can.
+        let next = tokio::spawn({
+            let accounts = accounts.clone();
+            async move {
+                accounts
+                    .acquire(eth::Ether::from(10))
+                    .await
+                    .map(|lease| lease.address())
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!next.is_finished());
+        drop(lease);
+        assert_eq!(next.await.unwrap(), Some(Address::repeat_byte(2)));
+    }
+}
@@ -6,24 +6,29 @@ use {
             competition::solution::Settlement,
             eth::{TxId, TxStatus},
         },
-        infra::{self, Ethereum, observe, solver::Solver},
+        infra::{self, Ethereum, observe},
     },
-    alloy::{consensus::Transaction, eips::eip1559::Eip1559Estimation},
+    alloy::{consensus::Transaction, eips::eip1559::Eip1559Estimation, primitives::Bytes},
     anyhow::Context,
-    ethrpc::block_stream::into_stream,
-    futures::{FutureExt, StreamExt, future::select_ok},
+    ethrpc::block_stream::{BlockInfo, into_stream},
+    futures::{FutureExt, Stream, StreamExt, future::select_ok},
     thiserror::Error,
     tracing::Instrument,
 };
 
+pub mod accounts;
+
+pub use accounts::Accounts;
+
 /// Factor by how much a transaction fee needs to be increased to override a
 /// pending transaction at the same nonce. The correct factor is actually
 /// 12.5% but to avoid rounding issues on chains with very low gas prices
 /// we increase slightly more.
 const GAS_PRICE_BUMP_PCT: u64 = 13;
 
-/// The gas amount required to cancel a transaction.
-const CANCELLATION_GAS_AMOUNT: u64 = 21000;
+/// The gas amount required for plain native token transfers, such as
+/// cancellations.
+const TRANSFER_GAS_AMOUNT: u64 = 21000;
 
 /// The mempools used to execute settlements.
 #[derive(Debug, Clone)]
@@ -41,17 +46,17 @@ impl Mempools {
         }
     }
 
-    /// Publish a settlement to the mempools.
+    /// Publish a settlement to the mempools from the given account.
     pub async fn execute(
         &self,
-        solver: &Solver,
+        account: &accounts::Lease,
         settlement: &Settlement,
         submission_deadline: BlockNo,
     ) -> Result<eth::TxId, Error> {
         let (submission, _remaining_futures) = select_ok(self.mempools.iter().map(|mempool| {
             async move {
                 let result = self
-                    .submit(mempool, solver, settlement, submission_deadline)
+                    .submit(mempool, account, settlement, submission_deadline)
                     .instrument(tracing::info_span!("mempool", kind = mempool.to_string()))
                     .await;
                 observe::mempool_executed(mempool, settlement, &result);
@@ -60,10 +65,50 @@ impl Mempools {
             .boxed()
         }))
         .await?;
+        account.mined();
 
         Ok(submission.tx_hash)
     }
 
+    /// Fetches the nonce of the given account.
+    pub async fn nonce(&self, address: eth::Address) -> Result<u64, Error> {
+        // There is always at least one mempool.
+        self.mempools[0].get_nonce(address).await
+    }
+
+    /// Sends native tokens from the given account, e.g. to top up another
+    /// account of the solver. Returns as soon as the transfer is pending.
+    pub async fn transfer(
+        &self,
+        account: &accounts::Lease,
+        to: eth::Address,
+        amount: eth::Ether,
+    ) -> Result<eth::TxId, Error> {
+        let mempool = self
+            .mempools
+            .iter()
+            .find(|mempool| !mempool.submits_bundles())
+            .context("no mempool accepts plain transactions")?;
+        let nonce = mempool.get_nonce(account.address()).await?;
+        let gas_price = self
+            .ethereum
+            .gas_price()
+            .await
+            .context("failed to compute current gas price")?;
+        let transfer = eth::Tx {
+            from: account.address(),
+            to,
+            value: amount,
+            input: Default::default(),
+            access_list: Default::default(),
+        };
+        let hash = mempool
+            .submit(transfer, gas_price, TRANSFER_GAS_AMOUNT.into(), nonce)
+            .await?;
+        account.sent(nonce);
+        Ok(hash)
+    }
+
     /// Defines if the mempools are configured in a way that guarantees that
     /// settled solution will not revert.
     pub fn revert_protection(&self) -> RevertProtection {
@@ -80,7 +125,7 @@ impl Mempools {
     async fn submit(
         &self,
         mempool: &infra::mempool::Mempool,
-        solver: &Solver,
+        account: &accounts::Lease,
         settlement: &Settlement,
         submission_deadline: BlockNo,
     ) -> Result<SubmissionSuccess, Error> {
@@ -93,7 +138,12 @@ impl Mempools {
             return Err(Error::Disabled);
         }
 
-        let tx = settlement.transaction(settlement::Internalization::Enable);
+        let tx = &eth::Tx {
+            from: account.address(),
+            ..settlement
+                .transaction(settlement::Internalization::Enable)
+                .clone()
+        };
 
         // Instantiate block stream and skip the current block before we submit the
         // settlement. This way we only run iterations in blocks that can potentially
@@ -126,7 +176,7 @@ impl Mempools {
         // Fetch the nonce to avoid race conditions between concurrent
         // transactions (e.g., settlement tx and cancellation tx) from the same
         // solver address.
-        let nonce = mempool.get_nonce(solver.address()).await?;
+        let nonce = mempool.get_nonce(account.address()).await?;
 
         // estimate the gas price such that the tx should still be included
         // even if the gas price increases the maximum amount until the submission
@@ -142,7 +192,7 @@ impl Mempools {
         // if there is still a tx pending we also have to make sure we outbid that one
         // enough to make the node replace it in the mempool
         let replacement_gas_price = self
-            .minimum_replacement_gas_price(mempool, solver, nonce)
+            .minimum_replacement_gas_price(mempool, account.address(), nonce)
             .await;
         let final_gas_price = match &replacement_gas_price {
             Some(replacement_gas_price)
@@ -161,15 +211,24 @@ impl Mempools {
             ?final_gas_price,
             "submitting settlement tx"
         );
+        if mempool.submits_bundles() {
+            return self
+                .submit_bundle(
+                    mempool,
+                    tx,
+                    settlement.gas.limit,
+                    final_gas_price,
+                    nonce,
+                    submission_block,
+                    submission_deadline,
+                    block_stream,
+                )
+                .await;
+        }
         let hash = mempool
-            .submit(
-                tx.clone(),
-                final_gas_price,
-                settlement.gas.limit,
-                solver,
-                nonce,
-            )
+            .submit(tx.clone(), final_gas_price, settlement.gas.limit, nonce)
             .await?;
+        account.sent(nonce);
 
         // Wait for the transaction to be mined, expired or failing.
         let result = async {
@@ -188,6 +247,7 @@ impl Mempools {
                         tx_hash: hash.clone(),
                         submitted_at_block: submission_block.into(),
                         included_in_block: block_number,
+                        bundles: Vec::new(),
                     }),
                     TxStatus::Reverted { block_number } => {
                         return Err(Error::Revert {
@@ -206,12 +266,13 @@ impl Mempools {
                                 "exceeded submission deadline, cancelling"
                             );
                             let _ = self
-                                .cancel(mempool, final_gas_price, solver, nonce)
+                                .cancel(mempool, final_gas_price, account.address(), nonce)
                                 .await;
                             return Err(Error::Expired {
                                 tx_id: hash.clone(),
                                 submitted_at_block: submission_block,
                                 submission_deadline,
+                                bundles: Vec::new(),
                             });
                         }
                         // Check if transaction still simulates
@@ -223,7 +284,7 @@ impl Mempools {
                                     "tx started failing in mempool, cancelling"
                                 );
                                 let _ = self
-                                    .cancel(mempool, final_gas_price, solver, nonce)
+                                    .cancel(mempool, final_gas_price, account.address(), nonce)
                                     .await;
                                 return Err(Error::SimulationRevert {
                                     submitted_at_block: submission_block,
@@ -257,24 +318,54 @@ impl Mempools {
                     tx_hash: hash,
                     included_in_block: block_number,
                     submitted_at_block: submission_block.into(),
+                    bundles: Vec::new(),
                 });
             }
         }
         result
     }
 
+    /// Submits a settlement as a bundle targeting the next block, and sends
+    /// it again targeting the following block until it gets included or the
+    /// submission deadline is reached. Builders drop bundles that would
+    /// revert, so unlike transactions they never need to be cancelled.
+    #[expect(clippy::too_many_arguments)]
+    async fn submit_bundle(
+        &self,
+        mempool: &infra::Mempool,
+        tx: &eth::Tx,
+        gas_limit: eth::Gas,
+        gas_price: Eip1559Estimation,
+        nonce: u64,
+        submission_block: BlockNo,
+        submission_deadline: BlockNo,
+        block_stream: impl Stream<Item = BlockInfo> + Unpin,
+    ) -> Result<SubmissionSuccess, Error> {
+        let (signed, hash) = mempool
+            .sign(tx.clone(), gas_price, gas_limit, nonce)
+            .await?;
+        let bundle = MempoolBundle {
+            mempool,
+            ethereum: &self.ethereum,
+            tx,
+            txs: [signed],
+            hash,
+        };
+        send_until_included(&bundle, submission_block, submission_deadline, block_stream).await
+    }
+
     /// Cancel a pending settlement by sending a transaction to self with a
     /// slightly higher gas price than the existing one.
     async fn cancel(
         &self,
         mempool: &infra::mempool::Mempool,
         original_tx_gas_price: Eip1559Estimation,
-        solver: &Solver,
+        account: eth::Address,
         nonce: u64,
     ) -> Result<TxId, Error> {
         let fallback_gas_price = original_tx_gas_price.scaled_by_pct(GAS_PRICE_BUMP_PCT);
         let replacement_gas_price = self
-            .minimum_replacement_gas_price(mempool, solver, nonce)
+            .minimum_replacement_gas_price(mempool, account, nonce)
             .await;
 
         // the node is the ultimate source of truth to compute the minimum
@@ -286,8 +377,8 @@ impl Mempools {
         };
 
         let cancellation = eth::Tx {
-            from: solver.address(),
-            to: solver.address(),
+            from: account,
+            to: account,
             value: 0.into(),
             input: Default::default(),
             access_list: Default::default(),
@@ -304,8 +395,7 @@ impl Mempools {
             .submit(
                 cancellation,
                 final_gas_price,
-                CANCELLATION_GAS_AMOUNT.into(),
-                solver,
+                TRANSFER_GAS_AMOUNT.into(),
                 nonce,
             )
             .await
@@ -318,10 +408,10 @@ impl Mempools {
     async fn minimum_replacement_gas_price(
         &self,
         mempool: &infra::Mempool,
-        solver: &Solver,
+        account: eth::Address,
         next_nonce: u64,
     ) -> Option<Eip1559Estimation> {
-        if let Some(last_submission) = mempool.last_submission(solver.address()) {
+        if let Some(last_submission) = mempool.last_submission(account) {
             if last_submission.nonce == next_nonce {
                 Some(last_submission.gas_price.scaled_by_pct(GAS_PRICE_BUMP_PCT))
             } else {
@@ -333,7 +423,7 @@ impl Mempools {
             // This is only done as a backup since it can incur significant latency and
             // is generally not very widely supported.
             let pending_tx = mempool
-                .find_pending_tx_in_mempool(solver.address(), next_nonce)
+                .find_pending_tx_in_mempool(account, next_nonce)
                 .await
                 .inspect_err(|err| tracing::debug!(?err, "could not inspect tx mempool"))
                 .ok()??;
@@ -351,12 +441,166 @@ impl Mempools {
     }
 }
 
+/// A signed settlement sent as a bundle, as seen by the loop re-targeting it
+/// at every new block.
+trait Bundle {
+    /// The hash of the bundled settlement transaction.
+    fn hash(&self) -> &eth::TxId;
+
+    /// Sends the bundle to the relays, targeting `block`.
+    async fn send(&self, block: BlockNo) -> Result<eth::BundleHash, Error>;
+
+    /// The onchain status of the bundled settlement transaction.
+    async fn status(&self) -> TxStatus;
+
+    /// Whether the bundled settlement transaction reverts when simulated
+    /// against the latest block.
+    async fn reverts(&self) -> bool;
+}
+
+/// A bundle sent through a bundle mempool.
+struct MempoolBundle<'a> {
+    mempool: &'a infra::Mempool,
+    ethereum: &'a Ethereum,
+    tx: &'a eth::Tx,
+    txs: [Bytes; 1],
+    hash: eth::TxId,
+}
+
+impl Bundle for MempoolBundle<'_> {
+    fn hash(&self) -> &eth::TxId {
+        &self.hash
+    }
+
+    async fn send(&self, block: BlockNo) -> Result<eth::BundleHash, Error> {
+        self.mempool.send_bundle(&self.txs, block).await
+    }
+
+    async fn status(&self) -> TxStatus {
+        self.ethereum
+            .transaction_status(&self.hash)
+            .await
+            .unwrap_or_else(|err| {
+                tracing::warn!(hash = ?self.hash, ?err, "failed to get transaction status");
+                TxStatus::Pending
+            })
+    }
+
+    async fn reverts(&self) -> bool {
+        match self.ethereum.estimate_gas(self.tx.clone()).await {
+            Ok(_) => false,
+            Err(err) if err.is_revert() => {
+                tracing::info!(settle_tx_hash = ?self.hash, ?err, "bundle started failing");
+                true
+            }
+            Err(err) => {
+                tracing::warn!(hash = ?self.hash, ?err, "couldn't re-simulate tx");
+                false
+            }
+        }
+    }
+}
+
+/// Sends `bundle` targeting the block after `submission_block`, and again
+/// targeting the next block for every new block of `block_stream`, until the
+/// bundled transaction is included, starts reverting or the submission
+/// deadline is reached.
+async fn send_until_included(
+    bundle: &impl Bundle,
+    submission_block: BlockNo,
+    submission_deadline: BlockNo,
+    mut block_stream: impl Stream<Item = BlockInfo> + Unpin,
+) -> Result<SubmissionSuccess, Error> {
+    let hash = bundle.hash();
+    let mut bundles = Vec::new();
+
+    let result = async {
+        let mut target_block = submission_block + 1;
+        loop {
+            match bundle.send(target_block).await {
+                Ok(sent) => bundles.push(sent),
+                Err(err) => {
+                    tracing::warn!(?hash, target_block, ?err, "failed to send bundle")
+                }
+            }
+
+            let Some(block) = block_stream.next().await else {
+                return Err(Error::Other(anyhow::anyhow!(
+                    "Block stream finished unexpectedly"
+                )));
+            };
+            tracing::debug!(?hash, current_block = ?block.number, "checking if bundle is included");
+            match bundle.status().await {
+                TxStatus::Executed { block_number } => {
+                    return Ok(SubmissionSuccess {
+                        tx_hash: hash.clone(),
+                        submitted_at_block: submission_block.into(),
+                        included_in_block: block_number,
+                        bundles: bundles.clone(),
+                    });
+                }
+                TxStatus::Reverted { block_number } => {
+                    return Err(Error::Revert {
+                        tx_id: hash.clone(),
+                        submitted_at_block: submission_block,
+                        reverted_at_block: block_number.into(),
+                    });
+                }
+                TxStatus::Pending => {}
+            }
+
+            if block.number >= submission_deadline {
+                tracing::debug!(
+                    submission_deadline,
+                    current_block = block.number,
+                    settle_tx_hash = ?hash,
+                    "exceeded submission deadline, no longer sending bundle"
+                );
+                return Err(Error::Expired {
+                    tx_id: hash.clone(),
+                    submitted_at_block: submission_block,
+                    submission_deadline,
+                    bundles: bundles.clone(),
+                });
+            }
+            if bundle.reverts().await {
+                return Err(Error::SimulationRevert {
+                    submitted_at_block: submission_block,
+                    reverted_at_block: block.number,
+                });
+            }
+            target_block = block.number + 1;
+        }
+    }
+    .await;
+
+    if result.is_err() {
+        // The bundle sent for the last block may still have been included.
+        if let TxStatus::Executed { block_number } = bundle.status().await {
+            tracing::info!(
+                ?hash,
+                ?block_number,
+                "Found confirmed transaction, ignoring error"
+            );
+            return Ok(SubmissionSuccess {
+                tx_hash: hash.clone(),
+                included_in_block: block_number,
+                submitted_at_block: submission_block.into(),
+                bundles,
+            });
+        }
+    }
+    result
+}
+
 pub struct SubmissionSuccess {
     pub tx_hash: eth::TxId,
     /// At which block we started to submit the transaction.
     pub included_in_block: eth::BlockNo,
     /// In which block the transaction actually appeared onchain.
     pub submitted_at_block: eth::BlockNo,
+    /// The bundles the transaction was sent in, for bundle mempools.
+    pub bundles: Vec<eth::BundleHash>,
 }
 
 #[derive(Debug, Error)]
@@ -398,9 +642,94 @@ pub enum Error {
         tx_id: eth::TxId,
         submitted_at_block: BlockNo,
         submission_deadline: BlockNo,
+        /// The bundles the transaction was sent in, for bundle mempools.
+        bundles: Vec<eth::BundleHash>,
     },
     #[error("Strategy disabled for this tx")]
     Disabled,
     #[error("Failed to submit: {0:?}")]
     Other(#[from] anyhow::Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloy::primitives::B256, std::sync::Mutex};
+
+    /// A relay whose bundle targeting `includes` gets included.
+    struct FakeRelay {
+        hash: eth::TxId,
+        includes: Option<BlockNo>,
+        targets: Mutex<Vec<BlockNo>>,
+    }
+
+    impl FakeRelay {
+        fn new(includes: Option<BlockNo>) -> Self {
+            Self {
+                hash: eth::TxId(B256::repeat_byte(1)),
+                includes,
+                targets: Default::default(),
+            }
+        }
+
+        fn targets(&self) -> Vec<BlockNo> {
+            self.targets.lock().unwrap().clone()
+        }
+    }
+
+    impl Bundle for FakeRelay {
+        fn hash(&self) -> &eth::TxId {
+            &self.hash
+        }
+
+        async fn send(&self, block: BlockNo) -> Result<eth::BundleHash, Error> {
+            self.targets.lock().unwrap().push(block);
+            Ok(eth::BundleHash(B256::with_last_byte(block as u8)))
+        }
+
+        async fn status(&self) -> TxStatus {
+            match self.includes {
+                Some(block) if self.targets.lock().unwrap().contains(&block) => {
+                    TxStatus::Executed {
+                        block_number: block.into(),
+                    }
+                }
+                _ => TxStatus::Pending,
+            }
+        }
+
+        async fn reverts(&self) -> bool {
+            false
+        }
+    }
+
+    fn blocks(numbers: impl IntoIterator<Item = BlockNo>) -> impl Stream<Item = BlockInfo> + Unpin {
+        futures::stream::iter(numbers.into_iter().map(|number| BlockInfo {
+            number,
+            ..Default::default()
+        }))
+    }
+
+    #[tokio::test]
+    async fn retargets_bundle_until_included() {
+        let relay = FakeRelay::new(Some(15));
+        let success = send_until_included(&relay, 10, 20, blocks([11, 12, 14, 15, 16]))
+            .await
+            .unwrap();
+
+        assert_eq!(relay.targets(), [11, 12, 13, 15]);
+        assert_eq!(success.included_in_block.0, 15);
+        assert_eq!(success.bundles.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn stops_sending_bundle_at_deadline() {
+        let relay = FakeRelay::new(None);
+        let result = send_until_included(&relay, 10, 13, blocks(11..)).await;
+
+        assert_eq!(relay.targets(), [11, 12, 13]);
+        assert!(matches!(
+            result,
+            Err(Error::Expired { bundles, submission_deadline: 13, .. }) if bundles.len() == 3
+        ));
+    }
+}
@@ -59,23 +59,9 @@ impl Api {
             tower_http::limit::RequestBodyLimitLayer::new(REQUEST_BODY_LIMIT),
         ));
 
-        let balance_fetcher = account_balances::cached(
-            self.eth.web3(),
-            self.eth.balance_simulator().clone(),
-            self.eth.current_block().clone(),
-        );
-
         let tokens = tokens::Fetcher::new(&self.eth);
-        let fetcher = Arc::new(domain::competition::DataAggregator::new(
-            self.eth.clone(),
-            app_data_retriever.clone(),
-            self.liquidity.clone(),
-            tokens.clone(),
-            balance_fetcher,
-        ));
-
-        let order_sorting_strategies =
-            Self::build_order_sorting_strategies(&order_priority_strategies);
+        let competitions =
+            self.competitions(&tokens, &order_priority_strategies, app_data_retriever);
 
         // Add the metrics, healthz, and gasprice endpoints.
         app = routes::metrics(app);
@@ -88,7 +74,8 @@ impl Api {
         // on the same driver so only one liquidity collector collects the liquidity
         // for all of them. This is important because liquidity collection is
         // computationally expensive for the Ethereum node.
-        for solver in self.solvers {
+        for competition in competitions {
+            let solver = competition.solver.clone();
             let name = solver.name().clone();
             let router = axum::Router::new();
             let router = routes::info(router);
@@ -97,40 +84,12 @@ impl Api {
             let router = routes::reveal(router);
             let router = routes::settle(router);
             let router = routes::notify(router);
-
-            let bad_order_config = solver.bad_order_detection();
-            let mut bad_tokens =
-                risk_detector::Detector::new(bad_order_config.tokens_supported.clone());
-            if bad_order_config.enable_simulation_strategy {
-                bad_tokens.with_simulation_detector(self.bad_token_detector.clone());
-            }
-
-            if bad_order_config.enable_metrics_strategy {
-                bad_tokens.with_metrics_detector(bad_orders::metrics::Detector::new(
-                    bad_order_config.metrics_strategy_failure_ratio,
-                    bad_order_config.metrics_strategy_required_measurements,
-                    bad_order_config.metrics_strategy_log_only,
-                    bad_order_config.metrics_strategy_order_freeze_time,
-                    bad_order_config.metrics_strategy_cache_gc_interval,
-                    bad_order_config.metrics_strategy_cache_max_age,
-                    name.clone(),
-                ));
-            }
+            let router = routes::ledger(router);
 
             let router = router.with_state(State(Arc::new(Inner {
                 eth: self.eth.clone(),
-                solver: solver.clone(),
-                competition: domain::Competition::new(
-                    solver,
-                    self.eth.clone(),
-                    self.liquidity.clone(),
-                    self.liquidity_sources_notifier.clone(),
-                    self.simulator.clone(),
-                    self.mempools.clone(),
-                    Arc::new(bad_tokens),
-                    fetcher.clone(),
-                    order_sorting_strategies.clone(),
-                ),
+                solver,
+                competition,
                 liquidity: self.liquidity.clone(),
                 tokens: tokens.clone(),
             })));
@@ -157,6 +116,68 @@ impl Api {
         server.with_graceful_shutdown(shutdown).await
     }
 
+    /// Assembles the competition of every configured solver.
+    pub fn competitions(
+        &self,
+        tokens: &tokens::Fetcher,
+        order_priority_strategies: &[OrderPriorityStrategy],
+        app_data_retriever: Option<AppDataRetriever>,
+    ) -> Vec<Arc<domain::Competition>> {
+        let balance_fetcher = account_balances::cached(
+            self.eth.web3(),
+            self.eth.balance_simulator().clone(),
+            self.eth.current_block().clone(),
+        );
+
+        let fetcher = Arc::new(domain::competition::DataAggregator::new(
+            self.eth.clone(),
+            app_data_retriever,
+            self.liquidity.clone(),
+            tokens.clone(),
+            balance_fetcher,
+        ));
+
+        let order_sorting_strategies =
+            Self::build_order_sorting_strategies(order_priority_strategies);
+
+        self.solvers
+            .iter()
+            .map(|solver| {
+                let name = solver.name().clone();
+                let bad_order_config = solver.bad_order_detection();
+                let mut bad_tokens =
+                    risk_detector::Detector::new(bad_order_config.tokens_supported.clone());
+                if bad_order_config.enable_simulation_strategy {
+                    bad_tokens.with_simulation_detector(self.bad_token_detector.clone());
+                }
+
+                if bad_order_config.enable_metrics_strategy {
+                    bad_tokens.with_metrics_detector(bad_orders::metrics::Detector::new(
+                        bad_order_config.metrics_strategy_failure_ratio,
+                        bad_order_config.metrics_strategy_required_measurements,
+                        bad_order_config.metrics_strategy_log_only,
+                        bad_order_config.metrics_strategy_order_freeze_time,
+                        bad_order_config.metrics_strategy_cache_gc_interval,
+                        bad_order_config.metrics_strategy_cache_max_age,
+                        name,
+                    ));
+                }
+
+                domain::Competition::new(
+                    solver.clone(),
+                    self.eth.clone(),
+                    self.liquidity.clone(),
+                    self.liquidity_sources_notifier.clone(),
+                    self.simulator.clone(),
+                    self.mempools.clone(),
+                    Arc::new(bad_tokens),
+                    fetcher.clone(),
+                    order_sorting_strategies.clone(),
+                )
+            })
+            .collect()
+    }
+
     fn build_order_sorting_strategies(
         order_priority_strategies: &[OrderPriorityStrategy],
     ) -> Vec<Arc<dyn sorting::SortingStrategy>> {
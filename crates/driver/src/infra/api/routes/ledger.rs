@@ -0,0 +1,32 @@
+use {
+    crate::infra::{api::State, ledger},
+    axum::Json,
+    serde::Deserialize,
+    tracing::instrument,
+};
+
+/// Number of entries returned if the request doesn't specify a limit.
+const DEFAULT_LIMIT: usize = 100;
+
+pub(in crate::infra::api) fn ledger(app: axum::Router<State>) -> axum::Router<State> {
+    app.route("/ledger", axum::routing::get(route))
+}
+
+#[derive(Debug, Deserialize)]
+struct Params {
+    /// How many of the most recent entries to return.
+    limit: Option<usize>,
+}
+
+#[instrument(skip(state))]
+async fn route(
+    state: axum::extract::State<State>,
+    params: axum::extract::Query<Params>,
+) -> Json<ledger::Report> {
+    Json(
+        state
+            .solver()
+            .ledger()
+            .report(params.limit.unwrap_or(DEFAULT_LIMIT)),
+    )
+}
@@ -1,6 +1,7 @@
 mod gasprice;
 mod healthz;
 mod info;
+mod ledger;
 mod metrics;
 mod notify;
 mod quote;
@@ -12,6 +13,7 @@ pub(super) use {
     gasprice::gasprice,
     healthz::healthz,
     info::info,
+    ledger::ledger,
     metrics::metrics,
     notify::notify,
     quote::{OrderError, quote},
@@ -36,10 +36,18 @@ async fn route(
         // have capacity again.
         competition.ensure_settle_queue_capacity()?;
         observe::solved(solver, &result);
-        Ok(axum::Json(dto::SolveResponse::new(
-            result?,
-            &competition.solver,
-        )))
+        let solved = result?;
+        let auction_id = solved
+            .as_ref()
+            .and_then(|solved| competition.auction_id(solved.id.get()));
+        let response = dto::SolveResponse::new(solved, &competition.solver);
+        if let Some(id) = auction_id {
+            competition
+                .solver
+                .persistence()
+                .archive_response(id, &response);
+        }
+        Ok(axum::Json(response))
     };
 
     handle_request
@@ -291,6 +291,18 @@ impl Ethereum {
             .map_err(Into::into)
     }
 
+    /// Returns the receipt of the transaction, if it was mined.
+    pub async fn transaction_receipt(
+        &self,
+        tx_hash: &eth::TxId,
+    ) -> Result<Option<TransactionReceipt>, Error> {
+        self.web3
+            .alloy
+            .get_transaction_receipt(tx_hash.0)
+            .await
+            .map_err(Into::into)
+    }
+
     #[instrument(skip(self), ret(level = Level::DEBUG))]
     pub(super) async fn simulation_gas_price(&self) -> Option<u128> {
         let base_fee = self.current_block().borrow().base_fee;
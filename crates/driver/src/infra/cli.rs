@@ -1,7 +1,7 @@
 use {
     reqwest::Url,
     shared::{arguments::TracingArguments, current_block},
-    std::{net::SocketAddr, path::PathBuf},
+    std::{net::SocketAddr, path::PathBuf, time::Duration},
 };
 
 #[derive(Debug, clap::Parser)]
@@ -49,4 +49,30 @@ pub struct Args {
     /// https://github.com/cowprotocol/services/blob/main/crates/driver/example.toml.
     #[clap(long, env)]
     pub config: PathBuf,
+
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Runs something other than the driver API.
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Replays an archived auction against a solver engine and compares the
+    /// result with what the driver originally submitted. `--ethrpc` should
+    /// point to a node forked at the block the auction was solved at.
+    Replay {
+        /// The path of an archived `/solve` request, or the ID of an auction
+        /// archived in the local archive directory of the solver.
+        #[clap(long)]
+        auction: String,
+
+        /// The name of the solver to replay the auction with. Can be omitted
+        /// if only one solver is configured.
+        #[clap(long)]
+        solver: Option<String>,
+
+        /// How long the solver gets to solve the replayed auction.
+        #[clap(long, default_value = "15s", value_parser = humantime::parse_duration)]
+        solve_time: Duration,
+    },
 }
@@ -1,13 +1,15 @@
 use {
     crate::{
-        domain::{competition::risk_detector, eth},
+        domain::{competition::risk_detector, eth, liquidity::curve, mempools},
         infra::{
             self,
             blockchain,
             config::file,
+            ledger,
             liquidity,
             mempool,
             notify,
+            persistence,
             simulator,
             solver::{self, Account, BadOrderDetection, SolutionMerging},
         },
@@ -52,25 +54,24 @@ pub async fn load(chain: Chain, path: &Path) -> infra::Config {
     );
     infra::Config {
         solvers: join_all(config.solvers.into_iter().map(|solver_config| async move {
-            let account: Account = match solver_config.account {
-                file::Account::PrivateKey(private_key) => {
-                    PrivateKeySigner::from_bytes(&private_key)
-                        .expect(
-                            "private key should
-                                            be valid",
-                        )
-                        .into()
-                }
-                file::Account::Kms(arn) => {
-                    let sdk_config = alloy::signers::aws::aws_config::load_from_env().await;
-                    let client = alloy::signers::aws::aws_sdk_kms::Client::new(&sdk_config);
-                    AwsSigner::new(client, arn.0, config.chain_id)
-                        .await
-                        .expect("unable to load kms account {arn:?}")
-                        .into()
+            let account = load_account(solver_config.account, config.chain_id).await;
+            let submission_accounts = join_all(
+                solver_config
+                    .submission_accounts
+                    .into_iter()
+                    .map(|account| load_account(account, config.chain_id)),
+            )
+            .await;
+            let account_rebalancing = solver_config.account_rebalancing.map(|rebalancing| {
+                assert!(
+                    rebalancing.target_balance >= rebalancing.min_balance,
+                    "account rebalancing target balance must not be below the minimum balance"
+                );
+                mempools::accounts::Rebalancing {
+                    min_balance: eth::Ether(rebalancing.min_balance),
+                    target_balance: eth::Ether(rebalancing.target_balance),
                 }
-                file::Account::Address(address) => Account::Address(address),
-            };
+            });
             solver::Config {
                 endpoint: solver_config.endpoint,
                 name: solver_config.name.into(),
@@ -84,6 +85,8 @@ pub async fn load(chain: Chain, path: &Path) -> infra::Config {
                     solver::Liquidity::Fetch
                 },
                 account,
+                submission_accounts,
+                account_rebalancing,
                 timeouts: solver::Timeouts {
                     http_delay: chrono::Duration::from_std(solver_config.timeouts.http_time_buffer)
                         .unwrap(),
@@ -103,7 +106,22 @@ pub async fn load(chain: Chain, path: &Path) -> infra::Config {
                     },
                     false => SolutionMerging::Forbidden,
                 },
-                s3: solver_config.s3.map(Into::into),
+                archive: match (solver_config.s3, solver_config.local_archive) {
+                    (Some(_), Some(_)) => {
+                        panic!("only one of s3 and local-archive can be configured per solver")
+                    }
+                    (Some(s3), None) => Some(persistence::Archive::S3(s3.into())),
+                    (None, Some(local)) => {
+                        Some(persistence::Archive::Local(persistence::local::Config {
+                            directory: local.directory,
+                            retention: local.retention,
+                        }))
+                    }
+                    (None, None) => None,
+                },
+                ledger: solver_config
+                    .ledger
+                    .map(|ledger| ledger::Config { path: ledger.path }),
                 solver_native_token: solver_config.manage_native_token.to_domain(),
                 quote_tx_origin: solver_config.quote_tx_origin,
                 response_size_limit_max_bytes: solver_config.response_size_limit_max_bytes,
@@ -309,6 +327,25 @@ pub async fn load(chain: Chain, path: &Path) -> infra::Config {
                     },
                 })
                 .collect(),
+            curve: config
+                .liquidity
+                .curve
+                .map(|config| liquidity::config::Curve {
+                    pools: config
+                        .pools
+                        .into_iter()
+                        .map(|pool| liquidity::config::CurvePool {
+                            address: pool.address.into(),
+                            kind: match pool.kind {
+                                file::CurvePoolKind::StableSwap => curve::Kind::StableSwap,
+                                file::CurvePoolKind::StableSwapNg => curve::Kind::StableSwapNg,
+                                file::CurvePoolKind::TwoCrypto => curve::Kind::TwoCrypto,
+                                file::CurvePoolKind::TwoCryptoNg => curve::Kind::TwoCryptoNg,
+                                file::CurvePoolKind::TricryptoNg => curve::Kind::TricryptoNg,
+                            },
+                        })
+                        .collect(),
+                }),
             zeroex: config
                 .liquidity
                 .zeroex
@@ -345,12 +382,23 @@ pub async fn load(chain: Chain, path: &Path) -> infra::Config {
                     .clone()
                     .unwrap_or_else(|| format!("mempool_{index}")),
                 url: mempool.url.clone(),
-                revert_protection: match mempool.mines_reverting_txs {
-                    true => mempool::RevertProtection::Disabled,
-                    false => mempool::RevertProtection::Enabled,
+                // Bundles that would revert are dropped by the builders.
+                revert_protection: match (mempool.mines_reverting_txs, &mempool.bundle) {
+                    (true, None) => mempool::RevertProtection::Disabled,
+                    _ => mempool::RevertProtection::Enabled,
                 },
                 max_additional_tip: mempool.max_additional_tip,
                 additional_tip_percentage: mempool.additional_tip_percentage,
+                kind: match &mempool.bundle {
+                    None => mempool::Kind::Transaction,
+                    Some(bundle) => mempool::Kind::Bundle(mempool::bundle::Config {
+                        relays: bundle.relays.clone(),
+                        auth_key: bundle.auth_key.map(|key| {
+                            PrivateKeySigner::from_bytes(&key)
+                                .expect("bundle auth key should be valid")
+                        }),
+                    }),
+                },
             })
             .collect(),
         simulator: match (config.tenderly, config.enso) {
@@ -393,3 +441,20 @@ pub async fn load(chain: Chain, path: &Path) -> infra::Config {
         tx_gas_limit: config.tx_gas_limit,
     }
 }
+
+async fn load_account(account: file::Account, chain_id: Option<u64>) -> Account {
+    match account {
+        file::Account::PrivateKey(private_key) => PrivateKeySigner::from_bytes(&private_key)
+            .expect("private key should be valid")
+            .into(),
+        file::Account::Kms(arn) => {
+            let sdk_config = alloy::signers::aws::aws_config::load_from_env().await;
+            let client = alloy::signers::aws::aws_sdk_kms::Client::new(&sdk_config);
+            AwsSigner::new(client, arn.0, chain_id)
+                .await
+                .expect("unable to load kms account {arn:?}")
+                .into()
+        }
+        file::Account::Address(address) => Account::Address(address),
+    }
+}
@@ -11,7 +11,7 @@ use {
         default_reward_percentile,
     },
     solver::solver::Arn,
-    std::{collections::HashMap, time::Duration},
+    std::{collections::HashMap, path::PathBuf, time::Duration},
 };
 
 mod load;
@@ -182,6 +182,22 @@ struct Mempool {
     /// assume reverting transactions will get mined eventually.
     #[serde(default = "default_mines_reverting_txs")]
     mines_reverting_txs: bool,
+    /// Submit settlements as bundles to block builder relays instead of
+    /// sending them to the RPC, which is then only used to read the chain
+    /// state. Bundles that would revert are never mined.
+    #[serde(default)]
+    bundle: Option<BundleMempool>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct BundleMempool {
+    /// The builder relays to send `eth_sendBundle` requests to.
+    relays: Vec<Url>,
+    /// The private key signing requests to the relays, which identifies the
+    /// sender to them. Expects a 32-byte hex encoded string. A random key is
+    /// used if unset.
+    auth_key: Option<eth::B256>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -220,6 +236,10 @@ fn default_gas_price_cap() -> eth::U256 {
     eth::U256::from(1000) * eth::U256::from(10).pow(eth::U256::from(9))
 }
 
+fn default_archive_retention() -> Duration {
+    Duration::from_secs(7 * 24 * 60 * 60)
+}
+
 fn default_target_confirm_time() -> Duration {
     Duration::from_secs(30)
 }
@@ -267,6 +287,16 @@ struct SolverConfig {
     /// The account which should be used to sign settlements for this solver.
     account: Account,
 
+    /// Further accounts settlements of this solver can be submitted from, so
+    /// that several settlements can be pending at the same time. Like the
+    /// main account, they have to be allowed to settle auctions.
+    #[serde(default)]
+    submission_accounts: Vec<Account>,
+
+    /// Keeps the native token balances of the solver's accounts topped up.
+    #[serde(default)]
+    account_rebalancing: Option<AccountRebalancing>,
+
     /// Timeout configuration for the solver.
     #[serde(default, flatten)]
     timeouts: Timeouts,
@@ -296,6 +326,16 @@ struct SolverConfig {
     #[serde(default)]
     s3: Option<S3>,
 
+    /// Local directory for storing the auctions in the form they are sent to
+    /// the solver engine, as an alternative to S3.
+    #[serde(default)]
+    local_archive: Option<LocalArchive>,
+
+    /// Where to store the profit and loss ledger of the solver, so that it
+    /// survives restarts.
+    #[serde(default)]
+    ledger: Option<Ledger>,
+
     /// Whether the native token is wrapped or not when sent to the solvers
     #[serde(default)]
     manage_native_token: ManageNativeToken,
@@ -342,6 +382,45 @@ pub struct S3 {
     /// Prepended to the auction id to form the final instance filename on AWS
     /// S3 bucket. Something like "staging/mainnet/"
     pub prefix: String,
+
+    /// Endpoint of an S3 compatible object store (e.g. MinIO) to use instead
+    /// of AWS S3.
+    #[serde(default)]
+    pub endpoint: Option<Url>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LocalArchive {
+    /// The directory archived auctions are stored in, in one subdirectory
+    /// per day.
+    pub directory: PathBuf,
+
+    /// How long archived auctions are kept.
+    #[serde(with = "humantime_serde", default = "default_archive_retention")]
+    pub retention: Duration,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Ledger {
+    /// The file ledger entries are appended to as JSON lines.
+    path: PathBuf,
+}
+
+#[serde_as]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct AccountRebalancing {
+    /// Accounts whose balance drops below this amount in wei get topped up
+    /// by the other accounts, and the solver gets notified about them.
+    #[serde_as(as = "serialize::U256")]
+    min_balance: eth::U256,
+
+    /// The balance in wei accounts get topped up to. Accounts only send funds
+    /// if they keep at least this balance themselves.
+    #[serde_as(as = "serialize::U256")]
+    target_balance: eth::U256,
 }
 
 #[serde_as]
@@ -482,6 +561,10 @@ struct LiquidityConfig {
     #[serde(default)]
     balancer_v2: Vec<BalancerV2Config>,
 
+    /// Liquidity provided by Curve pools.
+    #[serde(default)]
+    curve: Option<CurveConfig>,
+
     /// Liquidity provided by 0x API.
     #[serde(default)]
     zeroex: Option<ZeroExConfig>,
@@ -687,6 +770,33 @@ enum BalancerV2Preset {
     BalancerV2,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct CurveConfig {
+    /// The Curve pools to fetch liquidity for.
+    pools: Vec<CurvePoolConfig>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct CurvePoolConfig {
+    /// The address of the pool contract.
+    address: eth::Address,
+
+    /// The flavour of the pool.
+    kind: CurvePoolKind,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+enum CurvePoolKind {
+    StableSwap,
+    StableSwapNg,
+    TwoCrypto,
+    TwoCryptoNg,
+    TricryptoNg,
+}
+
 fn default_reinit_interval() -> Option<Duration> {
     Some(Duration::from_secs(12 * 60 * 60))
 }
@@ -995,6 +1105,29 @@ enum AtBlock {
 mod tests {
     use super::*;
 
+    #[test]
+    fn curve_liquidity() {
+        let config: LiquidityConfig = toml::from_str(
+            r#"
+            [curve]
+            pools = [
+                { address = "0xbEbc44782C7dB0a1A60Cb6fe97d0b483032FF1C7", kind = "stable-swap" },
+                { address = "0x4eBdF703948ddCEA3B11f675B4D1Fba9d2414A14", kind = "tricrypto-ng" },
+            ]
+        "#,
+        )
+        .unwrap();
+
+        let pools = config.curve.unwrap().pools;
+        assert_eq!(pools.len(), 2);
+        assert_eq!(
+            pools[0].address,
+            alloy::primitives::address!("bEbc44782C7dB0a1A60Cb6fe97d0b483032FF1C7")
+        );
+        assert_eq!(pools[0].kind, CurvePoolKind::StableSwap);
+        assert_eq!(pools[1].kind, CurvePoolKind::TricryptoNg);
+    }
+
     #[test]
     fn gas_estimator_alloy_defaults() {
         let config: GasEstimatorType = toml::from_str(
@@ -0,0 +1,689 @@
+//! Profit and loss ledger of a solver.
+//!
+//! Every revealed solution gets an entry recording what the solver expected
+//! to earn with it: the score it competed with, the value of the fees charged
+//! by its trades and the minimum amounts its interactions promised to send to
+//! the settlement contract. Once the settlement was submitted the entry is
+//! completed from the transaction receipt with the gas actually paid and the
+//! amounts actually received, which gives the slippage realized against the
+//! promised amounts.
+//!
+//! Entries are appended as JSON lines to a file, a later line replacing an
+//! earlier one for the same solution. On startup the file is loaded and
+//! rewritten without the replaced lines. Only the most recent entries are kept
+//! in memory, next to running totals over all of them.
+
+use {
+    crate::{
+        domain::{
+            competition::{auction, solution::Settlement},
+            eth,
+            mempools,
+        },
+        infra::{Ethereum, observe, solver},
+        util::serialize,
+    },
+    alloy::{primitives::I256, rpc::types::TransactionReceipt, sol, sol_types::SolEvent},
+    anyhow::{Context, Result},
+    chrono::{DateTime, Utc},
+    serde::{Deserialize, Serialize},
+    serde_with::{DisplayFromStr, serde_as},
+    std::{
+        collections::{BTreeMap, HashMap, HashSet, VecDeque},
+        io::{BufRead, Write},
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex, mpsc},
+    },
+    tracing::Instrument,
+};
+
+sol! {
+    event Transfer(address indexed from, address indexed to, uint256 value);
+}
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The file the ledger is stored in.
+    pub path: PathBuf,
+}
+
+#[derive(Clone, Debug)]
+pub struct Ledger {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    solver: solver::Name,
+    state: Mutex<State>,
+    /// Sends updated entries to the thread appending them to the ledger file.
+    writer: Option<mpsc::Sender<Entry>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Expectations of the solutions proposed for the most recent auctions,
+    /// kept until they get revealed.
+    expected: VecDeque<Entry>,
+    /// Entries of the most recently revealed solutions, oldest first.
+    entries: VecDeque<Entry>,
+    /// The totals over all entries, including the ones no longer kept.
+    totals: Totals,
+}
+
+/// Number of proposed solutions whose expectations are kept until they get
+/// revealed.
+const MAX_EXPECTATIONS: usize = 20;
+
+/// Number of most recent entries kept to be completed once settled and to be
+/// reported.
+const MAX_ENTRIES: usize = 1_000;
+
+impl Ledger {
+    /// Creates the ledger of a solver, loading the entries recorded by
+    /// previous runs if it is stored in a file.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the ledger file can't be read.
+    pub fn new(solver: solver::Name, config: Option<&Config>) -> Self {
+        let mut entries = Vec::new();
+        let writer = config.map(|config| {
+            entries = load(&config.path).expect("ledger file should be readable");
+            if let Err(err) = rewrite(&config.path, &entries) {
+                tracing::warn!(?err, path = ?config.path, "failed to compact ledger file");
+            }
+            let (sender, receiver) = mpsc::channel::<Entry>();
+            let path = config.path.clone();
+            std::thread::spawn(move || {
+                for entry in receiver {
+                    if let Err(err) = append(&path, &entry) {
+                        tracing::warn!(?err, ?path, "failed to write ledger entry");
+                    }
+                }
+            });
+            sender
+        });
+        let totals = Totals::new(&entries);
+        observe::ledger(&solver, &totals);
+        let recent = entries.len().saturating_sub(MAX_ENTRIES);
+        Self {
+            inner: Arc::new(Inner {
+                solver,
+                state: Mutex::new(State {
+                    expected: Default::default(),
+                    entries: entries.drain(recent..).collect(),
+                    totals,
+                }),
+                writer,
+            }),
+        }
+    }
+
+    /// Records what the solver expects to earn with the solution it proposes
+    /// for an auction. It only makes it into the ledger if the solution gets
+    /// revealed.
+    pub fn solved(&self, settlement: &Settlement, score: eth::Ether, prices: &auction::Prices) {
+        let fee_revenue = settlement
+            .fees()
+            .into_iter()
+            .filter_map(|fee| Some(prices.get(&fee.token)?.in_eth(fee.amount)))
+            .sum::<eth::Ether>();
+        let mut promised = BTreeMap::<eth::TokenAddress, eth::U256>::new();
+        for output in settlement
+            .interactions()
+            .iter()
+            .filter(|interaction| !interaction.internalize())
+            .flat_map(|interaction| interaction.outputs())
+        {
+            let amount = promised.entry(output.token).or_default();
+            *amount = amount.saturating_add(output.amount.0);
+        }
+
+        let entry = Entry {
+            auction_id: settlement.auction_id.0,
+            solution_id: settlement.solution().get(),
+            revealed_at: Utc::now(),
+            score: score.0,
+            fee_revenue: fee_revenue.0,
+            outputs: promised
+                .into_iter()
+                .map(|(token, promised)| Output {
+                    token: token.into(),
+                    promised,
+                    received: None,
+                    native_price: prices.get(&token).map(|price| price.0.0),
+                })
+                .collect(),
+            settlement: None,
+        };
+        let mut state = self.inner.state.lock().unwrap();
+        state.expected.push_front(entry);
+        state.expected.truncate(MAX_EXPECTATIONS);
+    }
+
+    /// Starts the entry of a revealed solution.
+    pub fn revealed(&self, auction_id: auction::Id, solution_id: u64) {
+        let mut state = self.inner.state.lock().unwrap();
+        let Some(index) = state
+            .expected
+            .iter()
+            .position(|entry| entry.is(auction_id, solution_id))
+        else {
+            tracing::debug!(%auction_id, solution_id, "no expectations for revealed solution");
+            return;
+        };
+        let mut entry = state.expected.remove(index).unwrap();
+        entry.revealed_at = Utc::now();
+        self.store(&mut state, entry);
+    }
+
+    /// Completes the entry of a settlement with the outcome of its
+    /// submission. The receipt of the settlement transaction is fetched in
+    /// the background.
+    pub fn settled(
+        &self,
+        eth: &Ethereum,
+        settlement: &Settlement,
+        executed: &Result<eth::TxId, mempools::Error>,
+    ) {
+        let tx_hash = match executed {
+            Ok(tx_hash) | Err(mempools::Error::Revert { tx_id: tx_hash, .. }) => {
+                Some(tx_hash.clone())
+            }
+            Err(_) => None,
+        };
+        let this = self.clone();
+        let eth = eth.clone();
+        let auction_id = settlement.auction_id;
+        let solution_id = settlement.solution().get();
+        let owners = settlement.owners();
+        tokio::spawn(
+            async move {
+                let receipt = match &tx_hash {
+                    Some(tx_hash) => eth
+                        .transaction_receipt(tx_hash)
+                        .await
+                        .inspect_err(|err| {
+                            tracing::warn!(?err, ?tx_hash, "failed to fetch settlement receipt")
+                        })
+                        .ok()
+                        .flatten(),
+                    None => None,
+                };
+                let settlement_contract = *eth.contracts().settlement().address();
+                let mut state = this.inner.state.lock().unwrap();
+                let Some(mut entry) = state
+                    .entries
+                    .iter()
+                    .rev()
+                    .find(|entry| entry.is(auction_id, solution_id))
+                    .cloned()
+                else {
+                    tracing::debug!(%auction_id, solution_id, "settled solution was not revealed");
+                    return;
+                };
+                entry.complete(receipt.as_ref(), settlement_contract, &owners);
+                this.store(&mut state, entry);
+            }
+            .instrument(tracing::Span::current()),
+        );
+    }
+
+    /// The totals of the ledger and its most recent entries, most recent
+    /// first.
+    pub fn report(&self, limit: usize) -> Report {
+        let state = self.inner.state.lock().unwrap();
+        Report {
+            totals: state.totals.clone(),
+            entries: state.entries.iter().rev().take(limit).cloned().collect(),
+        }
+    }
+
+    /// Inserts or replaces an entry, persists it and updates the metrics.
+    fn store(&self, state: &mut State, entry: Entry) {
+        if let Some(writer) = &self.inner.writer {
+            // The writer thread only stops when the sender is dropped.
+            let _ = writer.send(entry.clone());
+        }
+        state.store(entry);
+        observe::ledger(&self.inner.solver, &state.totals);
+    }
+}
+
+impl State {
+    /// Inserts or replaces an entry, evicting the oldest entry beyond
+    /// [`MAX_ENTRIES`] and keeping the totals up to date.
+    fn store(&mut self, entry: Entry) {
+        self.totals.add(&entry);
+        match self
+            .entries
+            .iter_mut()
+            .rev()
+            .find(|existing| existing.is(auction::Id(entry.auction_id), entry.solution_id))
+        {
+            Some(existing) => {
+                self.totals.remove(existing);
+                *existing = entry;
+            }
+            None => {
+                self.entries.push_back(entry);
+                if self.entries.len() > MAX_ENTRIES {
+                    self.entries.pop_front();
+                }
+            }
+        }
+    }
+}
+
+/// What a revealed solution was expected to earn and, once settled, what it
+/// actually earned.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub auction_id: i64,
+    pub solution_id: u64,
+    pub revealed_at: DateTime<Utc>,
+    /// The score the solution competed with, in wei.
+    #[serde_as(as = "serialize::U256")]
+    pub score: eth::U256,
+    /// The value of the fees charged by the solution's trades, in wei.
+    #[serde_as(as = "serialize::U256")]
+    pub fee_revenue: eth::U256,
+    /// The tokens the solution's interactions send to the settlement
+    /// contract.
+    pub outputs: Vec<Output>,
+    /// The outcome of the settlement, once it was submitted.
+    pub settlement: Option<Outcome>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Output {
+    pub token: eth::Address,
+    /// The minimum amount the interactions promised to send.
+    #[serde_as(as = "serialize::U256")]
+    pub promised: eth::U256,
+    /// The amount actually received by the settlement contract, once
+    /// settled.
+    #[serde_as(as = "Option<serialize::U256>")]
+    pub received: Option<eth::U256>,
+    /// The native price of the token in the auction.
+    #[serde_as(as = "Option<serialize::U256>")]
+    pub native_price: Option<eth::U256>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outcome {
+    pub status: Status,
+    /// The settlement transaction, unless the settlement failed before
+    /// anything got mined.
+    pub tx_hash: Option<eth::B256>,
+    pub gas_used: u64,
+    #[serde_as(as = "serialize::U256")]
+    pub effective_gas_price: eth::U256,
+    /// The gas paid for the settlement transaction, in wei.
+    #[serde_as(as = "serialize::U256")]
+    pub gas_cost: eth::U256,
+    /// The value of the amounts received beyond the promised amounts, in wei.
+    /// Negative if less was received than promised.
+    #[serde_as(as = "DisplayFromStr")]
+    pub slippage: I256,
+    /// The fee revenue and slippage of a successful settlement minus the gas
+    /// paid, in wei.
+    #[serde_as(as = "DisplayFromStr")]
+    pub net: I256,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Status {
+    Success,
+    Reverted,
+    /// The settlement transaction wasn't mined, e.g. because it expired.
+    Failed,
+}
+
+impl Entry {
+    fn is(&self, auction_id: auction::Id, solution_id: u64) -> bool {
+        self.auction_id == auction_id.0 && self.solution_id == solution_id
+    }
+
+    /// Fills in the outcome of the settlement from the receipt of its
+    /// transaction, if it got mined.
+    fn complete(
+        &mut self,
+        receipt: Option<&TransactionReceipt>,
+        settlement_contract: eth::Address,
+        owners: &HashSet<eth::Address>,
+    ) {
+        let Some(receipt) = receipt else {
+            self.settlement = Some(Outcome {
+                status: Status::Failed,
+                tx_hash: None,
+                gas_used: 0,
+                effective_gas_price: eth::U256::ZERO,
+                gas_cost: eth::U256::ZERO,
+                slippage: I256::ZERO,
+                net: I256::ZERO,
+            });
+            return;
+        };
+
+        let effective_gas_price = eth::U256::from(receipt.effective_gas_price);
+        let gas_cost = eth::U256::from(receipt.gas_used).saturating_mul(effective_gas_price);
+        let status = if receipt.status() {
+            Status::Success
+        } else {
+            Status::Reverted
+        };
+        let mut slippage = I256::ZERO;
+        if status == Status::Success {
+            let received = received(receipt, settlement_contract, owners);
+            for output in &mut self.outputs {
+                let amount = received.get(&output.token).copied().unwrap_or_default();
+                output.received = Some(amount);
+                if let Some(price) = output.native_price.map(auction::Price::from) {
+                    slippage = slippage
+                        .saturating_add(signed(price.in_eth(amount.into()).0))
+                        .saturating_sub(signed(price.in_eth(output.promised.into()).0));
+                }
+            }
+        }
+        let net = match status {
+            Status::Success => signed(self.fee_revenue).saturating_add(slippage),
+            Status::Reverted | Status::Failed => I256::ZERO,
+        }
+        .saturating_sub(signed(gas_cost));
+
+        self.settlement = Some(Outcome {
+            status,
+            tx_hash: Some(receipt.transaction_hash),
+            gas_used: receipt.gas_used,
+            effective_gas_price,
+            gas_cost,
+            slippage,
+            net,
+        });
+    }
+}
+
+/// The amounts per token transferred to the settlement contract by anyone
+/// but the traders, i.e. by the interactions.
+fn received(
+    receipt: &TransactionReceipt,
+    settlement_contract: eth::Address,
+    owners: &HashSet<eth::Address>,
+) -> HashMap<eth::Address, eth::U256> {
+    let mut received = HashMap::<_, eth::U256>::new();
+    for log in receipt.inner.logs() {
+        let Ok(transfer) = Transfer::decode_log(&log.inner) else {
+            continue;
+        };
+        if transfer.to == settlement_contract && !owners.contains(&transfer.from) {
+            let amount = received.entry(transfer.address).or_default();
+            *amount = amount.saturating_add(transfer.value);
+        }
+    }
+    received
+}
+
+fn signed(value: eth::U256) -> I256 {
+    I256::try_from(value).unwrap_or(I256::MAX)
+}
+
+/// The sums over all entries of a ledger.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Totals {
+    pub revealed: usize,
+    pub settled: usize,
+    pub reverted: usize,
+    pub failed: usize,
+    #[serde_as(as = "serialize::U256")]
+    pub score: eth::U256,
+    /// The fee revenue of all revealed solutions.
+    #[serde_as(as = "serialize::U256")]
+    pub expected_fee_revenue: eth::U256,
+    /// The fee revenue of the successfully settled solutions.
+    #[serde_as(as = "serialize::U256")]
+    pub fee_revenue: eth::U256,
+    #[serde_as(as = "serialize::U256")]
+    pub gas_cost: eth::U256,
+    #[serde_as(as = "DisplayFromStr")]
+    pub slippage: I256,
+    #[serde_as(as = "DisplayFromStr")]
+    pub net: I256,
+}
+
+impl Totals {
+    fn new<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> Self {
+        let mut totals = Self::default();
+        for entry in entries {
+            totals.add(entry);
+        }
+        totals
+    }
+
+    /// Adds an entry to the totals.
+    fn add(&mut self, entry: &Entry) {
+        self.revealed += 1;
+        self.score = self.score.saturating_add(entry.score);
+        self.expected_fee_revenue = self.expected_fee_revenue.saturating_add(entry.fee_revenue);
+        let Some(outcome) = &entry.settlement else {
+            return;
+        };
+        match outcome.status {
+            Status::Success => {
+                self.settled += 1;
+                self.fee_revenue = self.fee_revenue.saturating_add(entry.fee_revenue);
+            }
+            Status::Reverted => self.reverted += 1,
+            Status::Failed => self.failed += 1,
+        }
+        self.gas_cost = self.gas_cost.saturating_add(outcome.gas_cost);
+        self.slippage = self.slippage.saturating_add(outcome.slippage);
+        self.net = self.net.saturating_add(outcome.net);
+    }
+
+    /// Removes an entry previously added to the totals, e.g. when it gets
+    /// replaced.
+    fn remove(&mut self, entry: &Entry) {
+        self.revealed = self.revealed.saturating_sub(1);
+        self.score = self.score.saturating_sub(entry.score);
+        self.expected_fee_revenue = self.expected_fee_revenue.saturating_sub(entry.fee_revenue);
+        let Some(outcome) = &entry.settlement else {
+            return;
+        };
+        match outcome.status {
+            Status::Success => {
+                self.settled = self.settled.saturating_sub(1);
+                self.fee_revenue = self.fee_revenue.saturating_sub(entry.fee_revenue);
+            }
+            Status::Reverted => self.reverted = self.reverted.saturating_sub(1),
+            Status::Failed => self.failed = self.failed.saturating_sub(1),
+        }
+        self.gas_cost = self.gas_cost.saturating_sub(outcome.gas_cost);
+        self.slippage = self.slippage.saturating_sub(outcome.slippage);
+        self.net = self.net.saturating_sub(outcome.net);
+    }
+
+    /// The amounts of the totals in ETH, by name.
+    pub fn in_eth(&self) -> [(&'static str, f64); 6] {
+        let ether = |wei: eth::U256| f64::from(wei) / 1e18;
+        let signed_ether = |wei: I256| {
+            let (sign, abs) = wei.into_sign_and_abs();
+            if sign.is_negative() {
+                -ether(abs)
+            } else {
+                ether(abs)
+            }
+        };
+        [
+            ("score", ether(self.score)),
+            ("expected_fee_revenue", ether(self.expected_fee_revenue)),
+            ("fee_revenue", ether(self.fee_revenue)),
+            ("gas_cost", ether(self.gas_cost)),
+            ("slippage", signed_ether(self.slippage)),
+            ("net", signed_ether(self.net)),
+        ]
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub totals: Totals,
+    pub entries: Vec<Entry>,
+}
+
+/// Reads the entries of a ledger file. Later lines replace earlier ones for
+/// the same solution.
+fn load(path: &Path) -> Result<Vec<Entry>> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).with_context(|| format!("failed to open {}", path.display())),
+    };
+    let mut entries = Vec::<Entry>::new();
+    let mut positions = HashMap::new();
+    for (i, line) in std::io::BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: Entry = serde_json::from_str(&line)
+            .with_context(|| format!("invalid entry on line {}", i + 1))?;
+        match positions.get(&(entry.auction_id, entry.solution_id)) {
+            Some(&position) => entries[position] = entry,
+            None => {
+                positions.insert((entry.auction_id, entry.solution_id), entries.len());
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Replaces the ledger file with one line per entry, dropping the lines of
+/// replaced entries.
+fn rewrite(path: &Path, entries: &[Entry]) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let tmp = path.with_extension("tmp");
+    let mut file = std::io::BufWriter::new(std::fs::File::create(&tmp)?);
+    for entry in entries {
+        serde_json::to_writer(&mut file, entry)?;
+        file.write_all(b"\n")?;
+    }
+    file.into_inner()?.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn append(path: &Path, entry: &Entry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+    file.write_all(&line)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(solution_id: u64, settlement: Option<Outcome>) -> Entry {
+        Entry {
+            auction_id: 1,
+            solution_id,
+            revealed_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            score: eth::U256::from(10),
+            fee_revenue: eth::U256::from(5),
+            outputs: vec![Output {
+                token: eth::Address::repeat_byte(1),
+                promised: eth::U256::from(100),
+                received: None,
+                native_price: Some(eth::U256::from(10).pow(eth::U256::from(18))),
+            }],
+            settlement,
+        }
+    }
+
+    fn outcome(status: Status, gas_cost: u64, slippage: i64, net: i64) -> Outcome {
+        Outcome {
+            status,
+            tx_hash: Some(eth::B256::repeat_byte(2)),
+            gas_used: gas_cost,
+            effective_gas_price: eth::U256::from(1),
+            gas_cost: eth::U256::from(gas_cost),
+            slippage: I256::try_from(slippage).unwrap(),
+            net: I256::try_from(net).unwrap(),
+        }
+    }
+
+    #[test]
+    fn later_lines_replace_earlier_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ledger/solver.jsonl");
+        assert!(load(&path).unwrap().is_empty());
+
+        let settled = entry(1, Some(outcome(Status::Success, 3, -1, 1)));
+        let reverted = entry(2, Some(outcome(Status::Reverted, 4, 0, -4)));
+        append(&path, &entry(1, None)).unwrap();
+        append(&path, &entry(2, None)).unwrap();
+        append(&path, &settled).unwrap();
+        append(&path, &reverted).unwrap();
+
+        let entries = load(&path).unwrap();
+        assert_eq!(entries, [settled.clone(), reverted.clone()]);
+        rewrite(&path, &entries).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+        assert_eq!(load(&path).unwrap(), [settled, reverted]);
+        assert_eq!(
+            Totals::new(&entries),
+            Totals {
+                revealed: 2,
+                settled: 1,
+                reverted: 1,
+                failed: 0,
+                score: eth::U256::from(20),
+                expected_fee_revenue: eth::U256::from(10),
+                fee_revenue: eth::U256::from(5),
+                gas_cost: eth::U256::from(7),
+                slippage: I256::try_from(-1).unwrap(),
+                net: I256::try_from(-3).unwrap(),
+            }
+        );
+    }
+
+    #[test]
+    fn keeps_totals_over_evicted_entries() {
+        let mut state = State::default();
+        for solution_id in 0..=MAX_ENTRIES as u64 {
+            state.store(entry(solution_id, None));
+        }
+        let settled = entry(MAX_ENTRIES as u64, Some(outcome(Status::Success, 3, -1, 1)));
+        state.store(settled.clone());
+
+        assert_eq!(state.entries.len(), MAX_ENTRIES);
+        assert_eq!(state.entries.front().unwrap().solution_id, 1);
+        assert_eq!(state.entries.back(), Some(&settled));
+        let mut all = (0..MAX_ENTRIES as u64)
+            .map(|solution_id| entry(solution_id, None))
+            .collect::<Vec<_>>();
+        all.push(settled);
+        assert_eq!(state.totals, Totals::new(&all));
+    }
+}
@@ -1,5 +1,8 @@
 use {
-    crate::domain::eth::{self, ContractAddress},
+    crate::domain::{
+        eth::{self, ContractAddress},
+        liquidity::curve,
+    },
     alloy::primitives::Address,
     chain::Chain,
     contracts::alloy::BalancerV2Vault,
@@ -40,6 +43,9 @@ pub struct Config {
     /// for.
     pub balancer_v2: Vec<BalancerV2>,
 
+    /// Curve pools to fetch liquidity for.
+    pub curve: Option<Curve>,
+
     /// 0x liquidity fetcher.
     pub zeroex: Option<ZeroEx>,
 }
@@ -292,6 +298,24 @@ impl BalancerV2 {
     }
 }
 
+/// Curve liquidity fetching options.
+#[derive(Clone, Debug)]
+pub struct Curve {
+    /// The pools to index. Curve pools aren't discovered automatically, so
+    /// only the listed pools are used for liquidity.
+    pub pools: Vec<CurvePool>,
+}
+
+/// A Curve pool to fetch liquidity for.
+#[derive(Clone, Copy, Debug)]
+pub struct CurvePool {
+    /// The address of the pool contract.
+    pub address: eth::ContractAddress,
+    /// The flavour of the pool, which determines how it is priced and
+    /// traded.
+    pub kind: curve::Kind,
+}
+
 /// ZeroEx liquidity fetching options.
 #[derive(Clone, Debug)]
 pub struct ZeroEx {
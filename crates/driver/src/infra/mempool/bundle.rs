@@ -0,0 +1,181 @@
+//! Private submission of settlements as bundles to block builder relays.
+//!
+//! Bundles are sent with `eth_sendBundle` and only target a single block, so
+//! they have to be sent again for every block until they are included.
+//! Builders drop bundles whose transactions revert and never share them
+//! with the public mempool.
+
+use {
+    crate::domain::{BlockNo, eth},
+    alloy::{
+        primitives::{Bytes, keccak256},
+        signers::{Signer, local::PrivateKeySigner},
+    },
+    futures::future::join_all,
+    serde::{Deserialize, Serialize},
+    std::time::Duration,
+    url::Url,
+};
+
+/// Upper bound on the time a relay gets to accept a bundle.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The builder relays bundles are sent to.
+    pub relays: Vec<Url>,
+    /// The key signing requests to the relays, which some of them use to
+    /// identify and rate limit searchers. A random key is used if unset.
+    pub auth_key: Option<PrivateKeySigner>,
+}
+
+/// Client sending bundles to the configured relays.
+#[derive(Debug, Clone)]
+pub struct Relays {
+    client: reqwest::Client,
+    relays: Vec<Url>,
+    signer: PrivateKeySigner,
+}
+
+impl Relays {
+    pub fn new(config: Config) -> Self {
+        Self {
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap(),
+            relays: config.relays,
+            signer: config.auth_key.unwrap_or_else(PrivateKeySigner::random),
+        }
+    }
+
+    /// Sends a bundle of signed transactions targeting `block` to all relays.
+    /// Succeeds if at least one relay accepted the bundle.
+    pub async fn send(
+        &self,
+        txs: &[Bytes],
+        block: BlockNo,
+    ) -> Result<eth::BundleHash, anyhow::Error> {
+        let body = serde_json::to_string(&Request::new(txs, block))?;
+        let signature = self.sign(&body).await?;
+
+        let results = join_all(self.relays.iter().map(|relay| {
+            let body = body.clone();
+            let signature = signature.clone();
+            async move {
+                let response: Response = self
+                    .client
+                    .post(relay.clone())
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .header("X-Flashbots-Signature", signature)
+                    .body(body)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                match response.error {
+                    Some(err) => Err(anyhow::anyhow!("relay rejected bundle: {err}")),
+                    None => Ok(()),
+                }
+            }
+        }))
+        .await;
+
+        let mut accepted = false;
+        for (relay, result) in self.relays.iter().zip(results) {
+            match result {
+                Ok(()) => accepted = true,
+                Err(err) => tracing::debug!(%relay, ?err, "failed to send bundle"),
+            }
+        }
+        anyhow::ensure!(accepted, "no relay accepted the bundle");
+        Ok(hash(txs))
+    }
+
+    /// Signs a request body the way relays authenticate searchers: the
+    /// hex encoded hash of the body is signed as a personal message.
+    async fn sign(&self, body: &str) -> Result<String, anyhow::Error> {
+        let message = const_hex::encode_prefixed(keccak256(body));
+        let signature = self.signer.sign_message(message.as_bytes()).await?;
+        Ok(format!(
+            "{}:{}",
+            self.signer.address(),
+            const_hex::encode_prefixed(signature.as_bytes())
+        ))
+    }
+}
+
+/// The hash of a bundle, which relays derive from the hashes of its
+/// transactions.
+fn hash(txs: &[Bytes]) -> eth::BundleHash {
+    let hashes = txs
+        .iter()
+        .flat_map(|tx| keccak256(tx).0)
+        .collect::<Vec<_>>();
+    eth::BundleHash(keccak256(hashes))
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'static str,
+    params: [Bundle<'a>; 1],
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Bundle<'a> {
+    txs: &'a [Bytes],
+    block_number: String,
+}
+
+impl<'a> Request<'a> {
+    fn new(txs: &'a [Bytes], block: BlockNo) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id: 1,
+            method: "eth_sendBundle",
+            params: [Bundle {
+                txs,
+                block_number: format!("{:#x}", block),
+            }],
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct Response {
+    error: Option<serde_json::Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_request() {
+        let txs = [Bytes::from(vec![0x02, 0xab])];
+        let request = serde_json::to_value(Request::new(&txs, 20_000_000)).unwrap();
+        assert_eq!(
+            request,
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_sendBundle",
+                "params": [{
+                    "txs": ["0x02ab"],
+                    "blockNumber": "0x1312d00",
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn hashes_transaction_hashes() {
+        let txs = [Bytes::from(vec![1]), Bytes::from(vec![2])];
+        let expected = keccak256([keccak256([1]).0, keccak256([2]).0].concat());
+        assert_eq!(hash(&txs).0, expected);
+    }
+}
@@ -1,13 +1,14 @@
 use {
     crate::{
         boundary::unbuffered_web3_client,
-        domain::{eth, mempools},
+        domain::{BlockNo, eth, mempools},
         infra::{self, solver::Account},
     },
     alloy::{
         consensus::Transaction,
-        eips::{BlockNumberOrTag, eip1559::Eip1559Estimation},
-        primitives::Address,
+        eips::{BlockNumberOrTag, eip1559::Eip1559Estimation, eip2718::Encodable2718},
+        network::TransactionBuilder,
+        primitives::{Address, Bytes},
         providers::{Provider, ext::TxPoolApi},
         rpc::types::TransactionRequest,
     },
@@ -18,6 +19,8 @@ use {
     url::Url,
 };
 
+pub mod bundle;
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub min_priority_fee: eth::U256,
@@ -32,6 +35,7 @@ pub struct Config {
     pub revert_protection: RevertProtection,
     pub max_additional_tip: eth::U256,
     pub additional_tip_percentage: f64,
+    pub kind: Kind,
 }
 
 #[cfg(test)]
@@ -48,10 +52,21 @@ impl Config {
             revert_protection: infra::mempool::RevertProtection::Disabled,
             nonce_block_number: None,
             url,
+            kind: Kind::Transaction,
         }
     }
 }
 
+/// How settlements are handed to the mempool.
+#[derive(Debug, Clone)]
+pub enum Kind {
+    /// Signed transactions are sent to the RPC with `eth_sendRawTransaction`.
+    Transaction,
+    /// Signed transactions are sent as bundles to block builder relays. The
+    /// RPC is only used to read the chain state.
+    Bundle(bundle::Config),
+}
+
 /// Don't submit transactions with high revert risk (i.e. transactions
 /// that interact with on-chain AMMs) to the public mempool.
 /// This can be enabled to avoid MEV when private transaction
@@ -68,6 +83,8 @@ pub struct Mempool {
     transport: Web3,
     config: Config,
     last_submissions: Arc<DashMap<Address, Submission>>,
+    /// The relays bundles are sent to, for bundle mempools.
+    relays: Option<bundle::Relays>,
 }
 
 #[derive(Debug, Clone)]
@@ -89,10 +106,15 @@ impl Mempool {
         for account in solver_accounts {
             transport.wallet.register_signer(account);
         }
+        let relays = match &config.kind {
+            Kind::Transaction => None,
+            Kind::Bundle(bundle) => Some(bundle::Relays::new(bundle.clone())),
+        };
         Self {
             transport,
             config,
             last_submissions: Default::default(),
+            relays,
         }
     }
 
@@ -123,23 +145,11 @@ impl Mempool {
         tx: eth::Tx,
         gas_price: Eip1559Estimation,
         gas_limit: eth::Gas,
-        solver: &infra::Solver,
         nonce: u64,
     ) -> Result<eth::TxId, mempools::Error> {
-        let max_fee_per_gas = gas_price.max_fee_per_gas;
-        let max_priority_fee_per_gas = gas_price.max_priority_fee_per_gas;
         let gas_limit = gas_limit.0.try_into().map_err(anyhow::Error::from)?;
-
-        let tx_request = TransactionRequest::default()
-            .from(solver.address())
-            .to(tx.to)
-            .nonce(nonce)
-            .max_fee_per_gas(max_fee_per_gas)
-            .max_priority_fee_per_gas(max_priority_fee_per_gas)
-            .gas_limit(gas_limit)
-            .input(tx.input.0.into())
-            .value(tx.value.0)
-            .access_list(tx.access_list.into());
+        let from = tx.from;
+        let tx_request = Self::request(tx, gas_price, gas_limit, nonce);
 
         let submission = self
             .transport
@@ -154,16 +164,16 @@ impl Mempool {
                     ?nonce,
                     ?gas_price,
                     ?gas_limit,
-                    solver = ?solver.address(),
+                    solver = ?from,
                     "successfully submitted tx to mempool"
                 );
                 self.last_submissions
-                    .insert(solver.address(), Submission { nonce, gas_price });
+                    .insert(from, Submission { nonce, gas_price });
                 Ok(eth::TxId(*tx.tx_hash()))
             }
             Err(err) => {
                 // log pending tx in case we failed to replace a pending tx
-                let last_submission = self.last_submission(solver.address());
+                let last_submission = self.last_submission(from);
 
                 tracing::debug!(
                     ?err,
@@ -171,7 +181,7 @@ impl Mempool {
                     ?nonce,
                     ?last_submission,
                     ?gas_limit,
-                    solver = ?solver.address(),
+                    solver = ?from,
                     "failed to submit tx to mempool"
                 );
                 Err(mempools::Error::Other(err))
@@ -179,6 +189,64 @@ impl Mempool {
         }
     }
 
+    /// Signs a transaction without submitting it, e.g. to send it as part of
+    /// a bundle. Returns the raw signed transaction and its hash.
+    pub async fn sign(
+        &self,
+        tx: eth::Tx,
+        gas_price: Eip1559Estimation,
+        gas_limit: eth::Gas,
+        nonce: u64,
+    ) -> Result<(Bytes, eth::TxId), mempools::Error> {
+        let gas_limit = gas_limit.0.try_into().map_err(anyhow::Error::from)?;
+        let chain_id = self
+            .transport
+            .alloy
+            .get_chain_id()
+            .await
+            .context("failed to fetch chain id")?;
+        let signed = Self::request(tx, gas_price, gas_limit, nonce)
+            .with_chain_id(chain_id)
+            .build(&self.transport.wallet)
+            .await
+            .context("failed to sign tx")?;
+        Ok((signed.encoded_2718().into(), eth::TxId(*signed.tx_hash())))
+    }
+
+    /// Sends signed transactions as a bundle targeting `block` to the
+    /// relays of a bundle mempool.
+    pub async fn send_bundle(
+        &self,
+        txs: &[Bytes],
+        block: BlockNo,
+    ) -> Result<eth::BundleHash, mempools::Error> {
+        let relays = self
+            .relays
+            .as_ref()
+            .context("mempool does not submit bundles")?;
+        let bundle = relays.send(txs, block).await?;
+        tracing::debug!(?bundle, block, "successfully sent bundle to relays");
+        Ok(bundle)
+    }
+
+    fn request(
+        tx: eth::Tx,
+        gas_price: Eip1559Estimation,
+        gas_limit: u64,
+        nonce: u64,
+    ) -> TransactionRequest {
+        TransactionRequest::default()
+            .from(tx.from)
+            .to(tx.to)
+            .nonce(nonce)
+            .max_fee_per_gas(gas_price.max_fee_per_gas)
+            .max_priority_fee_per_gas(gas_price.max_priority_fee_per_gas)
+            .gas_limit(gas_limit)
+            .input(tx.input.0.into())
+            .value(tx.value.0)
+            .access_list(tx.access_list.into())
+    }
+
     /// Queries the mempool for a pending transaction of the given solver and
     /// nonce.
     pub async fn find_pending_tx_in_mempool(
@@ -214,6 +282,11 @@ impl Mempool {
         &self.config
     }
 
+    /// Whether settlements are submitted as bundles to builder relays.
+    pub fn submits_bundles(&self) -> bool {
+        self.relays.is_some()
+    }
+
     pub fn reverts_can_get_mined(&self) -> bool {
         matches!(
             self.config.revert_protection,
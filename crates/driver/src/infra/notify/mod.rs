@@ -126,6 +126,15 @@ pub fn executed(
     );
 }
 
+/// One of the solver's accounts dropped below the configured minimum balance.
+pub fn insufficient_balance(solver: &Solver, required: eth::Ether) {
+    solver.notify(
+        None,
+        None,
+        notification::Kind::SolverAccountInsufficientBalance(required),
+    );
+}
+
 pub fn duplicated_solution_id(
     solver: &Solver,
     auction_id: Option<auction::Id>,
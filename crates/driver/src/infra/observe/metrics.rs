@@ -25,6 +25,9 @@ pub struct Metrics {
     /// atempted and the error detection.
     #[metric(labels("mempool", "result"))]
     pub mempool_submission_results_blocks_passed: prometheus::IntCounterVec,
+    /// The totals of the solvers' profit and loss ledgers, in ETH.
+    #[metric(labels("solver", "component"))]
+    pub ledger: prometheus::GaugeVec,
     /// How many orders detected by specific solver and strategy.
     #[metric(labels("solver"))]
     pub bad_orders_detected: prometheus::IntCounterVec,
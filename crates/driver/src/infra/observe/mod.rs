@@ -20,7 +20,7 @@ use {
             quote::{self, Quote},
             time::{Deadline, Remaining},
         },
-        infra::solver,
+        infra::{ledger, solver},
         util::http,
     },
     ethrpc::block_stream::BlockInfo,
@@ -191,6 +191,16 @@ pub fn revealed(solver: &solver::Name, result: &Result<competition::Revealed, co
     }
 }
 
+/// Observe the totals of a solver's profit and loss ledger.
+pub fn ledger(solver: &solver::Name, totals: &ledger::Totals) {
+    for (component, value) in totals.in_eth() {
+        metrics::get()
+            .ledger
+            .with_label_values(&[solver.as_str(), component])
+            .set(value);
+    }
+}
+
 /// Observe that the settlement process is about to start.
 pub fn settling() {
     tracing::trace!("settling solution");
@@ -329,6 +339,7 @@ pub fn mempool_executed(
         Ok(submission) => {
             tracing::info!(
                 txid = ?submission.tx_hash,
+                bundles = ?submission.bundles,
                 %mempool,
                 ?settlement,
                 "sending transaction via mempool succeeded",
@@ -379,9 +390,9 @@ pub fn mempool_executed(
             reverted_at_block,
         }) => Some(("Revert", submitted_at_block, reverted_at_block)),
         Err(mempools::Error::Expired {
-            tx_id: _,
             submitted_at_block,
             submission_deadline,
+            ..
         }) => Some(("Expired", submitted_at_block, submission_deadline)),
         Err(mempools::Error::Other(_)) => None,
         Err(mempools::Error::Disabled) => None,
@@ -0,0 +1,205 @@
+//! Archive in a local directory, for deployments without an object store.
+//!
+//! Objects are stored as gzipped JSON files in one subdirectory per (UTC)
+//! day. Whenever a new day starts, the subdirectories of days past the
+//! retention period are deleted.
+
+use {
+    anyhow::{Context, Result},
+    chrono::{NaiveDate, Utc},
+    flate2::{Compression, read::GzDecoder, write::GzEncoder},
+    std::{
+        io::Write,
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex},
+        time::Duration,
+    },
+};
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// The directory the archive is stored in.
+    pub directory: PathBuf,
+    /// How long archived objects are kept.
+    pub retention: Duration,
+}
+
+#[derive(Clone, Debug)]
+pub struct Directory {
+    config: Config,
+    /// The day objects were last stored on, to prune the archive once a day.
+    day: Arc<Mutex<Option<NaiveDate>>>,
+}
+
+impl Directory {
+    pub fn new(config: Config) -> Result<Self> {
+        std::fs::create_dir_all(&config.directory).with_context(|| {
+            format!(
+                "failed to create archive directory {}",
+                config.directory.display()
+            )
+        })?;
+        Ok(Self {
+            config,
+            day: Default::default(),
+        })
+    }
+
+    /// Stores the JSON encoded body under the given key. Returns the path of
+    /// the written file.
+    pub async fn store(&self, key: &str, body: serde_json::Value) -> Result<String> {
+        let this = self.clone();
+        let key = key.to_owned();
+        tokio::task::spawn_blocking(move || {
+            this.store_blocking(&key, &body, Utc::now().date_naive())
+        })
+        .await?
+    }
+
+    fn store_blocking(
+        &self,
+        key: &str,
+        body: &serde_json::Value,
+        today: NaiveDate,
+    ) -> Result<String> {
+        let new_day = {
+            let mut day = self.day.lock().unwrap();
+            day.replace(today) != Some(today)
+        };
+        if new_day {
+            self.prune(today);
+        }
+
+        let dir = self.config.directory.join(today.to_string());
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{key}.json.gz"));
+        // Write to a temporary file first so readers never see partial files.
+        let tmp = path.with_extension("gz.tmp");
+        let mut encoder = GzEncoder::new(std::fs::File::create(&tmp)?, Compression::default());
+        serde_json::to_writer(&mut encoder, body)?;
+        encoder.finish()?.flush()?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(path.display().to_string())
+    }
+
+    /// Deletes the subdirectories of days past the retention period.
+    fn prune(&self, today: NaiveDate) {
+        let retention =
+            chrono::Duration::from_std(self.config.retention).unwrap_or(chrono::Duration::MAX);
+        let Some(cutoff) = today.checked_sub_signed(retention) else {
+            return;
+        };
+        let entries = match std::fs::read_dir(&self.config.directory) {
+            Ok(entries) => entries,
+            Err(err) => {
+                tracing::warn!(?err, "failed to list archive directory");
+                return;
+            }
+        };
+        for entry in entries.flatten() {
+            let Some(day) = day_of(&entry.path()) else {
+                continue;
+            };
+            if day < cutoff {
+                if let Err(err) = std::fs::remove_dir_all(entry.path()) {
+                    tracing::warn!(?err, %day, "failed to prune archive");
+                } else {
+                    tracing::debug!(%day, "pruned archive");
+                }
+            }
+        }
+    }
+}
+
+/// Finds the file an object was archived in under `directory`, looking
+/// through the most recent days first.
+pub fn find(directory: &Path, key: &str) -> Option<PathBuf> {
+    let file = format!("{key}.json.gz");
+    let mut days = std::fs::read_dir(directory)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| day_of(path).is_some())
+        .collect::<Vec<_>>();
+    days.sort();
+    days.into_iter()
+        .rev()
+        .map(|day| day.join(&file))
+        .find(|path| path.is_file())
+}
+
+/// Reads an archived object, either gzipped or plain JSON.
+pub fn read(path: &Path) -> Result<serde_json::Value> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let value = if path.extension().is_some_and(|extension| extension == "gz") {
+        serde_json::from_reader(std::io::BufReader::new(GzDecoder::new(file)))?
+    } else {
+        serde_json::from_reader(std::io::BufReader::new(file))?
+    };
+    Ok(value)
+}
+
+/// The day of an archive subdirectory.
+fn day_of(path: &Path) -> Option<NaiveDate> {
+    path.file_name()?.to_str()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, day).unwrap()
+    }
+
+    #[test]
+    fn stores_gzipped_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = Directory::new(Config {
+            directory: dir.path().to_owned(),
+            retention: Duration::from_secs(24 * 60 * 60),
+        })
+        .unwrap();
+
+        let body = json!({"id": 1, "orders": []});
+        let path = archive.store_blocking("1", &body, day(2)).unwrap();
+        assert_eq!(
+            PathBuf::from(&path),
+            dir.path().join("2025-01-02/1.json.gz")
+        );
+        assert_eq!(read(Path::new(&path)).unwrap(), body);
+
+        // The most recent copy is found.
+        let body = json!({"id": 1, "orders": [1]});
+        archive.store_blocking("1", &body, day(3)).unwrap();
+        let path = find(dir.path(), "1").unwrap();
+        assert_eq!(read(&path).unwrap(), body);
+        assert!(find(dir.path(), "2").is_none());
+    }
+
+    #[test]
+    fn prunes_days_past_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = Directory::new(Config {
+            directory: dir.path().to_owned(),
+            retention: Duration::from_secs(2 * 24 * 60 * 60),
+        })
+        .unwrap();
+        std::fs::create_dir(dir.path().join("unrelated")).unwrap();
+
+        for today in 1..=5 {
+            archive.store_blocking("1", &json!({}), day(today)).unwrap();
+        }
+
+        let mut remaining = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        remaining.sort();
+        assert_eq!(
+            remaining,
+            ["2025-01-03", "2025-01-04", "2025-01-05", "unrelated"]
+        );
+    }
+}
@@ -1,14 +1,25 @@
 use {
     crate::{
-        domain::competition::auction::Id,
+        domain::{BlockNo, competition::auction::Id, eth, mempools},
         infra::{config::file, solver::Config},
     },
+    hyper::body::Bytes,
     serde::Serialize,
     serde_json::to_value,
     std::sync::Arc,
     tracing::Instrument,
+    url::Url,
 };
 
+pub mod local;
+
+/// Where auctions are archived.
+#[derive(Clone, Debug)]
+pub enum Archive {
+    S3(S3),
+    Local(local::Config),
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct S3 {
     /// Name of the AWS S3 bucket in which the auctions will be stored
@@ -17,6 +28,10 @@ pub struct S3 {
     /// Prepended to the auction id to form the final instance filename on AWS
     /// S3 bucket. Something like "staging/mainnet/"
     pub prefix: String,
+
+    /// Endpoint of an S3 compatible object store (e.g. MinIO) to use instead
+    /// of AWS S3.
+    pub endpoint: Option<Url>,
 }
 
 impl From<file::S3> for S3 {
@@ -24,6 +39,7 @@ impl From<file::S3> for S3 {
         Self {
             bucket: value.bucket,
             prefix: value.prefix,
+            endpoint: value.endpoint,
         }
     }
 }
@@ -33,47 +49,142 @@ impl From<S3> for s3::Config {
         Self {
             bucket: value.bucket,
             filename_prefix: value.prefix,
+            endpoint: value.endpoint.map(String::from),
         }
     }
 }
 
-#[derive(Clone, Debug)]
+/// A store for archived objects.
+#[async_trait::async_trait]
+trait Backend: Send + Sync {
+    /// Stores the body under the given key. Returns where it was stored.
+    async fn store(&self, key: &str, body: serde_json::Value) -> anyhow::Result<String>;
+}
+
+#[async_trait::async_trait]
+impl Backend for s3::Uploader {
+    async fn store(&self, key: &str, body: serde_json::Value) -> anyhow::Result<String> {
+        self.upload(key.to_owned(), body).await
+    }
+}
+
+#[async_trait::async_trait]
+impl Backend for local::Directory {
+    async fn store(&self, key: &str, body: serde_json::Value) -> anyhow::Result<String> {
+        local::Directory::store(self, key, body).await
+    }
+}
+
+#[derive(Clone)]
 pub struct Persistence {
-    s3: Option<Arc<s3::Uploader>>,
+    backend: Option<Arc<dyn Backend>>,
+}
+
+impl std::fmt::Debug for Persistence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Persistence")
+            .field("enabled", &self.backend.is_some())
+            .finish()
+    }
 }
 
 impl Persistence {
     pub async fn build(config: &Config) -> Self {
-        if let Some(s3) = &config.s3 {
-            Self {
-                s3: Some(Arc::new(s3::Uploader::new(s3.clone().into()).await)),
-            }
-        } else {
-            Self { s3: None }
-        }
+        let backend: Option<Arc<dyn Backend>> = match &config.archive {
+            Some(Archive::S3(s3)) => Some(Arc::new(s3::Uploader::new(s3.clone().into()).await)),
+            Some(Archive::Local(local)) => Some(Arc::new(
+                local::Directory::new(local.clone()).expect("archive directory should be usable"),
+            )),
+            None => None,
+        };
+        Self { backend }
+    }
+
+    /// Saves the `/solve` request of the given auction as it was received,
+    /// which allows replaying the auction later (non-blocking operation).
+    pub fn archive_request(&self, auction_id: Id, request: Bytes) {
+        // Parsing large requests takes a while, so it happens in the
+        // background as well.
+        self.store(format!("{auction_id}.request"), "request", move || {
+            serde_json::from_slice(&request)
+        });
     }
 
     /// Saves the given auction with liquidity with fire and forget mentality
     /// (non-blocking operation)
     pub fn archive_auction(&self, auction_id: Id, body: impl Serialize) {
-        let Some(uploader) = self.s3.clone() else {
-            return;
+        self.archive(auction_id.to_string(), "auction with liquidity", body);
+    }
+
+    /// Saves the solutions returned by the solver engine for the given
+    /// auction next to it (non-blocking operation).
+    pub fn archive_solutions(&self, auction_id: Id, body: impl Serialize) {
+        self.archive(format!("{auction_id}.solutions"), "solutions", body);
+    }
+
+    /// Saves the `/solve` response the driver submitted for the given
+    /// auction next to it (non-blocking operation).
+    pub fn archive_response(&self, auction_id: Id, body: impl Serialize) {
+        self.archive(format!("{auction_id}.response"), "response", body);
+    }
+
+    /// Saves the outcome of settling a solution of the given auction next to
+    /// it (non-blocking operation).
+    pub fn archive_settlement(
+        &self,
+        auction_id: Id,
+        solution_id: u64,
+        submission_deadline: BlockNo,
+        executed: &Result<eth::TxId, mempools::Error>,
+    ) {
+        let (tx_hash, error) = match executed {
+            Ok(tx_hash) => (Some(tx_hash.0), None),
+            Err(err) => (None, Some(err.to_string())),
         };
-        let body = match to_value(body) {
-            Ok(body) => body,
-            Err(err) => {
-                tracing::error!(?err, "failed to parse the auction with liquidity to JSON");
-                return;
-            }
+        self.archive(
+            format!("{auction_id}.settlement"),
+            "settlement",
+            SettlementOutcome {
+                solution_id,
+                submission_deadline,
+                tx_hash,
+                error,
+            },
+        );
+    }
+
+    fn archive(&self, key: String, kind: &'static str, body: impl Serialize) {
+        if self.backend.is_none() {
+            return;
+        }
+        let body = to_value(body);
+        self.store(key, kind, move || body);
+    }
+
+    fn store(
+        &self,
+        key: String,
+        kind: &'static str,
+        body: impl FnOnce() -> serde_json::Result<serde_json::Value> + Send + 'static,
+    ) {
+        let Some(backend) = self.backend.clone() else {
+            return;
         };
         tokio::spawn(
             async move {
-                match uploader.upload(auction_id.to_string(), body).await {
-                    Ok(key) => {
-                        tracing::debug!(?key, "uploaded auction with liquidity to s3");
+                let body = match body() {
+                    Ok(body) => body,
+                    Err(err) => {
+                        tracing::error!(?err, kind, "failed to serialize archived object to JSON");
+                        return;
+                    }
+                };
+                match backend.store(&key, body).await {
+                    Ok(location) => {
+                        tracing::debug!(?location, kind, "archived object");
                     }
                     Err(err) => {
-                        tracing::warn!(?err, "failed to upload auction to s3");
+                        tracing::warn!(?err, kind, "failed to archive object");
                     }
                 }
             }
@@ -81,3 +192,12 @@ impl Persistence {
         );
     }
 }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SettlementOutcome {
+    solution_id: u64,
+    submission_deadline: BlockNo,
+    tx_hash: Option<eth::B256>,
+    error: Option<String>,
+}
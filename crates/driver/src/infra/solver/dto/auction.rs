@@ -55,6 +55,7 @@ pub fn new(
             liquidity::Kind::UniswapV3(pool) => vec![pool.tokens.get().0, pool.tokens.get().1],
             liquidity::Kind::BalancerV2Stable(pool) => pool.reserves.tokens().collect(),
             liquidity::Kind::BalancerV2Weighted(pool) => pool.reserves.tokens().collect(),
+            liquidity::Kind::Curve(pool) => pool.tokens().collect(),
             liquidity::Kind::Swapr(pool) => pool.base.reserves.iter().map(|r| r.token).collect(),
             liquidity::Kind::ZeroEx(limit_order) => {
                 vec![
@@ -279,6 +280,22 @@ pub fn new(
                         },
                     )
                 }
+                liquidity::Kind::Curve(pool) => {
+                    solvers_dto::auction::Liquidity::Curve(solvers_dto::auction::CurvePool {
+                        id: liquidity.id.0.to_string(),
+                        address: pool.address.0,
+                        gas_estimate: liquidity.gas.into(),
+                        tokens: pool
+                            .reserves
+                            .iter()
+                            .map(|asset| solvers_dto::auction::CurveReserve {
+                                address: asset.token.into(),
+                                balance: asset.amount.into(),
+                            })
+                            .collect(),
+                        parameters: curve_parameters(pool),
+                    })
+                }
                 liquidity::Kind::Swapr(pool) => solvers_dto::auction::Liquidity::ConstantProduct(
                     solvers_dto::auction::ConstantProductPool {
                         id: liquidity.id.0.to_string(),
@@ -395,3 +412,42 @@ fn scaling_factor_to_decimal(
 ) -> bigdecimal::BigDecimal {
     bigdecimal::BigDecimal::new(scale.as_raw().into(), 18)
 }
+
+fn curve_parameters(pool: &liquidity::curve::Pool) -> solvers_dto::auction::CurveParameters {
+    match &pool.state {
+        liquidity::curve::State::Stable(state) => {
+            let parameters = solvers_dto::auction::CurveStableParameters {
+                rates: state.rates.clone(),
+                amp: state.amp,
+                fee: state.fee,
+                offpeg_fee_multiplier: state.offpeg_fee_multiplier,
+            };
+            match pool.kind {
+                liquidity::curve::Kind::StableSwapNg => {
+                    solvers_dto::auction::CurveParameters::StableSwapNg(parameters)
+                }
+                _ => solvers_dto::auction::CurveParameters::StableSwap(parameters),
+            }
+        }
+        liquidity::curve::State::Crypto(state) => {
+            let parameters = solvers_dto::auction::CurveCryptoParameters {
+                precisions: state.precisions.clone(),
+                price_scale: state.price_scale.clone(),
+                ann: state.ann,
+                gamma: state.gamma,
+                mid_fee: state.mid_fee,
+                out_fee: state.out_fee,
+                fee_gamma: state.fee_gamma,
+            };
+            match pool.kind {
+                liquidity::curve::Kind::TwoCryptoNg => {
+                    solvers_dto::auction::CurveParameters::TwoCryptoNg(parameters)
+                }
+                liquidity::curve::Kind::TricryptoNg => {
+                    solvers_dto::auction::CurveParameters::TricryptoNg(parameters)
+                }
+                _ => solvers_dto::auction::CurveParameters::TwoCrypto(parameters),
+            }
+        }
+    }
+}
@@ -10,13 +10,15 @@ use {
             },
             eth,
             liquidity,
+            mempools::{self, Accounts},
             time::Remaining,
         },
         infra::{
             self,
             blockchain::Ethereum,
             config::file::FeeHandler,
-            persistence::{Persistence, S3},
+            ledger::{self, Ledger},
+            persistence::{Archive, Persistence},
         },
         util,
     },
@@ -33,6 +35,7 @@ use {
     reqwest::header::HeaderName,
     std::{
         collections::HashMap,
+        sync::Arc,
         time::{Duration, Instant},
     },
     thiserror::Error,
@@ -104,6 +107,8 @@ pub struct Solver {
     config: Config,
     eth: Ethereum,
     persistence: Persistence,
+    accounts: Arc<Accounts>,
+    ledger: Ledger,
 }
 
 #[derive(Debug, Clone)]
@@ -161,6 +166,12 @@ pub struct Config {
     pub liquidity: Liquidity,
     /// The private key of this solver, used for settlement submission.
     pub account: Account,
+    /// Further accounts settlements can be submitted from, so that several
+    /// settlements of the solver can be pending at the same time.
+    pub submission_accounts: Vec<Account>,
+    /// How the native token balances of the solver's accounts are kept
+    /// topped up.
+    pub account_rebalancing: Option<mempools::accounts::Rebalancing>,
     /// How much time to spend for each step of the solving and competition.
     pub timeouts: Timeouts,
     /// HTTP headers that should be added to every request.
@@ -171,9 +182,12 @@ pub struct Config {
     /// TODO: Remove once all solvers are moved to use limit orders for quoting
     pub quote_using_limit_orders: bool,
     pub merge_solutions: SolutionMerging,
-    /// S3 configuration for storing the auctions in the form they are sent to
-    /// the solver engine
-    pub s3: Option<S3>,
+    /// Where to archive the auctions in the form they are sent to the solver
+    /// engine, along with the solver's responses and settlement outcomes.
+    pub archive: Option<Archive>,
+    /// Where the profit and loss ledger of the solver is stored. Without it
+    /// the ledger only covers the current run.
+    pub ledger: Option<ledger::Config>,
     /// Whether the native token is wrapped or not when sent to the solvers
     pub solver_native_token: ManageNativeToken,
     /// Which `tx.origin` is required to make quote verification pass.
@@ -208,6 +222,13 @@ impl Solver {
         }
 
         let persistence = Persistence::build(&config).await;
+        let accounts = Arc::new(Accounts::new(
+            std::iter::once(config.account.clone())
+                .chain(config.submission_accounts.iter().cloned())
+                .collect(),
+            config.account_rebalancing,
+        ));
+        let ledger = Ledger::new(config.name.clone(), config.ledger.as_ref());
 
         Ok(Self {
             client: reqwest::ClientBuilder::new()
@@ -217,6 +238,8 @@ impl Solver {
             config,
             eth,
             persistence,
+            accounts,
+            ledger,
         })
     }
 
@@ -228,6 +251,11 @@ impl Solver {
         self.persistence.clone()
     }
 
+    /// The profit and loss ledger of this solver.
+    pub fn ledger(&self) -> Ledger {
+        self.ledger.clone()
+    }
+
     pub fn name(&self) -> &Name {
         &self.config.name
     }
@@ -252,6 +280,11 @@ impl Solver {
         self.config.account.clone()
     }
 
+    /// The accounts settlements of this solver are submitted from.
+    pub fn accounts(&self) -> Arc<Accounts> {
+        self.accounts.clone()
+    }
+
     /// Timeout configuration for this solver.
     pub fn timeouts(&self) -> Timeouts {
         self.config.timeouts
@@ -375,6 +408,9 @@ impl Solver {
                     notify::Kind::DeserializationError(format!("Request format invalid: {err}")),
                 );
             })?;
+        if let Some(id) = auction.id() {
+            self.persistence.archive_solutions(id, &res);
+        }
         let solutions = dto::Solutions::from(res).into_domain(
             auction,
             liquidity,
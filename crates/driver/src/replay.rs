@@ -0,0 +1,224 @@
+//! Replay of auctions archived by the driver.
+//!
+//! The archived `/solve` request goes through the same pipeline as live
+//! auctions: liquidity fetching, solving, encoding, simulation and scoring.
+//! Everything runs against the node the driver is connected to, which should
+//! be forked at the block the auction was solved at. The result is compared
+//! to the `/solve` response archived next to the request.
+
+use {
+    crate::{
+        domain::competition::{order::app_data::AppDataRetriever, solution::settlement},
+        infra::{
+            self,
+            Api,
+            api::routes::solve::dto::SolveResponse,
+            config::file::OrderPriorityStrategy,
+            persistence::{self, local},
+            tokens,
+        },
+    },
+    hyper::body::Bytes,
+    serde_json::Value,
+    shared::json,
+    std::{
+        path::{Path, PathBuf},
+        time::Duration,
+    },
+};
+
+pub struct Replay {
+    /// The archived `/solve` request.
+    request: Value,
+    /// The archived `/solve` response, if the driver submitted a solution.
+    original: Option<Value>,
+    solve_time: Duration,
+}
+
+impl Replay {
+    /// Loads the archived auction and removes all solvers but the one
+    /// replaying it from the configuration.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the solver isn't configured or the auction can't
+    /// be loaded.
+    pub fn new(
+        config: &mut infra::Config,
+        auction: &str,
+        solver: Option<&str>,
+        solve_time: Duration,
+    ) -> Self {
+        let index = match solver {
+            Some(name) => config
+                .solvers
+                .iter()
+                .position(|solver| solver.name.as_str() == name)
+                .unwrap_or_else(|| panic!("solver {name} is not configured")),
+            None => {
+                assert_eq!(
+                    config.solvers.len(),
+                    1,
+                    "the solver to replay the auction with has to be specified"
+                );
+                0
+            }
+        };
+        let mut solver = config.solvers.swap_remove(index);
+        let directory = match &solver.archive {
+            Some(persistence::Archive::Local(local)) => Some(local.directory.clone()),
+            _ => None,
+        };
+
+        let path = match auction.parse::<i64>() {
+            Ok(id) => {
+                let directory = directory.as_deref().expect(
+                    "auctions can only be looked up by ID in a local archive, pass the path of \
+                     the archived request instead",
+                );
+                local::find(directory, &format!("{id}.request"))
+                    .unwrap_or_else(|| panic!("auction {id} is not archived in {directory:?}"))
+            }
+            Err(_) => PathBuf::from(auction),
+        };
+        let request = local::read(&path)
+            .unwrap_or_else(|err| panic!("failed to read archived request {path:?}: {err:?}"));
+        let original = original_response(&path, directory.as_deref(), &request);
+
+        // The replay must not overwrite what was archived for the auction, nor
+        // send any transactions.
+        solver.archive = None;
+        solver.submission_accounts.clear();
+        solver.account_rebalancing = None;
+        solver.ledger = None;
+        config.solvers = vec![solver];
+
+        Self {
+            request,
+            original,
+            solve_time,
+        }
+    }
+
+    /// Solves the auction again and prints how the result compares to what
+    /// was originally submitted.
+    pub async fn run(
+        self,
+        api: Api,
+        order_priority_strategies: &[OrderPriorityStrategy],
+        app_data_retriever: Option<AppDataRetriever>,
+    ) {
+        let tokens = tokens::Fetcher::new(&api.eth);
+        let competition = api
+            .competitions(&tokens, order_priority_strategies, app_data_retriever)
+            .pop()
+            .expect("the replaying solver is configured");
+
+        let mut request = self.request;
+        let id = auction_id(&request);
+        // The original deadline has long passed.
+        let deadline = chrono::Utc::now()
+            + chrono::Duration::from_std(self.solve_time).expect("solve time should be valid");
+        request["deadline"] = Value::String(deadline.to_rfc3339());
+        let body = Bytes::from(serde_json::to_vec(&request).expect("JSON values serialize"));
+
+        let solved = match competition.solve(body).await {
+            Ok(solved) => solved,
+            Err(err) => {
+                println!("auction {id}: replay failed: {err}");
+                return;
+            }
+        };
+        match &solved {
+            Some(solved) => println!(
+                "auction {id}: solved with score {}, simulated gas {}",
+                solved.score.0,
+                solved
+                    .gas
+                    .map_or_else(|| "unknown".to_owned(), |gas| gas.0.to_string()),
+            ),
+            None => println!(
+                "auction {id}: no solution passed encoding and simulation, see the logs for why"
+            ),
+        }
+        if let Some(settlement) = competition.settlements.lock().unwrap().front() {
+            let tx = settlement.transaction(settlement::Internalization::Enable);
+            println!(
+                "  settlement: to {}, calldata {}",
+                tx.to,
+                const_hex::encode_prefixed(&tx.input.0)
+            );
+        }
+
+        let replayed = serde_json::to_value(SolveResponse::new(solved, &competition.solver))
+            .unwrap_or_default();
+        let Some(original) = self.original else {
+            println!("  no solution was originally submitted, or its response wasn't archived");
+            return;
+        };
+        let differences = json::diff(&without_ids(original), &without_ids(replayed));
+        if differences.is_empty() {
+            println!("  response unchanged");
+        } else {
+            println!("  response changed");
+            for difference in differences {
+                println!("    {difference}");
+            }
+        }
+    }
+}
+
+fn auction_id(request: &Value) -> String {
+    match &request["id"] {
+        Value::String(id) => id.clone(),
+        id => id.to_string(),
+    }
+}
+
+/// Looks for the archived `/solve` response next to the request, or anywhere
+/// in the local archive.
+fn original_response(
+    request_path: &Path,
+    directory: Option<&Path>,
+    request: &Value,
+) -> Option<Value> {
+    let key = format!("{}.response", auction_id(request));
+    let path = request_path
+        .parent()
+        .map(|parent| parent.join(format!("{key}.json.gz")))
+        .filter(|path| path.is_file())
+        .or_else(|| local::find(directory?, &key))?;
+    local::read(&path)
+        .inspect_err(|err| tracing::warn!(?err, ?path, "failed to read archived response"))
+        .ok()
+}
+
+/// Solution IDs are assigned per driver run, so they always differ.
+fn without_ids(mut response: Value) -> Value {
+    if let Some(solutions) = response.get_mut("solutions").and_then(Value::as_array_mut) {
+        for solution in solutions.iter_mut().filter_map(Value::as_object_mut) {
+            solution.remove("solutionId");
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    #[test]
+    fn diffs_responses_ignoring_solution_ids() {
+        let old = json!({
+            "solutions": [{ "solutionId": 3, "score": "10", "clearingPrices": { "0x01": "1" } }]
+        });
+        let new = json!({
+            "solutions": [{ "solutionId": 1, "score": "10", "clearingPrices": { "0x01": "2" } }]
+        });
+        assert!(json::diff(&without_ids(old.clone()), &without_ids(old.clone())).is_empty());
+        assert_eq!(
+            json::diff(&without_ids(old), &without_ids(new)),
+            [r#"solutions[0].clearingPrices.0x01: "1" -> "2""#]
+        );
+    }
+}
@@ -15,6 +15,7 @@ use {
             simulator::{self, Simulator},
             solver::Solver,
         },
+        replay::Replay,
     },
     clap::Parser,
     futures::future::join_all,
@@ -55,13 +56,27 @@ async fn run_with(args: cli::Args, addr_sender: Option<oneshot::Sender<SocketAdd
     ));
 
     let ethrpc = ethrpc(&args).await;
-    let config = config::file::load(ethrpc.chain(), &args.config).await;
+    let mut config = config::file::load(ethrpc.chain(), &args.config).await;
 
     let commit_hash = option_env!("VERGEN_GIT_SHA").unwrap_or("COMMIT_INFO_NOT_FOUND");
 
     tracing::info!(%commit_hash, "running driver with {config:#?}");
 
-    let (shutdown_sender, shutdown_receiver) = tokio::sync::oneshot::channel();
+    // Select the replayed auction and solver before the solvers get built.
+    let replay = match args.command {
+        Some(cli::Command::Replay {
+            auction,
+            solver,
+            solve_time,
+        }) => Some(Replay::new(
+            &mut config,
+            &auction,
+            solver.as_deref(),
+            solve_time,
+        )),
+        None => None,
+    };
+
     let eth = ethereum(&config, ethrpc, &args.current_block).await;
     let app_data_retriever = match &config.app_data_fetching {
         config::file::AppDataFetching::Enabled {
@@ -70,7 +85,7 @@ async fn run_with(args: cli::Args, addr_sender: Option<oneshot::Sender<SocketAdd
         } => Some(AppDataRetriever::new(orderbook_url.clone(), *cache_size)),
         config::file::AppDataFetching::Disabled => None,
     };
-    let serve = Api {
+    let api = Api {
         solvers: solvers(&config, &eth).await,
         liquidity: liquidity(&config, &eth).await,
         liquidity_sources_notifier: liquidity_sources_notifier(&config, &eth),
@@ -85,7 +100,11 @@ async fn run_with(args: cli::Args, addr_sender: Option<oneshot::Sender<SocketAdd
                         config
                             .solvers
                             .iter()
-                            .map(|config| config.account.clone())
+                            .flat_map(|config| {
+                                std::iter::once(&config.account)
+                                    .chain(&config.submission_accounts)
+                                    .cloned()
+                            })
                             .collect(),
                     )
                 })
@@ -100,8 +119,17 @@ async fn run_with(args: cli::Args, addr_sender: Option<oneshot::Sender<SocketAdd
         eth,
         addr: args.addr,
         addr_sender,
+    };
+
+    if let Some(replay) = replay {
+        replay
+            .run(api, &config.order_priority_strategies, app_data_retriever)
+            .await;
+        return;
     }
-    .serve(
+
+    let (shutdown_sender, shutdown_receiver) = tokio::sync::oneshot::channel();
+    let serve = api.serve(
         async {
             let _ = shutdown_receiver.await;
         },
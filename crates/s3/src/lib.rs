@@ -13,6 +13,9 @@ pub struct Config {
     pub bucket: String,
     /// Prepended to the the final filename for each uploaded object.
     pub filename_prefix: String,
+    /// Endpoint of an S3 compatible object store (e.g. MinIO) to use instead
+    /// of AWS. Objects are addressed by path rather than by subdomain.
+    pub endpoint: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -24,10 +27,20 @@ pub struct Uploader {
 
 impl Uploader {
     pub async fn new(config: Config) -> Self {
+        let sdk_config = aws_config::from_env().load().await;
+        let client = match config.endpoint {
+            Some(endpoint) => Client::from_conf(
+                aws_sdk_s3::config::Builder::from(&sdk_config)
+                    .endpoint_url(endpoint)
+                    .force_path_style(true)
+                    .build(),
+            ),
+            None => Client::new(&sdk_config),
+        };
         let uploader = Self {
             bucket: config.bucket,
             filename_prefix: config.filename_prefix,
-            client: Client::new(&aws_config::from_env().load().await),
+            client,
         };
         uploader.assert_credentials_are_usable().await;
         uploader
@@ -97,6 +110,7 @@ mod tests {
         let config = Config {
             bucket: std::env::var("BUCKET").unwrap(),
             filename_prefix: "test/".to_string(),
+            endpoint: std::env::var("ENDPOINT").ok(),
         };
 
         // Upload a reasonable amount of data. This helps see the benefits of
@@ -0,0 +1,72 @@
+//! Helpers for working with JSON values.
+
+use serde_json::Value;
+
+/// Lists the differences between two JSON values as `path: old -> new`, where
+/// `path` uses `.` for object keys and `[i]` for array indices. Keys and
+/// elements missing on one side compare as `null`.
+pub fn diff(old: &Value, new: &Value) -> Vec<String> {
+    let mut differences = Vec::new();
+    diff_at("", old, new, &mut differences);
+    differences
+}
+
+fn diff_at(path: &str, old: &Value, new: &Value, differences: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let added = new.keys().filter(|key| !old.contains_key(*key));
+            for key in old.keys().chain(added) {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                diff_at(
+                    &path,
+                    old.get(key).unwrap_or(&Value::Null),
+                    new.get(key).unwrap_or(&Value::Null),
+                    differences,
+                );
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for i in 0..old.len().max(new.len()) {
+                diff_at(
+                    &format!("{path}[{i}]"),
+                    old.get(i).unwrap_or(&Value::Null),
+                    new.get(i).unwrap_or(&Value::Null),
+                    differences,
+                );
+            }
+        }
+        (old, new) if old != new => differences.push(format!("{path}: {old} -> {new}")),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    #[test]
+    fn diffs_nested_values() {
+        let old = json!({
+            "solutions": [{ "id": 0, "prices": { "0x01": "1", "0x02": "2" } }]
+        });
+        assert!(diff(&old, &old).is_empty());
+
+        let new = json!({
+            "solutions": [
+                { "id": 0, "prices": { "0x01": "1", "0x02": "3" } },
+                { "id": 1 }
+            ]
+        });
+        assert_eq!(
+            diff(&old, &new),
+            [
+                r#"solutions[0].prices.0x02: "2" -> "3""#,
+                r#"solutions[1]: null -> {"id":1}"#,
+            ]
+        );
+    }
+}
@@ -0,0 +1,3 @@
+//! Curve StableSwap and CryptoSwap baseline liquidity source implementation.
+pub mod pool_cache;
+pub mod pool_fetching;
@@ -0,0 +1,56 @@
+use {
+    crate::{
+        recent_block_cache::{Block, CacheConfig, CacheFetching, CacheKey, RecentBlockCache},
+        sources::curve::pool_fetching::{CurvePoolFetcher, CurvePoolFetching, Pool},
+    },
+    alloy::primitives::Address,
+    anyhow::Result,
+    ethrpc::block_stream::CurrentBlockWatcher,
+    model::TokenPair,
+    std::{collections::HashSet, sync::Arc},
+};
+
+/// Caches the state of Curve pools for recent blocks, keyed by pool address.
+pub struct PoolCache {
+    fetcher: Arc<CurvePoolFetcher>,
+    cache: RecentBlockCache<Address, Pool, Arc<CurvePoolFetcher>>,
+}
+
+impl CacheKey<Pool> for Address {
+    fn first_ord() -> Self {
+        Address::ZERO
+    }
+
+    fn for_value(value: &Pool) -> Self {
+        value.address
+    }
+}
+
+#[async_trait::async_trait]
+impl CacheFetching<Address, Pool> for Arc<CurvePoolFetcher> {
+    async fn fetch_values(&self, keys: HashSet<Address>, block: Block) -> Result<Vec<Pool>> {
+        Ok(self.pools(keys, block).await)
+    }
+}
+
+impl PoolCache {
+    /// Creates a new pool cache.
+    pub fn new(
+        config: CacheConfig,
+        fetcher: Arc<CurvePoolFetcher>,
+        block_stream: CurrentBlockWatcher,
+    ) -> Result<Self> {
+        Ok(Self {
+            cache: RecentBlockCache::new(config, fetcher.clone(), block_stream, "curve")?,
+            fetcher,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl CurvePoolFetching for PoolCache {
+    async fn fetch(&self, token_pairs: HashSet<TokenPair>, at_block: Block) -> Result<Vec<Pool>> {
+        let relevant = self.fetcher.relevant(&token_pairs).await;
+        self.cache.fetch(relevant, at_block).await
+    }
+}
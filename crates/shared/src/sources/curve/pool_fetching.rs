@@ -0,0 +1,553 @@
+//! Fetching on-chain state of a configured set of Curve pools.
+
+use {
+    crate::recent_block_cache::Block,
+    alloy::{
+        eips::BlockId,
+        primitives::{Address, U256},
+        providers::Provider,
+        rpc::types::TransactionRequest,
+        sol,
+        sol_types::SolCall,
+    },
+    anyhow::{Context, Result, anyhow, ensure},
+    ethrpc::{AlloyProvider, Web3},
+    futures::future::{join_all, try_join, try_join_all},
+    model::{TokenPair, order::BUY_ETH_ADDRESS},
+    std::{
+        collections::{HashMap, HashSet},
+        sync::{Arc, Mutex},
+    },
+    tracing::instrument,
+};
+
+/// Upper bound on the number of coins probed for StableSwap pools.
+const MAX_STABLE_COINS: usize = 8;
+/// The precision of the amplification coefficient returned by `A_precise()`.
+const A_PRECISION: u64 = 100;
+
+sol! {
+    interface ICurvePool {
+        function coins(uint256 i) external view returns (address);
+        function balances(uint256 i) external view returns (uint256);
+        function fee() external view returns (uint256);
+    }
+
+    interface IStableSwap {
+        function A() external view returns (uint256);
+        function A_precise() external view returns (uint256);
+    }
+
+    interface IStableSwapNg {
+        function offpeg_fee_multiplier() external view returns (uint256);
+        function stored_rates() external view returns (uint256[] memory);
+    }
+
+    interface ICryptoSwap {
+        function A() external view returns (uint256);
+        function gamma() external view returns (uint256);
+        function mid_fee() external view returns (uint256);
+        function out_fee() external view returns (uint256);
+        function fee_gamma() external view returns (uint256);
+    }
+
+    interface ITwoCrypto {
+        function price_scale() external view returns (uint256);
+    }
+
+    interface ITricrypto {
+        function price_scale(uint256 k) external view returns (uint256);
+    }
+
+    interface IERC20Decimals {
+        function decimals() external view returns (uint8);
+    }
+}
+
+/// The flavour of a Curve pool. This determines the invariant used for
+/// pricing as well as the signature of the pool's `exchange` function.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PoolKind {
+    /// Plain StableSwap pools (e.g. 3pool).
+    StableSwap,
+    /// StableSwap-NG pools with dynamic off-peg fees and oracle rates.
+    StableSwapNg,
+    /// Original two-coin CryptoSwap pools.
+    TwoCrypto,
+    /// Two-coin CryptoSwap-NG pools.
+    TwoCryptoNg,
+    /// Three-coin Tricrypto-NG pools.
+    TricryptoNg,
+}
+
+impl PoolKind {
+    /// Returns the fixed number of coins for CryptoSwap pools, `None` for
+    /// StableSwap pools which can hold a variable number of coins.
+    pub fn n_coins(self) -> Option<usize> {
+        match self {
+            PoolKind::StableSwap | PoolKind::StableSwapNg => None,
+            PoolKind::TwoCrypto | PoolKind::TwoCryptoNg => Some(2),
+            PoolKind::TricryptoNg => Some(3),
+        }
+    }
+
+    /// Returns `true` if the pool is a StableSwap pool whose `exchange`
+    /// function takes `int128` coin indices.
+    pub fn is_stable(self) -> bool {
+        matches!(self, PoolKind::StableSwap | PoolKind::StableSwapNg)
+    }
+}
+
+/// A Curve pool that should be indexed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct PoolConfig {
+    pub address: Address,
+    pub kind: PoolKind,
+}
+
+/// A Curve pool with its state at a specific block.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Pool {
+    pub address: Address,
+    pub kind: PoolKind,
+    /// The pool coins, in pool order.
+    pub coins: Vec<Address>,
+    /// Raw coin balances held by the pool, in pool order.
+    pub balances: Vec<U256>,
+    pub state: PoolState,
+}
+
+impl Pool {
+    /// Returns the index of `token` in the pool's coins.
+    pub fn index(&self, token: Address) -> Option<usize> {
+        self.coins.iter().position(|coin| *coin == token)
+    }
+
+    /// Returns `true` if the pool can trade the specified token pair.
+    pub fn has_pair(&self, pair: &TokenPair) -> bool {
+        let (a, b) = pair.get();
+        self.index(a).is_some() && self.index(b).is_some()
+    }
+}
+
+/// The invariant specific state of a Curve pool.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PoolState {
+    Stable(StableState),
+    Crypto(CryptoState),
+}
+
+/// State of a StableSwap pool.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StableState {
+    /// Rate multipliers normalizing balances to 18 decimals. This is
+    /// `10**(36 - decimals)` for plain pools and the `stored_rates()` of NG
+    /// pools (which include oracle rates).
+    pub rates: Vec<U256>,
+    /// Amplification coefficient in `A_PRECISION` units, as returned by
+    /// `A_precise()`.
+    pub amp: U256,
+    /// Swap fee in `1e10` units.
+    pub fee: U256,
+    /// The off-peg fee multiplier of NG pools, `None` for plain pools.
+    pub offpeg_fee_multiplier: Option<U256>,
+}
+
+/// State of a CryptoSwap pool.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CryptoState {
+    /// Multipliers normalizing balances to 18 decimals (`10**(18 -
+    /// decimals)`).
+    pub precisions: Vec<U256>,
+    /// Price of coins `1..N` in terms of coin 0.
+    pub price_scale: Vec<U256>,
+    /// The amplification coefficient as stored on-chain.
+    pub ann: U256,
+    pub gamma: U256,
+    pub mid_fee: U256,
+    pub out_fee: U256,
+    pub fee_gamma: U256,
+}
+
+#[cfg_attr(any(test, feature = "test-util"), mockall::automock)]
+#[async_trait::async_trait]
+pub trait CurvePoolFetching: Send + Sync {
+    /// Fetches the state of all known pools that can trade any of the
+    /// specified token pairs.
+    async fn fetch(&self, token_pairs: HashSet<TokenPair>, at_block: Block) -> Result<Vec<Pool>>;
+}
+
+/// Fetches Curve pool state over RPC for a configured list of pools.
+pub struct CurvePoolFetcher {
+    reader: PoolReader,
+    pools: HashMap<Address, PoolKind>,
+}
+
+impl CurvePoolFetcher {
+    pub fn new(web3: Web3, pools: Vec<PoolConfig>) -> Self {
+        Self {
+            reader: PoolReader::new(web3.alloy.clone()),
+            pools: pools
+                .into_iter()
+                .map(|config| (config.address, config.kind))
+                .collect(),
+        }
+    }
+
+    /// Returns the addresses of the configured pools that can trade any of
+    /// the specified token pairs.
+    pub async fn relevant(&self, token_pairs: &HashSet<TokenPair>) -> Vec<Address> {
+        let relevant = join_all(self.pools.iter().map(|(address, kind)| async move {
+            let statics = self.reader.statics(*address, *kind).await.ok()?;
+            token_pairs
+                .iter()
+                .any(|pair| {
+                    let (a, b) = pair.get();
+                    statics.coins.contains(&a) && statics.coins.contains(&b)
+                })
+                .then_some(*address)
+        }))
+        .await;
+        relevant.into_iter().flatten().collect()
+    }
+
+    /// Fetches the state of the specified pools at `block`, skipping the ones
+    /// that can't be read.
+    pub async fn pools(&self, addresses: HashSet<Address>, block: Block) -> Vec<Pool> {
+        let block = BlockId::from(block);
+        let pools = join_all(addresses.into_iter().filter_map(|address| {
+            let kind = *self.pools.get(&address)?;
+            Some(async move { (address, self.pool(address, kind, block).await) })
+        }))
+        .await;
+
+        pools
+            .into_iter()
+            .filter_map(|(address, result)| match result {
+                Ok(pool) => Some(pool),
+                Err(err) => {
+                    // A single misbehaving pool shouldn't prevent other
+                    // Curve liquidity from being used.
+                    tracing::warn!(pool = ?address, ?err, "failed to fetch Curve pool");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    async fn pool(&self, address: Address, kind: PoolKind, block: BlockId) -> Result<Pool> {
+        let statics = self.reader.statics(address, kind).await?;
+        // Pools holding native ETH require sending value along with the
+        // exchange, which settlement interactions don't support.
+        ensure!(
+            !statics.coins.contains(&BUY_ETH_ADDRESS),
+            "Curve pool {address:?} holds native ETH",
+        );
+
+        let balances = self.reader.balances(address, &statics, block);
+        let state = async {
+            Ok(if kind.is_stable() {
+                PoolState::Stable(
+                    self.reader
+                        .stable_state(kind, address, &statics, block)
+                        .await?,
+                )
+            } else {
+                PoolState::Crypto(
+                    self.reader
+                        .crypto_state(kind, address, &statics, block)
+                        .await?,
+                )
+            })
+        };
+        let (balances, state) = try_join(balances, state).await?;
+
+        Ok(Pool {
+            address,
+            kind,
+            coins: statics.coins.clone(),
+            balances,
+            state,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl CurvePoolFetching for CurvePoolFetcher {
+    #[instrument(skip_all)]
+    async fn fetch(&self, token_pairs: HashSet<TokenPair>, at_block: Block) -> Result<Vec<Pool>> {
+        let relevant = self.relevant(&token_pairs).await;
+        Ok(self.pools(relevant.into_iter().collect(), at_block).await)
+    }
+}
+
+/// Reads the state of Curve pools over RPC. This is shared by everything
+/// that prices Curve pools locally.
+///
+/// Coins and their decimals never change for a pool, so they are cached
+/// after the first read and only balances and parameters are re-read.
+pub struct PoolReader {
+    provider: AlloyProvider,
+    statics: Mutex<HashMap<Address, Arc<Statics>>>,
+}
+
+/// The immutable part of a pool's state.
+pub struct Statics {
+    /// The pool coins, in pool order.
+    pub coins: Vec<Address>,
+    pub decimals: Vec<u8>,
+}
+
+impl PoolReader {
+    pub fn new(provider: AlloyProvider) -> Self {
+        Self {
+            provider,
+            statics: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached coins and decimals of a pool, fetching them on first
+    /// use.
+    pub async fn statics(&self, address: Address, kind: PoolKind) -> Result<Arc<Statics>> {
+        if let Some(statics) = self.cached_statics(&address) {
+            return Ok(statics);
+        }
+
+        let latest = BlockId::latest();
+        let coins = match kind.n_coins() {
+            Some(n) => {
+                try_join_all((0..n).map(|i| {
+                    self.call(address, ICurvePool::coinsCall { i: U256::from(i) }, latest)
+                }))
+                .await?
+            }
+            None => self.probe_coins(address).await?,
+        };
+        let decimals = try_join_all(
+            coins
+                .iter()
+                .map(|coin| self.call(*coin, IERC20Decimals::decimalsCall {}, latest)),
+        )
+        .await?;
+
+        let statics = Arc::new(Statics { coins, decimals });
+        self.statics
+            .lock()
+            .unwrap()
+            .insert(address, statics.clone());
+        Ok(statics)
+    }
+
+    fn cached_statics(&self, address: &Address) -> Option<Arc<Statics>> {
+        self.statics.lock().unwrap().get(address).cloned()
+    }
+
+    /// StableSwap pools don't expose their number of coins in a uniform way,
+    /// so probe `coins(i)` until it reverts.
+    async fn probe_coins(&self, address: Address) -> Result<Vec<Address>> {
+        let mut coins = Vec::new();
+        for i in 0..MAX_STABLE_COINS {
+            match self
+                .call(
+                    address,
+                    ICurvePool::coinsCall { i: U256::from(i) },
+                    BlockId::latest(),
+                )
+                .await
+            {
+                Ok(coin) if !coin.is_zero() => coins.push(coin),
+                _ if coins.len() >= 2 => break,
+                Ok(_) => return Err(anyhow!("Curve pool {address:?} has fewer than 2 coins")),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(coins)
+    }
+
+    /// Reads the raw coin balances of a pool, in pool order.
+    pub async fn balances(
+        &self,
+        address: Address,
+        statics: &Statics,
+        block: BlockId,
+    ) -> Result<Vec<U256>> {
+        try_join_all((0..statics.coins.len()).map(|i| {
+            self.call(
+                address,
+                ICurvePool::balancesCall { i: U256::from(i) },
+                block,
+            )
+        }))
+        .await
+    }
+
+    /// Reads the parameters of a StableSwap pool.
+    pub async fn stable_state(
+        &self,
+        kind: PoolKind,
+        address: Address,
+        statics: &Statics,
+        block: BlockId,
+    ) -> Result<StableState> {
+        let (amp, fee) = try_join(
+            self.amp(address, block),
+            self.call(address, ICurvePool::feeCall {}, block),
+        )
+        .await?;
+
+        let (rates, offpeg_fee_multiplier) = match kind {
+            PoolKind::StableSwapNg => {
+                let (rates, multiplier) = try_join(
+                    self.call(address, IStableSwapNg::stored_ratesCall {}, block),
+                    self.call(address, IStableSwapNg::offpeg_fee_multiplierCall {}, block),
+                )
+                .await?;
+                (rates, Some(multiplier))
+            }
+            _ => (
+                statics
+                    .decimals
+                    .iter()
+                    .map(|decimals| scale(36, *decimals))
+                    .collect::<Result<_>>()?,
+                None,
+            ),
+        };
+
+        Ok(StableState {
+            rates,
+            amp,
+            fee,
+            offpeg_fee_multiplier,
+        })
+    }
+
+    /// Reads the amplification coefficient of a StableSwap pool in
+    /// `A_PRECISION` units. Legacy pools predate `A_precise()` and only
+    /// expose the plain `A()`.
+    async fn amp(&self, address: Address, block: BlockId) -> Result<U256> {
+        if let Ok(amp) = self
+            .call(address, IStableSwap::A_preciseCall {}, block)
+            .await
+        {
+            return Ok(amp);
+        }
+        let amp = self.call(address, IStableSwap::ACall {}, block).await?;
+        amp.checked_mul(U256::from(A_PRECISION))
+            .context("amplification coefficient overflows")
+    }
+
+    /// Reads the parameters of a CryptoSwap pool.
+    pub async fn crypto_state(
+        &self,
+        kind: PoolKind,
+        address: Address,
+        statics: &Statics,
+        block: BlockId,
+    ) -> Result<CryptoState> {
+        let price_scale = async {
+            match kind {
+                PoolKind::TricryptoNg => {
+                    try_join_all((0..statics.coins.len() - 1).map(|k| {
+                        self.call(
+                            address,
+                            ITricrypto::price_scaleCall { k: U256::from(k) },
+                            block,
+                        )
+                    }))
+                    .await
+                }
+                _ => Ok(vec![
+                    self.call(address, ITwoCrypto::price_scaleCall {}, block)
+                        .await?,
+                ]),
+            }
+        };
+        let (price_scale, ann, gamma, mid_fee, out_fee, fee_gamma) = futures::try_join!(
+            price_scale,
+            self.call(address, ICryptoSwap::ACall {}, block),
+            self.call(address, ICryptoSwap::gammaCall {}, block),
+            self.call(address, ICryptoSwap::mid_feeCall {}, block),
+            self.call(address, ICryptoSwap::out_feeCall {}, block),
+            self.call(address, ICryptoSwap::fee_gammaCall {}, block),
+        )?;
+
+        Ok(CryptoState {
+            precisions: statics
+                .decimals
+                .iter()
+                .map(|decimals| scale(18, *decimals))
+                .collect::<Result<_>>()?,
+            price_scale,
+            ann,
+            gamma,
+            mid_fee,
+            out_fee,
+            fee_gamma,
+        })
+    }
+
+    /// Executes a view call at `block`.
+    pub async fn call<C: SolCall>(
+        &self,
+        target: Address,
+        call: C,
+        block: BlockId,
+    ) -> Result<C::Return> {
+        let tx = TransactionRequest::default()
+            .to(target)
+            .input(call.abi_encode().into());
+        let result = self
+            .provider
+            .call(tx)
+            .block(block)
+            .await
+            .with_context(|| format!("{} call to {target:?} failed", C::SIGNATURE))?;
+        C::abi_decode_returns(&result)
+            .with_context(|| format!("failed to decode {} return data", C::SIGNATURE))
+    }
+}
+
+/// Returns `10**(base - decimals)`, the factor that normalizes an amount with
+/// `decimals` to `base` decimals.
+pub fn scale(base: u8, decimals: u8) -> Result<U256> {
+    let exponent = base
+        .checked_sub(decimals)
+        .context("coin has too many decimals")?;
+    Ok(U256::from(10).pow(U256::from(exponent)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimal_scaling() {
+        assert_eq!(scale(18, 18).unwrap(), U256::ONE);
+        assert_eq!(scale(18, 6).unwrap(), U256::from(1_000_000_000_000u64));
+        assert_eq!(scale(36, 6).unwrap(), U256::from(10).pow(U256::from(30)));
+        assert!(scale(18, 24).is_err());
+    }
+
+    #[test]
+    fn pool_token_pairs() {
+        let token = |byte| Address::repeat_byte(byte);
+        let pool = Pool {
+            address: token(0xaa),
+            kind: PoolKind::StableSwap,
+            coins: vec![token(1), token(2), token(3)],
+            balances: vec![U256::ONE; 3],
+            state: PoolState::Stable(StableState {
+                rates: vec![U256::ONE; 3],
+                amp: U256::ONE,
+                fee: U256::ZERO,
+                offpeg_fee_multiplier: None,
+            }),
+        };
+
+        assert_eq!(pool.index(token(3)), Some(2));
+        assert_eq!(pool.index(token(4)), None);
+        assert!(pool.has_pair(&TokenPair::new(token(1), token(3)).unwrap()));
+        assert!(!pool.has_pair(&TokenPair::new(token(1), token(4)).unwrap()));
+    }
+}
@@ -1,6 +1,7 @@
 //! Top-level module organizing all baseline liquidity sources.
 
 pub mod balancer_v2;
+pub mod curve;
 pub mod swapr;
 pub mod uniswap_v2;
 pub mod uniswap_v3;
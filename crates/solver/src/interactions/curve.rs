@@ -0,0 +1,92 @@
+use {
+    alloy::{
+        primitives::{Address, U256},
+        sol,
+        sol_types::SolCall,
+    },
+    shared::{
+        http_solver::model::TokenAmount,
+        interaction::{EncodedInteraction, Interaction},
+        sources::curve::pool_fetching::PoolKind,
+    },
+};
+
+sol! {
+    interface ICurveStableSwap {
+        function exchange(int128 i, int128 j, uint256 dx, uint256 min_dy) external returns (uint256);
+    }
+
+    interface ICurveCryptoSwap {
+        function exchange(uint256 i, uint256 j, uint256 dx, uint256 min_dy) external returns (uint256);
+    }
+}
+
+/// A Curve pool `exchange` selling coin `i` for coin `j`.
+///
+/// Curve pools only support exact input swaps, so the full `amount_in_max` is
+/// sold and `amount_out` is used as the minimum amount of tokens to receive.
+/// Any tokens received in excess of `amount_out` stay in the settlement
+/// contract.
+#[derive(Clone, Debug)]
+pub struct CurveExchangeInteraction {
+    pub pool: Address,
+    pub kind: PoolKind,
+    pub i: usize,
+    pub j: usize,
+    pub amount_in_max: TokenAmount,
+    pub amount_out: TokenAmount,
+}
+
+impl Interaction for CurveExchangeInteraction {
+    fn encode(&self) -> EncodedInteraction {
+        let dx = self.amount_in_max.amount;
+        let min_dy = self.amount_out.amount;
+        let call_data = if self.kind.is_stable() {
+            ICurveStableSwap::exchangeCall {
+                i: self.i as i128,
+                j: self.j as i128,
+                dx,
+                min_dy,
+            }
+            .abi_encode()
+        } else {
+            ICurveCryptoSwap::exchangeCall {
+                i: U256::from(self.i),
+                j: U256::from(self.j),
+                dx,
+                min_dy,
+            }
+            .abi_encode()
+        };
+        (self.pool, U256::ZERO, call_data.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloy::primitives::hex};
+
+    #[test]
+    fn encode_exchange() {
+        let interaction = |kind| CurveExchangeInteraction {
+            pool: Address::repeat_byte(0x01),
+            kind,
+            i: 0,
+            j: 2,
+            amount_in_max: TokenAmount::new(Address::repeat_byte(0x02), U256::from(1_000)),
+            amount_out: TokenAmount::new(Address::repeat_byte(0x03), U256::from(990)),
+        };
+
+        let (target, value, call_data) = interaction(PoolKind::StableSwap).encode();
+        assert_eq!(target, Address::repeat_byte(0x01));
+        assert_eq!(value, U256::ZERO);
+        // exchange(int128,int128,uint256,uint256)
+        assert_eq!(call_data[..4], hex!("3df02124"));
+        assert_eq!(call_data[36..68], U256::from(2).to_be_bytes::<32>());
+        assert_eq!(call_data[100..132], U256::from(990).to_be_bytes::<32>());
+
+        let (_, _, call_data) = interaction(PoolKind::TricryptoNg).encode();
+        // exchange(uint256,uint256,uint256,uint256)
+        assert_eq!(call_data[..4], hex!("5b41b908"));
+    }
+}
@@ -1,5 +1,6 @@
 pub mod allowances;
 mod balancer_v2;
+mod curve;
 mod erc20;
 mod uniswap_v2;
 mod uniswap_v3;
@@ -8,6 +9,7 @@ mod zeroex;
 
 pub use {
     balancer_v2::BalancerSwapGivenOutInteraction,
+    curve::CurveExchangeInteraction,
     erc20::Erc20ApproveInteraction,
     uniswap_v2::UniswapInteraction,
     uniswap_v3::UniswapV3Interaction,
@@ -0,0 +1,229 @@
+//! Module for providing Curve pool liquidity to the solvers.
+
+use {
+    crate::{
+        interactions::{
+            CurveExchangeInteraction,
+            allowances::{AllowanceManager, AllowanceManaging, Allowances},
+        },
+        liquidity::{AmmOrderExecution, CurvePoolOrder, Liquidity, SettlementHandling},
+        liquidity_collector::LiquidityCollecting,
+        settlement::SettlementEncoder,
+    },
+    alloy::primitives::Address,
+    anyhow::{Context, Result},
+    futures::future::try_join_all,
+    model::TokenPair,
+    shared::{
+        ethrpc::Web3,
+        http_solver::model::TokenAmount,
+        recent_block_cache::Block,
+        sources::curve::pool_fetching::{CurvePoolFetching, PoolKind},
+    },
+    std::{collections::HashSet, sync::Arc},
+    tracing::instrument,
+};
+
+/// A liquidity provider for Curve StableSwap and CryptoSwap pools.
+pub struct CurveLiquidity {
+    pool_fetcher: Arc<dyn CurvePoolFetching>,
+    allowance_manager: Box<dyn AllowanceManaging>,
+}
+
+impl CurveLiquidity {
+    pub fn new(web3: Web3, pool_fetcher: Arc<dyn CurvePoolFetching>, settlement: Address) -> Self {
+        Self {
+            pool_fetcher,
+            allowance_manager: Box::new(AllowanceManager::new(web3, settlement)),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl LiquidityCollecting for CurveLiquidity {
+    /// Returns relevant Curve pools given a list of off-chain orders.
+    #[instrument(name = "curve_liquidity", skip_all)]
+    async fn get_liquidity(
+        &self,
+        pairs: HashSet<TokenPair>,
+        block: Block,
+    ) -> Result<Vec<Liquidity>> {
+        let pools = self.pool_fetcher.fetch(pairs, block).await?;
+
+        // Every Curve pool pulls tokens itself, so allowances are tracked per
+        // pool rather than for a single shared spender.
+        try_join_all(pools.into_iter().map(|pool| async move {
+            let allowances = self
+                .allowance_manager
+                .get_allowances(pool.coins.iter().copied().collect(), pool.address)
+                .await?;
+            anyhow::Ok(Liquidity::Curve(CurvePoolOrder {
+                settlement_handling: Arc::new(SettlementHandler::new(
+                    pool.address,
+                    pool.kind,
+                    pool.coins.clone(),
+                    allowances,
+                )),
+                pool,
+            }))
+        }))
+        .await
+    }
+}
+
+pub struct SettlementHandler {
+    pool: Address,
+    kind: PoolKind,
+    coins: Vec<Address>,
+    allowances: Allowances,
+}
+
+impl SettlementHandler {
+    pub fn new(pool: Address, kind: PoolKind, coins: Vec<Address>, allowances: Allowances) -> Self {
+        Self {
+            pool,
+            kind,
+            coins,
+            allowances,
+        }
+    }
+
+    pub fn pool(&self) -> Address {
+        self.pool
+    }
+
+    pub fn kind(&self) -> PoolKind {
+        self.kind
+    }
+
+    /// Returns the exchange interaction selling `input_max` for at least
+    /// `output`. Returns `Err` if either token is not a pool coin.
+    pub fn swap(
+        &self,
+        input_max: TokenAmount,
+        output: TokenAmount,
+    ) -> Result<CurveExchangeInteraction> {
+        let index = |token| {
+            self.coins
+                .iter()
+                .position(|coin| *coin == token)
+                .with_context(|| format!("token {token:?} not in Curve pool {:?}", self.pool))
+        };
+        Ok(CurveExchangeInteraction {
+            pool: self.pool,
+            kind: self.kind,
+            i: index(input_max.token)?,
+            j: index(output.token)?,
+            amount_in_max: input_max,
+            amount_out: output,
+        })
+    }
+}
+
+impl SettlementHandling<CurvePoolOrder> for SettlementHandler {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn encode(&self, execution: AmmOrderExecution, encoder: &mut SettlementEncoder) -> Result<()> {
+        let swap = self.swap(execution.input_max.clone(), execution.output)?;
+        if let Some(approval) = self.allowances.approve_token(execution.input_max)? {
+            encoder.append_to_execution_plan_internalizable(
+                Arc::new(approval),
+                execution.internalizable,
+            );
+        }
+        encoder.append_to_execution_plan_internalizable(Arc::new(swap), execution.internalizable);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::interactions::allowances::Approval,
+        alloy::primitives::U256,
+        maplit::hashmap,
+        shared::{http_solver::model::InternalizationStrategy, interaction::Interaction},
+    };
+
+    #[test]
+    fn encodes_approval_and_exchange() {
+        let pool = Address::repeat_byte(0x90);
+        let coins = vec![Address::repeat_byte(0x70), Address::repeat_byte(0x71)];
+        let handler = SettlementHandler::new(
+            pool,
+            PoolKind::StableSwapNg,
+            coins,
+            Allowances::new(
+                pool,
+                hashmap! {
+                    Address::repeat_byte(0x70) => U256::from(0),
+                    Address::repeat_byte(0x71) => U256::from(100),
+                },
+            ),
+        );
+
+        let mut encoder = SettlementEncoder::new(Default::default());
+        for (sell, buy) in [(0x70, 0x71), (0x71, 0x70)] {
+            handler
+                .encode(
+                    AmmOrderExecution {
+                        input_max: TokenAmount::new(Address::repeat_byte(sell), U256::from(10)),
+                        output: TokenAmount::new(Address::repeat_byte(buy), U256::from(9)),
+                        internalizable: false,
+                    },
+                    &mut encoder,
+                )
+                .unwrap();
+        }
+
+        let [_, interactions, _] = encoder
+            .finish(InternalizationStrategy::SkipInternalizableInteraction)
+            .interactions;
+        assert_eq!(
+            interactions,
+            [
+                Approval {
+                    token: Address::repeat_byte(0x70),
+                    spender: pool,
+                }
+                .encode(),
+                handler
+                    .swap(
+                        TokenAmount::new(Address::repeat_byte(0x70), U256::from(10)),
+                        TokenAmount::new(Address::repeat_byte(0x71), U256::from(9)),
+                    )
+                    .unwrap()
+                    .encode(),
+                handler
+                    .swap(
+                        TokenAmount::new(Address::repeat_byte(0x71), U256::from(10)),
+                        TokenAmount::new(Address::repeat_byte(0x70), U256::from(9)),
+                    )
+                    .unwrap()
+                    .encode(),
+            ],
+        );
+    }
+
+    #[test]
+    fn rejects_foreign_tokens() {
+        let handler = SettlementHandler::new(
+            Address::repeat_byte(0x90),
+            PoolKind::TwoCrypto,
+            vec![Address::repeat_byte(0x70), Address::repeat_byte(0x71)],
+            Allowances::empty(Address::repeat_byte(0x90)),
+        );
+
+        assert!(
+            handler
+                .swap(
+                    TokenAmount::new(Address::repeat_byte(0x70), U256::from(10)),
+                    TokenAmount::new(Address::repeat_byte(0x72), U256::from(9)),
+                )
+                .is_err()
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub mod balancer_v2;
+pub mod curve;
 pub mod slippage;
 pub mod uniswap_v2;
 pub mod uniswap_v3;
@@ -27,6 +28,7 @@ use {
                 },
                 swap::fixed_point::Bfp,
             },
+            curve::pool_fetching::Pool as CurvePool,
             uniswap_v2::pool_fetching::Pool,
             uniswap_v3::pool_fetching::PoolInfo,
         },
@@ -44,6 +46,7 @@ pub enum Liquidity {
     BalancerStable(StablePoolOrder),
     LimitOrder(LimitOrder),
     Concentrated(ConcentratedLiquidity),
+    Curve(CurvePoolOrder),
 }
 
 /// A trait associating some liquidity model to how it is executed and encoded
@@ -315,6 +318,26 @@ impl std::fmt::Debug for StablePoolOrder {
     }
 }
 
+/// Curve StableSwap or CryptoSwap pool.
+#[derive(Clone)]
+#[cfg_attr(test, derive(Derivative))]
+#[cfg_attr(test, derivative(PartialEq))]
+pub struct CurvePoolOrder {
+    pub pool: CurvePool,
+    #[cfg_attr(test, derivative(PartialEq = "ignore"))]
+    pub settlement_handling: Arc<dyn SettlementHandling<Self>>,
+}
+
+impl std::fmt::Debug for CurvePoolOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Curve Pool {:?} {:?}",
+            self.pool.address, self.pool.coins
+        )
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct AmmOrderExecution {
     pub input_max: TokenAmount,
@@ -346,6 +369,14 @@ impl Settleable for StablePoolOrder {
     }
 }
 
+impl Settleable for CurvePoolOrder {
+    type Execution = AmmOrderExecution;
+
+    fn settlement_handling(&self) -> &dyn SettlementHandling<Self> {
+        &*self.settlement_handling
+    }
+}
+
 /// Concentrated type of liquidity with ticks (e.g. UniswapV3)
 #[derive(Clone)]
 #[cfg_attr(test, derive(Derivative))]
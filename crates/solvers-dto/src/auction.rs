@@ -168,6 +168,7 @@ pub enum Liquidity {
     Stable(StablePool),
     ConcentratedLiquidity(ConcentratedLiquidityPool),
     LimitOrder(ForeignLimitOrder),
+    Curve(CurvePool),
 }
 
 #[serde_as]
@@ -265,6 +266,72 @@ pub struct ConcentratedLiquidityPool {
     pub fee: BigDecimal,
 }
 
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurvePool {
+    pub id: String,
+    pub address: Address,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub gas_estimate: U256,
+    /// The pool coins and their balances, in pool order.
+    pub tokens: Vec<CurveReserve>,
+    pub parameters: CurveParameters,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurveReserve {
+    pub address: Address,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub balance: U256,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum CurveParameters {
+    StableSwap(CurveStableParameters),
+    StableSwapNg(CurveStableParameters),
+    TwoCrypto(CurveCryptoParameters),
+    TwoCryptoNg(CurveCryptoParameters),
+    TricryptoNg(CurveCryptoParameters),
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurveStableParameters {
+    #[serde_as(as = "Vec<HexOrDecimalU256>")]
+    pub rates: Vec<U256>,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub amp: U256,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub fee: U256,
+    #[serde_as(as = "Option<HexOrDecimalU256>")]
+    pub offpeg_fee_multiplier: Option<U256>,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurveCryptoParameters {
+    #[serde_as(as = "Vec<HexOrDecimalU256>")]
+    pub precisions: Vec<U256>,
+    #[serde_as(as = "Vec<HexOrDecimalU256>")]
+    pub price_scale: Vec<U256>,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub ann: U256,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub gamma: U256,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub mid_fee: U256,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub out_fee: U256,
+    #[serde_as(as = "HexOrDecimalU256")]
+    pub fee_gamma: U256,
+}
+
 #[serde_as]
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -1,7 +1,7 @@
 //! Serve a solver engine API.
 
 use {
-    crate::domain::solver::Solver,
+    crate::{domain::solver::Solver, infra::archive::Archive},
     observe::distributed_tracing::tracing_axum::{make_span, record_trace_id},
     std::{future::Future, net::SocketAddr, sync::Arc},
     tokio::sync::oneshot,
@@ -9,11 +9,15 @@ use {
 
 mod routes;
 
+pub use routes::solve_auction;
+
 const REQUEST_BODY_LIMIT: usize = 10 * 1024 * 1024;
 
 pub struct Api {
     pub addr: SocketAddr,
     pub solver: Solver,
+    /// The archive solved auctions are recorded to, if any.
+    pub archive: Option<Archive>,
 }
 
 impl Api {
@@ -29,6 +33,8 @@ impl Api {
             .route("/metrics", axum::routing::get(routes::metrics))
             .route("/healthz", axum::routing::get(routes::healthz))
             .route("/solve", axum::routing::post(routes::solve))
+            .route("/notify", axum::routing::post(routes::notify))
+            .layer(axum::Extension(self.archive.map(Arc::new)))
             .layer(
                 tower::ServiceBuilder::new()
                     .layer(tower_http::trace::TraceLayer::new_for_http().make_span_with(make_span))
@@ -1,5 +1,49 @@
-use axum::{http::StatusCode, response::IntoResponse};
+use {
+    crate::domain::solver::Solver,
+    axum::{extract::State, http::StatusCode, response::IntoResponse},
+    serde::Serialize,
+    std::sync::Arc,
+};
 
-pub async fn healthz() -> impl IntoResponse {
-    StatusCode::OK
+/// Reports the solver as healthy, listing the upstream API mirrors it
+/// currently avoids because of open circuit breakers. The engine keeps
+/// solving while upstreams are degraded, so the status code stays `200`.
+pub async fn healthz(State(solver): State<Arc<Solver>>) -> impl IntoResponse {
+    let open_breakers = solver
+        .open_breakers()
+        .into_iter()
+        .map(|breaker| OpenBreaker {
+            service: breaker.service,
+            url: breaker.url.to_string(),
+            half_open: breaker.half_open,
+        })
+        .collect::<Vec<_>>();
+    let status = if open_breakers.is_empty() {
+        "ok"
+    } else {
+        "degraded"
+    };
+
+    (
+        StatusCode::OK,
+        axum::Json(Health {
+            status,
+            open_breakers,
+        }),
+    )
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Health {
+    status: &'static str,
+    open_breakers: Vec<OpenBreaker>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OpenBreaker {
+    service: &'static str,
+    url: String,
+    half_open: bool,
 }
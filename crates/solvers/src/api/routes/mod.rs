@@ -2,9 +2,11 @@ use serde::Serialize;
 
 mod healthz;
 mod metrics;
+mod notify;
 mod solve;
 
-pub(super) use {healthz::healthz, metrics::metrics, solve::solve};
+pub use solve::solve_auction;
+pub(super) use {healthz::healthz, metrics::metrics, notify::notify, solve::solve};
 
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
@@ -0,0 +1,76 @@
+pub use solvers_dto::notification::Notification;
+use {
+    crate::{
+        domain::{auction, eth, notification, solution},
+        util::bytes::Bytes,
+    },
+    solvers_dto::notification::{Kind, SolutionId},
+};
+
+/// Converts a data transfer object into its domain object representation.
+pub fn to_domain(notification: Notification) -> notification::Notification {
+    notification::Notification {
+        auction_id: match notification.auction_id {
+            Some(id) => auction::Id::Solve(id),
+            None => auction::Id::Quote,
+        },
+        solution_id: notification.solution_id.map(|id| match id {
+            SolutionId::Single(id) => notification::Id::Single(solution::Id(id)),
+            SolutionId::Merged(ids) => notification::Id::Merged(ids),
+        }),
+        kind: match notification.kind {
+            Kind::Timeout => notification::Kind::Timeout,
+            Kind::EmptySolution => notification::Kind::EmptySolution,
+            Kind::DuplicatedSolutionId => notification::Kind::DuplicatedSolutionId,
+            Kind::SimulationFailed {
+                block,
+                tx,
+                succeeded_once,
+            } => notification::Kind::SimulationFailed(
+                block,
+                eth::Tx {
+                    from: tx.from,
+                    to: tx.to,
+                    value: eth::Ether(tx.value),
+                    input: Bytes(tx.input),
+                    access_list: tx.access_list,
+                },
+                succeeded_once,
+            ),
+            Kind::InvalidClearingPrices => {
+                notification::Kind::ScoringFailed(notification::ScoreKind::InvalidClearingPrices)
+            }
+            Kind::MissingPrice { token_address } => notification::Kind::ScoringFailed(
+                notification::ScoreKind::MissingPrice(eth::TokenAddress(token_address)),
+            ),
+            Kind::InvalidExecutedAmount => {
+                notification::Kind::ScoringFailed(notification::ScoreKind::InvalidExecutedAmount)
+            }
+            Kind::NonBufferableTokensUsed { tokens } => {
+                notification::Kind::NonBufferableTokensUsed(
+                    tokens.into_iter().map(eth::TokenAddress).collect(),
+                )
+            }
+            Kind::SolverAccountInsufficientBalance { required } => {
+                notification::Kind::SolverAccountInsufficientBalance(eth::Ether(required))
+            }
+            Kind::Success { transaction } => {
+                notification::Kind::Settled(notification::Settlement::Success(transaction))
+            }
+            Kind::Revert { transaction } => {
+                notification::Kind::Settled(notification::Settlement::Revert(transaction))
+            }
+            Kind::DriverError { reason } => notification::Kind::DriverError(reason),
+            Kind::Cancelled => {
+                notification::Kind::Settled(notification::Settlement::SimulationRevert)
+            }
+            Kind::Expired => notification::Kind::Settled(notification::Settlement::Expired),
+            Kind::Fail => notification::Kind::Settled(notification::Settlement::Fail),
+            Kind::PostprocessingTimedOut => notification::Kind::PostprocessingTimedOut,
+            Kind::Banned { until, .. } => notification::Kind::Banned(until),
+            Kind::DeserializationError { reason } => {
+                notification::Kind::DeserializationError(reason)
+            }
+        },
+    }
+}
@@ -95,6 +95,7 @@ pub fn into_domain(auction: Auction) -> Result<auction::Auction, Error> {
                     concentrated_liquidity_pool::to_domain(liquidity)
                 }
                 Liquidity::LimitOrder(liquidity) => Ok(foreign_limit_order::to_domain(liquidity)),
+                Liquidity::Curve(liquidity) => curve_pool::to_domain(liquidity),
             })
             .try_collect()?,
         gas_price: auction::GasPrice(eth::Ether(auction.effective_gas_price)),
@@ -271,3 +272,99 @@ mod foreign_limit_order {
         }
     }
 }
+
+mod curve_pool {
+    use {
+        super::*,
+        crate::domain::curve::pool::{self as curve, cryptoswap, stableswap},
+    };
+
+    pub fn to_domain(pool: &CurvePool) -> Result<liquidity::Liquidity, Error> {
+        let coins = pool
+            .tokens
+            .iter()
+            .map(|reserve| eth::TokenAddress(reserve.address))
+            .collect::<Vec<_>>();
+        if coins.len() < 2 || !coins.iter().all_unique() {
+            return Err("invalid Curve pool tokens".into());
+        }
+        let balances = pool
+            .tokens
+            .iter()
+            .map(|reserve| reserve.balance)
+            .collect::<Vec<_>>();
+
+        let (kind, state) = match &pool.parameters {
+            CurveParameters::StableSwap(parameters) => {
+                (curve::Kind::StableSwap, stable_state(balances, parameters)?)
+            }
+            CurveParameters::StableSwapNg(parameters) => (
+                curve::Kind::StableSwapNg,
+                stable_state(balances, parameters)?,
+            ),
+            CurveParameters::TwoCrypto(parameters) => {
+                (curve::Kind::TwoCrypto, crypto_state(balances, parameters)?)
+            }
+            CurveParameters::TwoCryptoNg(parameters) => (
+                curve::Kind::TwoCryptoNg,
+                crypto_state(balances, parameters)?,
+            ),
+            CurveParameters::TricryptoNg(parameters) => (
+                curve::Kind::TricryptoNg,
+                crypto_state(balances, parameters)?,
+            ),
+        };
+        if kind.n_coins().is_some_and(|n| n != coins.len()) {
+            return Err("invalid number of Curve pool tokens".into());
+        }
+
+        Ok(liquidity::Liquidity {
+            id: liquidity::Id(pool.id.clone()),
+            address: pool.address,
+            gas: eth::Gas(pool.gas_estimate),
+            state: liquidity::State::Curve(liquidity::curve::Pool { kind, coins, state }),
+        })
+    }
+
+    fn stable_state(
+        balances: Vec<eth::U256>,
+        parameters: &CurveStableParameters,
+    ) -> Result<curve::State, Error> {
+        if parameters.rates.len() != balances.len() {
+            return Err("invalid number of Curve pool rates".into());
+        }
+        Ok(curve::State::Stable(stableswap::Pool {
+            balances,
+            rates: parameters.rates.clone(),
+            amp: parameters.amp,
+            fee: parameters.fee,
+            offpeg_fee_multiplier: parameters.offpeg_fee_multiplier,
+            // The LP token supply is only needed for pricing liquidity
+            // additions and removals, which aren't offered as auction
+            // liquidity.
+            total_supply: eth::U256::ZERO,
+        }))
+    }
+
+    fn crypto_state(
+        balances: Vec<eth::U256>,
+        parameters: &CurveCryptoParameters,
+    ) -> Result<curve::State, Error> {
+        if parameters.precisions.len() != balances.len()
+            || parameters.price_scale.len() + 1 != balances.len()
+        {
+            return Err("invalid number of Curve pool precisions or prices".into());
+        }
+        Ok(curve::State::Crypto(cryptoswap::Pool {
+            balances,
+            precisions: parameters.precisions.clone(),
+            price_scale: parameters.price_scale.clone(),
+            ann: parameters.ann,
+            gamma: parameters.gamma,
+            mid_fee: parameters.mid_fee,
+            out_fee: parameters.out_fee,
+            fee_gamma: parameters.fee_gamma,
+            total_supply: eth::U256::ZERO,
+        }))
+    }
+}
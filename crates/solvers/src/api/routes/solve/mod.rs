@@ -2,36 +2,54 @@ use {super::Response, tracing::Instrument};
 
 mod dto;
 
-use {crate::domain::solver::Solver, std::sync::Arc};
+use {
+    crate::{
+        domain::solver::Solver,
+        infra::archive::{self, Archive},
+    },
+    std::sync::Arc,
+};
 
 pub async fn solve(
     state: axum::extract::State<Arc<Solver>>,
+    axum::extract::Extension(archive): axum::extract::Extension<Option<Arc<Archive>>>,
     axum::extract::Json(auction): axum::extract::Json<dto::Auction>,
 ) -> (
     axum::http::StatusCode,
     axum::response::Json<Response<dto::Solutions>>,
 ) {
     let handle_request = async {
-        let auction = match dto::auction::into_domain(auction) {
-            Ok(value) => value,
+        let received_at = chrono::Utc::now();
+        // The auction is consumed by solving, so keep a copy to record.
+        let recorded = match &archive {
+            Some(_) => serde_json::to_value(&auction).ok(),
+            None => None,
+        };
+        let session = archive.as_ref().map(|_| archive::Session::record());
+
+        let solutions = match solve_auction(&state, auction, session.clone()).await {
+            Ok(solutions) => solutions,
             Err(err) => {
                 tracing::warn!(?err, "invalid auction");
                 return (
                     axum::http::StatusCode::BAD_REQUEST,
-                    axum::response::Json(Response::Err(err)),
+                    axum::response::Json(Response::Err(err.into())),
                 );
             }
         };
 
-        let auction_id = auction.id;
-        let solutions = state
-            .solve(auction)
-            .instrument(tracing::info_span!("auction", id = %auction_id))
-            .await;
-
-        tracing::trace!(?auction_id, ?solutions);
+        if let (Some(archive), Some(session), Some(auction)) = (archive, session, recorded) {
+            match serde_json::from_value(auction) {
+                Ok(auction) => archive.append(archive::Record {
+                    received_at,
+                    auction,
+                    exchanges: session.exchanges(),
+                    solutions: solutions.clone(),
+                }),
+                Err(err) => tracing::warn!(?err, "failed to archive auction"),
+            }
+        }
 
-        let solutions = dto::solution::from_domain(&solutions);
         (
             axum::http::StatusCode::OK,
             axum::response::Json(Response::Ok(solutions)),
@@ -42,3 +60,21 @@ pub async fn solve(
         .instrument(tracing::info_span!("/solve"))
         .await
 }
+
+/// Solves an auction within an archive session, if any.
+pub async fn solve_auction(
+    solver: &Solver,
+    auction: dto::Auction,
+    session: Option<Arc<archive::Session>>,
+) -> Result<dto::Solutions, &'static str> {
+    let auction = dto::auction::into_domain(auction).map_err(|err| err.message)?;
+
+    let auction_id = auction.id;
+    let solutions = archive::scope(session, solver.solve(auction))
+        .instrument(tracing::info_span!("auction", id = %auction_id))
+        .await;
+
+    tracing::trace!(?auction_id, ?solutions);
+
+    Ok(dto::solution::from_domain(&solutions))
+}
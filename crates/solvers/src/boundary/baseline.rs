@@ -218,6 +218,22 @@ fn to_boundary_liquidity(
                         }
                     }
                 }
+                liquidity::State::Curve(pool) => {
+                    if let Some(boundary_pool) =
+                        boundary::liquidity::curve::to_boundary_pool(pool, liquidity.gas)
+                    {
+                        for pair in pool.token_pairs() {
+                            let token_pair = to_boundary_token_pair(&pair);
+                            onchain_liquidity.entry(token_pair).or_default().push(
+                                OnchainLiquidity {
+                                    id: liquidity.id.clone(),
+                                    token_pair,
+                                    source: LiquiditySource::Curve(boundary_pool.clone()),
+                                },
+                            );
+                        }
+                    }
+                }
                 liquidity::State::LimitOrder(limit_order) => {
                     if let Some(token_pair) =
                         TokenPair::new(limit_order.maker.token.0, limit_order.taker.token.0)
@@ -275,6 +291,7 @@ enum LiquiditySource {
     Stable(boundary::liquidity::stable::Pool),
     LimitOrder(liquidity::limit_order::LimitOrder),
     Concentrated(boundary::liquidity::concentrated::Pool),
+    Curve(boundary::liquidity::curve::Pool),
 }
 
 impl BaselineSolvable for OnchainLiquidity {
@@ -291,6 +308,7 @@ impl BaselineSolvable for OnchainLiquidity {
                 limit_order.get_amount_out(out_token, input).await
             }
             LiquiditySource::Concentrated(pool) => pool.get_amount_out(out_token, input).await,
+            LiquiditySource::Curve(pool) => pool.get_amount_out(out_token, input).await,
         }
     }
 
@@ -307,6 +325,7 @@ impl BaselineSolvable for OnchainLiquidity {
                 limit_order.get_amount_in(in_token, out).await
             }
             LiquiditySource::Concentrated(pool) => pool.get_amount_in(in_token, out).await,
+            LiquiditySource::Curve(pool) => pool.get_amount_in(in_token, out).await,
         }
     }
 
@@ -317,6 +336,7 @@ impl BaselineSolvable for OnchainLiquidity {
             LiquiditySource::Stable(pool) => pool.gas_cost().await,
             LiquiditySource::LimitOrder(limit_order) => limit_order.gas_cost().await,
             LiquiditySource::Concentrated(pool) => pool.gas_cost().await,
+            LiquiditySource::Curve(pool) => pool.gas_cost().await,
         }
     }
 }
@@ -1,14 +1,19 @@
-//! Build CustomInteraction for Curve Router exchange calls.
+//! Build CustomInteraction for Curve Router exchange calls and direct pool
+//! liquidity calls.
 
-use {
-    crate::{
-        boundary::curve::router::{self, ROUTER_ADDRESS},
-        domain::{curve::api::Route, eth, solution},
+use crate::{
+    boundary::curve::{liquidity, router},
+    domain::{
+        curve::{api::Route, pool},
+        eth,
+        solution,
     },
 };
 
-/// Builds a CustomInteraction for executing a swap through the Curve Router.
+/// Builds a CustomInteraction for executing a swap through the Curve Router
+/// deployed at `router`.
 pub fn build_exchange_interaction(
+    router: eth::Address,
     route: &Route,
     sell_token: eth::TokenAddress,
     sell_amount: eth::U256,
@@ -19,7 +24,7 @@ pub fn build_exchange_interaction(
     let calldata = router::encode_exchange(route, sell_amount, min_output, receiver);
 
     solution::CustomInteraction {
-        target: ROUTER_ADDRESS,
+        target: router,
         value: eth::Ether(eth::U256::ZERO),
         calldata,
         internalize: false,
@@ -32,7 +37,7 @@ pub fn build_exchange_interaction(
             amount: min_output,
         }],
         allowances: vec![solution::Allowance {
-            spender: ROUTER_ADDRESS,
+            spender: router,
             asset: eth::Asset {
                 token: sell_token,
                 amount: sell_amount,
@@ -41,6 +46,76 @@ pub fn build_exchange_interaction(
     }
 }
 
+/// Builds a CustomInteraction calling a pool's liquidity function directly.
+///
+/// The pool sends its output to the caller, so unlike Router exchanges there
+/// is no receiver. Returns `None` if the call can't be encoded.
+pub fn build_pool_interaction(
+    call: &liquidity::Call,
+    sell_token: eth::TokenAddress,
+    sell_amount: eth::U256,
+    buy_token: eth::TokenAddress,
+    min_output: eth::U256,
+) -> Option<solution::CustomInteraction> {
+    let calldata = call.encode(sell_amount, min_output)?;
+    let allowances = if call.needs_allowance() {
+        vec![solution::Allowance {
+            spender: call.pool,
+            asset: eth::Asset {
+                token: sell_token,
+                amount: sell_amount,
+            },
+        }]
+    } else {
+        vec![]
+    };
+
+    Some(solution::CustomInteraction {
+        target: call.pool,
+        value: eth::Ether(eth::U256::ZERO),
+        calldata,
+        internalize: false,
+        inputs: vec![eth::Asset {
+            token: sell_token,
+            amount: sell_amount,
+        }],
+        outputs: vec![eth::Asset {
+            token: buy_token,
+            amount: min_output,
+        }],
+        allowances,
+    })
+}
+
+/// Builds a CustomInteraction burning `amount` of a pool's LP token for all
+/// of its coins in a balanced `remove_liquidity`, receiving at least
+/// `min_outputs`, one per coin in pool order. LP tokens are burnt by the pool
+/// without an allowance. Returns `None` if the call can't be encoded.
+pub fn build_remove_liquidity_interaction(
+    metadata: &pool::Metadata,
+    amount: eth::U256,
+    min_outputs: Vec<eth::Asset>,
+) -> Option<solution::CustomInteraction> {
+    let calldata = liquidity::encode_remove_liquidity(
+        metadata.kind,
+        amount,
+        min_outputs.iter().map(|output| output.amount).collect(),
+    )?;
+
+    Some(solution::CustomInteraction {
+        target: metadata.address,
+        value: eth::Ether(eth::U256::ZERO),
+        calldata,
+        internalize: false,
+        inputs: vec![eth::Asset {
+            token: metadata.lp_token,
+            amount,
+        }],
+        outputs: min_outputs,
+        allowances: vec![],
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,8 +133,10 @@ mod tests {
         let sell_token = eth::TokenAddress(Address::repeat_byte(1));
         let buy_token = eth::TokenAddress(Address::repeat_byte(2));
         let receiver = Address::repeat_byte(3);
+        let router = Address::repeat_byte(4);
 
         let interaction = build_exchange_interaction(
+            router,
             &route,
             sell_token,
             eth::U256::from(1000u64),
@@ -68,11 +145,71 @@ mod tests {
             receiver,
         );
 
-        assert_eq!(interaction.target, ROUTER_ADDRESS);
+        assert_eq!(interaction.target, router);
+        assert_eq!(interaction.allowances[0].spender, router);
         assert_eq!(interaction.inputs.len(), 1);
         assert_eq!(interaction.outputs.len(), 1);
         assert_eq!(interaction.allowances.len(), 1);
         assert_eq!(interaction.inputs[0].token, sell_token);
         assert_eq!(interaction.outputs[0].token, buy_token);
     }
+
+    #[test]
+    fn test_build_pool_interaction() {
+        let pool = Address::repeat_byte(4);
+        let call = |operation| liquidity::Call {
+            pool,
+            kind: crate::domain::curve::pool::Kind::StableSwapNg,
+            n_coins: 2,
+            operation,
+        };
+        let sell_token = eth::TokenAddress(Address::repeat_byte(1));
+        let buy_token = eth::TokenAddress(Address::repeat_byte(2));
+
+        let burn = build_pool_interaction(
+            &call(crate::domain::curve::pool::Operation::RemoveLiquidityOneCoin { j: 1 }),
+            sell_token,
+            eth::U256::from(1000u64),
+            buy_token,
+            eth::U256::from(990u64),
+        )
+        .unwrap();
+        assert_eq!(burn.target, pool);
+        assert!(burn.allowances.is_empty());
+
+        let mint = build_pool_interaction(
+            &call(crate::domain::curve::pool::Operation::AddLiquidity { i: 0 }),
+            sell_token,
+            eth::U256::from(1000u64),
+            buy_token,
+            eth::U256::from(990u64),
+        )
+        .unwrap();
+        assert_eq!(mint.allowances.len(), 1);
+        assert_eq!(mint.allowances[0].spender, pool);
+    }
+
+    #[test]
+    fn test_build_remove_liquidity_interaction() {
+        let metadata = pool::Metadata {
+            address: Address::repeat_byte(4),
+            lp_token: eth::TokenAddress(Address::repeat_byte(4)),
+            kind: pool::Kind::StableSwapNg,
+        };
+        let output = |byte| eth::Asset {
+            token: eth::TokenAddress(Address::repeat_byte(byte)),
+            amount: eth::U256::from(490u64),
+        };
+
+        let exit = build_remove_liquidity_interaction(
+            &metadata,
+            eth::U256::from(1000u64),
+            vec![output(1), output(2)],
+        )
+        .unwrap();
+        assert_eq!(exit.target, metadata.address);
+        assert_eq!(exit.inputs[0].token, metadata.lp_token);
+        assert_eq!(exit.outputs.len(), 2);
+        assert!(exit.allowances.is_empty());
+    }
 }
@@ -0,0 +1,335 @@
+//! Direct Curve pool liquidity calls.
+//!
+//! Single hop LP token mints and burns can be executed against the pool
+//! itself instead of through the Curve Router, which saves the Router's gas
+//! overhead. The ABI of the liquidity functions differs between pool
+//! flavours: older StableSwap and all CryptoSwap pools take fixed size amount
+//! arrays, StableSwap-NG pools take dynamic ones, and only StableSwap pools
+//! index coins with `int128`.
+
+use {
+    crate::domain::{
+        curve::{api::Route, gas, pool},
+        eth,
+    },
+    alloy::{primitives::U256, sol, sol_types::SolCall},
+};
+
+/// The placeholder Curve uses for native ETH, which pools send and receive
+/// as value rather than as a token transfer.
+pub const NATIVE_ETH: eth::Address =
+    alloy::primitives::address!("EeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE");
+
+sol! {
+    interface IPool2 {
+        function add_liquidity(uint256[2] amounts, uint256 min_mint_amount) external;
+        function remove_liquidity(uint256 amount, uint256[2] min_amounts) external;
+    }
+
+    interface IPool3 {
+        function add_liquidity(uint256[3] amounts, uint256 min_mint_amount) external;
+        function remove_liquidity(uint256 amount, uint256[3] min_amounts) external;
+    }
+
+    interface IPool4 {
+        function add_liquidity(uint256[4] amounts, uint256 min_mint_amount) external;
+        function remove_liquidity(uint256 amount, uint256[4] min_amounts) external;
+    }
+
+    interface IStableSwapNg {
+        function add_liquidity(uint256[] amounts, uint256 min_mint_amount) external;
+        function remove_liquidity(uint256 burn_amount, uint256[] min_amounts) external;
+    }
+
+    interface IStableSwap {
+        function remove_liquidity_one_coin(uint256 token_amount, int128 i, uint256 min_amount) external;
+    }
+
+    interface ICryptoSwap {
+        function remove_liquidity_one_coin(uint256 token_amount, uint256 i, uint256 min_amount) external;
+    }
+}
+
+/// A single hop LP token mint or burn that can be sent to the pool directly.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Call {
+    pub pool: eth::Address,
+    pub kind: pool::Kind,
+    pub n_coins: usize,
+    pub operation: pool::Operation,
+}
+
+impl Call {
+    /// Extracts the pool call performed by a Curve Router route, if the route
+    /// is a single `add_liquidity` or `remove_liquidity_one_coin` hop on a
+    /// pool whose ABI is supported.
+    pub fn from_route(route: &Route) -> Option<Self> {
+        if route.swap_params[1].iter().any(|param| *param != 0)
+            || route.pools[0] != eth::Address::ZERO
+            || route.route[..3].contains(&NATIVE_ETH)
+        {
+            return None;
+        }
+
+        let [i, j, swap_type, pool_type, n_coins] = route.swap_params[0];
+        let operation = match swap_type {
+            4 => pool::Operation::AddLiquidity { i: i as usize },
+            6 => pool::Operation::RemoveLiquidityOneCoin { j: j as usize },
+            _ => return None,
+        };
+        let call = Self {
+            pool: route.route[1],
+            kind: pool::Kind::from_router_pool_type(pool_type)?,
+            n_coins: n_coins as usize,
+            operation,
+        };
+        call.encode(U256::ZERO, U256::ZERO)?;
+        Some(call)
+    }
+
+    /// Encodes the call for `amount` of input tokens, reverting unless at
+    /// least `min_output` tokens are received.
+    pub fn encode(&self, amount: U256, min_output: U256) -> Option<Vec<u8>> {
+        match self.operation {
+            pool::Operation::AddLiquidity { i } => {
+                let mut amounts = vec![U256::ZERO; self.n_coins];
+                *amounts.get_mut(i)? = amount;
+                encode_add_liquidity(self.kind, amounts, min_output)
+            }
+            pool::Operation::RemoveLiquidityOneCoin { j } => {
+                if j >= self.n_coins {
+                    return None;
+                }
+                encode_remove_liquidity_one_coin(self.kind, amount, j, min_output)
+            }
+            pool::Operation::Exchange { .. } => None,
+        }
+    }
+
+    /// Whether the pool pulls the input tokens, so the settlement needs to
+    /// approve it. LP tokens are burnt by the pool without an allowance.
+    pub fn needs_allowance(&self) -> bool {
+        matches!(self.operation, pool::Operation::AddLiquidity { .. })
+    }
+
+    /// Estimated gas used by the call, which is that of the same hop in a
+    /// Router route without the Router's overhead.
+    pub fn gas(&self) -> u64 {
+        let swap_type = match self.operation {
+            pool::Operation::AddLiquidity { .. } => 4,
+            _ => 6,
+        };
+        gas::hop_gas(swap_type, self.kind.router_pool_type(), self.n_coins as u64)
+    }
+}
+
+/// Encodes `add_liquidity` for a pool of the specified flavour with one
+/// amount per coin.
+pub fn encode_add_liquidity(
+    kind: pool::Kind,
+    amounts: Vec<U256>,
+    min_mint_amount: U256,
+) -> Option<Vec<u8>> {
+    if kind == pool::Kind::StableSwapNg {
+        return Some(
+            IStableSwapNg::add_liquidityCall {
+                amounts,
+                min_mint_amount,
+            }
+            .abi_encode(),
+        );
+    }
+    if !supports(kind, amounts.len()) {
+        return None;
+    }
+    Some(match amounts.len() {
+        2 => IPool2::add_liquidityCall {
+            amounts: amounts.try_into().ok()?,
+            min_mint_amount,
+        }
+        .abi_encode(),
+        3 => IPool3::add_liquidityCall {
+            amounts: amounts.try_into().ok()?,
+            min_mint_amount,
+        }
+        .abi_encode(),
+        4 => IPool4::add_liquidityCall {
+            amounts: amounts.try_into().ok()?,
+            min_mint_amount,
+        }
+        .abi_encode(),
+        _ => return None,
+    })
+}
+
+/// Encodes a balanced `remove_liquidity` burning `amount` LP tokens for all
+/// coins of a pool of the specified flavour, with one minimum per coin.
+pub fn encode_remove_liquidity(
+    kind: pool::Kind,
+    amount: U256,
+    min_amounts: Vec<U256>,
+) -> Option<Vec<u8>> {
+    if kind == pool::Kind::StableSwapNg {
+        return Some(
+            IStableSwapNg::remove_liquidityCall {
+                burn_amount: amount,
+                min_amounts,
+            }
+            .abi_encode(),
+        );
+    }
+    if !supports(kind, min_amounts.len()) {
+        return None;
+    }
+    Some(match min_amounts.len() {
+        2 => IPool2::remove_liquidityCall {
+            amount,
+            min_amounts: min_amounts.try_into().ok()?,
+        }
+        .abi_encode(),
+        3 => IPool3::remove_liquidityCall {
+            amount,
+            min_amounts: min_amounts.try_into().ok()?,
+        }
+        .abi_encode(),
+        4 => IPool4::remove_liquidityCall {
+            amount,
+            min_amounts: min_amounts.try_into().ok()?,
+        }
+        .abi_encode(),
+        _ => return None,
+    })
+}
+
+/// Estimated gas used by a balanced `remove_liquidity`, which is that of a
+/// single coin burn plus the transfers of the other coins.
+pub fn remove_liquidity_gas(kind: pool::Kind, n_coins: usize) -> u64 {
+    gas::hop_gas(6, kind.router_pool_type(), 2)
+        .saturating_add((n_coins as u64).saturating_sub(1) * gas::GAS_PER_EXTRA_COIN)
+}
+
+/// Encodes `remove_liquidity_one_coin` burning `amount` LP tokens for coin
+/// `j` of a pool of the specified flavour.
+pub fn encode_remove_liquidity_one_coin(
+    kind: pool::Kind,
+    amount: U256,
+    j: usize,
+    min_amount: U256,
+) -> Option<Vec<u8>> {
+    Some(match kind {
+        pool::Kind::StableSwap | pool::Kind::StableSwapNg => {
+            IStableSwap::remove_liquidity_one_coinCall {
+                token_amount: amount,
+                i: j.try_into().ok()?,
+                min_amount,
+            }
+            .abi_encode()
+        }
+        pool::Kind::TwoCrypto | pool::Kind::TwoCryptoNg | pool::Kind::TricryptoNg => {
+            ICryptoSwap::remove_liquidity_one_coinCall {
+                token_amount: amount,
+                i: U256::from(j),
+                min_amount,
+            }
+            .abi_encode()
+        }
+    })
+}
+
+/// Whether pools of the specified flavour with `n_coins` coins take fixed
+/// size amount arrays of that size.
+fn supports(kind: pool::Kind, n_coins: usize) -> bool {
+    match kind.n_coins() {
+        Some(n) => n == n_coins,
+        None => (2..=4).contains(&n_coins),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloy::primitives::Address};
+
+    fn route(swap_params: [u64; 5]) -> Route {
+        let mut route = Route {
+            route: [Address::ZERO; 11],
+            swap_params: [[0; 5]; 5],
+            pools: [Address::ZERO; 5],
+            expected_output: U256::ZERO,
+        };
+        route.route[0] = Address::repeat_byte(1);
+        route.route[1] = Address::repeat_byte(2);
+        route.route[2] = Address::repeat_byte(3);
+        route.swap_params[0] = swap_params;
+        route
+    }
+
+    #[test]
+    fn single_hop_liquidity_routes() {
+        let call = Call::from_route(&route([0, 1, 6, 30, 3])).unwrap();
+        assert_eq!(call.pool, Address::repeat_byte(2));
+        assert_eq!(call.kind, pool::Kind::TricryptoNg);
+        assert_eq!(
+            call.operation,
+            pool::Operation::RemoveLiquidityOneCoin { j: 1 }
+        );
+        assert!(!call.needs_allowance());
+
+        let call = Call::from_route(&route([2, 0, 4, 10, 5])).unwrap();
+        assert_eq!(call.operation, pool::Operation::AddLiquidity { i: 2 });
+        assert!(call.needs_allowance());
+
+        // Exchanges, multi hop routes, unknown pool types and ETH go through
+        // the Router.
+        assert_eq!(Call::from_route(&route([0, 1, 1, 10, 2])), None);
+        let mut multi_hop = route([0, 1, 6, 10, 2]);
+        multi_hop.swap_params[1] = [0, 1, 1, 10, 2];
+        assert_eq!(Call::from_route(&multi_hop), None);
+        assert_eq!(Call::from_route(&route([0, 1, 6, 4, 2])), None);
+        let mut eth = route([0, 1, 6, 1, 2]);
+        eth.route[2] = NATIVE_ETH;
+        assert_eq!(Call::from_route(&eth), None);
+        // Fixed size arrays only exist for up to 4 coins.
+        assert_eq!(Call::from_route(&route([0, 1, 4, 1, 5])), None);
+    }
+
+    #[test]
+    fn abi_depends_on_pool_flavour() {
+        let amount = U256::from(1_000);
+        let min = U256::from(990);
+
+        let stable =
+            encode_remove_liquidity_one_coin(pool::Kind::StableSwap, amount, 1, min).unwrap();
+        let crypto =
+            encode_remove_liquidity_one_coin(pool::Kind::TwoCryptoNg, amount, 1, min).unwrap();
+        assert_eq!(
+            stable[..4],
+            IStableSwap::remove_liquidity_one_coinCall::SELECTOR
+        );
+        assert_eq!(
+            crypto[..4],
+            ICryptoSwap::remove_liquidity_one_coinCall::SELECTOR
+        );
+
+        let fixed =
+            encode_add_liquidity(pool::Kind::StableSwap, vec![amount, U256::ZERO], min).unwrap();
+        let dynamic =
+            encode_add_liquidity(pool::Kind::StableSwapNg, vec![amount, U256::ZERO], min).unwrap();
+        assert_eq!(fixed[..4], IPool2::add_liquidityCall::SELECTOR);
+        assert_eq!(dynamic[..4], IStableSwapNg::add_liquidityCall::SELECTOR);
+        assert_eq!(
+            encode_add_liquidity(pool::Kind::TricryptoNg, vec![amount, U256::ZERO], min),
+            None
+        );
+
+        let balanced =
+            encode_remove_liquidity(pool::Kind::TricryptoNg, amount, vec![U256::ZERO; 3]).unwrap();
+        assert_eq!(balanced[..4], IPool3::remove_liquidityCall::SELECTOR);
+        let dynamic =
+            encode_remove_liquidity(pool::Kind::StableSwapNg, amount, vec![U256::ZERO; 5]).unwrap();
+        assert_eq!(dynamic[..4], IStableSwapNg::remove_liquidityCall::SELECTOR);
+        assert_eq!(
+            encode_remove_liquidity(pool::Kind::StableSwap, amount, vec![U256::ZERO; 5]),
+            None
+        );
+    }
+}
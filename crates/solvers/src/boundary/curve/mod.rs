@@ -1,4 +1,7 @@
 //! Curve Protocol boundary layer - contract interactions and encoding.
 
 pub mod interactions;
+pub mod liquidity;
+pub mod pool;
+pub mod registry;
 pub mod router;
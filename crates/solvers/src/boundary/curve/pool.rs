@@ -0,0 +1,392 @@
+//! Reading Curve pool state over RPC for local pricing.
+
+use {
+    crate::domain::{
+        curve::{lp_price, pool},
+        eth,
+    },
+    alloy::{
+        eips::BlockId,
+        primitives::{Address, U256},
+        rpc::types::Log,
+        sol,
+        sol_types::{SolCall, SolEvent},
+    },
+    futures::future::{try_join, try_join_all},
+    shared::sources::curve::pool_fetching::{PoolKind, PoolReader, Statics, scale},
+    std::{fmt, sync::Arc},
+};
+
+sol! {
+    #[derive(Debug)]
+    interface ICurvePool {
+        function get_virtual_price() external view returns (uint256);
+    }
+
+    #[derive(Debug)]
+    interface IStableSwapNg {
+        function offpeg_fee_multiplier() external view returns (uint256);
+        function stored_rates() external view returns (uint256[] memory);
+    }
+
+    #[derive(Debug)]
+    interface ICryptoSwap {
+        function lp_price() external view returns (uint256);
+        function gamma() external view returns (uint256);
+    }
+
+    #[derive(Debug)]
+    interface ITwoCrypto {
+        function price_oracle() external view returns (uint256);
+    }
+
+    #[derive(Debug)]
+    interface ITricrypto {
+        function price_scale(uint256 k) external view returns (uint256);
+        function price_oracle(uint256 k) external view returns (uint256);
+    }
+
+    #[derive(Debug)]
+    interface IERC20 {
+        function totalSupply() external view returns (uint256);
+
+        event Transfer(address indexed from, address indexed to, uint256 value);
+        event Approval(address indexed owner, address indexed spender, uint256 value);
+    }
+}
+
+/// Fetches Curve pool state over RPC.
+///
+/// The state is read the same way as for the driver's Curve liquidity, see
+/// [`PoolReader`].
+pub struct Fetcher {
+    reader: PoolReader,
+}
+
+impl Fetcher {
+    pub fn new(provider: ethrpc::AlloyProvider) -> Self {
+        Self {
+            reader: PoolReader::new(provider),
+        }
+    }
+
+    /// Fetches the current state of the specified pool.
+    pub async fn fetch(&self, metadata: &pool::Metadata) -> Result<pool::Pool, Error> {
+        let statics = self.statics(metadata).await?;
+        let address = metadata.address;
+        let latest = BlockId::latest();
+
+        let balances = self.reader.balances(address, &statics, latest);
+        let total_supply =
+            self.reader
+                .call(metadata.lp_token.0, IERC20::totalSupplyCall {}, latest);
+        let (balances, total_supply) = try_join(balances, total_supply).await?;
+
+        let state = match metadata.kind {
+            pool::Kind::StableSwap | pool::Kind::StableSwapNg => {
+                let state = self
+                    .reader
+                    .stable_state(kind(metadata.kind), address, &statics, latest)
+                    .await?;
+                pool::State::Stable(pool::stableswap::Pool {
+                    balances,
+                    rates: state.rates,
+                    amp: state.amp,
+                    fee: state.fee,
+                    offpeg_fee_multiplier: state.offpeg_fee_multiplier,
+                    total_supply,
+                })
+            }
+            pool::Kind::TwoCrypto | pool::Kind::TwoCryptoNg | pool::Kind::TricryptoNg => {
+                let state = self
+                    .reader
+                    .crypto_state(kind(metadata.kind), address, &statics, latest)
+                    .await?;
+                pool::State::Crypto(pool::cryptoswap::Pool {
+                    balances,
+                    precisions: state.precisions,
+                    price_scale: state.price_scale,
+                    ann: state.ann,
+                    gamma: state.gamma,
+                    mid_fee: state.mid_fee,
+                    out_fee: state.out_fee,
+                    fee_gamma: state.fee_gamma,
+                    total_supply,
+                })
+            }
+        };
+
+        Ok(pool::Pool {
+            metadata: metadata.clone(),
+            coins: statics
+                .coins
+                .iter()
+                .copied()
+                .map(eth::TokenAddress)
+                .collect(),
+            state,
+        })
+    }
+
+    /// Fetches the inputs for pricing the LP token of the specified pool at
+    /// `block`.
+    pub async fn pricing(
+        &self,
+        metadata: &pool::Metadata,
+        block: u64,
+    ) -> Result<lp_price::Pricing, Error> {
+        let statics = self.statics(metadata).await?;
+        let address = metadata.address;
+        let block = BlockId::number(block);
+
+        let valuation = match metadata.kind {
+            pool::Kind::StableSwap | pool::Kind::StableSwapNg => {
+                let virtual_price =
+                    self.call_at(address, ICurvePool::get_virtual_priceCall {}, block);
+                let rates = async {
+                    match metadata.kind {
+                        pool::Kind::StableSwapNg => {
+                            self.call_at(address, IStableSwapNg::stored_ratesCall {}, block)
+                                .await
+                        }
+                        _ => statics
+                            .decimals
+                            .iter()
+                            .map(|decimals| scale(36, *decimals).map_err(Error::from))
+                            .collect(),
+                    }
+                };
+                let (virtual_price, rates) = try_join(virtual_price, rates).await?;
+                lp_price::Valuation::Stable {
+                    virtual_price,
+                    rates,
+                }
+            }
+            pool::Kind::TwoCrypto | pool::Kind::TwoCryptoNg | pool::Kind::TricryptoNg => {
+                let lp_price = self.call_at(address, ICryptoSwap::lp_priceCall {}, block);
+                let price_oracle = async {
+                    match metadata.kind {
+                        pool::Kind::TricryptoNg => {
+                            try_join_all((0..statics.coins.len() - 1).map(|k| {
+                                self.call_at(
+                                    address,
+                                    ITricrypto::price_oracleCall { k: U256::from(k) },
+                                    block,
+                                )
+                            }))
+                            .await
+                        }
+                        _ => Ok(vec![
+                            self.call_at(address, ITwoCrypto::price_oracleCall {}, block)
+                                .await?,
+                        ]),
+                    }
+                };
+                let (lp_price, price_oracle) = try_join(lp_price, price_oracle).await?;
+                lp_price::Valuation::Crypto {
+                    lp_price,
+                    price_oracle,
+                    precisions: statics
+                        .decimals
+                        .iter()
+                        .map(|decimals| scale(18, *decimals))
+                        .collect::<Result<_, _>>()?,
+                }
+            }
+        };
+
+        Ok(lp_price::Pricing {
+            coins: statics
+                .coins
+                .iter()
+                .copied()
+                .map(eth::TokenAddress)
+                .collect(),
+            valuation,
+        })
+    }
+
+    /// Detects the flavour of a pool by probing functions that only exist on
+    /// some pool implementations.
+    ///
+    /// CryptoSwap pools expose `gamma()`, Tricrypto pools additionally index
+    /// `price_scale(k)` and NG pools double as their own LP token. Among
+    /// StableSwap pools, only NG pools expose `offpeg_fee_multiplier()`.
+    pub async fn detect_kind(
+        &self,
+        address: Address,
+        lp_token: Address,
+        n_coins: usize,
+    ) -> Result<pool::Kind, Error> {
+        let is_crypto = self.call(address, ICryptoSwap::gammaCall {}).await.is_ok();
+        if is_crypto {
+            let is_tricrypto = n_coins == 3
+                && self
+                    .call(address, ITricrypto::price_scaleCall { k: U256::ZERO })
+                    .await
+                    .is_ok();
+            return match (is_tricrypto, n_coins, lp_token == address) {
+                (true, ..) => Ok(pool::Kind::TricryptoNg),
+                (false, 2, true) => Ok(pool::Kind::TwoCryptoNg),
+                (false, 2, false) => Ok(pool::Kind::TwoCrypto),
+                _ => Err(Error::InvalidPool("unsupported CryptoSwap pool")),
+            };
+        }
+
+        let is_ng = self
+            .call(address, IStableSwapNg::offpeg_fee_multiplierCall {})
+            .await
+            .is_ok();
+        Ok(if is_ng {
+            pool::Kind::StableSwapNg
+        } else {
+            pool::Kind::StableSwap
+        })
+    }
+
+    /// Fetches the current total supply of an LP token.
+    pub async fn total_supply(&self, lp_token: eth::TokenAddress) -> Result<U256, Error> {
+        self.call(lp_token.0, IERC20::totalSupplyCall {}).await
+    }
+
+    /// Returns the cached coins and decimals of a pool, fetching them on first
+    /// use.
+    async fn statics(&self, metadata: &pool::Metadata) -> Result<Arc<Statics>, Error> {
+        Ok(self
+            .reader
+            .statics(metadata.address, kind(metadata.kind))
+            .await?)
+    }
+
+    async fn call<C: SolCall>(&self, target: Address, call: C) -> Result<C::Return, Error> {
+        self.call_at(target, call, BlockId::latest()).await
+    }
+
+    async fn call_at<C: SolCall>(
+        &self,
+        target: Address,
+        call: C,
+        block: BlockId,
+    ) -> Result<C::Return, Error> {
+        Ok(self.reader.call(target, call, block).await?)
+    }
+}
+
+/// Whether a log emitted by a pool may reflect a change of its state.
+///
+/// NG pools are their own LP token, so they also emit ERC-20 events. Those
+/// don't change the pool's state, minting and burning LP tokens comes with
+/// a liquidity event of its own.
+pub fn changes_state(log: &Log) -> bool {
+    !matches!(
+        log.topic0(),
+        Some(topic)
+            if *topic == IERC20::Transfer::SIGNATURE_HASH
+                || *topic == IERC20::Approval::SIGNATURE_HASH
+    )
+}
+
+fn kind(kind: pool::Kind) -> PoolKind {
+    match kind {
+        pool::Kind::StableSwap => PoolKind::StableSwap,
+        pool::Kind::StableSwapNg => PoolKind::StableSwapNg,
+        pool::Kind::TwoCrypto => PoolKind::TwoCrypto,
+        pool::Kind::TwoCryptoNg => PoolKind::TwoCryptoNg,
+        pool::Kind::TricryptoNg => PoolKind::TricryptoNg,
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Rpc(String),
+    InvalidPool(&'static str),
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(err: anyhow::Error) -> Self {
+        Error::Rpc(format!("{err:#}"))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Rpc(msg) => write!(f, "RPC error: {}", msg),
+            Error::InvalidPool(msg) => write!(f, "invalid pool: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::tests::curve};
+
+    /// Reads a plain StableSwap pool from the fake node.
+    async fn read(pool: curve::Pool) -> pool::Pool {
+        let node = curve::Curve::new().await;
+        node.pool(pool.clone());
+        let provider = ethrpc::web3(
+            Default::default(),
+            Default::default(),
+            &node.node_url(),
+            "test",
+        )
+        .alloy;
+        Fetcher::new(provider)
+            .fetch(&pool::Metadata {
+                address: pool.address,
+                lp_token: eth::TokenAddress(pool.address),
+                kind: pool::Kind::StableSwap,
+            })
+            .await
+            .unwrap()
+    }
+
+    fn three_pool(precise: bool) -> curve::Pool {
+        curve::Pool {
+            address: Address::repeat_byte(0xaa),
+            coins: vec![
+                Address::repeat_byte(1),
+                Address::repeat_byte(2),
+                Address::repeat_byte(3),
+            ],
+            balances: vec![U256::from(10u64.pow(24)); 3],
+            amp: U256::from(2_000),
+            fee: U256::from(1_000_000),
+            total_supply: U256::from(3 * 10u64.pow(24)),
+            precise,
+        }
+    }
+
+    #[test]
+    fn erc20_events_dont_change_state() {
+        let log = |topic| Log {
+            inner: alloy::primitives::Log::new_unchecked(
+                Address::repeat_byte(0xaa),
+                vec![topic],
+                Default::default(),
+            ),
+            ..Default::default()
+        };
+        assert!(!changes_state(&log(IERC20::Transfer::SIGNATURE_HASH)));
+        assert!(!changes_state(&log(IERC20::Approval::SIGNATURE_HASH)));
+        assert!(changes_state(&log(alloy::primitives::keccak256(
+            "TokenExchange(address,int128,uint256,int128,uint256)"
+        ))));
+    }
+
+    #[tokio::test]
+    async fn legacy_pools_fall_back_to_plain_amplification() {
+        for precise in [true, false] {
+            let pool = read(three_pool(precise)).await;
+            let pool::State::Stable(state) = pool.state else {
+                panic!("expected a StableSwap pool");
+            };
+            assert_eq!(state.amp, U256::from(2_000 * pool::stableswap::A_PRECISION));
+            assert_eq!(state.balances, vec![U256::from(10u64.pow(24)); 3]);
+            assert_eq!(state.total_supply, U256::from(3 * 10u64.pow(24)));
+        }
+    }
+}
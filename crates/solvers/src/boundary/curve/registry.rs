@@ -0,0 +1,681 @@
+//! Discovery of Curve pools from the on-chain factories and the MetaRegistry.
+//!
+//! Pools deployed by the StableSwap-NG, Twocrypto-NG and Tricrypto-NG
+//! factories are indexed from their deployment events with
+//! [`shared::event_handling`]. Older pools, which predate these factories, are
+//! bootstrapped once from the MetaRegistry. Discovered pools are persisted to
+//! a JSON file so that a restart resumes indexing where it left off.
+
+use {
+    crate::{
+        boundary::curve::pool::Fetcher,
+        domain::{curve::pool, eth},
+    },
+    alloy::{
+        eips::BlockId,
+        primitives::{Address, U256},
+        providers::{DynProvider, Provider},
+        rpc::types::{Filter, FilterSet, Log},
+        sol_types::SolEvent,
+    },
+    anyhow::{Context, Result},
+    contracts::alloy::curve::{
+        CurveMetaRegistry,
+        CurveStableSwapNGFactory::{
+            self,
+            CurveStableSwapNGFactory::{
+                CurveStableSwapNGFactoryEvents as StableSwapNgEvent,
+                MetaPoolDeployed,
+                PlainPoolDeployed,
+            },
+        },
+        CurveTricryptoFactory::{
+            self,
+            CurveTricryptoFactory::{
+                CurveTricryptoFactoryEvents as TricryptoEvent,
+                TricryptoPoolDeployed,
+            },
+        },
+        CurveTwocryptoFactory::{
+            self,
+            CurveTwocryptoFactory::{
+                CurveTwocryptoFactoryEvents as TwocryptoEvent,
+                TwocryptoPoolDeployed,
+            },
+        },
+    },
+    ethrpc::block_stream::{CurrentBlockWatcher, RangeInclusive},
+    futures::{StreamExt, stream},
+    serde::{Deserialize, Serialize},
+    shared::{
+        event_handling::{AlloyEventRetrieving, EventHandler, EventStoring},
+        maintenance::{Maintaining, ServiceMaintenance},
+    },
+    std::{
+        collections::{HashMap, HashSet},
+        path::{Path, PathBuf},
+        sync::{Arc, RwLock},
+    },
+};
+
+/// Number of MetaRegistry pools resolved concurrently while bootstrapping.
+const BOOTSTRAP_CONCURRENCY: usize = 16;
+
+/// Configuration of the Curve pool registry.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub chain_id: u64,
+    /// File the indexed pools are persisted to. `None` keeps them in memory,
+    /// which requires a full re-index on every start.
+    pub path: Option<PathBuf>,
+    /// Whether to bootstrap pools from the MetaRegistry if that didn't happen
+    /// before.
+    pub bootstrap: bool,
+}
+
+/// Curve pools discovered on-chain.
+#[derive(Clone)]
+pub struct Registry(Arc<Index>);
+
+impl Registry {
+    /// Loads the persisted pools and starts indexing the factories deployed on
+    /// the configured chain in the background on every new block. Without
+    /// `blocks` only the persisted and bootstrapped pools are known.
+    pub async fn new(
+        config: Config,
+        provider: DynProvider,
+        blocks: Option<CurrentBlockWatcher>,
+    ) -> Self {
+        let state = match &config.path {
+            Some(path) => State::load(path).unwrap_or_else(|err| {
+                tracing::warn!(?err, ?path, "failed to load persisted Curve pools");
+                State::default()
+            }),
+            None => State::default(),
+        };
+        tracing::info!(pools = state.pools.len(), "loaded persisted Curve pools");
+
+        let registry = Self(Arc::new(Index {
+            state: RwLock::new(state),
+            path: config.path.clone(),
+        }));
+
+        if config.bootstrap && !registry.0.read(|state| state.bootstrapped) {
+            let registry = registry.clone();
+            let provider = provider.clone();
+            tokio::spawn(async move {
+                if let Err(err) = registry.bootstrap(config.chain_id, provider).await {
+                    tracing::warn!(?err, "failed to bootstrap Curve pools from MetaRegistry");
+                }
+            });
+        }
+
+        match blocks {
+            Some(blocks) => registry.spawn_indexers(&config, provider, blocks),
+            None => tracing::warn!("no block stream, Curve factories are not indexed"),
+        }
+        registry
+    }
+
+    /// Returns whether `token` is the LP token of a discovered pool.
+    pub fn is_lp_token(&self, token: &eth::Address) -> bool {
+        self.0.read(|state| state.lp_tokens.contains_key(token))
+    }
+
+    /// Returns whether `token` is a coin of a discovered pool.
+    pub fn is_coin(&self, token: &eth::Address) -> bool {
+        self.0.read(|state| state.coins.contains(token))
+    }
+
+    /// Returns the pool minting the specified LP token.
+    pub fn pool(&self, lp_token: &eth::TokenAddress) -> Option<pool::Metadata> {
+        self.0.read(|state| {
+            let entry = state.pools.get(state.lp_tokens.get(&lp_token.0)?)?;
+            Some(pool::Metadata {
+                address: entry.address,
+                lp_token: eth::TokenAddress(entry.lp_token),
+                kind: pool::Kind::from_router_pool_type(entry.pool_type)?,
+            })
+        })
+    }
+
+    fn spawn_indexers(&self, config: &Config, provider: DynProvider, blocks: CurrentBlockWatcher) {
+        let chain_id = config.chain_id;
+        let mut maintainers: Vec<Arc<dyn Maintaining>> = Vec::new();
+        if let Some((address, block)) = CurveStableSwapNGFactory::deployment_info(chain_id) {
+            maintainers.push(self.indexer(
+                StableSwapNgFactory(CurveStableSwapNGFactory::Instance::new(
+                    address,
+                    provider.clone(),
+                )),
+                block,
+            ));
+        }
+        if let Some((address, block)) = CurveTwocryptoFactory::deployment_info(chain_id) {
+            maintainers.push(self.indexer(
+                TwocryptoFactory(CurveTwocryptoFactory::Instance::new(
+                    address,
+                    provider.clone(),
+                )),
+                block,
+            ));
+        }
+        if let Some((address, block)) = CurveTricryptoFactory::deployment_info(chain_id) {
+            maintainers.push(self.indexer(
+                TricryptoFactory(CurveTricryptoFactory::Instance::new(
+                    address,
+                    provider.clone(),
+                )),
+                block,
+            ));
+        }
+
+        if maintainers.is_empty() {
+            tracing::warn!(chain_id, "no Curve factories to index on this chain");
+            return;
+        }
+
+        let maintenance = ServiceMaintenance::new(maintainers);
+        tokio::task::spawn(maintenance.run_maintenance_on_new_block(blocks));
+    }
+
+    fn indexer<F: Factory>(
+        &self,
+        factory: F,
+        deployment_block: Option<u64>,
+    ) -> Arc<dyn Maintaining> {
+        tracing::info!(
+            factory = ?factory.factory_address(),
+            ?deployment_block,
+            "indexing Curve factory"
+        );
+        let storage = Storage {
+            factory: factory.clone(),
+            index: self.0.clone(),
+            // Start 1 block **before** the deployment to get all the events.
+            start_of_index: deployment_block.unwrap_or_default().saturating_sub(1),
+        };
+        Arc::new(tokio::sync::Mutex::new(EventHandler::new(
+            Arc::new(factory.provider().clone()),
+            factory,
+            storage,
+            None,
+        )))
+    }
+
+    /// Imports all pools listed in the MetaRegistry.
+    async fn bootstrap(&self, chain_id: u64, provider: DynProvider) -> Result<()> {
+        let address = CurveMetaRegistry::deployment_address(&chain_id)
+            .context("no MetaRegistry on this chain")?;
+        let registry = CurveMetaRegistry::Instance::new(address, provider.clone());
+        let fetcher = Fetcher::new(provider.clone());
+
+        let block = provider.get_block_number().await?;
+        let count = u64::try_from(registry.pool_count().call().await?)
+            .context("pool count overflows u64")?;
+        tracing::info!(count, "bootstrapping Curve pools from MetaRegistry");
+
+        let entries = stream::iter(0..count)
+            .map(|i| {
+                let registry = &registry;
+                let fetcher = &fetcher;
+                async move {
+                    let pool = registry.pool_list(U256::from(i)).call().await?;
+                    let (lp_token, coins, n_coins) = futures::try_join!(
+                        registry.get_lp_token(pool).call(),
+                        registry.get_coins(pool).call(),
+                        registry.get_n_coins(pool).call(),
+                    )?;
+                    let coins = coins
+                        .into_iter()
+                        .take(n_coins.saturating_to())
+                        .collect::<Vec<_>>();
+                    let kind = fetcher.detect_kind(pool, lp_token, coins.len()).await?;
+                    anyhow::Ok(Entry {
+                        address: pool,
+                        lp_token,
+                        pool_type: kind.router_pool_type(),
+                        coins,
+                        factory: None,
+                        block,
+                    })
+                }
+            })
+            .buffer_unordered(BOOTSTRAP_CONCURRENCY)
+            .filter_map(|result| async move {
+                result
+                    .inspect_err(|err| tracing::debug!(?err, "skipping MetaRegistry pool"))
+                    .ok()
+            })
+            .collect::<Vec<_>>()
+            .await;
+
+        tracing::info!(pools = entries.len(), "bootstrapped Curve pools");
+        self.0.write(|state| {
+            for entry in entries {
+                // Pools indexed from factory events take precedence.
+                if !state.pools.contains_key(&entry.address) {
+                    state.insert(entry);
+                }
+            }
+            state.bootstrapped = true;
+        });
+        self.0.persist()
+    }
+}
+
+/// The shared, persisted state of all factory indexers.
+struct Index {
+    state: RwLock<State>,
+    path: Option<PathBuf>,
+}
+
+impl Index {
+    fn read<T>(&self, f: impl FnOnce(&State) -> T) -> T {
+        f(&self
+            .state
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+
+    fn write<T>(&self, f: impl FnOnce(&mut State) -> T) -> T {
+        f(&mut self
+            .state
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+
+    /// Writes the current state to disk, if a path is configured.
+    fn persist(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let json = self.read(|state| serde_json::to_vec(&state.snapshot()))?;
+        // Write to a temporary file first so that a crash never leaves a
+        // truncated snapshot behind.
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, json).with_context(|| format!("writing {tmp:?}"))?;
+        std::fs::rename(&tmp, path).with_context(|| format!("renaming {tmp:?}"))?;
+        Ok(())
+    }
+}
+
+/// Discovered pools along with lookup tables derived from them.
+#[derive(Default)]
+struct State {
+    pools: HashMap<Address, Entry>,
+    /// Last indexed block per factory.
+    last_indexed_blocks: HashMap<Address, u64>,
+    bootstrapped: bool,
+    /// LP token to pool address.
+    lp_tokens: HashMap<Address, Address>,
+    coins: HashSet<Address>,
+}
+
+impl State {
+    fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let snapshot: Snapshot = serde_json::from_slice(&std::fs::read(path)?)?;
+        let mut state = Self {
+            last_indexed_blocks: snapshot.last_indexed_blocks.into_iter().collect(),
+            bootstrapped: snapshot.bootstrapped,
+            ..Default::default()
+        };
+        for entry in snapshot.pools {
+            state.insert(entry);
+        }
+        Ok(state)
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pools: self.pools.values().cloned().collect(),
+            last_indexed_blocks: self
+                .last_indexed_blocks
+                .iter()
+                .map(|(factory, block)| (*factory, *block))
+                .collect(),
+            bootstrapped: self.bootstrapped,
+        }
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        self.lp_tokens.insert(entry.lp_token, entry.address);
+        self.coins.extend(entry.coins.iter().copied());
+        self.pools.insert(entry.address, entry);
+    }
+
+    /// Removes the pools indexed from `factory` within the block range, e.g.
+    /// because of a reorg.
+    fn remove(&mut self, factory: Address, range: std::ops::RangeInclusive<u64>) {
+        let len = self.pools.len();
+        self.pools
+            .retain(|_, entry| entry.factory != Some(factory) || !range.contains(&entry.block));
+        if self.pools.len() == len {
+            return;
+        }
+
+        self.lp_tokens = self
+            .pools
+            .values()
+            .map(|entry| (entry.lp_token, entry.address))
+            .collect();
+        self.coins = self
+            .pools
+            .values()
+            .flat_map(|entry| entry.coins.iter().copied())
+            .collect();
+    }
+}
+
+/// A discovered pool.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    address: Address,
+    lp_token: Address,
+    /// The Curve Router pool type, see [`pool::Kind::router_pool_type`].
+    pool_type: u64,
+    coins: Vec<Address>,
+    /// The factory that deployed the pool, `None` for pools bootstrapped from
+    /// the MetaRegistry.
+    factory: Option<Address>,
+    /// The block at which the pool was discovered.
+    block: u64,
+}
+
+/// The persisted representation of [`State`].
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Snapshot {
+    pools: Vec<Entry>,
+    last_indexed_blocks: Vec<(Address, u64)>,
+    bootstrapped: bool,
+}
+
+/// A Curve factory whose pool deployment events are indexed.
+#[async_trait::async_trait]
+trait Factory: AlloyEventRetrieving + Clone + Send + Sync + 'static {
+    fn factory_address(&self) -> Address;
+
+    /// Resolves deployment events into the deployed pools.
+    async fn pools(&self, events: Vec<(Self::Event, Log)>) -> Result<Vec<Entry>>;
+}
+
+#[derive(Clone)]
+struct StableSwapNgFactory(CurveStableSwapNGFactory::Instance);
+
+impl AlloyEventRetrieving for StableSwapNgFactory {
+    type Event = StableSwapNgEvent;
+
+    fn filter(&self) -> Filter {
+        Filter::new()
+            .address(*self.0.address())
+            .event_signature(FilterSet::from_iter([
+                PlainPoolDeployed::SIGNATURE_HASH,
+                MetaPoolDeployed::SIGNATURE_HASH,
+            ]))
+    }
+
+    fn provider(&self) -> &DynProvider {
+        self.0.provider()
+    }
+}
+
+#[async_trait::async_trait]
+impl Factory for StableSwapNgFactory {
+    fn factory_address(&self) -> Address {
+        *self.0.address()
+    }
+
+    /// StableSwap-NG deployment events don't include the pool address. Pools
+    /// are appended to the factory's `pool_list` in deployment order, so the
+    /// last deployment of a block is the last pool listed at that block, the
+    /// one before it the second to last, and so on.
+    async fn pools(&self, events: Vec<(Self::Event, Log)>) -> Result<Vec<Entry>> {
+        let blocks = events
+            .iter()
+            .map(|(_, log)| log.block_number.context("missing block number"))
+            .collect::<Result<Vec<_>>>()?;
+        let mut remaining = HashMap::<u64, u64>::new();
+        for block in &blocks {
+            *remaining.entry(*block).or_default() += 1;
+        }
+
+        let mut entries = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            let from_end = remaining.get_mut(&block).expect("counted above");
+            let count = self
+                .0
+                .pool_count()
+                .block(BlockId::number(block))
+                .call()
+                .await?;
+            let index = count
+                .checked_sub(U256::from(*from_end))
+                .context("fewer pools listed than deployed")?;
+            *from_end -= 1;
+
+            let pool = self
+                .0
+                .pool_list(index)
+                .block(BlockId::number(block))
+                .call()
+                .await?;
+            let coins = self.0.get_coins(pool).call().await?;
+            entries.push(Entry {
+                address: pool,
+                lp_token: pool,
+                pool_type: pool::Kind::StableSwapNg.router_pool_type(),
+                coins,
+                factory: Some(self.factory_address()),
+                block,
+            });
+        }
+        Ok(entries)
+    }
+}
+
+#[derive(Clone)]
+struct TwocryptoFactory(CurveTwocryptoFactory::Instance);
+
+impl AlloyEventRetrieving for TwocryptoFactory {
+    type Event = TwocryptoEvent;
+
+    fn filter(&self) -> Filter {
+        Filter::new()
+            .address(*self.0.address())
+            .event_signature(FilterSet::from_iter([
+                TwocryptoPoolDeployed::SIGNATURE_HASH,
+            ]))
+    }
+
+    fn provider(&self) -> &DynProvider {
+        self.0.provider()
+    }
+}
+
+#[async_trait::async_trait]
+impl Factory for TwocryptoFactory {
+    fn factory_address(&self) -> Address {
+        *self.0.address()
+    }
+
+    async fn pools(&self, events: Vec<(Self::Event, Log)>) -> Result<Vec<Entry>> {
+        events
+            .into_iter()
+            .map(|(event, log)| {
+                let TwocryptoEvent::TwocryptoPoolDeployed(event) = event;
+                Ok(Entry {
+                    address: event.pool,
+                    lp_token: event.pool,
+                    pool_type: pool::Kind::TwoCryptoNg.router_pool_type(),
+                    coins: event.coins.to_vec(),
+                    factory: Some(self.factory_address()),
+                    block: log.block_number.context("missing block number")?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+struct TricryptoFactory(CurveTricryptoFactory::Instance);
+
+impl AlloyEventRetrieving for TricryptoFactory {
+    type Event = TricryptoEvent;
+
+    fn filter(&self) -> Filter {
+        Filter::new()
+            .address(*self.0.address())
+            .event_signature(FilterSet::from_iter([
+                TricryptoPoolDeployed::SIGNATURE_HASH,
+            ]))
+    }
+
+    fn provider(&self) -> &DynProvider {
+        self.0.provider()
+    }
+}
+
+#[async_trait::async_trait]
+impl Factory for TricryptoFactory {
+    fn factory_address(&self) -> Address {
+        *self.0.address()
+    }
+
+    async fn pools(&self, events: Vec<(Self::Event, Log)>) -> Result<Vec<Entry>> {
+        events
+            .into_iter()
+            .map(|(event, log)| {
+                let TricryptoEvent::TricryptoPoolDeployed(event) = event;
+                Ok(Entry {
+                    address: event.pool,
+                    lp_token: event.pool,
+                    pool_type: pool::Kind::TricryptoNg.router_pool_type(),
+                    coins: event.coins.to_vec(),
+                    factory: Some(self.factory_address()),
+                    block: log.block_number.context("missing block number")?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Stores the pools of a single factory in the shared [`Index`].
+struct Storage<F> {
+    factory: F,
+    index: Arc<Index>,
+    /// The earliest block where indexing the factory makes sense.
+    start_of_index: u64,
+}
+
+#[async_trait::async_trait]
+impl<F: Factory> EventStoring<(F::Event, Log)> for Storage<F> {
+    async fn replace_events(
+        &mut self,
+        events: Vec<(F::Event, Log)>,
+        range: RangeInclusive<u64>,
+    ) -> Result<()> {
+        let factory = self.factory.factory_address();
+        self.index
+            .write(|state| state.remove(factory, *range.start()..=*range.end()));
+        self.append_events(events).await
+    }
+
+    async fn append_events(&mut self, events: Vec<(F::Event, Log)>) -> Result<()> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let entries = self.factory.pools(events).await?;
+        self.index.write(|state| {
+            for entry in entries {
+                tracing::info!(pool = ?entry.address, coins = ?entry.coins, "indexed new Curve pool");
+                state.insert(entry);
+            }
+        });
+        Ok(())
+    }
+
+    async fn last_event_block(&self) -> Result<u64> {
+        let factory = self.factory.factory_address();
+        Ok(self.index.read(|state| {
+            state
+                .last_indexed_blocks
+                .get(&factory)
+                .copied()
+                .unwrap_or(self.start_of_index)
+        }))
+    }
+
+    async fn persist_last_indexed_block(&mut self, last_block: u64) -> Result<()> {
+        let factory = self.factory.factory_address();
+        self.index.write(|state| {
+            state.last_indexed_blocks.insert(factory, last_block);
+        });
+        self.index.persist()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(address: u8, factory: Option<u8>, block: u64, coins: &[u8]) -> Entry {
+        Entry {
+            address: Address::repeat_byte(address),
+            lp_token: Address::repeat_byte(address),
+            pool_type: pool::Kind::StableSwapNg.router_pool_type(),
+            coins: coins.iter().copied().map(Address::repeat_byte).collect(),
+            factory: factory.map(Address::repeat_byte),
+            block,
+        }
+    }
+
+    #[test]
+    fn reorged_pools_are_removed() {
+        let mut state = State::default();
+        state.insert(entry(0xa0, Some(0xf0), 10, &[1, 2]));
+        state.insert(entry(0xa1, Some(0xf0), 20, &[2, 3]));
+        state.insert(entry(0xa2, Some(0xf1), 20, &[4, 5]));
+        state.insert(entry(0xa3, None, 20, &[6, 7]));
+
+        state.remove(Address::repeat_byte(0xf0), 15..=25);
+
+        assert!(state.lp_tokens.contains_key(&Address::repeat_byte(0xa0)));
+        assert!(!state.lp_tokens.contains_key(&Address::repeat_byte(0xa1)));
+        assert!(state.lp_tokens.contains_key(&Address::repeat_byte(0xa2)));
+        assert!(state.lp_tokens.contains_key(&Address::repeat_byte(0xa3)));
+        assert!(state.coins.contains(&Address::repeat_byte(2)));
+        assert!(!state.coins.contains(&Address::repeat_byte(3)));
+    }
+
+    #[test]
+    fn snapshot_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pools.json");
+        let index = Index {
+            state: RwLock::new(State::default()),
+            path: Some(path.clone()),
+        };
+        index.write(|state| {
+            state.insert(entry(0xa0, Some(0xf0), 10, &[1, 2]));
+            state
+                .last_indexed_blocks
+                .insert(Address::repeat_byte(0xf0), 42);
+            state.bootstrapped = true;
+        });
+        index.persist().unwrap();
+
+        let state = State::load(&path).unwrap();
+        assert!(state.bootstrapped);
+        assert_eq!(state.last_indexed_blocks[&Address::repeat_byte(0xf0)], 42);
+        assert_eq!(
+            state.lp_tokens[&Address::repeat_byte(0xa0)],
+            Address::repeat_byte(0xa0)
+        );
+        assert!(state.coins.contains(&Address::repeat_byte(1)));
+    }
+}
@@ -3,15 +3,33 @@
 use {
     crate::domain::{curve::api::Route, eth},
     alloy::{
-        primitives::{Address, U256},
+        primitives::{Address, Bytes, U256, address},
         sol,
         sol_types::SolCall,
     },
+    chain::Chain,
     std::fmt,
 };
 
-/// Curve Router contract address on mainnet (v1.2).
-pub const ROUTER_ADDRESS: Address = alloy::primitives::address!("45312ea0eFf7E09C83CBE249fa1d7598c4C8cd4e");
+/// Returns the address of the Curve Router NG deployed on the specified
+/// chain, if any.
+pub fn deployment(chain: Chain) -> Option<Address> {
+    match chain {
+        // v1.2
+        Chain::Mainnet => Some(address!("45312ea0eFf7E09C83CBE249fa1d7598c4C8cd4e")),
+        Chain::ArbitrumOne => Some(address!("2191718CD32d02B8E60BAdFFeA33E4B5DD9A0A0D")),
+        Chain::Base => Some(address!("4f37A9d177470499A2dD084621020b023fcffc1F")),
+        Chain::Bnb => Some(address!("A72C85C258A81761433B4e8da60505Fe3Dd551CC")),
+        Chain::Gnosis | Chain::Optimism | Chain::Polygon | Chain::Avalanche => {
+            Some(address!("0DCDED3545D565bA3B19E683431381007245d983"))
+        }
+        _ => None,
+    }
+}
+
+/// Multicall3 address, deployed at the same address on all EVM chains.
+pub const MULTICALL_ADDRESS: Address =
+    alloy::primitives::address!("cA11bde05977b3631167028862bE2a173976CA11");
 
 // Define the Curve Router contract interface using alloy's sol! macro
 sol! {
@@ -35,6 +53,26 @@ sol! {
             address _receiver
         ) external payable returns (uint256);
     }
+
+    #[derive(Debug)]
+    interface IMulticall3 {
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+
+        struct Call3Result {
+            bool success;
+            bytes returnData;
+        }
+
+        /// Aggregate calls, optionally allowing individual calls to fail.
+        function aggregate3(Call3[] calldata calls)
+            external
+            payable
+            returns (Call3Result[] memory returnData);
+    }
 }
 
 /// Encodes a `get_dy` call for on-chain quote verification.
@@ -66,6 +104,24 @@ pub fn encode_exchange(
     call.abi_encode()
 }
 
+/// Decodes the route of an `exchange` call built with [`encode_exchange`].
+/// The route's expected output is the call's minimum output.
+pub fn decode_exchange(calldata: &[u8]) -> Option<Route> {
+    let call = ICurveRouter::exchangeCall::abi_decode(calldata).ok()?;
+    let mut swap_params = [[0; 5]; 5];
+    for (row, params) in swap_params.iter_mut().zip(&call._swap_params) {
+        for (param, value) in row.iter_mut().zip(params) {
+            *param = u64::try_from(*value).ok()?;
+        }
+    }
+    Some(Route {
+        route: call._route,
+        swap_params,
+        pools: call._pools,
+        expected_output: call._min_dy,
+    })
+}
+
 /// Decodes the result of a `get_dy` call.
 pub fn decode_get_dy_result(data: &[u8]) -> Result<eth::U256, DecodeError> {
     let result = ICurveRouter::get_dyCall::abi_decode_returns(data)
@@ -73,6 +129,39 @@ pub fn decode_get_dy_result(data: &[u8]) -> Result<eth::U256, DecodeError> {
     Ok(result)
 }
 
+/// Encodes a Multicall3 `aggregate3` call quoting `amount` through every
+/// route with `get_dy` on `router`, so that all candidates can be verified in
+/// a single `eth_call`. Individual routes are allowed to revert.
+pub fn encode_get_dy_batch(router: Address, routes: &[Route], amount: eth::U256) -> Vec<u8> {
+    let call = IMulticall3::aggregate3Call {
+        calls: routes
+            .iter()
+            .map(|route| IMulticall3::Call3 {
+                target: router,
+                allowFailure: true,
+                callData: Bytes::from(encode_get_dy(route, amount)),
+            })
+            .collect(),
+    };
+    call.abi_encode()
+}
+
+/// Decodes the result of a batch built with [`encode_get_dy_batch`]. Each
+/// entry is `None` if the `get_dy` call for the corresponding route reverted.
+pub fn decode_get_dy_batch_result(data: &[u8]) -> Result<Vec<Option<eth::U256>>, DecodeError> {
+    let results = IMulticall3::aggregate3Call::abi_decode_returns(data)
+        .map_err(|e| DecodeError(e.to_string()))?;
+    Ok(results
+        .into_iter()
+        .map(|result| {
+            result
+                .success
+                .then(|| decode_get_dy_result(&result.returnData).ok())
+                .flatten()
+        })
+        .collect())
+}
+
 /// Convert swap params from u64 arrays to U256 arrays as expected by the contract.
 fn convert_swap_params(params: &[[u64; 5]; 5]) -> [[U256; 5]; 5] {
     let mut result = [[U256::ZERO; 5]; 5];
@@ -112,4 +201,58 @@ mod tests {
         // Should start with the function selector for get_dy
         assert!(!encoded.is_empty());
     }
+
+    #[test]
+    fn test_exchange_round_trip() {
+        let mut route = Route {
+            route: [Address::repeat_byte(1); 11],
+            swap_params: [[0; 5]; 5],
+            pools: [Address::ZERO; 5],
+            expected_output: U256::from(990u64),
+        };
+        route.swap_params[0] = [0, 1, 1, 30, 3];
+
+        let calldata = encode_exchange(
+            &route,
+            U256::from(1000u64),
+            U256::from(990u64),
+            Address::repeat_byte(2),
+        );
+        let decoded = decode_exchange(&calldata).unwrap();
+        assert_eq!(decoded.route, route.route);
+        assert_eq!(decoded.swap_params, route.swap_params);
+        assert_eq!(decoded.expected_output, route.expected_output);
+        assert!(decode_exchange(&encode_get_dy(&route, U256::ONE)).is_none());
+    }
+
+    #[test]
+    fn test_get_dy_batch_round_trip() {
+        let route = Route {
+            route: [Address::ZERO; 11],
+            swap_params: [[0; 5]; 5],
+            pools: [Address::ZERO; 5],
+            expected_output: U256::ZERO,
+        };
+
+        let router = deployment(Chain::Mainnet).unwrap();
+        let encoded = encode_get_dy_batch(router, &[route.clone(), route], U256::from(1000u64));
+        let call = IMulticall3::aggregate3Call::abi_decode(&encoded).unwrap();
+        assert_eq!(call.calls.len(), 2);
+        assert!(call.calls.iter().all(|call| call.target == router));
+
+        let returns = IMulticall3::aggregate3Call::abi_encode_returns(&vec![
+            IMulticall3::Call3Result {
+                success: true,
+                returnData: ICurveRouter::get_dyCall::abi_encode_returns(&U256::from(42u64)).into(),
+            },
+            IMulticall3::Call3Result {
+                success: false,
+                returnData: Bytes::new(),
+            },
+        ]);
+        assert_eq!(
+            decode_get_dy_batch_result(&returns).unwrap(),
+            vec![Some(U256::from(42u64)), None]
+        );
+    }
 }
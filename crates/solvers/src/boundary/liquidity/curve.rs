@@ -0,0 +1,130 @@
+use {
+    crate::domain::{curve::exact_out, eth, liquidity},
+    alloy::primitives::{Address, U256},
+    shared::baseline_solver::BaselineSolvable,
+};
+
+/// Upper bound on how many times the sell amount is doubled while looking for
+/// an amount that buys the requested output.
+const MAX_DOUBLINGS: usize = 64;
+
+/// A Curve pool priced locally for the baseline solver.
+#[derive(Clone, Debug)]
+pub struct Pool {
+    pub pool: liquidity::curve::Pool,
+    pub gas: usize,
+}
+
+/// Converts a domain pool into a boundary pool. Returns `None` if the gas
+/// estimate doesn't fit into a `usize`.
+pub fn to_boundary_pool(pool: &liquidity::curve::Pool, gas: eth::Gas) -> Option<Pool> {
+    Some(Pool {
+        pool: pool.clone(),
+        gas: usize::try_from(gas.0).ok()?,
+    })
+}
+
+impl BaselineSolvable for Pool {
+    async fn get_amount_out(
+        &self,
+        out_token: Address,
+        (in_amount, in_token): (U256, Address),
+    ) -> Option<U256> {
+        self.pool.get_dy(
+            eth::TokenAddress(in_token),
+            eth::TokenAddress(out_token),
+            in_amount,
+        )
+    }
+
+    async fn get_amount_in(
+        &self,
+        in_token: Address,
+        (out_amount, out_token): (U256, Address),
+    ) -> Option<U256> {
+        let (sell, buy) = (eth::TokenAddress(in_token), eth::TokenAddress(out_token));
+        let (sell_balance, buy_balance) = (self.pool.balance(sell)?, self.pool.balance(buy)?);
+        if out_amount >= buy_balance {
+            return None;
+        }
+        let quote = |input| self.pool.get_dy(sell, buy, input);
+
+        // Curve pools only quote exact input swaps. Start from the input
+        // implied by the pool balances and double it until it buys enough,
+        // then narrow it down to the smallest such input.
+        let mut input = (out_amount.checked_mul(sell_balance)? / buy_balance).max(U256::ONE);
+        let mut max = None;
+        for _ in 0..MAX_DOUBLINGS {
+            let output = quote(input)?;
+            if output >= out_amount {
+                max = Some(exact_out::Point { input, output });
+                break;
+            }
+            input = input.checked_mul(U256::from(2))?;
+        }
+
+        exact_out::min_input(out_amount, max?, |input| async move { quote(input) })
+            .await
+            .map(|point| point.input)
+    }
+
+    async fn gas_cost(&self) -> usize {
+        self.gas
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::domain::curve::pool as curve};
+
+    fn pool() -> Pool {
+        let e18 = U256::from(10).pow(U256::from(18));
+        Pool {
+            pool: liquidity::curve::Pool {
+                kind: curve::Kind::StableSwap,
+                coins: vec![
+                    eth::TokenAddress(Address::repeat_byte(1)),
+                    eth::TokenAddress(Address::repeat_byte(2)),
+                ],
+                state: curve::State::Stable(curve::stableswap::Pool {
+                    balances: vec![e18 * U256::from(1_000_000); 2],
+                    rates: vec![e18; 2],
+                    amp: U256::from(200 * curve::stableswap::A_PRECISION),
+                    fee: U256::from(4_000_000),
+                    offpeg_fee_multiplier: None,
+                    total_supply: U256::ZERO,
+                }),
+            },
+            gas: 130_000,
+        }
+    }
+
+    #[tokio::test]
+    async fn amount_in_buys_requested_output() {
+        let pool = pool();
+        let (sell, buy) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let out_amount = U256::from(10).pow(U256::from(22));
+
+        let in_amount = pool.get_amount_in(sell, (out_amount, buy)).await.unwrap();
+        let output = pool.get_amount_out(buy, (in_amount, sell)).await.unwrap();
+        assert!(output >= out_amount);
+        // Within the search tolerance of the exact input.
+        let less = pool
+            .get_amount_out(
+                buy,
+                (in_amount * U256::from(9_998) / U256::from(10_000), sell),
+            )
+            .await
+            .unwrap();
+        assert!(less < out_amount);
+    }
+
+    #[tokio::test]
+    async fn amount_in_exceeding_reserves() {
+        let pool = pool();
+        let (sell, buy) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let out_amount = U256::from(10).pow(U256::from(24));
+
+        assert_eq!(pool.get_amount_in(sell, (out_amount, buy)).await, None);
+    }
+}
@@ -8,7 +8,7 @@ use {
 };
 
 /// The auction that the solvers need to find solutions to.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Auction {
     pub id: Id,
     pub tokens: Tokens,
@@ -19,7 +19,7 @@ pub struct Auction {
 }
 
 /// Information about tokens used in the auction.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Tokens(pub HashMap<eth::TokenAddress, Token>);
 
 impl Tokens {
@@ -52,7 +52,7 @@ impl Display for Id {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Token {
     pub decimals: Option<u8>,
     pub symbol: Option<String>,
@@ -77,6 +77,15 @@ impl Price {
             .checked_mul(eth::U256::from(Self::BASE))?
             .checked_div(self.0.0)
     }
+
+    /// Computes the [`eth::Ether`] value of the specified amount of tokens at
+    /// the given price.
+    pub fn ether(&self, amount: eth::U256) -> Option<eth::Ether> {
+        amount
+            .checked_mul(self.0.0)?
+            .checked_div(eth::U256::from(Self::BASE))
+            .map(eth::Ether)
+    }
 }
 
 /// The estimated effective gas price that will likely be used for executing the
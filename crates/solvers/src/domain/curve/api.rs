@@ -1,20 +1,20 @@
 //! Curve Router API client for fetching optimal routes.
 
 use {
-    crate::domain::eth,
+    super::upstream::{self, Upstream},
+    crate::{domain::eth, infra::metrics},
     reqwest::Url,
-    serde::Deserialize,
+    serde::{Deserialize, Serialize},
     std::{fmt, time::Duration},
 };
 
 /// Curve Router API client.
 pub struct Client {
-    http: reqwest::Client,
-    base_url: Url,
+    upstream: Upstream,
 }
 
 /// Route returned by the Curve Router API.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Route {
     /// The route path: [token, pool, token, pool, ...] (11 addresses)
     pub route: [eth::Address; 11],
@@ -26,6 +26,18 @@ pub struct Route {
     pub expected_output: eth::U256,
 }
 
+impl Route {
+    /// The pools traded through by the route, in order.
+    pub fn pools(&self) -> impl Iterator<Item = eth::Address> + '_ {
+        self.swap_params
+            .iter()
+            .enumerate()
+            .take_while(|(_, params)| params[2] != 0)
+            .map(|(hop, _)| self.route[2 * hop + 1])
+            .chain(self.pools.iter().copied().filter(|pool| !pool.is_zero()))
+    }
+}
+
 /// API response is an array of route options.
 type ApiResponse = Vec<RouteOption>;
 
@@ -56,20 +68,29 @@ struct RouteArgs {
 }
 
 impl Client {
-    /// Creates a new Curve Router API client.
-    pub fn new(base_url: Url) -> Self {
-        let http = reqwest::Client::builder()
-            .timeout(Duration::from_secs(10))
-            .build()
-            .expect("failed to build HTTP client");
-
-        Self { http, base_url }
+    /// Creates a new Curve Router API client, failing over to the `mirrors`
+    /// of `base_url` when it is unavailable.
+    pub fn new(base_url: Url, mirrors: Vec<Url>) -> Self {
+        Self {
+            upstream: Upstream::new("curve-api", base_url, mirrors),
+        }
     }
 
-    /// Fetches the optimal route for a swap.
+    /// The mirrors whose circuit breakers currently reject requests.
+    pub fn open_breakers(&self) -> Vec<upstream::OpenBreaker> {
+        self.upstream.open_breakers()
+    }
+
+    /// Fetches all candidate routes for a swap, in the order ranked by the
+    /// API (best first).
+    ///
+    /// The API ranks routes by its own off-chain estimate, which can be stale,
+    /// so callers should verify the candidates on-chain before committing to
+    /// one.
     ///
     /// Note: The Curve v1 API expects amounts in wei (raw token units).
-    pub async fn get_route(
+    #[expect(clippy::too_many_arguments)]
+    pub async fn get_routes(
         &self,
         chain_id: u64,
         token_in: eth::Address,
@@ -77,40 +98,35 @@ impl Client {
         amount_in: eth::U256,
         _token_in_decimals: u8,
         _token_out_decimals: u8,
-    ) -> Result<Route, Error> {
-        let url = format!(
-            "{}?chainId={}&tokenIn={:?}&tokenOut={:?}&amountIn={}&router=curve",
-            self.base_url, chain_id, token_in, token_out, amount_in
+        timeout: Duration,
+    ) -> Result<Vec<Route>, Error> {
+        let query = format!(
+            "?chainId={}&tokenIn={:?}&tokenOut={:?}&amountIn={}&router=curve",
+            chain_id, token_in, token_out, amount_in
         );
 
-        tracing::debug!(%url, "fetching Curve route");
+        tracing::debug!(%query, "fetching Curve route");
 
         let http_start = std::time::Instant::now();
-        let response = self
-            .http
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| Error::Network(e.to_string()))?;
-        let http_ms = http_start.elapsed().as_millis() as u64;
-
-        tracing::debug!(http_ms, status = response.status().as_u16(), "curve API response");
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
+        let response = self.upstream.get(&query, timeout).await;
+        let elapsed = http_start.elapsed();
+        metrics::api_request("router", elapsed);
+        let response = response.map_err(|e| Error::Network(e.to_string()))?;
+        let http_ms = elapsed.as_millis() as u64;
+
+        tracing::debug!(http_ms, status = response.status, "curve API response");
+
+        if !response.is_success() {
             return Err(Error::Api {
-                status: status.as_u16(),
-                message: body,
+                status: response.status,
+                message: response.body,
             });
         }
 
-        let api_response: ApiResponse = response
-            .json()
-            .await
-            .map_err(|e| Error::Parse(e.to_string()))?;
+        let api_response: ApiResponse =
+            serde_json::from_str(&response.body).map_err(|e| Error::Parse(e.to_string()))?;
 
-        Self::parse_route(api_response, token_in, token_out)
+        Self::parse_routes(api_response, token_in, token_out)
     }
 
     /// Validates that a constructed route matches the requested tokens.
@@ -152,17 +168,39 @@ impl Client {
         Ok(())
     }
 
-    fn parse_route(
+    /// Parses every route option in the response, preserving the API ranking.
+    /// Options that fail to parse or validate are skipped, as long as at least
+    /// one valid route remains.
+    fn parse_routes(
         response: ApiResponse,
         token_in: eth::Address,
         token_out: eth::Address,
-    ) -> Result<Route, Error> {
-        // Take the first route option (best route)
-        let route_option = response
-            .into_iter()
-            .next()
-            .ok_or_else(|| Error::Parse("empty route response".to_string()))?;
+    ) -> Result<Vec<Route>, Error> {
+        let mut routes = Vec::with_capacity(response.len());
+        let mut first_error = None;
+        for (rank, route_option) in response.into_iter().enumerate() {
+            match Self::parse_route(route_option, token_in, token_out) {
+                Ok(route) => routes.push(route),
+                Err(err) => {
+                    tracing::debug!(rank, %err, "skipping invalid Curve route option");
+                    first_error.get_or_insert(err);
+                }
+            }
+        }
 
+        if routes.is_empty() {
+            return Err(
+                first_error.unwrap_or_else(|| Error::Parse("empty route response".to_string()))
+            );
+        }
+        Ok(routes)
+    }
+
+    fn parse_route(
+        route_option: RouteOption,
+        token_in: eth::Address,
+        token_out: eth::Address,
+    ) -> Result<Route, Error> {
         // Parse the expected output (wei string in array)
         let amount_out_str = route_option
             .amount_out
@@ -284,8 +322,54 @@ mod tests {
 
         let token_in: eth::Address = "0xf5f5B97624542D72A9E06f04804Bf81baA15e2B4".parse().unwrap();
         let token_out: eth::Address = "0xdAC17F958D2ee523a2206206994597C13D831ec7".parse().unwrap();
-        let route = Client::parse_route(response, token_in, token_out).unwrap();
-        assert_eq!(route.expected_output, eth::U256::from(1_769_022_968u64));
+        let routes = Client::parse_routes(response, token_in, token_out).unwrap();
+        assert_eq!(routes.len(), 1);
+        assert_eq!(routes[0].expected_output, eth::U256::from(1_769_022_968u64));
+    }
+
+    #[test]
+    fn test_parse_routes_keeps_ranking_and_skips_invalid() {
+        let token_in = "0xf5f5B97624542D72A9E06f04804Bf81baA15e2B4";
+        let token_out = "0xdAC17F958D2ee523a2206206994597C13D831ec7";
+        let option = |amount_out: &str, token_out: &str| RouteOption {
+            amount_out: vec![amount_out.to_string()],
+            route: vec![RouteStep {
+                token_in: vec![token_in.to_string()],
+                token_out: vec![token_out.to_string()],
+                args: RouteArgs {
+                    pool_id: "factory-tricrypto-1".to_string(),
+                    swap_address: token_in.to_string(),
+                    swap_params: vec![0, 0, 6, 30, 3],
+                    pool_address: String::new(),
+                },
+            }],
+        };
+        let response: ApiResponse = vec![
+            option("300", token_out),
+            option("200", "0x0000000000000000000000000000000000000001"),
+            option("100", token_out),
+        ];
+
+        let routes = Client::parse_routes(
+            response,
+            token_in.parse().unwrap(),
+            token_out.parse().unwrap(),
+        )
+        .unwrap();
+        let outputs: Vec<_> = routes.iter().map(|r| r.expected_output).collect();
+        assert_eq!(outputs, vec![eth::U256::from(300), eth::U256::from(100)]);
+    }
+
+    #[test]
+    fn test_parse_routes_fails_without_valid_route() {
+        let token_in: eth::Address = "0xf5f5B97624542D72A9E06f04804Bf81baA15e2B4".parse().unwrap();
+        let token_out: eth::Address =
+            "0xdAC17F958D2ee523a2206206994597C13D831ec7".parse().unwrap();
+
+        assert!(matches!(
+            Client::parse_routes(vec![], token_in, token_out),
+            Err(Error::Parse(_))
+        ));
     }
 
     #[test]
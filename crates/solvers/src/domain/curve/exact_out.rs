@@ -0,0 +1,171 @@
+//! Exact-output search for buy orders.
+//!
+//! Curve only quotes exact-input swaps (`get_dy`), so the sell amount needed
+//! for an exact buy amount is found by searching over the quote function. The
+//! search relies on the output being monotonically increasing in the input,
+//! and converges quickly because Curve pricing is close to linear over the
+//! range of a single order.
+
+use {crate::domain::eth, std::future::Future};
+
+/// Maximum number of quotes evaluated by a single search.
+const MAX_ITERATIONS: usize = 6;
+/// The search stops once the bracket around the minimal input is narrower
+/// than this, in basis points of the current best input.
+const TOLERANCE_BPS: u64 = 1;
+
+/// An input amount together with the output it was quoted for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Point {
+    pub input: eth::U256,
+    pub output: eth::U256,
+}
+
+/// Finds the smallest input for which `quote` yields at least `target`,
+/// given a known feasible point `max` (typically the order's full sell
+/// amount).
+///
+/// Uses regula falsi (secant steps that keep the root bracketed) starting from
+/// the origin, so every point returned has been quoted as feasible. A failed
+/// quote (`None`) ends the search early with the best point found so far.
+/// Returns `None` if `max` itself does not reach `target`.
+pub async fn min_input<F, Fut>(target: eth::U256, max: Point, mut quote: F) -> Option<Point>
+where
+    F: FnMut(eth::U256) -> Fut,
+    Fut: Future<Output = Option<eth::U256>>,
+{
+    if max.output < target {
+        return None;
+    }
+
+    let mut lo = Point {
+        input: eth::U256::ZERO,
+        output: eth::U256::ZERO,
+    };
+    let mut hi = max;
+    for _ in 0..MAX_ITERATIONS {
+        let tolerance = (hi.input * eth::U256::from(TOLERANCE_BPS) / eth::U256::from(10_000))
+            .max(eth::U256::ONE);
+        if hi.input - lo.input <= tolerance {
+            break;
+        }
+
+        let Some(input) = interpolate(lo, hi, target) else {
+            break;
+        };
+        // Keep the next point strictly inside the bracket so that every
+        // iteration makes progress.
+        let input = input.clamp(lo.input + eth::U256::ONE, hi.input - eth::U256::ONE);
+        let Some(output) = quote(input).await else {
+            break;
+        };
+
+        let point = Point { input, output };
+        if output >= target {
+            hi = point;
+        } else {
+            lo = point;
+        }
+    }
+    Some(hi)
+}
+
+/// Estimates the input reaching `target` by interpolating linearly between
+/// the origin and `max`.
+///
+/// Curve output is concave in the input, so the estimate is feasible without
+/// being quoted. It is the first step of [`min_input`] and is useful on its own
+/// when there is no time to quote.
+pub fn linear_estimate(target: eth::U256, max: Point) -> Option<eth::U256> {
+    let origin = Point {
+        input: eth::U256::ZERO,
+        output: eth::U256::ZERO,
+    };
+    if max.output < target {
+        return None;
+    }
+    Some(interpolate(origin, max, target)?.min(max.input))
+}
+
+/// Returns the input at which the line through `lo` and `hi` reaches
+/// `target`, rounded up.
+fn interpolate(lo: Point, hi: Point, target: eth::U256) -> Option<eth::U256> {
+    let numerator = (hi.input - lo.input).checked_mul(target.checked_sub(lo.output)?)?;
+    let denominator = hi.output.checked_sub(lo.output)?;
+    if denominator.is_zero() {
+        return None;
+    }
+    Some(lo.input + numerator.div_ceil(denominator))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A concave quote function similar in shape to a constant product pool:
+    /// `y = x * 1e6 / (x + 1e6)`, scaled up.
+    fn curve(input: eth::U256) -> eth::U256 {
+        let reserve = eth::U256::from(1_000_000_000_000u64);
+        input * reserve / (input + reserve)
+    }
+
+    #[tokio::test]
+    async fn finds_minimal_input() {
+        let max_input = eth::U256::from(500_000_000_000u64);
+        let max = Point {
+            input: max_input,
+            output: curve(max_input),
+        };
+        let target = eth::U256::from(200_000_000_000u64);
+
+        let mut quotes = 0;
+        let point = min_input(target, max, |input| {
+            quotes += 1;
+            async move { Some(curve(input)) }
+        })
+        .await
+        .unwrap();
+
+        assert!(point.output >= target);
+        assert!(quotes <= MAX_ITERATIONS);
+        // The exact answer is 250e9; allow for the search tolerance.
+        let exact = eth::U256::from(250_000_000_000u64);
+        assert!(point.input >= exact);
+        assert!(point.input - exact <= exact / eth::U256::from(1_000));
+    }
+
+    #[tokio::test]
+    async fn infeasible_target() {
+        let max = Point {
+            input: eth::U256::from(100),
+            output: eth::U256::from(90),
+        };
+        let point = min_input(eth::U256::from(91), max, |_| async { None }).await;
+        assert_eq!(point, None);
+    }
+
+    #[tokio::test]
+    async fn failed_quote_keeps_best_point() {
+        let max = Point {
+            input: eth::U256::from(1_000),
+            output: eth::U256::from(900),
+        };
+        let point = min_input(eth::U256::from(450), max, |_| async { None }).await;
+        assert_eq!(point, Some(max));
+    }
+
+    #[test]
+    fn linear_estimate_is_conservative() {
+        let max_input = eth::U256::from(500_000_000_000u64);
+        let max = Point {
+            input: max_input,
+            output: curve(max_input),
+        };
+        let target = eth::U256::from(200_000_000_000u64);
+
+        let estimate = linear_estimate(target, max).unwrap();
+        assert!(curve(estimate) >= target);
+        assert!(estimate < max_input);
+        assert_eq!(linear_estimate(max.output + eth::U256::ONE, max), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Gas estimation for Curve Router routes.
+//!
+//! Estimates start from a table of typical costs per hop, keyed by the
+//! Router's swap and pool types, and are refined with the gas actually used
+//! when routes settle. They are cached by route shape: the pools and swap
+//! parameters of a route, independent of the amounts traded.
+
+use {
+    crate::domain::{curve::api::Route, eth},
+    std::{collections::HashMap, sync::Mutex},
+};
+
+/// Gas used by the Router itself: pulling the input, approving the first
+/// pool and sending the output to the receiver.
+const ROUTER_OVERHEAD: u64 = 60_000;
+/// Additional gas per coin beyond two for operations whose cost scales with
+/// the size of the pool.
+pub const GAS_PER_EXTRA_COIN: u64 = 15_000;
+/// Upper bound on the number of cached route shapes.
+const MAX_CACHED_SHAPES: usize = 4_096;
+/// Weight of a new observation in a learned estimate, in percent.
+const LEARNING_RATE_PCT: u64 = 25;
+
+/// The pools and swap parameters of a route, which determine its gas usage.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Shape(Vec<(eth::Address, [u64; 5])>);
+
+impl Shape {
+    pub fn of(route: &Route) -> Self {
+        Self(
+            route
+                .swap_params
+                .iter()
+                .enumerate()
+                .take_while(|(_, params)| params[2] != 0)
+                .map(|(hop, params)| (route.route[2 * hop + 1], *params))
+                .collect(),
+        )
+    }
+
+    /// The estimate from the per-hop table, for shapes without observations.
+    fn table_estimate(&self) -> u64 {
+        self.0.iter().fold(ROUTER_OVERHEAD, |gas, (_, params)| {
+            let [_, _, swap_type, pool_type, n_coins] = *params;
+            gas.saturating_add(hop_gas(swap_type, pool_type, n_coins))
+        })
+    }
+}
+
+/// Typical gas used by a single pool operation, identified by its Router
+/// `swap_type` and `pool_type`.
+pub fn hop_gas(swap_type: u64, pool_type: u64, n_coins: u64) -> u64 {
+    // Router pool types: 1 StableSwap, 2 TwoCrypto, 3 Tricrypto, 4 LLAMMA,
+    // with NG versions of the same flavour using 10 times the identifier.
+    let crypto = matches!(pool_type, 2 | 3 | 20 | 30);
+    let extra_coins = n_coins.saturating_sub(2).saturating_mul(GAS_PER_EXTRA_COIN);
+    match (swap_type, crypto) {
+        (1, _) if pool_type == 4 => 250_000,
+        (1, false) => 110_000,
+        (1, true) => 160_000,
+        (2, false) => 200_000,
+        (2, true) => 250_000,
+        (3, _) => 350_000,
+        (4, false) => 150_000 + extra_coins,
+        (4, true) => 200_000 + extra_coins,
+        (5, _) => 300_000,
+        (6, false) => 110_000,
+        (6, true) => 180_000,
+        (7, _) => 280_000,
+        (8, _) => 50_000,
+        (9, _) => 90_000,
+        _ => 200_000,
+    }
+}
+
+/// Per-route gas estimates, learned from settled routes.
+#[derive(Default)]
+pub struct Estimator {
+    shapes: Mutex<HashMap<Shape, u64>>,
+}
+
+impl Estimator {
+    /// Returns the estimated gas used by a Router exchange along `route`.
+    pub fn estimate(&self, route: &Route) -> u64 {
+        let shape = Shape::of(route);
+        let mut shapes = match self.shapes.lock() {
+            Ok(shapes) => shapes,
+            Err(_) => return shape.table_estimate(),
+        };
+        if let Some(gas) = shapes.get(&shape) {
+            return *gas;
+        }
+        let gas = shape.table_estimate();
+        if shapes.len() < MAX_CACHED_SHAPES {
+            shapes.insert(shape, gas);
+        }
+        gas
+    }
+
+    /// Records the gas used by the interaction of a settled route, moving its
+    /// estimate towards the observation.
+    pub fn record(&self, route: &Route, gas_used: u64) {
+        let shape = Shape::of(route);
+        let Ok(mut shapes) = self.shapes.lock() else {
+            return;
+        };
+        if !shapes.contains_key(&shape) && shapes.len() >= MAX_CACHED_SHAPES {
+            return;
+        }
+        let estimate = shapes
+            .entry(shape)
+            .or_insert_with_key(Shape::table_estimate);
+        *estimate = blend(*estimate, gas_used);
+    }
+}
+
+/// Moves `estimate` towards `observed` by the learning rate.
+fn blend(estimate: u64, observed: u64) -> u64 {
+    let estimate = u128::from(estimate);
+    let observed = u128::from(observed);
+    let blended = (estimate * u128::from(100 - LEARNING_RATE_PCT)
+        + observed * u128::from(LEARNING_RATE_PCT))
+        / 100;
+    blended as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloy::primitives::Address};
+
+    fn route(hops: &[[u64; 5]], expected_output: u64) -> Route {
+        let mut route = Route {
+            route: [Address::ZERO; 11],
+            swap_params: [[0; 5]; 5],
+            pools: [Address::ZERO; 5],
+            expected_output: eth::U256::from(expected_output),
+        };
+        for (hop, params) in hops.iter().enumerate() {
+            route.route[2 * hop + 1] = Address::repeat_byte(hop as u8 + 1);
+            route.swap_params[hop] = *params;
+        }
+        route
+    }
+
+    #[test]
+    fn estimates_depend_on_route_shape() {
+        let stable_burn = route(&[[0, 1, 6, 10, 2]], 1);
+        let tricrypto_zap = route(&[[0, 1, 1, 30, 3]; 5], 1);
+
+        let estimator = Estimator::default();
+        let burn = estimator.estimate(&stable_burn);
+        let zap = estimator.estimate(&tricrypto_zap);
+        assert!(burn < 350_000);
+        assert!(zap > 350_000);
+        assert_eq!(zap, ROUTER_OVERHEAD + 5 * hop_gas(1, 30, 3));
+
+        // Amounts don't change the shape.
+        assert_eq!(
+            Shape::of(&stable_burn),
+            Shape::of(&route(&[[0, 1, 6, 10, 2]], 1_000))
+        );
+    }
+
+    #[test]
+    fn learns_from_observations() {
+        let route = route(&[[0, 1, 6, 10, 2]], 1);
+        let estimator = Estimator::default();
+        let table = estimator.estimate(&route);
+
+        for _ in 0..32 {
+            estimator.record(&route, 90_000);
+        }
+        let learned = estimator.estimate(&route);
+        assert!(learned < table);
+        assert!(learned.abs_diff(90_000) < 1_000);
+    }
+}
@@ -0,0 +1,194 @@
+//! Native prices of Curve LP tokens derived from on-chain pool state.
+//!
+//! StableSwap LP tokens are valued at their pool's `get_virtual_price`
+//! denominated in the cheapest of the pool's coins, which guards against
+//! pricing the LP token at a depegged coin's price. CryptoSwap LP tokens are
+//! valued at their pool's `lp_price`, which is denominated in the pool's first
+//! coin, using the pool's own `price_oracle` to denominate it in any other
+//! coin with a known price. All arithmetic is done on integers.
+
+use {
+    crate::domain::eth,
+    std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    },
+};
+
+/// `1e18`, the precision of virtual prices, LP prices and price oracles.
+const PRECISION: u128 = 10_u128.pow(18);
+
+/// The on-chain inputs for pricing the LP token of a pool.
+#[derive(Clone, Debug)]
+pub struct Pricing {
+    pub coins: Vec<eth::TokenAddress>,
+    pub valuation: Valuation,
+}
+
+#[derive(Clone, Debug)]
+pub enum Valuation {
+    Stable {
+        /// The value of one LP token in normalized coin units, `1e18` scaled.
+        virtual_price: eth::U256,
+        /// The rates normalizing coin amounts to `1e18` units, `1e18` scaled,
+        /// as in [`super::pool::stableswap::Pool::rates`].
+        rates: Vec<eth::U256>,
+    },
+    Crypto {
+        /// The value of one LP token in normalized units of the first coin,
+        /// `1e18` scaled.
+        lp_price: eth::U256,
+        /// The price of every coin but the first in normalized units of the
+        /// first coin, `1e18` scaled.
+        price_oracle: Vec<eth::U256>,
+        /// The factors normalizing coin amounts to 18 decimals.
+        precisions: Vec<eth::U256>,
+    },
+}
+
+impl Pricing {
+    /// Computes the native price of the LP token, i.e. the amount of wei
+    /// needed to buy 10**18 of it, from the native prices of the pool's coins
+    /// in the same unit. Returns `None` if none of the coins has a price.
+    pub fn native_price(
+        &self,
+        price: impl Fn(&eth::TokenAddress) -> Option<eth::U256>,
+    ) -> Option<eth::U256> {
+        let prices = self.coins.iter().map(price);
+        match &self.valuation {
+            // price * 1e18 / rate is the price of a whole normalized coin,
+            // scaled by the virtual price.
+            Valuation::Stable {
+                virtual_price,
+                rates,
+            } => prices
+                .zip(rates)
+                .filter_map(|(price, rate)| virtual_price.checked_mul(price?)?.checked_div(*rate))
+                .min(),
+            // price / precision is the price of a whole normalized coin, which
+            // the price oracle converts to whole normalized first coins.
+            Valuation::Crypto {
+                lp_price,
+                price_oracle,
+                precisions,
+            } => prices
+                .zip(precisions)
+                .zip(
+                    std::iter::once(eth::U256::from(PRECISION)).chain(price_oracle.iter().copied()),
+                )
+                .filter_map(|((price, precision), oracle)| {
+                    lp_price
+                        .checked_mul(price?)?
+                        .checked_div(precision.checked_mul(oracle)?)
+                })
+                .min(),
+        }
+        .filter(|price| !price.is_zero())
+    }
+}
+
+/// Caches the pricing inputs of pools for the block they were read at.
+#[derive(Default)]
+pub struct Cache(Mutex<HashMap<eth::TokenAddress, (u64, Arc<Pricing>)>>);
+
+impl Cache {
+    /// Returns the pricing inputs of the LP token if they were read at `block`.
+    pub fn get(&self, lp_token: &eth::TokenAddress, block: u64) -> Option<Arc<Pricing>> {
+        let cache = self.0.lock().ok()?;
+        let (cached_block, pricing) = cache.get(lp_token)?;
+        (*cached_block == block).then(|| pricing.clone())
+    }
+
+    pub fn insert(&self, lp_token: eth::TokenAddress, block: u64, pricing: Arc<Pricing>) {
+        if let Ok(mut cache) = self.0.lock() {
+            cache.insert(lp_token, (block, pricing));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloy::primitives::Address};
+
+    fn e(exponent: u64) -> eth::U256 {
+        eth::U256::from(10).pow(eth::U256::from(exponent))
+    }
+
+    fn coins(n: u8) -> Vec<eth::TokenAddress> {
+        (1..=n)
+            .map(|i| eth::TokenAddress(Address::repeat_byte(i)))
+            .collect()
+    }
+
+    #[test]
+    fn stable_lp_is_valued_at_cheapest_coin() {
+        // A USDC (6 decimals) / DAI (18 decimals) pool with a virtual price of
+        // 1.02. USDC trades at 1/2000 ETH, DAI slightly depegged below it.
+        let pricing = Pricing {
+            coins: coins(2),
+            valuation: Valuation::Stable {
+                virtual_price: e(16) * eth::U256::from(102),
+                rates: vec![e(30), e(18)],
+            },
+        };
+        let usdc = e(27) / eth::U256::from(2); // 5e14 wei per USDC
+        let dai = e(14) * eth::U256::from(4); // 4e14 wei per DAI
+
+        assert_eq!(
+            pricing.native_price(|coin| match coin.0.0[0] {
+                1 => Some(usdc),
+                _ => Some(dai),
+            }),
+            Some(e(12) * eth::U256::from(408)),
+        );
+        assert_eq!(
+            pricing.native_price(|coin| (coin.0.0[0] == 1).then_some(usdc)),
+            Some(e(12) * eth::U256::from(510)),
+        );
+        assert_eq!(pricing.native_price(|_| None), None);
+    }
+
+    #[test]
+    fn crypto_lp_is_valued_through_price_oracle() {
+        // A USDT (6 decimals) / WETH pool where one LP token is worth 2 USDT
+        // and WETH trades at 2000 USDT.
+        let pricing = Pricing {
+            coins: coins(2),
+            valuation: Valuation::Crypto {
+                lp_price: e(18) * eth::U256::from(2),
+                price_oracle: vec![e(18) * eth::U256::from(2_000)],
+                precisions: vec![e(12), eth::U256::ONE],
+            },
+        };
+        let usdt = e(27) / eth::U256::from(2); // 5e14 wei per USDT
+        let weth = e(18);
+
+        // Priced from the first coin directly, and from WETH through the
+        // price oracle.
+        assert_eq!(
+            pricing.native_price(|coin| (coin.0.0[0] == 1).then_some(usdt)),
+            Some(e(15)),
+        );
+        assert_eq!(
+            pricing.native_price(|coin| (coin.0.0[0] == 2).then_some(weth)),
+            Some(e(15)),
+        );
+    }
+
+    #[test]
+    fn cache_is_per_block() {
+        let cache = Cache::default();
+        let lp = eth::TokenAddress(Address::repeat_byte(0xaa));
+        let pricing = Arc::new(Pricing {
+            coins: coins(2),
+            valuation: Valuation::Stable {
+                virtual_price: e(18),
+                rates: vec![e(18); 2],
+            },
+        });
+
+        cache.insert(lp, 1, pricing);
+        assert!(cache.get(&lp, 1).is_some());
+        assert!(cache.get(&lp, 2).is_none());
+    }
+}
@@ -1,4 +1,9 @@
 //! Curve Protocol integration for LP token solving.
 
 pub mod api;
+pub mod exact_out;
+pub mod gas;
+pub mod lp_price;
+pub mod pool;
 pub mod price_api;
+pub mod upstream;
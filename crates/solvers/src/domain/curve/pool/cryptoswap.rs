@@ -0,0 +1,464 @@
+//! CryptoSwap invariant math.
+//!
+//! Integer port of the Newton solvers and view functions of Curve's
+//! CryptoSwap pools. The invariant is the same for two-coin (twocrypto) and
+//! three-coin (tricrypto) pools, so a single implementation generic over the
+//! number of coins covers both. The NG pools solve for `y` analytically where
+//! possible, which may differ from the Newton solution by a few wei; callers
+//! that execute on-chain should still verify with `get_dy`.
+
+use crate::domain::eth::U256;
+
+/// Denominator for all Curve fee values (fees are expressed in 1e10).
+pub const FEE_DENOMINATOR: u64 = 10_000_000_000;
+/// Multiplier applied to `A * N**N` in the stored `A` value.
+pub const A_MULTIPLIER: u64 = 10_000;
+/// Fixed point precision of prices and normalized balances.
+const PRECISION: u128 = 1_000_000_000_000_000_000;
+/// Flat fee added to imbalanced liquidity operations to cover rounding.
+const NOISE_FEE: u64 = 100_000;
+/// Iteration limit of the Newton solvers, same as on-chain.
+const MAX_ITERATIONS: usize = 255;
+
+/// The state of a CryptoSwap pool.
+#[derive(Clone, Debug)]
+pub struct Pool {
+    /// Raw coin balances held by the pool.
+    pub balances: Vec<U256>,
+    /// Multipliers normalizing balances to 18 decimals (`10**(18 -
+    /// decimals)`).
+    pub precisions: Vec<U256>,
+    /// Price of coins `1..N` in terms of coin 0 (18 decimals fixed point).
+    pub price_scale: Vec<U256>,
+    /// The amplification coefficient as stored on-chain, i.e. `A * N**N *
+    /// A_MULTIPLIER`.
+    pub ann: U256,
+    pub gamma: U256,
+    /// Fee charged when the pool is perfectly balanced.
+    pub mid_fee: U256,
+    /// Fee charged when the pool is maximally imbalanced.
+    pub out_fee: U256,
+    /// Controls how quickly the fee moves from `mid_fee` to `out_fee`.
+    pub fee_gamma: U256,
+    /// Total supply of the pool's LP token.
+    pub total_supply: U256,
+}
+
+impl Pool {
+    /// Returns the amount of coin `j` received for selling `dx` of coin `i`.
+    pub fn get_dy(&self, i: usize, j: usize, dx: U256) -> Option<U256> {
+        let n = self.balances.len();
+        if i == j || i >= n || j >= n {
+            return None;
+        }
+
+        let d = newton_d(self.ann, self.gamma, &self.xp(&self.balances)?)?;
+        let mut balances = self.balances.clone();
+        balances[i] = balances[i].checked_add(dx)?;
+        let mut xp = self.xp(&balances)?;
+
+        let y = newton_y(self.ann, self.gamma, &xp, d, j)?;
+        let mut dy = xp[j].checked_sub(y)?.checked_sub(U256::ONE)?;
+        xp[j] = y;
+        if j > 0 {
+            dy = dy
+                .checked_mul(U256::from(PRECISION))?
+                .checked_div(self.price_scale[j - 1])?;
+        }
+        dy = dy.checked_div(self.precisions[j])?;
+
+        let fee = self.fee(&xp)?.checked_mul(dy)? / U256::from(FEE_DENOMINATOR);
+        dy.checked_sub(fee)
+    }
+
+    /// Returns the amount of coin `i` received for burning `token_amount` LP
+    /// tokens.
+    pub fn calc_withdraw_one_coin(&self, token_amount: U256, i: usize) -> Option<U256> {
+        let n = self.balances.len();
+        if i >= n {
+            return None;
+        }
+
+        let xp = self.xp(&self.balances)?;
+        let d0 = newton_d(self.ann, self.gamma, &xp)?;
+        let fee = self.fee(&xp)?;
+        let dd = token_amount
+            .checked_mul(d0)?
+            .checked_div(self.total_supply)?;
+        let dd_fee = fee.checked_mul(dd)? / U256::from(2 * FEE_DENOMINATOR) + U256::ONE;
+        let d = d0.checked_sub(dd.checked_sub(dd_fee)?)?;
+        let y = newton_y(self.ann, self.gamma, &xp, d, i)?;
+
+        let price_scale_i = if i == 0 {
+            U256::from(PRECISION).checked_mul(self.precisions[0])?
+        } else {
+            self.price_scale[i - 1].checked_mul(self.precisions[i])?
+        };
+        xp[i]
+            .checked_sub(y)?
+            .checked_mul(U256::from(PRECISION))?
+            .checked_div(price_scale_i)
+    }
+
+    /// Returns the amount of LP tokens minted (when `deposit`) or burned
+    /// (otherwise) for adding or removing the specified coin `amounts`,
+    /// including imbalance fees.
+    pub fn calc_token_amount(&self, amounts: &[U256], deposit: bool) -> Option<U256> {
+        let n = self.balances.len();
+        if amounts.len() != n {
+            return None;
+        }
+
+        let new_balances = self
+            .balances
+            .iter()
+            .zip(amounts)
+            .map(|(balance, amount)| {
+                if deposit {
+                    balance.checked_add(*amount)
+                } else {
+                    balance.checked_sub(*amount)
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let xp = self.xp(&new_balances)?;
+        let amountsp = self.xp(amounts)?;
+
+        let d0 = newton_d(self.ann, self.gamma, &self.xp(&self.balances)?)?;
+        let d = newton_d(self.ann, self.gamma, &xp)?;
+        let scaled_supply = self.total_supply.checked_mul(d)?.checked_div(d0)?;
+        let d_token = if deposit {
+            scaled_supply.checked_sub(self.total_supply)?
+        } else {
+            self.total_supply.checked_sub(scaled_supply)?
+        };
+
+        let fee = self.calc_token_fee(&amountsp, &xp)?.checked_mul(d_token)?
+            / U256::from(FEE_DENOMINATOR)
+            + U256::ONE;
+        if deposit {
+            d_token.checked_sub(fee)
+        } else {
+            d_token.checked_add(fee)
+        }
+    }
+
+    /// Normalizes raw balances to 18 decimals and to the price of coin 0.
+    fn xp(&self, balances: &[U256]) -> Option<Vec<U256>> {
+        if balances.len() != self.precisions.len() || balances.len() != self.price_scale.len() + 1 {
+            return None;
+        }
+        balances
+            .iter()
+            .zip(&self.precisions)
+            .enumerate()
+            .map(|(k, (balance, precision))| {
+                let x = balance.checked_mul(*precision)?;
+                if k == 0 {
+                    Some(x)
+                } else {
+                    Some(x.checked_mul(self.price_scale[k - 1])? / U256::from(PRECISION))
+                }
+            })
+            .collect()
+    }
+
+    /// The dynamic swap fee for normalized balances `xp`.
+    fn fee(&self, xp: &[U256]) -> Option<U256> {
+        let precision = U256::from(PRECISION);
+        let n = U256::from(xp.len());
+        let s = xp
+            .iter()
+            .try_fold(U256::ZERO, |acc, x| acc.checked_add(*x))?;
+
+        let mut k = precision.checked_mul(n.checked_pow(n)?)?;
+        for x in xp {
+            k = k.checked_mul(*x)?.checked_div(s)?;
+        }
+        let f = self
+            .fee_gamma
+            .checked_mul(precision)?
+            .checked_div(self.fee_gamma.checked_add(precision)?.checked_sub(k)?)?;
+
+        self.mid_fee
+            .checked_mul(f)?
+            .checked_add(self.out_fee.checked_mul(precision.checked_sub(f)?)?)?
+            .checked_div(precision)
+    }
+
+    /// The fee charged on imbalanced liquidity operations.
+    fn calc_token_fee(&self, amounts: &[U256], xp: &[U256]) -> Option<U256> {
+        let n = amounts.len();
+        let fee = self
+            .fee(xp)?
+            .checked_mul(U256::from(n))?
+            .checked_div(U256::from(4 * (n - 1)))?;
+
+        let s = amounts
+            .iter()
+            .try_fold(U256::ZERO, |acc, x| acc.checked_add(*x))?;
+        let avg = s / U256::from(n);
+        let s_diff = amounts
+            .iter()
+            .try_fold(U256::ZERO, |acc, x| acc.checked_add(x.abs_diff(avg)))?;
+
+        Some(fee.checked_mul(s_diff)?.checked_div(s)? + U256::from(NOISE_FEE))
+    }
+}
+
+/// Computes the CryptoSwap invariant `D` for normalized balances.
+fn newton_d(ann: U256, gamma: U256, x_unsorted: &[U256]) -> Option<U256> {
+    let precision = U256::from(PRECISION);
+    let n = U256::from(x_unsorted.len());
+    let mut x = x_unsorted.to_vec();
+    x.sort_unstable_by(|a, b| b.cmp(a));
+    if x.iter().any(U256::is_zero) {
+        return None;
+    }
+
+    let mut d = n.checked_mul(geometric_mean(&x)?)?;
+    let s = x
+        .iter()
+        .try_fold(U256::ZERO, |acc, x| acc.checked_add(*x))?;
+
+    for _ in 0..MAX_ITERATIONS {
+        let d_prev = d;
+
+        let mut k0 = precision;
+        for x_k in &x {
+            k0 = k0.checked_mul(*x_k)?.checked_mul(n)?.checked_div(d)?;
+        }
+
+        let g1k0 = gamma.checked_add(precision)?.abs_diff(k0) + U256::ONE;
+        // D / (A * N**N) * g1k0**2 / gamma**2
+        let mul1 = precision
+            .checked_mul(d)?
+            .checked_div(gamma)?
+            .checked_mul(g1k0)?
+            .checked_div(gamma)?
+            .checked_mul(g1k0)?
+            .checked_mul(U256::from(A_MULTIPLIER))?
+            .checked_div(ann)?;
+        // 2 * N * K0 / g1k0
+        let mul2 = precision
+            .checked_mul(U256::from(2))?
+            .checked_mul(n)?
+            .checked_mul(k0)?
+            .checked_div(g1k0)?;
+
+        let neg_fprime = s
+            .checked_add(s.checked_mul(mul2)? / precision)?
+            .checked_add(mul1.checked_mul(n)?.checked_div(k0)?)?
+            .checked_sub(mul2.checked_mul(d)? / precision)?;
+
+        // D -= f / fprime
+        let d_plus = d
+            .checked_mul(neg_fprime.checked_add(s)?)?
+            .checked_div(neg_fprime)?;
+        let mut d_minus = d.checked_mul(d)?.checked_div(neg_fprime)?;
+        let correction = d.checked_mul(mul1.checked_div(neg_fprime)?)? / precision;
+        if precision > k0 {
+            d_minus =
+                d_minus.checked_add(correction.checked_mul(precision - k0)?.checked_div(k0)?)?;
+        } else {
+            d_minus =
+                d_minus.checked_sub(correction.checked_mul(k0 - precision)?.checked_div(k0)?)?;
+        }
+
+        d = if d_plus > d_minus {
+            d_plus - d_minus
+        } else {
+            (d_minus - d_plus) / U256::from(2)
+        };
+
+        let diff = d.abs_diff(d_prev);
+        if diff.checked_mul(U256::from(100_000_000_000_000u64))?
+            < d.max(U256::from(10_000_000_000_000_000u64))
+        {
+            return Some(d);
+        }
+    }
+    None
+}
+
+/// Computes the normalized balance of coin `i` for the invariant `d`, keeping
+/// all other balances constant.
+fn newton_y(ann: U256, gamma: U256, x: &[U256], d: U256, i: usize) -> Option<U256> {
+    let precision = U256::from(PRECISION);
+    let n_coins = x.len();
+    let n = U256::from(n_coins);
+
+    let mut x_sorted = x.to_vec();
+    x_sorted[i] = U256::ZERO;
+    x_sorted.sort_unstable_by(|a, b| b.cmp(a));
+    let limit = U256::from(100_000_000_000_000u64);
+    let convergence_limit = (x_sorted[0] / limit).max(d / limit).max(U256::from(100));
+
+    let mut y = d / n;
+    let mut s_i = U256::ZERO;
+    // Small balances first.
+    for x_j in x_sorted[..n_coins - 1].iter().rev() {
+        y = y.checked_mul(d)?.checked_div(x_j.checked_mul(n)?)?;
+        s_i = s_i.checked_add(*x_j)?;
+    }
+    // Large balances first.
+    let mut k0_i = precision;
+    for x_j in &x_sorted[..n_coins - 1] {
+        k0_i = k0_i.checked_mul(*x_j)?.checked_mul(n)?.checked_div(d)?;
+    }
+
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+
+        let k0 = k0_i.checked_mul(y)?.checked_mul(n)?.checked_div(d)?;
+        let s = s_i.checked_add(y)?;
+
+        let g1k0 = gamma.checked_add(precision)?.abs_diff(k0) + U256::ONE;
+        // D / (A * N**N) * g1k0**2 / gamma**2
+        let mul1 = precision
+            .checked_mul(d)?
+            .checked_div(gamma)?
+            .checked_mul(g1k0)?
+            .checked_div(gamma)?
+            .checked_mul(g1k0)?
+            .checked_mul(U256::from(A_MULTIPLIER))?
+            .checked_div(ann)?;
+        // 1 + 2 * K0 / g1k0
+        let mul2 = precision.checked_add(
+            precision
+                .checked_mul(U256::from(2))?
+                .checked_mul(k0)?
+                .checked_div(g1k0)?,
+        )?;
+
+        let yfprime = precision
+            .checked_mul(y)?
+            .checked_add(s.checked_mul(mul2)?)?
+            .checked_add(mul1)?;
+        let dyfprime = d.checked_mul(mul2)?;
+        if yfprime < dyfprime {
+            y = y_prev / U256::from(2);
+            continue;
+        }
+        let yfprime = yfprime - dyfprime;
+        let fprime = yfprime.checked_div(y)?;
+
+        let mut y_minus = mul1.checked_div(fprime)?;
+        let y_plus = yfprime
+            .checked_add(precision.checked_mul(d)?)?
+            .checked_div(fprime)?
+            .checked_add(y_minus.checked_mul(precision)?.checked_div(k0)?)?;
+        y_minus = y_minus.checked_add(precision.checked_mul(s)?.checked_div(fprime)?)?;
+
+        y = if y_plus < y_minus {
+            y_prev / U256::from(2)
+        } else {
+            y_plus - y_minus
+        };
+
+        let diff = y.abs_diff(y_prev);
+        if diff < convergence_limit.max(y / limit) {
+            return Some(y);
+        }
+    }
+    None
+}
+
+/// Computes the geometric mean of balances sorted in descending order.
+fn geometric_mean(x: &[U256]) -> Option<U256> {
+    let precision = U256::from(PRECISION);
+    let n = U256::from(x.len());
+    let mut d = *x.first()?;
+    for _ in 0..MAX_ITERATIONS {
+        let d_prev = d;
+        let mut tmp = precision;
+        for x_k in x {
+            tmp = tmp.checked_mul(*x_k)?.checked_div(d)?;
+        }
+        d = d
+            .checked_mul(
+                n.checked_sub(U256::ONE)?
+                    .checked_mul(precision)?
+                    .checked_add(tmp)?,
+            )?
+            .checked_div(n.checked_mul(precision)?)?;
+        let diff = d.abs_diff(d_prev);
+        if diff <= U256::ONE || diff.checked_mul(precision)? < d {
+            return Some(d);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const E18: u128 = 1_000_000_000_000_000_000;
+    const E6: u128 = 1_000_000;
+    const E8: u128 = 100_000_000;
+
+    /// A balanced tricrypto-like pool (USDT/WBTC/WETH) with $10M of each coin
+    /// at BTC = $50k and ETH = $2.5k.
+    fn tricrypto() -> Pool {
+        Pool {
+            balances: vec![
+                U256::from(10_000_000 * E6),
+                U256::from(200 * E8),
+                U256::from(4_000 * E18),
+            ],
+            precisions: vec![
+                U256::from(1_000_000_000_000u64),
+                U256::from(10_000_000_000u64),
+                U256::ONE,
+            ],
+            price_scale: vec![U256::from(50_000 * E18), U256::from(2_500 * E18)],
+            ann: U256::from(1_707_629u64),
+            gamma: U256::from(11_809_167_828_997u64),
+            mid_fee: U256::from(3_000_000u64),
+            out_fee: U256::from(30_000_000u64),
+            fee_gamma: U256::from(500_000_000_000_000u64),
+            total_supply: U256::from(10_000 * E18),
+        }
+    }
+
+    #[test]
+    fn balanced_swap_is_close_to_price_scale() {
+        let pool = tricrypto();
+        // 1 ETH -> USDT at $2.5k, minus a 3bps mid fee.
+        let dy = pool.get_dy(2, 0, U256::from(E18)).unwrap();
+        assert!(dy < U256::from(2_500 * E6));
+        assert!(dy > U256::from(2_498 * E6), "dy = {dy}");
+    }
+
+    #[test]
+    fn swap_rejects_invalid_indices() {
+        let pool = tricrypto();
+        assert!(pool.get_dy(1, 1, U256::from(1)).is_none());
+        assert!(pool.get_dy(3, 0, U256::from(1)).is_none());
+    }
+
+    #[test]
+    fn deposit_and_withdraw_round_trip_loses_fees() {
+        let pool = tricrypto();
+        let amounts = [U256::ZERO, U256::ZERO, U256::from(E18)];
+        let minted = pool.calc_token_amount(&amounts, true).unwrap();
+        assert!(!minted.is_zero());
+
+        let withdrawn = pool.calc_withdraw_one_coin(minted, 2).unwrap();
+        assert!(withdrawn < U256::from(E18));
+        assert!(
+            withdrawn > U256::from(E18 / 100 * 99),
+            "withdrawn = {withdrawn}"
+        );
+    }
+
+    #[test]
+    fn withdraw_values_lp_tokens_by_share_of_d() {
+        let pool = tricrypto();
+        // Each LP token is worth 1/10000th of the $30M pool, so $3k.
+        let dy = pool.calc_withdraw_one_coin(U256::from(E18), 0).unwrap();
+        assert!(dy < U256::from(3_000 * E6));
+        assert!(dy > U256::from(2_990 * E6), "dy = {dy}");
+    }
+}
@@ -0,0 +1,333 @@
+//! Offline Curve pool pricing.
+//!
+//! Native implementations of the invariants used by Curve pools, fed with
+//! pool state read over RPC (see [`crate::boundary::curve::pool`]). This
+//! allows the Curve LP solver to quote LP token mints and burns locally
+//! without depending on the Curve Router API.
+
+pub mod cryptoswap;
+pub mod stableswap;
+
+use crate::domain::{curve::api, eth};
+
+/// The flavour of a Curve pool, which determines both the invariant used for
+/// pricing and how the Curve Router interacts with it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Kind {
+    /// Plain StableSwap pools (e.g. 3pool).
+    StableSwap,
+    /// StableSwap-NG pools with dynamic off-peg fees and oracle rates.
+    StableSwapNg,
+    /// Original two-coin CryptoSwap pools.
+    TwoCrypto,
+    /// Two-coin CryptoSwap-NG pools.
+    TwoCryptoNg,
+    /// Three-coin Tricrypto-NG pools.
+    TricryptoNg,
+}
+
+impl Kind {
+    /// The pool type identifier used in Curve Router `swap_params`.
+    pub fn router_pool_type(self) -> u64 {
+        match self {
+            Kind::StableSwap => 1,
+            Kind::StableSwapNg => 10,
+            Kind::TwoCrypto => 2,
+            Kind::TwoCryptoNg => 20,
+            Kind::TricryptoNg => 30,
+        }
+    }
+
+    /// The inverse of [`Kind::router_pool_type`].
+    pub fn from_router_pool_type(pool_type: u64) -> Option<Self> {
+        match pool_type {
+            1 => Some(Kind::StableSwap),
+            10 => Some(Kind::StableSwapNg),
+            2 => Some(Kind::TwoCrypto),
+            20 => Some(Kind::TwoCryptoNg),
+            30 => Some(Kind::TricryptoNg),
+            _ => None,
+        }
+    }
+
+    /// Returns the fixed number of coins for CryptoSwap pools, `None` for
+    /// StableSwap pools which can hold a variable number of coins.
+    pub fn n_coins(self) -> Option<usize> {
+        match self {
+            Kind::StableSwap | Kind::StableSwapNg => None,
+            Kind::TwoCrypto | Kind::TwoCryptoNg => Some(2),
+            Kind::TricryptoNg => Some(3),
+        }
+    }
+}
+
+/// Static information identifying a Curve pool.
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    pub address: eth::Address,
+    /// The LP token of the pool. For NG pools this is the pool itself.
+    pub lp_token: eth::TokenAddress,
+    pub kind: Kind,
+}
+
+/// A Curve pool with its current on-chain state.
+#[derive(Clone, Debug)]
+pub struct Pool {
+    pub metadata: Metadata,
+    pub coins: Vec<eth::TokenAddress>,
+    pub state: State,
+}
+
+/// The invariant specific state of a pool.
+#[derive(Clone, Debug)]
+pub enum State {
+    Stable(stableswap::Pool),
+    Crypto(cryptoswap::Pool),
+}
+
+/// A single pool operation that trades one token for another.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Operation {
+    /// Swap coin `i` for coin `j`.
+    Exchange { i: usize, j: usize },
+    /// Deposit coin `i` for LP tokens.
+    AddLiquidity { i: usize },
+    /// Burn LP tokens for coin `j`.
+    RemoveLiquidityOneCoin { j: usize },
+}
+
+impl Operation {
+    /// The swap type identifier used in Curve Router `swap_params`.
+    fn router_swap_type(self) -> u64 {
+        match self {
+            Operation::Exchange { .. } => 1,
+            Operation::AddLiquidity { .. } => 4,
+            Operation::RemoveLiquidityOneCoin { .. } => 6,
+        }
+    }
+}
+
+impl Pool {
+    /// Returns the operation that trades `sell` for `buy` in this pool, if
+    /// any.
+    pub fn operation(&self, sell: eth::TokenAddress, buy: eth::TokenAddress) -> Option<Operation> {
+        let index = |token| self.coins.iter().position(|coin| *coin == token);
+        let lp_token = self.metadata.lp_token;
+        match (index(sell), index(buy)) {
+            (Some(i), Some(j)) if i != j => Some(Operation::Exchange { i, j }),
+            (Some(i), None) if buy == lp_token => Some(Operation::AddLiquidity { i }),
+            (None, Some(j)) if sell == lp_token => Some(Operation::RemoveLiquidityOneCoin { j }),
+            _ => None,
+        }
+    }
+
+    /// Computes the amount of `buy` tokens received for selling `amount` of
+    /// `sell` tokens. Returns `None` if the pool cannot trade the pair or the
+    /// pool math fails.
+    pub fn quote(
+        &self,
+        sell: eth::TokenAddress,
+        buy: eth::TokenAddress,
+        amount: eth::U256,
+    ) -> Option<eth::U256> {
+        let operation = self.operation(sell, buy)?;
+        let single = |i: usize| {
+            let mut amounts = vec![eth::U256::ZERO; self.coins.len()];
+            amounts[i] = amount;
+            amounts
+        };
+        match (&self.state, operation) {
+            (State::Stable(pool), Operation::Exchange { i, j }) => pool.get_dy(i, j, amount),
+            (State::Stable(pool), Operation::AddLiquidity { i }) => {
+                pool.calc_token_amount(&single(i), true)
+            }
+            (State::Stable(pool), Operation::RemoveLiquidityOneCoin { j }) => {
+                pool.calc_withdraw_one_coin(amount, j)
+            }
+            (State::Crypto(pool), Operation::Exchange { i, j }) => pool.get_dy(i, j, amount),
+            (State::Crypto(pool), Operation::AddLiquidity { i }) => {
+                pool.calc_token_amount(&single(i), true)
+            }
+            (State::Crypto(pool), Operation::RemoveLiquidityOneCoin { j }) => {
+                pool.calc_withdraw_one_coin(amount, j)
+            }
+        }
+    }
+
+    /// The total supply of the pool's LP token.
+    pub fn total_supply(&self) -> eth::U256 {
+        match &self.state {
+            State::Stable(pool) => pool.total_supply,
+            State::Crypto(pool) => pool.total_supply,
+        }
+    }
+
+    /// Computes the amounts of all coins, in pool order, received for burning
+    /// `amount` LP tokens in a balanced `remove_liquidity`. Each coin is paid
+    /// out in proportion to its balance without any fee. Amounts are rounded
+    /// down by one more unit, as CryptoSwap pools do.
+    pub fn remove_liquidity(&self, amount: eth::U256) -> Option<Vec<eth::U256>> {
+        let balances = match &self.state {
+            State::Stable(pool) => &pool.balances,
+            State::Crypto(pool) => &pool.balances,
+        };
+        let total_supply = self.total_supply();
+        if amount > total_supply {
+            return None;
+        }
+        balances
+            .iter()
+            .map(|balance| {
+                Some(
+                    balance
+                        .checked_mul(amount)?
+                        .checked_div(total_supply)?
+                        .saturating_sub(eth::U256::ONE),
+                )
+            })
+            .collect()
+    }
+
+    /// Builds a single hop Curve Router route trading `amount` of `sell` for
+    /// `buy` through this pool, with the expected output priced locally.
+    pub fn route(
+        &self,
+        sell: eth::TokenAddress,
+        buy: eth::TokenAddress,
+        amount: eth::U256,
+    ) -> Option<api::Route> {
+        let operation = self.operation(sell, buy)?;
+        let expected_output = self.quote(sell, buy, amount)?;
+
+        let (i, j) = match operation {
+            Operation::Exchange { i, j } => (i, j),
+            Operation::AddLiquidity { i } => (i, 0),
+            Operation::RemoveLiquidityOneCoin { j } => (0, j),
+        };
+
+        let mut route = [eth::Address::ZERO; 11];
+        route[0] = sell.0;
+        route[1] = self.metadata.address;
+        route[2] = buy.0;
+
+        let mut swap_params = [[0u64; 5]; 5];
+        swap_params[0] = [
+            i as u64,
+            j as u64,
+            operation.router_swap_type(),
+            self.metadata.kind.router_pool_type(),
+            self.coins.len() as u64,
+        ];
+
+        Some(api::Route {
+            route,
+            swap_params,
+            pools: [eth::Address::ZERO; 5],
+            expected_output,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloy::primitives::Address};
+
+    fn pool() -> Pool {
+        let e18 = eth::U256::from(10).pow(eth::U256::from(18));
+        Pool {
+            metadata: Metadata {
+                address: Address::repeat_byte(0xaa),
+                lp_token: eth::TokenAddress(Address::repeat_byte(0xaa)),
+                kind: Kind::StableSwapNg,
+            },
+            coins: vec![
+                eth::TokenAddress(Address::repeat_byte(1)),
+                eth::TokenAddress(Address::repeat_byte(2)),
+            ],
+            state: State::Stable(stableswap::Pool {
+                balances: vec![e18 * eth::U256::from(1_000_000); 2],
+                rates: vec![e18; 2],
+                amp: eth::U256::from(100 * stableswap::A_PRECISION),
+                fee: eth::U256::from(4_000_000),
+                offpeg_fee_multiplier: Some(eth::U256::from(2 * stableswap::FEE_DENOMINATOR)),
+                total_supply: e18 * eth::U256::from(2_000_000),
+            }),
+        }
+    }
+
+    #[test]
+    fn router_pool_type_round_trip() {
+        for kind in [
+            Kind::StableSwap,
+            Kind::StableSwapNg,
+            Kind::TwoCrypto,
+            Kind::TwoCryptoNg,
+            Kind::TricryptoNg,
+        ] {
+            assert_eq!(
+                Kind::from_router_pool_type(kind.router_pool_type()),
+                Some(kind)
+            );
+        }
+        assert_eq!(Kind::from_router_pool_type(3), None);
+    }
+
+    #[test]
+    fn operations() {
+        let pool = pool();
+        let lp = pool.metadata.lp_token;
+        let (a, b) = (pool.coins[0], pool.coins[1]);
+        let other = eth::TokenAddress(Address::repeat_byte(3));
+
+        assert_eq!(
+            pool.operation(a, b),
+            Some(Operation::Exchange { i: 0, j: 1 })
+        );
+        assert_eq!(
+            pool.operation(b, lp),
+            Some(Operation::AddLiquidity { i: 1 })
+        );
+        assert_eq!(
+            pool.operation(lp, a),
+            Some(Operation::RemoveLiquidityOneCoin { j: 0 })
+        );
+        assert_eq!(pool.operation(a, a), None);
+        assert_eq!(pool.operation(lp, other), None);
+        assert_eq!(pool.operation(other, a), None);
+    }
+
+    #[test]
+    fn lp_burn_route() {
+        let pool = pool();
+        let lp = pool.metadata.lp_token;
+        let coin = pool.coins[1];
+
+        let route = pool
+            .route(lp, coin, eth::U256::from(10).pow(eth::U256::from(18)))
+            .unwrap();
+        assert_eq!(route.route[0], lp.0);
+        assert_eq!(route.route[1], pool.metadata.address);
+        assert_eq!(route.route[2], coin.0);
+        assert_eq!(route.swap_params[0], [0, 1, 6, 10, 2]);
+        assert!(!route.expected_output.is_zero());
+    }
+
+    #[test]
+    fn balanced_lp_burn() {
+        let pool = pool();
+        let e18 = eth::U256::from(10).pow(eth::U256::from(18));
+
+        // Burning 1% of the supply pays out 1% of each balance.
+        let amounts = pool
+            .remove_liquidity(e18 * eth::U256::from(20_000))
+            .unwrap();
+        assert_eq!(
+            amounts,
+            vec![e18 * eth::U256::from(10_000) - eth::U256::ONE; 2]
+        );
+        assert_eq!(
+            pool.remove_liquidity(e18 * eth::U256::from(2_000_001)),
+            None
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! StableSwap invariant math.
+//!
+//! Integer port of the view functions of Curve's plain StableSwap pools and
+//! StableSwap-NG pools. NG pools additionally scale the swap fee with the
+//! `offpeg_fee_multiplier` when a pool is imbalanced.
+
+use crate::domain::eth::U256;
+
+/// Denominator for all Curve fee values (fees are expressed in 1e10).
+pub const FEE_DENOMINATOR: u64 = 10_000_000_000;
+/// Precision of the amplification coefficient as returned by `A_precise()`.
+pub const A_PRECISION: u64 = 100;
+/// Fixed point precision of rates and normalized balances.
+const PRECISION: u128 = 1_000_000_000_000_000_000;
+/// Iteration limit of the Newton solvers, same as on-chain.
+const MAX_ITERATIONS: usize = 255;
+
+/// The state of a StableSwap pool.
+#[derive(Clone, Debug)]
+pub struct Pool {
+    /// Raw coin balances held by the pool.
+    pub balances: Vec<U256>,
+    /// Rate multipliers normalizing balances to 18 decimals. This is
+    /// `10**(36 - decimals)` for plain coins and the `stored_rates()` of NG
+    /// pools (which include oracle rates).
+    pub rates: Vec<U256>,
+    /// Amplification coefficient multiplied by [`A_PRECISION`].
+    pub amp: U256,
+    /// Swap fee in [`FEE_DENOMINATOR`] units.
+    pub fee: U256,
+    /// The off-peg fee multiplier of NG pools, `None` for plain pools.
+    pub offpeg_fee_multiplier: Option<U256>,
+    /// Total supply of the pool's LP token.
+    pub total_supply: U256,
+}
+
+impl Pool {
+    /// Returns the amount of coin `j` received for selling `dx` of coin `i`.
+    pub fn get_dy(&self, i: usize, j: usize, dx: U256) -> Option<U256> {
+        let n = self.balances.len();
+        if i == j || i >= n || j >= n {
+            return None;
+        }
+
+        let xp = self.xp(&self.balances)?;
+        let x = xp[i].checked_add(dx.checked_mul(self.rates[i])? / U256::from(PRECISION))?;
+        let d = get_d(&xp, self.amp)?;
+        let y = get_y(i, j, x, &xp, self.amp, d)?;
+        let dy = xp[j].checked_sub(y)?.checked_sub(U256::ONE)?;
+
+        let fee = self
+            .dynamic_fee(
+                xp[i].checked_add(x)? / U256::from(2),
+                xp[j].checked_add(y)? / U256::from(2),
+                self.fee,
+            )?
+            .checked_mul(dy)?
+            / U256::from(FEE_DENOMINATOR);
+        dy.checked_sub(fee)?
+            .checked_mul(U256::from(PRECISION))?
+            .checked_div(self.rates[j])
+    }
+
+    /// Returns the amount of coin `i` received for burning `token_amount` LP
+    /// tokens.
+    pub fn calc_withdraw_one_coin(&self, token_amount: U256, i: usize) -> Option<U256> {
+        let n = self.balances.len();
+        if i >= n || n < 2 {
+            return None;
+        }
+
+        let xp = self.xp(&self.balances)?;
+        let d0 = get_d(&xp, self.amp)?;
+        let d1 = d0.checked_sub(
+            token_amount
+                .checked_mul(d0)?
+                .checked_div(self.total_supply)?,
+        )?;
+        let new_y = get_y_d(self.amp, i, &xp, d1)?;
+
+        let base_fee = self.base_fee()?;
+        let ys = d0.checked_add(d1)? / U256::from(n);
+        let mut xp_reduced = xp.clone();
+        for (k, xp_k) in xp.iter().enumerate() {
+            let xp_k_after = xp_k.checked_mul(d1)?.checked_div(d0)?;
+            let (dx_expected, xavg) = if k == i {
+                (
+                    xp_k_after.checked_sub(new_y)?,
+                    xp_k.checked_add(new_y)? / U256::from(2),
+                )
+            } else {
+                (xp_k.checked_sub(xp_k_after)?, *xp_k)
+            };
+            let fee = self
+                .dynamic_fee(xavg, ys, base_fee)?
+                .checked_mul(dx_expected)?
+                / U256::from(FEE_DENOMINATOR);
+            xp_reduced[k] = xp_reduced[k].checked_sub(fee)?;
+        }
+
+        let dy = xp_reduced[i].checked_sub(get_y_d(self.amp, i, &xp_reduced, d1)?)?;
+        dy.checked_sub(U256::ONE)?
+            .checked_mul(U256::from(PRECISION))?
+            .checked_div(self.rates[i])
+    }
+
+    /// Returns the amount of LP tokens minted (when `deposit`) or burned
+    /// (otherwise) for adding or removing the specified coin `amounts`,
+    /// including imbalance fees.
+    pub fn calc_token_amount(&self, amounts: &[U256], deposit: bool) -> Option<U256> {
+        let n = self.balances.len();
+        if amounts.len() != n || n < 2 {
+            return None;
+        }
+
+        let d0 = get_d(&self.xp(&self.balances)?, self.amp)?;
+        let new_balances = self
+            .balances
+            .iter()
+            .zip(amounts)
+            .map(|(balance, amount)| {
+                if deposit {
+                    balance.checked_add(*amount)
+                } else {
+                    balance.checked_sub(*amount)
+                }
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let d1 = get_d(&self.xp(&new_balances)?, self.amp)?;
+        if self.total_supply.is_zero() {
+            return deposit.then_some(d1);
+        }
+
+        let base_fee = self.base_fee()?;
+        let ys = d0.checked_add(d1)? / U256::from(n);
+        let mut balances = new_balances.clone();
+        for k in 0..n {
+            let ideal = d1.checked_mul(self.balances[k])?.checked_div(d0)?;
+            let difference = ideal.abs_diff(new_balances[k]);
+            let xs = self.rates[k].checked_mul(self.balances[k].checked_add(new_balances[k])?)?
+                / U256::from(PRECISION);
+            let fee = self
+                .dynamic_fee(xs, ys, base_fee)?
+                .checked_mul(difference)?
+                / U256::from(FEE_DENOMINATOR);
+            balances[k] = balances[k].checked_sub(fee)?;
+        }
+        let d2 = get_d(&self.xp(&balances)?, self.amp)?;
+
+        let diff = if deposit {
+            d2.checked_sub(d0)?
+        } else {
+            d0.checked_sub(d2)?
+        };
+        diff.checked_mul(self.total_supply)?.checked_div(d0)
+    }
+
+    /// Normalizes raw balances to 18 decimals using the pool rates.
+    fn xp(&self, balances: &[U256]) -> Option<Vec<U256>> {
+        if balances.len() != self.rates.len() {
+            return None;
+        }
+        balances
+            .iter()
+            .zip(&self.rates)
+            .map(|(balance, rate)| Some(balance.checked_mul(*rate)? / U256::from(PRECISION)))
+            .collect()
+    }
+
+    /// The fee charged on imbalanced liquidity operations.
+    fn base_fee(&self) -> Option<U256> {
+        let n = self.balances.len();
+        self.fee
+            .checked_mul(U256::from(n))?
+            .checked_div(U256::from(4 * (n - 1)))
+    }
+
+    /// Scales `fee` by the off-peg multiplier based on how imbalanced `xpi`
+    /// and `xpj` are. Plain pools always return `fee` unchanged.
+    fn dynamic_fee(&self, xpi: U256, xpj: U256, fee: U256) -> Option<U256> {
+        let denominator = U256::from(FEE_DENOMINATOR);
+        match self.offpeg_fee_multiplier {
+            Some(multiplier) if multiplier > denominator => {
+                let sum = xpi.checked_add(xpj)?;
+                let xps2 = sum.checked_mul(sum)?;
+                let imbalance = (multiplier - denominator)
+                    .checked_mul(U256::from(4))?
+                    .checked_mul(xpi)?
+                    .checked_mul(xpj)?
+                    .checked_div(xps2)?;
+                multiplier
+                    .checked_mul(fee)?
+                    .checked_div(imbalance.checked_add(denominator)?)
+            }
+            _ => Some(fee),
+        }
+    }
+}
+
+/// Computes the StableSwap invariant `D` for normalized balances.
+fn get_d(xp: &[U256], amp: U256) -> Option<U256> {
+    let n = U256::from(xp.len());
+    let s = xp
+        .iter()
+        .try_fold(U256::ZERO, |acc, x| acc.checked_add(*x))?;
+    if s.is_zero() {
+        return Some(U256::ZERO);
+    }
+
+    let a_precision = U256::from(A_PRECISION);
+    let ann = amp.checked_mul(n)?;
+    let mut d = s;
+    for _ in 0..MAX_ITERATIONS {
+        let mut d_p = d;
+        for x in xp {
+            d_p = d_p.checked_mul(d)?.checked_div(x.checked_mul(n)?)?;
+        }
+        let d_prev = d;
+        let numerator = (ann.checked_mul(s)? / a_precision)
+            .checked_add(d_p.checked_mul(n)?)?
+            .checked_mul(d)?;
+        let denominator = (ann.checked_sub(a_precision)?.checked_mul(d)? / a_precision)
+            .checked_add(n.checked_add(U256::ONE)?.checked_mul(d_p)?)?;
+        d = numerator.checked_div(denominator)?;
+        if d.abs_diff(d_prev) <= U256::ONE {
+            return Some(d);
+        }
+    }
+    None
+}
+
+/// Computes the new normalized balance of coin `j` when the balance of coin
+/// `i` is set to `x`, keeping the invariant `d` constant.
+fn get_y(i: usize, j: usize, x: U256, xp: &[U256], amp: U256, d: U256) -> Option<U256> {
+    let n = U256::from(xp.len());
+    let mut c = d;
+    let mut s = U256::ZERO;
+    for (k, xp_k) in xp.iter().enumerate() {
+        let x_k = if k == i {
+            x
+        } else if k != j {
+            *xp_k
+        } else {
+            continue;
+        };
+        s = s.checked_add(x_k)?;
+        c = c.checked_mul(d)?.checked_div(x_k.checked_mul(n)?)?;
+    }
+    solve_y(c, s, d, amp, n)
+}
+
+/// Computes the normalized balance of coin `i` for the invariant `d`, keeping
+/// all other balances constant.
+fn get_y_d(amp: U256, i: usize, xp: &[U256], d: U256) -> Option<U256> {
+    let n = U256::from(xp.len());
+    let mut c = d;
+    let mut s = U256::ZERO;
+    for (k, x_k) in xp.iter().enumerate() {
+        if k == i {
+            continue;
+        }
+        s = s.checked_add(*x_k)?;
+        c = c.checked_mul(d)?.checked_div(x_k.checked_mul(n)?)?;
+    }
+    solve_y(c, s, d, amp, n)
+}
+
+/// Newton iteration for `y**2 + (b - D) * y = c` shared by [`get_y`] and
+/// [`get_y_d`].
+fn solve_y(c: U256, s: U256, d: U256, amp: U256, n: U256) -> Option<U256> {
+    let a_precision = U256::from(A_PRECISION);
+    let ann = amp.checked_mul(n)?;
+    let c = c
+        .checked_mul(d)?
+        .checked_mul(a_precision)?
+        .checked_div(ann.checked_mul(n)?)?;
+    let b = s.checked_add(d.checked_mul(a_precision)?.checked_div(ann)?)?;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        y = y.checked_mul(y)?.checked_add(c)?.checked_div(
+            y.checked_mul(U256::from(2))?
+                .checked_add(b)?
+                .checked_sub(d)?,
+        )?;
+        if y.abs_diff(y_prev) <= U256::ONE {
+            return Some(y);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const E18: u128 = 1_000_000_000_000_000_000;
+    const E6: u128 = 1_000_000;
+
+    /// A balanced 3pool-like pool (DAI/USDC/USDT) with 100M of each coin.
+    fn three_pool() -> Pool {
+        Pool {
+            balances: vec![
+                U256::from(100_000_000 * E18),
+                U256::from(100_000_000 * E6),
+                U256::from(100_000_000 * E6),
+            ],
+            rates: vec![
+                U256::from(E18),
+                U256::from(E18 * 1_000_000_000_000),
+                U256::from(E18 * 1_000_000_000_000),
+            ],
+            amp: U256::from(2_000 * A_PRECISION),
+            fee: U256::from(1_000_000), // 1bps
+            offpeg_fee_multiplier: None,
+            total_supply: U256::from(300_000_000 * E18),
+        }
+    }
+
+    #[test]
+    fn balanced_swap_is_close_to_par() {
+        let pool = three_pool();
+        // 1000 DAI -> USDC
+        let dy = pool.get_dy(0, 1, U256::from(1_000 * E18)).unwrap();
+        // 1bps fee and a negligible amount of price impact.
+        assert!(dy < U256::from(1_000 * E6));
+        assert!(dy > U256::from(999_800_000u64), "dy = {dy}");
+    }
+
+    #[test]
+    fn swap_rejects_invalid_indices() {
+        let pool = three_pool();
+        assert!(pool.get_dy(0, 0, U256::from(1)).is_none());
+        assert!(pool.get_dy(0, 3, U256::from(1)).is_none());
+    }
+
+    #[test]
+    fn withdraw_one_coin_at_virtual_price() {
+        let pool = three_pool();
+        // Burning 1000 LP tokens of a balanced pool with a virtual price of 1
+        // returns ~1000 USDT.
+        let dy = pool
+            .calc_withdraw_one_coin(U256::from(1_000 * E18), 2)
+            .unwrap();
+        assert!(dy < U256::from(1_000 * E6));
+        assert!(dy > U256::from(999_800_000u64), "dy = {dy}");
+    }
+
+    #[test]
+    fn deposit_and_withdraw_round_trip_loses_fees() {
+        let pool = three_pool();
+        let amounts = [U256::ZERO, U256::from(1_000 * E6), U256::ZERO];
+        let minted = pool.calc_token_amount(&amounts, true).unwrap();
+        assert!(minted < U256::from(1_000 * E18));
+        assert!(minted > U256::from(999 * E18), "minted = {minted}");
+
+        let withdrawn = pool.calc_withdraw_one_coin(minted, 1).unwrap();
+        assert!(withdrawn < U256::from(1_000 * E6));
+    }
+
+    #[test]
+    fn offpeg_multiplier_increases_fees_on_imbalanced_pools() {
+        let mut plain = three_pool();
+        plain.balances[1] = U256::from(10_000_000 * E6);
+        let mut ng = plain.clone();
+        ng.offpeg_fee_multiplier = Some(U256::from(5 * FEE_DENOMINATOR));
+
+        let dx = U256::from(1_000 * E18);
+        assert!(ng.get_dy(0, 1, dx).unwrap() < plain.get_dy(0, 1, dx).unwrap());
+    }
+}
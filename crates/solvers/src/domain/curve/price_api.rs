@@ -1,7 +1,9 @@
 //! Curve Price API client for fetching LP token USD prices.
 
 use {
-    crate::domain::eth,
+    super::upstream::{self, Upstream},
+    crate::{domain::eth, infra::metrics},
+    chain::Chain,
     reqwest::Url,
     serde::Deserialize,
     std::{
@@ -12,14 +14,27 @@ use {
     },
 };
 
-/// WETH address on Ethereum mainnet.
-const WETH_ADDRESS: eth::Address =
-    alloy::primitives::address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+/// Returns the name the Curve Price API uses for the specified chain, if it
+/// covers it.
+pub fn chain_name(chain: Chain) -> Option<&'static str> {
+    match chain {
+        Chain::Mainnet => Some("ethereum"),
+        Chain::Gnosis => Some("xdai"),
+        Chain::ArbitrumOne => Some("arbitrum"),
+        Chain::Base => Some("base"),
+        Chain::Optimism => Some("optimism"),
+        Chain::Polygon => Some("polygon"),
+        Chain::Avalanche => Some("avalanche"),
+        Chain::Bnb => Some("bsc"),
+        _ => None,
+    }
+}
 
 /// Curve Price API client.
 pub struct Client {
-    http: reqwest::Client,
-    base_url: Url,
+    upstream: Upstream,
+    /// The wrapped native token, which prices are denominated in.
+    weth: eth::WethAddress,
     cache: Mutex<HashMap<eth::Address, CachedPrice>>,
 }
 
@@ -43,20 +58,22 @@ struct CachedPrice {
 const CACHE_TTL: Duration = Duration::from_secs(60);
 
 impl Client {
-    /// Creates a new Curve Price API client.
-    pub fn new(base_url: Url) -> Self {
-        let http = reqwest::Client::builder()
-            .timeout(Duration::from_secs(10))
-            .build()
-            .expect("failed to build HTTP client");
-
+    /// Creates a new Curve Price API client quoting prices in the native
+    /// token wrapped by `weth`, failing over to the `mirrors` of `base_url`
+    /// when it is unavailable.
+    pub fn new(base_url: Url, mirrors: Vec<Url>, weth: eth::WethAddress) -> Self {
         Self {
-            http,
-            base_url,
+            upstream: Upstream::new("curve-price-api", base_url, mirrors),
+            weth,
             cache: Mutex::new(HashMap::new()),
         }
     }
 
+    /// The mirrors whose circuit breakers currently reject requests.
+    pub fn open_breakers(&self) -> Vec<upstream::OpenBreaker> {
+        self.upstream.open_breakers()
+    }
+
     /// Fetches the ETH-denominated price for a token.
     /// Returns price as U256 representing wei needed to buy 10^18 of the token.
     /// This is compatible with `auction::Price`.
@@ -64,6 +81,7 @@ impl Client {
         &self,
         chain: &str,
         token: eth::Address,
+        timeout: Duration,
     ) -> Result<eth::U256, Error> {
         if let Some(price) = self.cached_price(token) {
             return Ok(price);
@@ -71,8 +89,8 @@ impl Client {
 
         // Fetch both token and WETH USD prices in parallel
         let (token_usd, weth_usd) = tokio::join!(
-            self.get_usd_price_raw(chain, token),
-            self.get_usd_price_raw(chain, WETH_ADDRESS),
+            self.get_usd_price_raw(chain, token, timeout),
+            self.get_usd_price_raw(chain, self.weth.0, timeout),
         );
         let token_usd = token_usd?;
         let weth_usd = weth_usd?;
@@ -102,31 +120,30 @@ impl Client {
     }
 
     /// Fetches raw USD price for a token as f64.
-    async fn get_usd_price_raw(&self, chain: &str, token: eth::Address) -> Result<f64, Error> {
-        let url = format!("{}v1/usd_price/{}/{:?}", self.base_url, chain, token);
+    async fn get_usd_price_raw(
+        &self,
+        chain: &str,
+        token: eth::Address,
+        timeout: Duration,
+    ) -> Result<f64, Error> {
+        let path = format!("v1/usd_price/{}/{:?}", chain, token);
 
-        tracing::debug!(%url, "fetching Curve token price");
+        tracing::debug!(%path, "fetching Curve token price");
 
-        let response = self
-            .http
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| Error::Network(e.to_string()))?;
+        let start = std::time::Instant::now();
+        let response = self.upstream.get(&path, timeout).await;
+        metrics::api_request("price", start.elapsed());
+        let response = response.map_err(|e| Error::Network(e.to_string()))?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
+        if !response.is_success() {
             return Err(Error::Api {
-                status: status.as_u16(),
-                message: body,
+                status: response.status,
+                message: response.body,
             });
         }
 
-        let price_response: PriceResponse = response
-            .json()
-            .await
-            .map_err(|e| Error::Parse(e.to_string()))?;
+        let price_response: PriceResponse =
+            serde_json::from_str(&response.body).map_err(|e| Error::Parse(e.to_string()))?;
 
         let usd_price = price_response.data.usd_price;
         if !usd_price.is_finite() || usd_price <= 0.0 {
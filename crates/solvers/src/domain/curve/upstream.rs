@@ -0,0 +1,363 @@
+//! Health tracked access to mirrored upstream HTTP APIs.
+//!
+//! Requests go to the first mirror whose circuit breaker lets them through,
+//! failing over to the next mirror on network errors, timeouts and server
+//! errors. A breaker opens once too many of the recent requests to its mirror
+//! failed, and then rejects requests without reaching out to the mirror.
+//! After a cool-down it lets a single probe request through (half-open),
+//! closing again if the probe succeeds.
+//!
+//! Requests made while solving an auction are bounded by its deadline, see
+//! [`with_deadline`], and split the time that remains between the mirrors
+//! they may still fail over to.
+
+use {
+    crate::infra::archive,
+    reqwest::Url,
+    std::{collections::VecDeque, fmt, sync::Mutex, time::Duration},
+    tokio::time::Instant,
+};
+
+/// Upper bound on the time spent on a single request.
+const MAX_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Attempts get at least this much time, unless the deadline is closer.
+/// Attempts timing out with less time than this aren't held against the
+/// mirror.
+const MIN_ATTEMPT_TIMEOUT: Duration = Duration::from_millis(250);
+/// Number of recent requests the error rate of a mirror is computed over.
+const WINDOW: usize = 20;
+/// Minimum number of recent requests before a breaker may open.
+const MIN_REQUESTS: usize = 5;
+/// Error rate in percent at which a breaker opens.
+const MAX_ERROR_RATE_PCT: usize = 50;
+/// How long an open breaker rejects requests before probing its mirror.
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+/// Runs `future` with all of its upstream requests bounded by `deadline`.
+pub async fn with_deadline<F: Future>(deadline: Instant, future: F) -> F::Output {
+    DEADLINE.scope(deadline, future).await
+}
+
+/// The deadline set by [`with_deadline`] for the current task, if any.
+pub fn deadline() -> Option<Instant> {
+    DEADLINE.try_with(|deadline| *deadline).ok()
+}
+
+/// An upstream API served by one or more mirrors, in order of preference.
+pub struct Upstream {
+    /// The name of the upstream in archives and health reports.
+    service: &'static str,
+    http: reqwest::Client,
+    mirrors: Vec<Mirror>,
+}
+
+struct Mirror {
+    url: Url,
+    breaker: Mutex<Breaker>,
+}
+
+/// A mirror whose circuit breaker currently rejects requests.
+#[derive(Clone, Debug)]
+pub struct OpenBreaker {
+    pub service: &'static str,
+    pub url: Url,
+    /// Whether a probe request is in flight.
+    pub half_open: bool,
+}
+
+impl Upstream {
+    /// Creates an upstream served by `url`, falling back to the `mirrors`.
+    pub fn new(service: &'static str, url: Url, mirrors: Vec<Url>) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(MAX_REQUEST_TIMEOUT)
+            .build()
+            .expect("failed to build HTTP client");
+
+        Self {
+            service,
+            http,
+            mirrors: std::iter::once(url)
+                .chain(mirrors)
+                .map(|url| Mirror {
+                    url,
+                    breaker: Default::default(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Sends a GET request to `{mirror}{path}`, within `timeout` or the
+    /// deadline of the current auction, whichever is earlier.
+    ///
+    /// Server error responses are only returned if no mirror responded
+    /// successfully.
+    pub async fn get(&self, path: &str, timeout: Duration) -> Result<archive::HttpResponse, Error> {
+        let now = Instant::now();
+        let deadline = deadline().map_or(now + timeout, |deadline| deadline.min(now + timeout));
+
+        let mut last = None;
+        for (i, mirror) in self.mirrors.iter().enumerate() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                last.get_or_insert(Err(Error::Timeout(timeout)));
+                break;
+            }
+            if !mirror.admit() {
+                continue;
+            }
+
+            let mirrors_left = u32::try_from(self.mirrors.len() - i).unwrap_or(u32::MAX);
+            let timeout = (remaining / mirrors_left)
+                .max(MIN_ATTEMPT_TIMEOUT)
+                .min(remaining);
+            let url = format!("{}{}", mirror.url, path);
+            let result =
+                tokio::time::timeout(timeout, archive::get(self.service, &self.http, &url)).await;
+
+            let failure = match result {
+                Ok(Ok(response)) if is_server_error(response.status) => {
+                    let error = Error::Status(response.status);
+                    last = Some(Ok(response));
+                    Some(error)
+                }
+                Ok(Ok(response)) => {
+                    mirror.record(false, self.service);
+                    return Ok(response);
+                }
+                Ok(Err(archive::Error::NotRecorded(err))) => {
+                    return Err(Error::NotRecorded(err));
+                }
+                Ok(Err(archive::Error::Upstream(err))) => {
+                    last = Some(Err(Error::Network(err.clone())));
+                    Some(Error::Network(err))
+                }
+                Err(_) => {
+                    last = Some(Err(Error::Timeout(timeout)));
+                    // Attempts cut short by the deadline don't tell much
+                    // about the health of the mirror.
+                    (timeout >= MIN_ATTEMPT_TIMEOUT).then_some(Error::Timeout(timeout))
+                }
+            };
+            if let Some(err) = failure {
+                tracing::debug!(
+                    service = self.service, url = %mirror.url, %err,
+                    "upstream request failed"
+                );
+                mirror.record(true, self.service);
+            }
+        }
+
+        last.unwrap_or(Err(Error::Unavailable))
+    }
+
+    /// The mirrors whose circuit breakers currently reject requests.
+    pub fn open_breakers(&self) -> Vec<OpenBreaker> {
+        self.mirrors
+            .iter()
+            .filter_map(|mirror| {
+                let breaker = mirror.breaker.lock().ok()?;
+                let half_open = match breaker.state {
+                    State::Closed => return None,
+                    State::Open { .. } => false,
+                    State::HalfOpen { .. } => true,
+                };
+                Some(OpenBreaker {
+                    service: self.service,
+                    url: mirror.url.clone(),
+                    half_open,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Mirror {
+    /// Whether the breaker lets a request through.
+    fn admit(&self) -> bool {
+        self.breaker
+            .lock()
+            .map(|mut breaker| breaker.admit(Instant::now()))
+            .unwrap_or(true)
+    }
+
+    fn record(&self, failed: bool, service: &str) {
+        let Ok(mut breaker) = self.breaker.lock() else {
+            return;
+        };
+        let was_closed = matches!(breaker.state, State::Closed);
+        breaker.record(failed, Instant::now());
+        match (was_closed, &breaker.state) {
+            (true, State::Open { .. }) => {
+                tracing::warn!(service, url = %self.url, "opened upstream circuit breaker")
+            }
+            (false, State::Closed) => {
+                tracing::info!(service, url = %self.url, "closed upstream circuit breaker")
+            }
+            _ => {}
+        }
+    }
+}
+
+/// An error rate based circuit breaker.
+#[derive(Debug, Default)]
+struct Breaker {
+    /// Outcomes of the recent requests while closed, `true` for failures.
+    recent: VecDeque<bool>,
+    state: State,
+}
+
+#[derive(Debug, Default)]
+enum State {
+    /// Requests are let through.
+    #[default]
+    Closed,
+    /// Requests are rejected until the cool-down ends.
+    Open { until: Instant },
+    /// A single probe request was let through. Should its outcome never be
+    /// recorded, another probe is let through after a cool-down.
+    HalfOpen { until: Instant },
+}
+
+impl Breaker {
+    fn admit(&mut self, now: Instant) -> bool {
+        match self.state {
+            State::Closed => true,
+            State::Open { until } | State::HalfOpen { until } if now >= until => {
+                self.state = State::HalfOpen {
+                    until: now + OPEN_DURATION,
+                };
+                true
+            }
+            State::Open { .. } | State::HalfOpen { .. } => false,
+        }
+    }
+
+    fn record(&mut self, failed: bool, now: Instant) {
+        match self.state {
+            State::Closed => {
+                self.recent.push_back(failed);
+                if self.recent.len() > WINDOW {
+                    self.recent.pop_front();
+                }
+                let failures = self.recent.iter().filter(|failed| **failed).count();
+                if self.recent.len() >= MIN_REQUESTS
+                    && failures * 100 >= self.recent.len() * MAX_ERROR_RATE_PCT
+                {
+                    self.open(now);
+                }
+            }
+            State::HalfOpen { .. } if failed => self.open(now),
+            State::HalfOpen { .. } => self.state = State::Closed,
+            // Outcome of a request let through before the breaker opened.
+            State::Open { .. } => {}
+        }
+    }
+
+    fn open(&mut self, now: Instant) {
+        self.recent.clear();
+        self.state = State::Open {
+            until: now + OPEN_DURATION,
+        };
+    }
+}
+
+/// Rate limiting responses are treated like server errors, since another
+/// mirror may well serve the request.
+fn is_server_error(status: u16) -> bool {
+    status >= 500 || status == 429
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Network(String),
+    Status(u16),
+    Timeout(Duration),
+    /// All mirrors have open circuit breakers.
+    Unavailable,
+    /// A replayed request has no recorded response.
+    NotRecorded(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Network(msg) => write!(f, "{}", msg),
+            Error::Status(status) => write!(f, "server error (status {})", status),
+            Error::Timeout(timeout) => {
+                write!(f, "request timed out after {}ms", timeout.as_millis())
+            }
+            Error::Unavailable => write!(f, "all upstream circuit breakers are open"),
+            Error::NotRecorded(msg) => write!(f, "not recorded: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_on_error_rate() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+        for _ in 0..MIN_REQUESTS - 1 {
+            breaker.record(true, now);
+        }
+        // Too few requests to judge the mirror.
+        assert!(breaker.admit(now));
+
+        breaker.record(true, now);
+        assert!(!breaker.admit(now));
+    }
+
+    #[test]
+    fn stays_closed_below_error_rate() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+        for _ in 0..WINDOW {
+            breaker.record(false, now);
+            breaker.record(false, now);
+            breaker.record(true, now);
+        }
+        assert!(breaker.admit(now));
+    }
+
+    #[test]
+    fn probes_after_cool_down() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+        breaker.open(now);
+        assert!(!breaker.admit(now + OPEN_DURATION / 2));
+
+        // A single probe is let through, and a failed probe opens the
+        // breaker again.
+        let later = now + OPEN_DURATION;
+        assert!(breaker.admit(later));
+        assert!(!breaker.admit(later));
+        breaker.record(true, later);
+        assert!(matches!(breaker.state, State::Open { .. }));
+
+        // A successful probe closes it.
+        let later = later + OPEN_DURATION;
+        assert!(breaker.admit(later));
+        breaker.record(false, later);
+        assert!(matches!(breaker.state, State::Closed));
+        assert!(breaker.admit(later));
+    }
+
+    #[test]
+    fn probes_again_if_probe_is_lost() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+        breaker.open(now);
+        assert!(breaker.admit(now + OPEN_DURATION));
+        assert!(!breaker.admit(now + OPEN_DURATION));
+        assert!(breaker.admit(now + OPEN_DURATION * 2));
+    }
+}
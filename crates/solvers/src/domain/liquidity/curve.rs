@@ -0,0 +1,100 @@
+use {
+    crate::domain::{curve::pool as curve, eth, liquidity},
+    itertools::Itertools as _,
+};
+
+/// The state of a Curve StableSwap or CryptoSwap pool.
+///
+/// Pools are priced with the same native invariant implementations used by
+/// the Curve LP solver. Only coin-to-coin exchanges are supported, so the LP
+/// token supply of the pool state is unused.
+#[derive(Clone, Debug)]
+pub struct Pool {
+    pub kind: curve::Kind,
+    /// The pool coins, in pool order.
+    pub coins: Vec<eth::TokenAddress>,
+    pub state: curve::State,
+}
+
+impl Pool {
+    /// Returns an iterator over the token pairs that can be exchanged in the
+    /// pool.
+    pub fn token_pairs(&self) -> impl Iterator<Item = liquidity::TokenPair> + '_ {
+        self.coins
+            .iter()
+            .tuple_combinations()
+            .filter_map(|(a, b)| liquidity::TokenPair::new(*a, *b))
+    }
+
+    /// Returns the raw balance of each coin held by the pool.
+    pub fn balances(&self) -> &[eth::U256] {
+        match &self.state {
+            curve::State::Stable(pool) => &pool.balances,
+            curve::State::Crypto(pool) => &pool.balances,
+        }
+    }
+
+    /// Returns the balance of `token` held by the pool.
+    pub fn balance(&self, token: eth::TokenAddress) -> Option<eth::U256> {
+        self.balances().get(self.index(token)?).copied()
+    }
+
+    /// Computes the amount of `buy` tokens received for selling `amount` of
+    /// `sell` tokens. Returns `None` if either token isn't a pool coin or the
+    /// pool math fails.
+    pub fn get_dy(
+        &self,
+        sell: eth::TokenAddress,
+        buy: eth::TokenAddress,
+        amount: eth::U256,
+    ) -> Option<eth::U256> {
+        let (i, j) = (self.index(sell)?, self.index(buy)?);
+        match &self.state {
+            curve::State::Stable(pool) => pool.get_dy(i, j, amount),
+            curve::State::Crypto(pool) => pool.get_dy(i, j, amount),
+        }
+    }
+
+    fn index(&self, token: eth::TokenAddress) -> Option<usize> {
+        self.coins.iter().position(|coin| *coin == token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::domain::curve::pool::stableswap, alloy::primitives::Address};
+
+    #[test]
+    fn exchanges_pool_coins() {
+        let e18 = eth::U256::from(10).pow(eth::U256::from(18));
+        let coins = [1, 2, 3].map(|byte| eth::TokenAddress(Address::repeat_byte(byte)));
+        let pool = Pool {
+            kind: curve::Kind::StableSwap,
+            coins: coins.to_vec(),
+            state: curve::State::Stable(stableswap::Pool {
+                balances: vec![e18 * eth::U256::from(1_000_000); 3],
+                rates: vec![e18; 3],
+                amp: eth::U256::from(200 * stableswap::A_PRECISION),
+                fee: eth::U256::from(1_000_000),
+                offpeg_fee_multiplier: None,
+                total_supply: eth::U256::ZERO,
+            }),
+        };
+
+        assert_eq!(pool.token_pairs().count(), 3);
+        assert_eq!(
+            pool.balance(coins[2]),
+            Some(e18 * eth::U256::from(1_000_000))
+        );
+
+        // A small trade in a balanced pool executes close to 1:1, minus the
+        // 0.01% fee.
+        let out = pool.get_dy(coins[0], coins[2], e18).unwrap();
+        assert!(out < e18);
+        assert!(out > e18 * eth::U256::from(9_998) / eth::U256::from(10_000));
+
+        let other = eth::TokenAddress(Address::repeat_byte(4));
+        assert_eq!(pool.get_dy(coins[0], other, e18), None);
+        assert_eq!(pool.get_dy(coins[0], coins[0], e18), None);
+    }
+}
@@ -2,6 +2,7 @@
 
 pub mod concentrated;
 pub mod constant_product;
+pub mod curve;
 pub mod limit_order;
 pub mod stable;
 pub mod weighted_product;
@@ -29,6 +30,7 @@ pub enum State {
     Stable(stable::Pool),
     Concentrated(concentrated::Pool),
     LimitOrder(limit_order::LimitOrder),
+    Curve(curve::Pool),
 }
 
 /// An ordered token pair.
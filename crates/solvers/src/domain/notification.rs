@@ -4,6 +4,7 @@ use {
         eth::{self, Ether, TokenAddress},
         solution::{self},
     },
+    chrono::{DateTime, Utc},
     std::collections::BTreeSet,
 };
 
@@ -16,7 +17,7 @@ pub type SimulationSucceededAtLeastOnce = bool;
 
 /// The notification about important events happened in driver, that solvers
 /// need to know about.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Notification {
     pub auction_id: auction::Id,
     pub solution_id: Option<Id>,
@@ -30,7 +31,7 @@ pub enum Id {
 }
 
 /// All types of notifications solvers can be informed about.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Kind {
     Timeout,
     EmptySolution,
@@ -42,18 +43,21 @@ pub enum Kind {
     Settled(Settlement),
     DriverError(String),
     PostprocessingTimedOut,
+    Banned(DateTime<Utc>),
+    DeserializationError(String),
 }
 
 /// The result of winning solver trying to settle the transaction onchain.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum Settlement {
     Success(TransactionHash),
     Revert(TransactionHash),
     SimulationRevert,
+    Expired,
     Fail,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum ScoreKind {
     InvalidClearingPrices,
     InvalidExecutedAmount,
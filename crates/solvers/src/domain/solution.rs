@@ -4,7 +4,7 @@ use {
     std::{collections::HashMap, slice},
 };
 
-#[derive(Debug, Default, Copy, Clone)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
 pub struct Id(pub u64);
 
 #[derive(Debug, Default)]
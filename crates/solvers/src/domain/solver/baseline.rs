@@ -15,7 +15,6 @@ use {
             order::{self, Order, Side},
             solution::{self, Solution},
         },
-        infra::metrics,
     },
     alloy::primitives::U256,
     reqwest::Url,
@@ -104,8 +103,6 @@ impl Solver {
     /// Solves the specified auction, returning a vector of all possible
     /// solutions.
     pub async fn solve(&self, auction: auction::Auction) -> Vec<solution::Solution> {
-        metrics::solve(&auction);
-        let deadline = auction.deadline.clone();
         // Make sure to push the CPU-heavy code to a separate thread in order to
         // not lock up the [`tokio`] runtime and cause it to slow down handling
         // the real async things. For larger settlements, this can block in the
@@ -136,7 +133,6 @@ impl Solver {
         while let Ok(solution) = receiver.try_recv() {
             solutions.push(solution);
         }
-        metrics::solved(&deadline, &solutions);
         solutions
     }
 }
@@ -0,0 +1,495 @@
+//! Composite solver running several solver engines on the same auction.
+//!
+//! Every auction is fanned out to all sub-solvers, which solve it concurrently
+//! within the shared deadline. Sub-solvers number their solutions
+//! independently and may solve the same orders, so the composite keeps at most
+//! one solution per order, preferring the solutions with the highest estimated
+//! surplus, and re-numbers the remaining ones. The sub-solver that produced
+//! each solution is remembered so that notifications reach it with the
+//! solution ID it assigned.
+
+use {
+    super::{baseline, curve_lp},
+    crate::domain::{
+        auction::{self, Auction},
+        curve::upstream,
+        eth,
+        notification,
+        order,
+        solution::{self, Solution},
+    },
+    futures::future,
+    std::{
+        collections::{BTreeMap, HashMap, HashSet, VecDeque},
+        sync::Mutex,
+    },
+};
+
+/// Number of recent solutions whose origin is remembered for notifications.
+const MAX_TRACKED_SOLUTIONS: usize = 4_096;
+
+/// Configuration for the composite solver.
+pub struct Config {
+    pub solvers: Vec<SubSolver>,
+}
+
+/// A solver engine run by the composite solver.
+pub struct SubSolver {
+    /// The name used to identify the sub-solver in logs.
+    pub name: String,
+    pub engine: Engine,
+}
+
+/// The configuration of a sub-solver's engine.
+pub enum Engine {
+    Baseline(baseline::Config),
+    CurveLp(curve_lp::Config),
+}
+
+/// Solver delegating to several sub-solvers.
+pub struct Solver {
+    solvers: Vec<(String, Inner)>,
+    origins: Mutex<Origins>,
+}
+
+/// A sub-solver. Composite solvers can't be nested, which keeps solving
+/// non-recursive.
+enum Inner {
+    Baseline(baseline::Solver),
+    CurveLp(curve_lp::Solver),
+}
+
+impl Inner {
+    async fn solve(&self, auction: Auction) -> Vec<Solution> {
+        match self {
+            Inner::Baseline(solver) => solver.solve(auction).await,
+            Inner::CurveLp(solver) => solver.solve(auction).await,
+        }
+    }
+
+    fn notify(&self, notification: notification::Notification) {
+        match self {
+            Inner::Baseline(_) => {}
+            Inner::CurveLp(solver) => solver.notify(notification),
+        }
+    }
+}
+
+/// Which sub-solver produced the solutions of recent auctions, and under which
+/// ID.
+#[derive(Default)]
+struct Origins {
+    solutions: HashMap<(i64, u64), Origin>,
+    /// Insertion order of `solutions`, for evicting the oldest.
+    order: VecDeque<(i64, u64)>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Origin {
+    solver: usize,
+    id: solution::Id,
+}
+
+impl Solver {
+    /// Creates a new composite solver, creating all of its sub-solvers.
+    pub async fn new(config: Config) -> Self {
+        let solvers = future::join_all(config.solvers.into_iter().map(|sub| async move {
+            let solver = match sub.engine {
+                Engine::Baseline(config) => Inner::Baseline(baseline::Solver::new(config).await),
+                Engine::CurveLp(config) => Inner::CurveLp(curve_lp::Solver::new(config).await),
+            };
+            (sub.name, solver)
+        }))
+        .await;
+
+        Self {
+            solvers,
+            origins: Default::default(),
+        }
+    }
+
+    /// Solves the auction with all sub-solvers, returning the non-conflicting
+    /// solutions ranked by estimated surplus.
+    pub async fn solve(&self, auction: Auction) -> Vec<Solution> {
+        let remaining = auction.deadline.remaining().unwrap_or_default();
+        let results = future::join_all(self.solvers.iter().map(|(name, solver)| {
+            let solve = solver.solve(auction.clone());
+            async move {
+                match tokio::time::timeout(remaining, solve).await {
+                    Ok(solutions) => {
+                        tracing::debug!(
+                            solver = %name,
+                            solutions = solutions.len(),
+                            "sub-solver done"
+                        );
+                        solutions
+                    }
+                    Err(_) => {
+                        tracing::warn!(solver = %name, "sub-solver missed the deadline");
+                        vec![]
+                    }
+                }
+            }
+        }))
+        .await;
+
+        let candidates = results
+            .into_iter()
+            .enumerate()
+            .flat_map(|(solver, solutions)| {
+                solutions.into_iter().map(move |solution| {
+                    let origin = Origin {
+                        solver,
+                        id: solution.id,
+                    };
+                    (origin, solution)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let selected = select(candidates, &auction);
+        tracing::debug!(solutions = selected.len(), "selected composite solutions");
+
+        selected
+            .into_iter()
+            .enumerate()
+            .map(|(i, (origin, solution))| {
+                let id = solution::Id(i as u64);
+                if let auction::Id::Solve(auction_id) = auction.id {
+                    self.remember(auction_id, id, origin);
+                }
+                solution.with_id(id)
+            })
+            .collect()
+    }
+
+    /// Forwards a notification to the sub-solvers that produced the solution
+    /// it is about, translating the solution IDs back to the ones they
+    /// assigned. Notifications about unknown solutions reach all sub-solvers.
+    pub fn notify(&self, notification: notification::Notification) {
+        let Some(origins) = self.origins(&notification) else {
+            for (_, solver) in &self.solvers {
+                solver.notify(notification.clone());
+            }
+            return;
+        };
+
+        for (index, ids) in origins {
+            let solution_id = match ids.as_slice() {
+                [id] => notification::Id::Single(*id),
+                _ => notification::Id::Merged(ids.iter().map(|id| id.0).collect()),
+            };
+            self.solvers[index].1.notify(notification::Notification {
+                solution_id: Some(solution_id),
+                ..notification.clone()
+            });
+        }
+    }
+
+    /// The upstream API mirrors of the sub-solvers whose circuit breakers
+    /// currently reject requests.
+    pub fn open_breakers(&self) -> Vec<upstream::OpenBreaker> {
+        self.solvers
+            .iter()
+            .flat_map(|(_, solver)| match solver {
+                Inner::Baseline(_) => vec![],
+                Inner::CurveLp(solver) => solver.open_breakers(),
+            })
+            .collect()
+    }
+
+    fn remember(&self, auction: i64, id: solution::Id, origin: Origin) {
+        let Ok(mut origins) = self.origins.lock() else {
+            return;
+        };
+        let key = (auction, id.0);
+        if origins.solutions.insert(key, origin).is_none() {
+            origins.order.push_back(key);
+        }
+        while origins.order.len() > MAX_TRACKED_SOLUTIONS {
+            if let Some(oldest) = origins.order.pop_front() {
+                origins.solutions.remove(&oldest);
+            }
+        }
+    }
+
+    /// Returns the sub-solver assigned IDs of the solution a notification is
+    /// about, grouped by sub-solver.
+    fn origins(
+        &self,
+        notification: &notification::Notification,
+    ) -> Option<BTreeMap<usize, Vec<solution::Id>>> {
+        let auction::Id::Solve(auction) = notification.auction_id else {
+            return None;
+        };
+        let ids = match notification.solution_id.as_ref()? {
+            notification::Id::Single(id) => vec![id.0],
+            notification::Id::Merged(ids) => ids.clone(),
+        };
+        let origins = self.origins.lock().ok()?;
+        let mut grouped = BTreeMap::<_, Vec<_>>::new();
+        for id in ids {
+            let origin = origins.solutions.get(&(auction, id))?;
+            grouped.entry(origin.solver).or_default().push(origin.id);
+        }
+        Some(grouped)
+    }
+}
+
+/// Ranks the candidate solutions by estimated surplus and drops the ones
+/// trading an order that a better ranked solution already trades. Ties keep
+/// the order of the sub-solvers.
+fn select<T>(candidates: Vec<(T, Solution)>, auction: &Auction) -> Vec<(T, Solution)> {
+    let mut ranked = candidates
+        .into_iter()
+        .map(|(origin, solution)| (surplus(&solution, &auction.tokens), origin, solution))
+        .collect::<Vec<_>>();
+    ranked.sort_by(|(a, ..), (b, ..)| b.cmp(a));
+
+    let mut traded = HashSet::new();
+    ranked
+        .into_iter()
+        .filter_map(|(_, origin, solution)| {
+            let uids = orders(&solution);
+            if uids.iter().any(|uid| traded.contains(uid)) {
+                return None;
+            }
+            traded.extend(uids);
+            Some((origin, solution))
+        })
+        .collect()
+}
+
+/// The UIDs of the auction orders traded by a solution.
+fn orders(solution: &Solution) -> Vec<order::Uid> {
+    solution
+        .trades
+        .iter()
+        .filter_map(|trade| match trade {
+            solution::Trade::Fulfillment(fulfillment) => Some(fulfillment.order().uid),
+            solution::Trade::Jit(_) => None,
+        })
+        .collect()
+}
+
+/// Estimates the surplus of a solution in the native token, valuing the
+/// surplus of each trade with the reference price of its surplus token. Trades
+/// whose surplus can't be valued don't contribute.
+fn surplus(solution: &Solution, tokens: &auction::Tokens) -> eth::Ether {
+    let total = solution
+        .trades
+        .iter()
+        .filter_map(|trade| match trade {
+            solution::Trade::Fulfillment(fulfillment) => {
+                let (token, amount) = trade_surplus(fulfillment, &solution.prices)?;
+                tokens.reference_price(&token)?.ether(amount)
+            }
+            solution::Trade::Jit(_) => None,
+        })
+        .fold(eth::U256::ZERO, |total, surplus| {
+            total.saturating_add(surplus.0)
+        });
+    eth::Ether(total)
+}
+
+/// Computes the surplus of a trade at the solution's clearing prices in its
+/// surplus token, the buy token for sell orders and the sell token for buy
+/// orders.
+fn trade_surplus(
+    fulfillment: &solution::Fulfillment,
+    prices: &solution::ClearingPrices,
+) -> Option<(eth::TokenAddress, eth::U256)> {
+    let order = fulfillment.order();
+    let executed = fulfillment.executed().amount;
+    let fee = fulfillment
+        .surplus_fee()
+        .map(|fee| fee.amount)
+        .unwrap_or_default();
+    let sell_price = *prices.0.get(&order.sell.token)?;
+    let buy_price = *prices.0.get(&order.buy.token)?;
+    if [sell_price, buy_price, order.sell.amount, order.buy.amount].contains(&eth::U256::ZERO) {
+        return None;
+    }
+
+    match order.side {
+        order::Side::Sell => {
+            let bought = executed.checked_mul(sell_price)?.checked_div(buy_price)?;
+            let limit = order
+                .buy
+                .amount
+                .checked_mul(executed.checked_add(fee)?)?
+                .div_ceil(order.sell.amount);
+            Some((order.buy.token, bought.saturating_sub(limit)))
+        }
+        order::Side::Buy => {
+            let sold = executed
+                .checked_mul(buy_price)?
+                .div_ceil(sell_price)
+                .checked_add(fee)?;
+            let limit = order
+                .sell
+                .amount
+                .checked_mul(executed)?
+                .checked_div(order.buy.amount)?;
+            Some((order.sell.token, limit.saturating_sub(sold)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloy::primitives::Address};
+
+    fn token(byte: u8) -> eth::TokenAddress {
+        eth::TokenAddress(Address::repeat_byte(byte))
+    }
+
+    fn auction() -> Auction {
+        let price = |wei: u64| auction::Token {
+            decimals: Some(18),
+            symbol: None,
+            reference_price: Some(auction::Price(eth::Ether(eth::U256::from(wei)))),
+            available_balance: eth::U256::ZERO,
+            trusted: false,
+        };
+        Auction {
+            id: auction::Id::Solve(1),
+            tokens: auction::Tokens(
+                [
+                    (token(1), price(1_000_000_000_000_000_000)),
+                    (token(2), price(500_000_000_000_000_000)),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            orders: vec![],
+            liquidity: vec![],
+            gas_price: auction::GasPrice(eth::Ether(eth::U256::ONE)),
+            deadline: auction::Deadline(chrono::Utc::now()),
+        }
+    }
+
+    /// A sell order of 100 of token 1 for at least 100 of token 2.
+    fn order(uid: u8) -> order::Order {
+        order::Order {
+            uid: order::Uid([uid; 56]),
+            sell: eth::Asset {
+                token: token(1),
+                amount: eth::U256::from(100),
+            },
+            buy: eth::Asset {
+                token: token(2),
+                amount: eth::U256::from(100),
+            },
+            side: order::Side::Sell,
+            class: order::Class::Market,
+            partially_fillable: false,
+            flashloan_hint: None,
+            wrappers: vec![],
+        }
+    }
+
+    /// A solution filling the orders at `bought` of token 2 per 100 token 1.
+    fn solution(orders: &[u8], bought: u64) -> Solution {
+        Solution {
+            prices: solution::ClearingPrices::new([
+                (token(1), eth::U256::from(bought)),
+                (token(2), eth::U256::from(100)),
+            ]),
+            trades: orders
+                .iter()
+                .map(|uid| {
+                    solution::Trade::Fulfillment(solution::Fulfillment::fill(order(*uid)).unwrap())
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn estimates_surplus_in_native_token() {
+        let auction = auction();
+        // 10 token 2 of surplus per order at 0.5 ETH each.
+        assert_eq!(
+            surplus(&solution(&[1, 2], 110), &auction.tokens),
+            eth::Ether(eth::U256::from(10))
+        );
+        assert_eq!(
+            surplus(&solution(&[1], 100), &auction.tokens),
+            eth::Ether(eth::U256::ZERO)
+        );
+    }
+
+    #[test]
+    fn drops_conflicting_solutions() {
+        let auction = auction();
+        let candidates = vec![
+            ("baseline", solution(&[1], 105)),
+            ("curve-lp", solution(&[1, 2], 110)),
+            ("baseline", solution(&[3], 101)),
+            ("curve-lp", solution(&[2], 130)),
+        ];
+
+        let selected = select(candidates, &auction)
+            .into_iter()
+            .map(|(origin, solution)| (origin, orders(&solution)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            selected,
+            vec![
+                ("curve-lp", vec![order::Uid([2; 56])]),
+                ("baseline", vec![order::Uid([1; 56])]),
+                ("baseline", vec![order::Uid([3; 56])]),
+            ]
+        );
+    }
+
+    #[test]
+    fn groups_notified_solutions_by_origin() {
+        let solver = Solver {
+            solvers: vec![],
+            origins: Default::default(),
+        };
+        solver.remember(
+            1,
+            solution::Id(0),
+            Origin {
+                solver: 1,
+                id: solution::Id(3),
+            },
+        );
+        solver.remember(
+            1,
+            solution::Id(1),
+            Origin {
+                solver: 0,
+                id: solution::Id(0),
+            },
+        );
+        solver.remember(
+            1,
+            solution::Id(2),
+            Origin {
+                solver: 1,
+                id: solution::Id(5),
+            },
+        );
+
+        let notification = |id| notification::Notification {
+            auction_id: auction::Id::Solve(1),
+            solution_id: Some(id),
+            kind: notification::Kind::Timeout,
+        };
+        assert_eq!(
+            solver.origins(&notification(notification::Id::Merged(vec![0, 1, 2]))),
+            Some(BTreeMap::from([
+                (0, vec![solution::Id(0)]),
+                (1, vec![solution::Id(3), solution::Id(5)]),
+            ]))
+        );
+        assert_eq!(
+            solver.origins(&notification(notification::Id::Single(solution::Id(4)))),
+            None
+        );
+    }
+}
@@ -1,24 +1,51 @@
 //! Curve LP Token Solver
 //!
 //! A solver specialized for Curve LP token orders. It handles LP sell orders
-//! by routing through the Curve Router API and contract.
+//! by routing through the Curve Router API and contract. Orders against
+//! configured pools are priced locally from on-chain pool state, in which case
+//! the Router API only serves as a cross-check. Sell orders of the same auction
+//! trading the same pair are settled together, netting opposing orders
+//! against each other. Router API routes are cached across auctions and only
+//! requoted on-chain while the pools they trade through remain unchanged.
+//! Slippage tolerances are set per trade from the pools routed through, the
+//! trade size and recent price volatility.
+
+mod batch;
+mod buffers;
+mod exits;
+mod feedback;
+mod route_cache;
+mod slippage;
 
 use {
     crate::{
-        boundary::curve::{interactions, router},
+        boundary::{
+            self,
+            curve::{interactions, liquidity, registry::Registry, router},
+        },
         domain::{
             auction::{self, Auction},
-            curve::{api, price_api},
+            curve::{api, exact_out, gas, lp_price, pool, price_api, upstream},
             eth,
+            notification,
             order::{self, Order},
             solution::{self, Solution},
         },
+        infra::{archive, metrics},
+    },
+    alloy::{
+        primitives::U256,
+        providers::Provider,
+        rpc::types::{Filter, TransactionRequest},
     },
-    alloy::{primitives::U256, providers::Provider, rpc::types::TransactionRequest},
-    alloy::primitives::address,
-    futures::stream::StreamExt,
+    futures::{future::Either, stream::StreamExt},
     reqwest::Url,
-    std::{collections::HashSet, fmt, sync::Arc, time::Duration},
+    std::{
+        collections::{HashMap, HashSet},
+        fmt,
+        sync::Arc,
+        time::Duration,
+    },
     tracing::Instrument,
 };
 
@@ -31,14 +58,39 @@ const MAX_CONCURRENT_ORDERS: usize = 8;
 const ROUTE_REQUEST_TIMEOUT: Duration = Duration::from_millis(2500);
 /// Maximum time spent waiting for on-chain quote verification per order.
 const ONCHAIN_VERIFY_TIMEOUT: Duration = Duration::from_millis(1500);
+/// Gas assumed for a Curve Router exchange before its route is known.
+const EXCHANGE_GAS: u64 = 350_000;
+/// Upper bound on the configured number of partial fill attempts, which keeps
+/// the bisection denominator within 256 bits.
+const MAX_PARTIAL_ATTEMPTS: usize = 128;
+/// Time kept before the deadline for solving the partial fill found by the
+/// bisection.
+const PARTIAL_FILL_RESERVE: Duration = ONCHAIN_VERIFY_TIMEOUT;
+/// Maximum time spent searching for the exact sell amount of a buy order.
+const EXACT_OUT_SEARCH_TIMEOUT: Duration = Duration::from_millis(1000);
 /// Maximum time spent waiting for token price fallback per order.
 const PRICE_FETCH_TIMEOUT: Duration = Duration::from_millis(1200);
+/// Maximum time spent reading pool state for local pricing per order.
+const POOL_FETCH_TIMEOUT: Duration = Duration::from_millis(1000);
+/// Maximum time spent reading the supply of an LP token per order.
+const SUPPLY_FETCH_TIMEOUT: Duration = Duration::from_millis(500);
+/// Maximum time spent waiting for the Curve routing API when it only serves
+/// as a cross-check of a locally priced route.
+const API_CROSS_CHECK_TIMEOUT: Duration = Duration::from_millis(800);
+/// Poll interval of the block stream driving route cache invalidation.
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Maximum number of blocks whose pool events are checked at once. The route
+/// cache is cleared instead when more blocks passed since the last check.
+const MAX_EVENT_BLOCK_RANGE: u64 = 100;
+/// Maximum number of invalidated routes fetched again per block, so that a
+/// busy block doesn't flood the Curve routing API.
+const MAX_WARMED_ROUTES_PER_BLOCK: usize = 16;
+/// Maximum number of routes fetched concurrently while warming the cache.
+const MAX_CONCURRENT_WARMING: usize = 4;
 
 // CoW native-price probe detection constants
 /// The sentinel sell_amount CoW uses for native price probes (2^144).
 const NATIVE_PRICE_SELL_SENTINEL: U256 = U256::from_limbs([0, 0, 65536, 0]);
-/// WETH address on Ethereum mainnet.
-const WETH: eth::Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
 
 /// Curve LP token solver.
 pub struct Solver {
@@ -49,39 +101,136 @@ pub struct Solver {
 pub struct Config {
     /// Chain ID (1 for mainnet).
     pub chain_id: u64,
+    /// Curve Router contract.
+    pub router: eth::Address,
+    /// The wrapped native token, which native price probes buy.
+    pub weth: eth::WethAddress,
+    /// The name of the chain in the Curve Price API (e.g. "ethereum").
+    pub price_api_chain: String,
     /// Whitelisted LP tokens that this solver handles.
-    /// `None` means accept any sell token.
+    /// `None` means LP tokens discovered by the pool registry, or any sell
+    /// token if the registry is disabled.
     pub lp_tokens: Option<Vec<eth::Address>>,
     /// Allowed buy tokens (crvUSD + pool underlyings).
-    /// `None` means accept any buy token.
+    /// `None` means coins of pools discovered by the pool registry, or any
+    /// buy token if the registry is disabled.
     pub allowed_buy_tokens: Option<Vec<eth::Address>>,
     /// Curve Router API URL.
     pub curve_api_url: Url,
+    /// Mirrors of the Curve Router API, used while it is unavailable.
+    pub curve_api_mirrors: Vec<Url>,
     /// Curve Price API URL.
     pub curve_price_api_url: Url,
+    /// Mirrors of the Curve Price API, used while it is unavailable.
+    pub curve_price_api_mirrors: Vec<Url>,
     /// Node URL for on-chain verification.
     pub node_url: Url,
-    /// Slippage buffer in basis points (e.g., 100 = 1%).
+    /// Base slippage tolerance of routes through CryptoSwap pools in basis
+    /// points (e.g., 100 = 1%).
     pub slippage_bps: u32,
+    /// Base slippage tolerance of routes only trading through StableSwap
+    /// pools in basis points.
+    pub stable_slippage_bps: u32,
+    /// Upper bound on the slippage tolerance widened for trade size and
+    /// volatility, in basis points.
+    pub max_slippage_bps: u32,
+    /// Fixed slippage tolerances of trades buying or selling these tokens, in
+    /// basis points.
+    pub slippage_overrides: HashMap<eth::TokenAddress, u32>,
     /// Maximum deviation between API quote and on-chain get_dy (basis points).
     pub max_quote_deviation_bps: u32,
     /// Gas offset for solution gas estimation.
     pub solution_gas_offset: eth::SignedGas,
+    /// The maximum number of fill amounts tried for partially fillable
+    /// orders, including the full amount.
+    pub max_partial_attempts: usize,
     /// The settlement contract address (receiver for swaps).
     pub settlement_contract: eth::Address,
+    /// Curve pools whose LP tokens are priced locally from on-chain state.
+    pub pools: Vec<pool::Metadata>,
+    /// On-chain discovery of Curve pools, `None` disables it.
+    pub registry: Option<boundary::curve::registry::Config>,
+    /// The amount of each token that may be paid out of the settlement
+    /// contract's buffers per auction to fill orders without trading on
+    /// Curve. Buffers of other tokens are only used to internalize
+    /// interactions.
+    pub buffer_budgets: HashMap<eth::TokenAddress, eth::U256>,
+    /// Whether to skip all work in the background that reaches out to the
+    /// network, i.e. following the chain to index Curve pools and maintain
+    /// the route cache, and bootstrapping the registry. Used for replays.
+    pub offline: bool,
 }
 
 struct Inner {
     chain_id: u64,
-    lp_tokens: Option<HashSet<eth::Address>>,
-    allowed_buy_tokens: Option<HashSet<eth::Address>>,
+    router: eth::Address,
+    weth: eth::WethAddress,
+    price_api_chain: String,
+    lp_tokens: TokenFilter,
+    allowed_buy_tokens: TokenFilter,
     api_client: api::Client,
     price_client: price_api::Client,
     provider: ethrpc::AlloyProvider,
-    slippage_bps: u32,
+    slippage: slippage::Slippage,
     max_quote_deviation_bps: u32,
     solution_gas_offset: eth::SignedGas,
+    max_partial_attempts: usize,
     settlement_contract: eth::Address,
+    /// Locally priced pools, indexed by their LP token.
+    pools: HashMap<eth::TokenAddress, pool::Metadata>,
+    pool_fetcher: boundary::curve::pool::Fetcher,
+    /// On-chain pricing inputs of LP tokens, cached per block.
+    lp_prices: lp_price::Cache,
+    registry: Option<Registry>,
+    buffer_budgets: HashMap<eth::TokenAddress, eth::U256>,
+    gas: gas::Estimator,
+    feedback: feedback::Feedback,
+    /// Router API routes reused across auctions.
+    route_cache: route_cache::Cache,
+}
+
+/// Decides which tokens pass one of the order filters.
+enum TokenFilter {
+    /// Accept any token.
+    Any,
+    /// A static list of tokens from the configuration.
+    List(HashSet<eth::Address>),
+    /// LP tokens of pools discovered by the registry.
+    LpTokens(Registry),
+    /// Coins of pools discovered by the registry.
+    Coins(Registry),
+}
+
+impl TokenFilter {
+    /// A configured list takes precedence over the registry.
+    fn new(
+        list: Option<Vec<eth::Address>>,
+        registry: Option<&Registry>,
+        discovered: fn(Registry) -> Self,
+    ) -> Self {
+        match (list, registry) {
+            (Some(list), _) => Self::List(list.into_iter().collect()),
+            (None, Some(registry)) => discovered(registry.clone()),
+            (None, None) => Self::Any,
+        }
+    }
+
+    fn accepts(&self, token: &eth::Address) -> bool {
+        match self {
+            Self::Any => true,
+            Self::List(tokens) => tokens.contains(token),
+            Self::LpTokens(registry) => registry.is_lp_token(token),
+            Self::Coins(registry) => registry.is_coin(token),
+        }
+    }
+
+    /// Whether the token is on a configured list.
+    fn is_listed(&self, token: &eth::Address) -> bool {
+        match self {
+            Self::List(tokens) => tokens.contains(token),
+            _ => false,
+        }
+    }
 }
 
 impl Solver {
@@ -93,43 +242,118 @@ impl Solver {
             "initialized Curve LP token filters"
         );
 
-        if config.lp_tokens.is_none() && config.allowed_buy_tokens.is_none() {
+        if config.lp_tokens.is_none()
+            && config.allowed_buy_tokens.is_none()
+            && config.registry.is_none()
+        {
             tracing::warn!(
                 "Curve LP solver is running without token filters; \
                  all sell orders will be attempted and this can cause timeouts"
             );
         }
 
-        let api_client = api::Client::new(config.curve_api_url);
-        let price_client = price_api::Client::new(config.curve_price_api_url);
+        let api_client = api::Client::new(config.curve_api_url, config.curve_api_mirrors);
+        let price_client = price_api::Client::new(
+            config.curve_price_api_url,
+            config.curve_price_api_mirrors,
+            config.weth,
+        );
         let web3 = ethrpc::web3(
             Default::default(),
             Default::default(),
             &config.node_url,
             "curve-lp",
         );
+        let provider = archive::rpc::archived(web3.alloy);
 
-        Self {
-            inner: Arc::new(Inner {
-                chain_id: config.chain_id,
-                lp_tokens: config.lp_tokens.map(|v| v.into_iter().collect()),
-                allowed_buy_tokens: config.allowed_buy_tokens.map(|v| v.into_iter().collect()),
-                api_client,
-                price_client,
-                provider: web3.alloy,
-                slippage_bps: config.slippage_bps,
-                max_quote_deviation_bps: config.max_quote_deviation_bps,
-                solution_gas_offset: config.solution_gas_offset,
-                settlement_contract: config.settlement_contract,
+        tracing::info!(
+            pool_count = config.pools.len(),
+            "initialized local Curve pools"
+        );
+
+        #[allow(deprecated)]
+        let blocks = if config.offline {
+            None
+        } else {
+            ethrpc::block_stream::current_block_stream(config.node_url, BLOCK_POLL_INTERVAL)
+                .await
+                .inspect_err(|err| {
+                    tracing::warn!(
+                        ?err,
+                        "failed to start block stream, Curve pools are not indexed and \
+                         cached routes only expire by age"
+                    )
+                })
+                .ok()
+        };
+
+        let registry = match config.registry {
+            Some(registry) => {
+                let registry = boundary::curve::registry::Config {
+                    bootstrap: registry.bootstrap && !config.offline,
+                    ..registry
+                };
+                Some(Registry::new(registry, provider.clone(), blocks.clone()).await)
+            }
+            None => None,
+        };
+
+        let inner = Arc::new(Inner {
+            chain_id: config.chain_id,
+            router: config.router,
+            weth: config.weth,
+            price_api_chain: config.price_api_chain,
+            lp_tokens: TokenFilter::new(config.lp_tokens, registry.as_ref(), TokenFilter::LpTokens),
+            allowed_buy_tokens: TokenFilter::new(
+                config.allowed_buy_tokens,
+                registry.as_ref(),
+                TokenFilter::Coins,
+            ),
+            api_client,
+            price_client,
+            pool_fetcher: boundary::curve::pool::Fetcher::new(provider.clone()),
+            lp_prices: lp_price::Cache::default(),
+            provider,
+            slippage: slippage::Slippage::new(slippage::Config {
+                default_bps: config.slippage_bps,
+                stable_bps: config.stable_slippage_bps,
+                max_bps: config.max_slippage_bps,
+                overrides: config.slippage_overrides,
             }),
+            max_quote_deviation_bps: config.max_quote_deviation_bps,
+            solution_gas_offset: config.solution_gas_offset,
+            max_partial_attempts: config.max_partial_attempts,
+            settlement_contract: config.settlement_contract,
+            pools: config
+                .pools
+                .into_iter()
+                .map(|pool| (pool.lp_token, pool))
+                .collect(),
+            registry,
+            buffer_budgets: config.buffer_budgets,
+            gas: gas::Estimator::default(),
+            feedback: feedback::Feedback::default(),
+            route_cache: route_cache::Cache::default(),
+        });
+
+        if let Some(blocks) = blocks {
+            let inner = inner.clone();
+            tokio::spawn(async move {
+                let mut blocks = ethrpc::block_stream::into_stream(blocks);
+                while let Some(block) = blocks.next().await {
+                    inner.maintain_route_cache(block.number).await;
+                }
+            });
         }
+
+        Self { inner }
     }
 
     /// Solves the auction, returning solutions for LP token orders.
     pub async fn solve(&self, auction: Auction) -> Vec<Solution> {
         let start = std::time::Instant::now();
-        let deadline = auction.deadline.clone();
-        let remaining = deadline
+        let remaining = auction
+            .deadline
             .clone()
             .reduce(DEADLINE_SLACK)
             .remaining()
@@ -165,11 +389,14 @@ impl Solver {
 
         let inner = self.inner.clone();
         let span = tracing::Span::current();
+        let upstream_deadline = tokio::time::Instant::now() + remaining;
         let background_work = async move {
-            inner.solve(auction, sender).instrument(span).await;
+            upstream::with_deadline(upstream_deadline, inner.solve(auction, sender))
+                .instrument(span)
+                .await;
         };
 
-        let mut handle = tokio::spawn(background_work);
+        let mut handle = tokio::spawn(archive::inherit(background_work));
 
         // Wait for completion or timeout
         let mut timed_out = false;
@@ -211,17 +438,264 @@ impl Solver {
         );
         solutions
     }
+
+    /// The upstream API mirrors whose circuit breakers currently reject
+    /// requests.
+    pub fn open_breakers(&self) -> Vec<upstream::OpenBreaker> {
+        let mut breakers = self.inner.api_client.open_breakers();
+        breakers.extend(self.inner.price_client.open_breakers());
+        breakers
+    }
+
+    /// Reacts to the outcome of one of the solver's solutions in the
+    /// background.
+    pub fn notify(&self, notification: notification::Notification) {
+        let inner = self.inner.clone();
+        let span = tracing::Span::current();
+        tokio::spawn(async move { inner.notify(notification).instrument(span).await });
+    }
 }
 
 /// Detects whether an order is a CoW native-price probe.
 ///
 /// The CoW driver generates these Buy-side quote probes to discover native
 /// token prices. All five conditions must match.
-fn is_native_price_probe(order: &Order, is_quote: bool) -> bool {
+fn is_native_price_probe(order: &Order, is_quote: bool, weth: eth::WethAddress) -> bool {
     is_quote
         && order.side == order::Side::Buy
         && order.sell.amount == NATIVE_PRICE_SELL_SENTINEL
-        && order.buy.token.0 == WETH
+        && order.buy.token.0 == weth.0
+}
+
+/// Scales the amounts of `order` down to `numerator / denominator`, rounding
+/// the buy amount up so that the limit price is never relaxed. Solving the
+/// scaled order yields a valid partial fill of the original one. Returns
+/// `None` if either amount would be zero.
+fn partial_order(order: &Order, numerator: U256, denominator: U256) -> Option<Order> {
+    let sell = order.sell.amount.checked_mul(numerator)? / denominator;
+    let buy = order
+        .buy
+        .amount
+        .checked_mul(numerator)?
+        .div_ceil(denominator);
+    if sell.is_zero() || buy.is_zero() {
+        return None;
+    }
+    Some(Order {
+        sell: eth::Asset {
+            amount: sell,
+            ..order.sell
+        },
+        buy: eth::Asset {
+            amount: buy,
+            ..order.buy
+        },
+        ..order.clone()
+    })
+}
+
+/// Bisects over the filled fraction `numerator / 2^(attempts - 1)` of an
+/// order for the largest one that `covers`, returning its numerator. Stops
+/// early, keeping the largest fraction found so far, once the next step
+/// wouldn't finish before the `deadline`, judging by how long the previous
+/// step took.
+async fn bisect_fill<F, Fut>(
+    attempts: usize,
+    deadline: Option<tokio::time::Instant>,
+    mut covers: F,
+) -> U256
+where
+    F: FnMut(U256) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let (mut lo, mut hi) = (U256::ZERO, U256::ONE << (attempts - 1));
+    let mut step = Duration::ZERO;
+    for _ in 1..attempts {
+        let start = tokio::time::Instant::now();
+        let fillable = match deadline {
+            Some(deadline) if start + step >= deadline => break,
+            Some(deadline) => tokio::time::timeout_at(deadline, covers((lo + hi) >> 1)).await,
+            None => Ok(covers((lo + hi) >> 1).await),
+        };
+        match fillable {
+            Ok(true) => lo = (lo + hi) >> 1,
+            Ok(false) => hi = (lo + hi) >> 1,
+            Err(_) => break,
+        }
+        step = start.elapsed();
+    }
+    lo
+}
+
+/// Builds the solution of `order` swapping `input` of its sell token for at
+/// least `min_output` of its buy token, charging a surplus fee of `fee`.
+/// Returns the solution and the output amount it executes.
+///
+/// For sell orders: input is the full sell amount, output is slippage-adjusted.
+/// For buy orders: output is the exact desired buy amount and input is the
+/// searched sell amount, so the remainder of the sell amount is returned as
+/// surplus. Input is capped at sell_amount minus fee, because into_solution()
+/// adds the surplus fee back to the sell side (input + fee must not exceed
+/// order.sell.amount).
+fn single_solution(
+    order: &Order,
+    input: U256,
+    min_output: U256,
+    interactions: Vec<solution::Interaction>,
+    gas: eth::Gas,
+    fee: U256,
+) -> Result<(Solution, U256), SolveError> {
+    let (input_amount, output_amount) = match order.side {
+        order::Side::Sell => (order.sell.amount, min_output),
+        order::Side::Buy => (
+            order
+                .sell
+                .amount
+                .checked_sub(fee)
+                .ok_or(SolveError::FeeCalculation)?
+                .min(input),
+            order.buy.amount,
+        ),
+    };
+
+    let single = solution::Single {
+        order: order.clone(),
+        input: eth::Asset {
+            token: order.sell.token,
+            amount: input_amount,
+        },
+        output: eth::Asset {
+            token: order.buy.token,
+            amount: output_amount,
+        },
+        interactions,
+        gas,
+        wrappers: order.wrappers.clone(),
+    };
+
+    let solution = single
+        .into_solution(eth::SellTokenAmount(fee))
+        .ok_or(SolveError::SolutionConstruction)?;
+    Ok((solution, output_amount))
+}
+
+/// Candidate routes for a trade, in order of preference.
+#[derive(Debug)]
+struct Routes {
+    routes: Vec<api::Route>,
+    /// Per route whether its expected output already is its on-chain quote
+    /// for the traded amount, as for routes requoted from the route cache.
+    verified: Vec<bool>,
+    /// Per route where it comes from.
+    sources: Vec<RouteSource>,
+}
+
+/// Where a candidate route comes from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum RouteSource {
+    /// Priced locally from the state of a configured pool.
+    Local,
+    /// Proposed by the Curve Router API.
+    Api,
+}
+
+impl RouteSource {
+    fn label(self) -> &'static str {
+        match self {
+            RouteSource::Local => "local",
+            RouteSource::Api => "api",
+        }
+    }
+}
+
+impl Routes {
+    /// API routes whose expected outputs are off-chain quotes.
+    fn unverified(routes: Vec<api::Route>) -> Self {
+        Self {
+            verified: vec![false; routes.len()],
+            sources: vec![RouteSource::Api; routes.len()],
+            routes,
+        }
+    }
+
+    /// API routes whose expected outputs are on-chain quotes.
+    fn verified(routes: Vec<api::Route>) -> Self {
+        Self {
+            verified: vec![true; routes.len()],
+            sources: vec![RouteSource::Api; routes.len()],
+            routes,
+        }
+    }
+
+    /// A locally priced route.
+    fn local(route: api::Route) -> Self {
+        Self {
+            routes: vec![route],
+            verified: vec![false],
+            sources: vec![RouteSource::Local],
+        }
+    }
+
+    /// The source of the route at `rank` and its rank among the routes of
+    /// that source.
+    fn source_rank(&self, rank: usize) -> (RouteSource, usize) {
+        let source = self.sources[rank];
+        let same = self.sources[..rank]
+            .iter()
+            .filter(|other| **other == source)
+            .count();
+        (source, same)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &api::Route> {
+        self.routes.iter()
+    }
+
+    fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+
+    /// Appends less preferred routes.
+    fn chain(mut self, other: Routes) -> Self {
+        self.routes.extend(other.routes);
+        self.verified.extend(other.verified);
+        self.sources.extend(other.sources);
+        self
+    }
+
+    fn retain(&mut self, keep: impl Fn(&api::Route) -> bool) {
+        (self.routes, self.verified) = std::mem::take(&mut self.routes)
+            .into_iter()
+            .zip(std::mem::take(&mut self.verified))
+            .filter(|(route, _)| keep(route))
+            .unzip();
+    }
+}
+
+/// Returns the most preferred of the candidate routes.
+fn preferred(routes: Routes) -> Result<api::Route, SolveError> {
+    routes
+        .routes
+        .into_iter()
+        .next()
+        .ok_or_else(|| SolveError::Api(api::Error::Parse("no candidate routes".to_string())))
+}
+
+/// Returns the rank and amount of the largest on-chain output, skipping routes
+/// that reverted. Ties go to the higher ranked route.
+fn best_output(outputs: &[Option<eth::U256>]) -> Option<(usize, eth::U256)> {
+    outputs
+        .iter()
+        .enumerate()
+        .filter_map(|(rank, output)| Some((rank, (*output)?)))
+        .fold(None, |best, (rank, output)| match best {
+            Some((_, best_output)) if best_output >= output => best,
+            _ => Some((rank, output)),
+        })
 }
 
 impl Inner {
@@ -230,81 +704,123 @@ impl Inner {
         auction: Auction,
         sender: tokio::sync::mpsc::UnboundedSender<Solution>,
     ) {
-        let is_quote = matches!(auction.id, auction::Id::Quote);
+        let auction_id = auction.id;
+        let is_quote = matches!(auction_id, auction::Id::Quote);
         let mut sent_count: usize = 0;
         let mut receiver_dropped = false;
-        let mut stream = futures::stream::iter(
-            auction
-                .orders
-                .into_iter()
-                .enumerate()
-                .filter(|(_, order)| {
-                    match self.rejection_reason(order) {
-                        None => true,
-                        Some(reason) if is_quote => {
-                            tracing::debug!(
-                                order_uid = %order.uid,
-                                sell_token = ?order.sell.token,
-                                buy_token = ?order.buy.token,
-                                reason,
-                                "order not supported"
-                            );
-                            false
-                        }
-                        Some(_) => false,
-                    }
-                })
-                .map(|(i, order)| {
-                    let tokens = &auction.tokens;
-                    let gas_price = &auction.gas_price;
-                    async move {
-                        tracing::debug!(
-                            order_uid = %order.uid,
-                            sell_token = ?order.sell.token,
-                            buy_token = ?order.buy.token,
-                            "processing Curve LP order"
-                        );
-
-                        match self.solve_order(&order, tokens, gas_price, is_quote).await {
-                            Ok((solution, output_amount, route_ms, price_fetch_ms)) => {
-                                tracing::info!(
-                                    order_uid = %order.uid,
-                                    sell_token = ?order.sell.token,
-                                    buy_token = ?order.buy.token,
-                                    side = ?order.side,
-                                    sell_amount = %order.sell.amount,
-                                    order_buy_min = %order.buy.amount,
-                                    solution_output = %output_amount,
-                                    route_ms,
-                                    price_fetch_ms,
-                                    is_quote,
-                                    "solved order"
-                                );
-                                Some((solution.with_id(solution::Id(i as u64)), order))
-                            }
-                            Err(err) => {
-                                tracing::warn!(order_uid = %order.uid, ?err, "failed to solve order");
-                                None
-                            }
-                        }
-                    }
-                }),
-        )
-        .buffer_unordered(MAX_CONCURRENT_ORDERS);
-
-        while let Some(result) = stream.next().await {
-            if let Some((solution, order)) = result {
-                if sender.send(solution).is_err() {
+        let tokens = &auction.tokens;
+        let gas_price = &auction.gas_price;
+        let buffers = buffers::Buffers::new(tokens, &self.buffer_budgets);
+        let auction_orders = auction.orders.len();
+        let orders: Vec<_> = auction
+            .orders
+            .into_iter()
+            .enumerate()
+            .filter(|(_, order)| {
+                let Some(reason) = self.rejection_reason(order) else {
+                    return true;
+                };
+                metrics::order_rejected(reason);
+                if is_quote {
                     tracing::debug!(
                         order_uid = %order.uid,
                         sell_token = ?order.sell.token,
                         buy_token = ?order.buy.token,
+                        reason,
+                        "order not supported"
+                    );
+                }
+                false
+            })
+            .collect();
+
+        // The reference prices of real auctions track the volatility of the
+        // traded pairs.
+        if !is_quote {
+            self.slippage.observe(
+                orders
+                    .iter()
+                    .flat_map(|(_, order)| [order.sell.token, order.buy.token])
+                    .filter_map(|token| Some((token, tokens.reference_price(&token)?.0.0))),
+            );
+        }
+
+        // Orders of real auctions exiting the same pool into several coins
+        // are additionally settled together. Their solutions are numbered
+        // after the orders of the auction.
+        let exits = if is_quote {
+            Vec::new()
+        } else {
+            exits::partition(&orders)
+        };
+        let exits = exits.into_iter().enumerate().map(|(k, orders)| {
+            let id = solution::Id((auction_orders + k) as u64);
+            Either::Left(Either::Right(async move {
+                self.solve_exit(id, orders, tokens, gas_price)
+                    .await
+                    .into_iter()
+                    .collect::<Vec<_>>()
+            }))
+        });
+
+        // Orders of real auctions trading the same pair are settled together,
+        // falling back to solving them individually.
+        let (batches, singles) = if is_quote {
+            (Vec::new(), orders)
+        } else {
+            batch::partition(orders)
+        };
+        let batches = batches.into_iter().map(|orders| {
+            Either::Left(Either::Left(async move {
+                let (solution, unbatched) = self.solve_batch(orders, tokens, gas_price).await;
+                let singles =
+                    futures::future::join_all(unbatched.into_iter().map(|(i, order)| {
+                        self.solve_single(i, order, tokens, gas_price, is_quote)
+                    }))
+                    .await;
+                solution
+                    .into_iter()
+                    .chain(singles.into_iter().flatten())
+                    .collect::<Vec<_>>()
+            }))
+        });
+        let singles = singles.into_iter().map(|(i, order)| {
+            Either::Right(async move {
+                self.solve_single(i, order, tokens, gas_price, is_quote)
+                    .await
+                    .into_iter()
+                    .collect::<Vec<_>>()
+            })
+        });
+        let mut stream = futures::stream::iter(batches.chain(exits).chain(singles))
+            .buffer_unordered(MAX_CONCURRENT_ORDERS);
+
+        'outer: while let Some(solutions) = stream.next().await {
+            for solution in solutions {
+                // Quotes are priced as if trading on Curve, independently of
+                // the buffers at the time.
+                let solution = if is_quote {
+                    solution
+                } else {
+                    self.use_buffers(solution, &buffers, tokens)
+                };
+                let solution_id = solution.id.0;
+                if let auction::Id::Solve(id) = auction_id {
+                    let used = feedback::Used::of(&solution, self.router, self.weth);
+                    self.feedback.record(id, solution.id, used);
+                }
+                for token in self.lp_tokens_of(&solution) {
+                    metrics::lp_token_solution(&token, is_quote);
+                }
+                if sender.send(solution).is_err() {
+                    tracing::debug!(
+                        solution_id,
                         is_quote,
                         solutions_sent = sent_count,
                         "deadline hit, receiver dropped"
                     );
                     receiver_dropped = true;
-                    break;
+                    break 'outer;
                 }
                 sent_count += 1;
             }
@@ -319,47 +835,307 @@ impl Inner {
         }
     }
 
+    async fn notify(&self, notification: notification::Notification) {
+        let Some(used) = self.feedback.used(&notification) else {
+            return;
+        };
+        match notification.kind {
+            notification::Kind::Settled(
+                notification::Settlement::Revert(_) | notification::Settlement::SimulationRevert,
+            ) => {
+                tracing::warn!(
+                    auction_id = %notification.auction_id,
+                    pools = ?used.pools,
+                    "settlement reverted, blocklisting its pools"
+                );
+                self.feedback.block_pools(&used.pools);
+            }
+            notification::Kind::SimulationFailed(..) => {
+                tracing::debug!(
+                    auction_id = %notification.auction_id,
+                    tokens = ?used.tokens,
+                    "solution failed simulation"
+                );
+                self.feedback.record_failure(&used.tokens);
+            }
+            notification::Kind::Settled(notification::Settlement::Success(tx)) => {
+                self.feedback.clear_failures(&used.tokens);
+                self.learn_gas(tx, &used).await;
+            }
+            _ => {}
+        }
+    }
+
+    /// Attributes the gas a settlement used beyond its estimate to its route,
+    /// for settlements of only a solution with a single Router exchange that
+    /// is executed for sure.
+    async fn learn_gas(&self, tx: eth::B256, used: &feedback::Used) {
+        let ([route], Some(estimate), false) =
+            (used.routes.as_slice(), used.gas, used.internalized)
+        else {
+            return;
+        };
+        let receipt = match self.provider.get_transaction_receipt(tx).await {
+            Ok(Some(receipt)) => receipt,
+            Ok(None) => return,
+            Err(err) => {
+                tracing::debug!(?err, "failed to fetch settlement receipt");
+                return;
+            }
+        };
+        let overhead = u64::try_from(estimate.0)
+            .unwrap_or(u64::MAX)
+            .saturating_sub(self.gas.estimate(route));
+        self.gas
+            .record(route, receipt.gas_used.saturating_sub(overhead));
+    }
+
+    /// Invalidates cached routes trading through pools whose state changed
+    /// since the last checked block, and fetches the routes of listed LP
+    /// tokens again so that the next auction finds them cached. At most
+    /// [`MAX_WARMED_ROUTES_PER_BLOCK`] routes are fetched again, the others
+    /// are fetched when an auction needs them.
+    async fn maintain_route_cache(&self, block: u64) {
+        let stale = match self.route_cache.advance(block) {
+            Some(checked) if checked >= block => return,
+            Some(checked) if block - checked <= MAX_EVENT_BLOCK_RANGE => {
+                match self.changed_pools(checked + 1, block).await {
+                    Ok(changed) => self.route_cache.invalidate(&changed),
+                    Err(err) => {
+                        tracing::debug!(?err, "failed to fetch Curve pool events");
+                        self.route_cache.clear()
+                    }
+                }
+            }
+            Some(_) => self.route_cache.clear(),
+            None => self.route_cache.invalidate(&HashSet::new()),
+        };
+
+        let mut warm = stale
+            .into_iter()
+            .filter(|request| {
+                self.lp_tokens.is_listed(&request.sell.0)
+                    || self.lp_tokens.is_listed(&request.buy.0)
+            })
+            .collect::<Vec<_>>();
+        if warm.is_empty() {
+            return;
+        }
+        let skipped = warm.len().saturating_sub(MAX_WARMED_ROUTES_PER_BLOCK);
+        warm.truncate(MAX_WARMED_ROUTES_PER_BLOCK);
+        tracing::debug!(
+            block,
+            routes = warm.len(),
+            skipped,
+            "warming Curve route cache"
+        );
+        futures::stream::iter(warm)
+            .for_each_concurrent(MAX_CONCURRENT_WARMING, |request| async move {
+                match self.fetch_api_routes(&request, ROUTE_REQUEST_TIMEOUT).await {
+                    Ok(routes) => self.route_cache.insert(request, routes),
+                    Err(err) => tracing::debug!(?err, ?request, "failed to warm Curve route"),
+                }
+            })
+            .await;
+    }
+
+    /// Returns the pools of cached routes that emitted state changing events
+    /// in the block range.
+    async fn changed_pools(&self, from: u64, to: u64) -> Result<HashSet<eth::Address>, SolveError> {
+        let pools = self.route_cache.pools();
+        if pools.is_empty() {
+            return Ok(pools);
+        }
+        let filter = Filter::new()
+            .address(pools.into_iter().collect::<Vec<_>>())
+            .from_block(from)
+            .to_block(to);
+        let logs = self
+            .provider
+            .get_logs(&filter)
+            .await
+            .map_err(|e| SolveError::OnchainVerification(e.to_string()))?;
+        Ok(logs
+            .iter()
+            .filter(|log| boundary::curve::pool::changes_state(log))
+            .map(|log| log.address())
+            .collect())
+    }
+
+    /// Returns the LP tokens traded by the orders of a solution.
+    fn lp_tokens_of(&self, solution: &Solution) -> HashSet<eth::TokenAddress> {
+        solution
+            .trades
+            .iter()
+            .filter_map(|trade| match trade {
+                solution::Trade::Fulfillment(fulfillment) => Some(fulfillment.order()),
+                solution::Trade::Jit(_) => None,
+            })
+            .filter_map(|order| {
+                [order.sell.token, order.buy.token]
+                    .into_iter()
+                    .find(|token| self.lp_tokens.accepts(&token.0))
+            })
+            .collect()
+    }
+
     /// Returns `None` if the order is supported, or a static reason string if
     /// it should be rejected.
     fn rejection_reason(&self, order: &Order) -> Option<&'static str> {
         match order.side {
             order::Side::Sell => {
-                if let Some(ref lp_tokens) = self.lp_tokens {
-                    let sell_is_lp = lp_tokens.contains(&order.sell.token.0);
-                    let buy_is_lp = lp_tokens.contains(&order.buy.token.0);
-                    if !sell_is_lp && !buy_is_lp {
-                        return Some("no_lp_token_match");
-                    }
+                let sell_is_lp = self.lp_tokens.accepts(&order.sell.token.0);
+                let buy_is_lp = self.lp_tokens.accepts(&order.buy.token.0);
+                if !sell_is_lp && !buy_is_lp {
+                    return Some("no_lp_token_match");
                 }
-                if let Some(ref allowed) = self.allowed_buy_tokens {
-                    if !allowed.contains(&order.buy.token.0)
-                        && !allowed.contains(&order.sell.token.0)
-                    {
-                        return Some("buy_token_not_allowed");
-                    }
+                if !self.allowed_buy_tokens.accepts(&order.buy.token.0)
+                    && !self.allowed_buy_tokens.accepts(&order.sell.token.0)
+                {
+                    return Some("buy_token_not_allowed");
                 }
                 None
             }
             order::Side::Buy => {
-                if let Some(ref lp_tokens) = self.lp_tokens {
-                    let sell_is_lp = lp_tokens.contains(&order.sell.token.0);
-                    let buy_is_lp = lp_tokens.contains(&order.buy.token.0);
-                    if !sell_is_lp && !buy_is_lp {
-                        return Some("no_lp_token_match");
-                    }
+                let sell_is_lp = self.lp_tokens.accepts(&order.sell.token.0);
+                let buy_is_lp = self.lp_tokens.accepts(&order.buy.token.0);
+                if !sell_is_lp && !buy_is_lp {
+                    return Some("no_lp_token_match");
                 }
-                if let Some(ref allowed) = self.allowed_buy_tokens {
-                    if !allowed.contains(&order.sell.token.0)
-                        && !allowed.contains(&order.buy.token.0)
-                    {
-                        return Some("buy_token_not_allowed");
-                    }
+                if !self.allowed_buy_tokens.accepts(&order.sell.token.0)
+                    && !self.allowed_buy_tokens.accepts(&order.buy.token.0)
+                {
+                    return Some("buy_token_not_allowed");
                 }
                 None
             }
         }
     }
 
+    /// Solves a single order and logs the outcome.
+    async fn solve_single(
+        &self,
+        i: usize,
+        order: Order,
+        tokens: &auction::Tokens,
+        gas_price: &auction::GasPrice,
+        is_quote: bool,
+    ) -> Option<Solution> {
+        tracing::debug!(
+            order_uid = %order.uid,
+            sell_token = ?order.sell.token,
+            buy_token = ?order.buy.token,
+            "processing Curve LP order"
+        );
+
+        let result = self.fill_order(&order, tokens, gas_price, is_quote).await;
+        if is_native_price_probe(&order, is_quote, self.weth) {
+            metrics::native_price_probe(match &result {
+                Ok(_) => "solved",
+                Err(SolveError::InsufficientOutput { .. }) => "insufficient_output",
+                Err(_) => "error",
+            });
+        }
+
+        match result {
+            Ok((solution, output_amount, route_ms, price_fetch_ms)) => {
+                tracing::info!(
+                    order_uid = %order.uid,
+                    sell_token = ?order.sell.token,
+                    buy_token = ?order.buy.token,
+                    side = ?order.side,
+                    sell_amount = %order.sell.amount,
+                    order_buy_min = %order.buy.amount,
+                    solution_output = %output_amount,
+                    route_ms,
+                    price_fetch_ms,
+                    is_quote,
+                    "solved order"
+                );
+                Some(solution.with_id(solution::Id(i as u64)))
+            }
+            Err(err) => {
+                tracing::warn!(order_uid = %order.uid, ?err, "failed to solve order");
+                metrics::solve_error(err.reason());
+                None
+            }
+        }
+    }
+
+    /// Solves a single LP order, falling back to the largest partial fill the
+    /// Curve routes can cover if the order is partially fillable and cannot
+    /// be filled completely.
+    async fn fill_order(
+        &self,
+        order: &Order,
+        tokens: &auction::Tokens,
+        gas_price: &auction::GasPrice,
+        is_quote: bool,
+    ) -> Result<(Solution, eth::U256, u64, u64), SolveError> {
+        let err = match self.solve_order(order, tokens, gas_price, is_quote).await {
+            Err(err @ SolveError::InsufficientOutput { .. })
+                if order.partially_fillable && self.max_partial_attempts > 1 =>
+            {
+                err
+            }
+            result => return result,
+        };
+
+        // Bisect over the filled fraction `numerator / denominator` using route
+        // quotes, which are much cheaper than fully solving each candidate.
+        // The full fill counts as the first attempt. Time is kept for solving
+        // the partial fill found before the deadline.
+        let attempts = self.max_partial_attempts.min(MAX_PARTIAL_ATTEMPTS);
+        let denominator = U256::ONE << (attempts - 1);
+        let deadline = upstream::deadline().map(|deadline| {
+            deadline
+                .checked_sub(PARTIAL_FILL_RESERVE)
+                .unwrap_or(deadline)
+        });
+        let lo = bisect_fill(attempts, deadline, |numerator| async move {
+            match partial_order(order, numerator, denominator) {
+                Some(partial) => self.covers(&partial, tokens).await,
+                None => false,
+            }
+        })
+        .await;
+
+        let Some(partial) = partial_order(order, lo, denominator) else {
+            return Err(err);
+        };
+        tracing::debug!(
+            order_uid = %order.uid,
+            sell_amount = %partial.sell.amount,
+            buy_amount = %partial.buy.amount,
+            "attempting partial fill"
+        );
+        self.solve_order(&partial, tokens, gas_price, is_quote)
+            .await
+    }
+
+    /// Returns whether the best quoted route for the order's sell amount
+    /// covers its buy amount after slippage.
+    async fn covers(&self, order: &Order, tokens: &auction::Tokens) -> bool {
+        let decimals = |token| tokens.get(&token).and_then(|t| t.decimals).unwrap_or(18);
+        let routes = self
+            .routes(
+                order.sell.token,
+                order.buy.token,
+                order.sell.amount,
+                decimals(order.sell.token),
+                decimals(order.buy.token),
+            )
+            .await;
+        let Ok(routes) = routes else {
+            return false;
+        };
+        let depth = self.depth(order.sell.token, order.buy.token).await;
+        routes.iter().any(|route| {
+            let slippage_bps = self.order_slippage_bps(order, route, depth);
+            self.apply_slippage(route.expected_output, slippage_bps) >= order.buy.amount
+        })
+    }
+
     /// Solves a single LP order (sell or buy).
     ///
     /// When `is_quote` is true, skip the expensive on-chain `get_dy`
@@ -384,29 +1160,19 @@ impl Inner {
             .unwrap_or(18);
 
         // Native-price probe: reverse-then-forward routing.
-        if is_native_price_probe(order, is_quote) {
+        if is_native_price_probe(order, is_quote, self.weth) {
             let route_start = std::time::Instant::now();
 
             // Step 1: Reverse route (buy_token → sell_token) to estimate sell cost
-            let reverse_route = tokio::time::timeout(
-                ROUTE_REQUEST_TIMEOUT,
-                self.api_client.get_route(
-                    self.chain_id,
-                    order.buy.token.0,
-                    order.sell.token.0,
+            let reverse_route = self
+                .route(
+                    order.buy.token,
+                    order.sell.token,
                     order.buy.amount,
                     buy_token_decimals,
                     sell_token_decimals,
-                ),
-            )
-            .await
-            .map_err(|_| {
-                SolveError::Api(api::Error::Network(format!(
-                    "reverse route timed out after {}ms",
-                    ROUTE_REQUEST_TIMEOUT.as_millis()
-                )))
-            })?
-            .map_err(SolveError::Api)?;
+                )
+                .await?;
 
             let reverse_output = reverse_route.expected_output;
 
@@ -420,25 +1186,15 @@ impl Inner {
                     .saturating_mul(U256::from(10_000 + padding_bps))
                     / U256::from(10_000u32);
 
-                let result = tokio::time::timeout(
-                    ROUTE_REQUEST_TIMEOUT,
-                    self.api_client.get_route(
-                        self.chain_id,
-                        order.sell.token.0,
-                        order.buy.token.0,
+                let result = self
+                    .route(
+                        order.sell.token,
+                        order.buy.token,
                         estimated_sell,
                         sell_token_decimals,
                         buy_token_decimals,
-                    ),
-                )
-                .await
-                .map_err(|_| {
-                    SolveError::Api(api::Error::Network(format!(
-                        "forward route timed out after {}ms",
-                        ROUTE_REQUEST_TIMEOUT.as_millis()
-                    )))
-                })?
-                .map_err(SolveError::Api)?;
+                    )
+                    .await?;
 
                 tracing::debug!(
                     reverse_output = %reverse_output,
@@ -464,27 +1220,18 @@ impl Inner {
             let route_ms = route_start.elapsed().as_millis() as u64;
 
             // Build interaction from the FORWARD route (correct direction).
-            let interaction = interactions::build_exchange_interaction(
-                &fwd_route,
-                order.sell.token,
-                estimated_sell,
-                order.buy.token,
-                order.buy.amount,
-                self.settlement_contract,
-            );
+            let input = eth::Asset {
+                token: order.sell.token,
+                amount: estimated_sell,
+            };
+            let (interaction, gas) = self.swap_interaction(&fwd_route, input, order.buy);
 
             let single = solution::Single {
                 order: order.clone(),
-                input: eth::Asset {
-                    token: order.sell.token,
-                    amount: estimated_sell,
-                },
-                output: eth::Asset {
-                    token: order.buy.token,
-                    amount: order.buy.amount,
-                },
+                input,
+                output: order.buy,
                 interactions: vec![solution::Interaction::Custom(interaction)],
-                gas: eth::Gas(U256::from(350_000)) + self.solution_gas_offset,
+                gas,
                 wrappers: order.wrappers.clone(),
             };
 
@@ -496,21 +1243,19 @@ impl Inner {
             return Ok((solution, order.buy.amount, route_ms, 0));
         }
 
-        // 1. Get route from Curve API (fail fast if upstream is slow).
+        // 1. Get route, locally priced or from the Curve API (fail fast if
+        // upstream is slow).
         let route_start = std::time::Instant::now();
         let route_fut = async {
-            let result = tokio::time::timeout(
-                ROUTE_REQUEST_TIMEOUT,
-                self.api_client.get_route(
-                    self.chain_id,
-                    order.sell.token.0,
-                    order.buy.token.0,
+            let result = self
+                .routes(
+                    order.sell.token,
+                    order.buy.token,
                     order.sell.amount,
                     sell_token_decimals,
                     buy_token_decimals,
-                ),
-            )
-            .await;
+                )
+                .await;
             let route_ms = route_start.elapsed().as_millis() as u64;
             (result, route_ms)
         };
@@ -523,11 +1268,11 @@ impl Inner {
             if needs_price {
                 let result = tokio::time::timeout(
                     PRICE_FETCH_TIMEOUT,
-                    self.price_client.get_eth_price("ethereum", order.sell.token.0),
+                    self.native_price(order.sell.token, tokens),
                 )
                 .await
                 .ok()
-                .and_then(Result::ok);
+                .flatten();
                 let price_ms = price_start.elapsed().as_millis() as u64;
                 (result, price_ms)
             } else {
@@ -535,99 +1280,114 @@ impl Inner {
             }
         };
 
-        let (route, min_output, fetched_price, route_ms, price_fetch_ms) = if is_quote {
+        // The LP token supply sizes the slippage tolerance, and is fetched
+        // while waiting for the route.
+        let depth_fetch = self.depth(order.sell.token, order.buy.token);
+
+        let (route, swap, slippage_bps, fetched_price, route_ms, price_fetch_ms) = if is_quote {
             // For quotes: run route + price fetch in parallel, skip on-chain
             // verification. Apply slippage to the API estimate directly.
-            let ((route_result, route_ms), (fetched_price, price_fetch_ms)) =
-                tokio::join!(route_fut, price_fetch);
-            let route = route_result
-                .map_err(|_| {
-                    SolveError::Api(api::Error::Network(format!(
-                        "route request timed out after {}ms",
-                        ROUTE_REQUEST_TIMEOUT.as_millis()
-                    )))
-                })?
-                .map_err(SolveError::Api)?;
+            let ((routes_result, route_ms), (fetched_price, price_fetch_ms), depth) =
+                tokio::join!(route_fut, price_fetch, depth_fetch);
+            let route = preferred(routes_result?)?;
+            let slippage_bps = self.order_slippage_bps(order, &route, depth);
 
             tracing::debug!(
                 expected_output = %route.expected_output,
                 route_ms,
                 price_fetch_ms,
-                "got route from Curve API (quote)"
+                "got Curve route (quote)"
             );
 
-            let min_output = self.apply_slippage(route.expected_output);
-            (route, min_output, fetched_price, route_ms, price_fetch_ms)
+            let full = exact_out::Point {
+                input: order.sell.amount,
+                output: route.expected_output,
+            };
+            // Buy orders are only estimated: Curve output is concave in the
+            // input, so interpolating from the full sell amount is feasible.
+            let swap = match order.side {
+                order::Side::Sell => full,
+                order::Side::Buy => {
+                    let target = self.required_output(order.buy.amount, slippage_bps);
+                    exact_out::linear_estimate(target, full)
+                        .map(|input| exact_out::Point {
+                            input,
+                            output: target,
+                        })
+                        .unwrap_or(full)
+                }
+            };
+            (
+                route,
+                swap,
+                slippage_bps,
+                fetched_price,
+                route_ms,
+                price_fetch_ms,
+            )
         } else {
-            // For real auctions: get route first (need it for on-chain verify),
-            // then run verify + price fetch in parallel.
-            let (route_result, route_ms) = route_fut.await;
-            let route = route_result
-                .map_err(|_| {
-                    SolveError::Api(api::Error::Network(format!(
-                        "route request timed out after {}ms",
-                        ROUTE_REQUEST_TIMEOUT.as_millis()
-                    )))
-                })?
-                .map_err(SolveError::Api)?;
+            // For real auctions: get candidate routes first (need them for
+            // on-chain verify), then run verify + price fetch in parallel.
+            let ((routes_result, route_ms), depth) = tokio::join!(route_fut, depth_fetch);
+            let routes = routes_result?;
+            let best_expected_output = routes
+                .iter()
+                .map(|route| route.expected_output)
+                .max()
+                .unwrap_or_default();
 
             tracing::debug!(
-                expected_output = %route.expected_output,
+                candidates = routes.len(),
+                best_expected_output = %best_expected_output,
                 route_ms,
-                "got route from Curve API"
+                "got Curve routes"
             );
 
             // Fast-fail: if even the best-case API quote (allowing max deviation
-            // upward) can't fill the order after slippage, skip the expensive
-            // on-chain get_dy call.
-            let optimistic_output = route.expected_output.saturating_add(
-                route
-                    .expected_output
-                    .saturating_mul(U256::from(self.max_quote_deviation_bps))
+            // upward) can't fill the order after the smallest slippage of any
+            // candidate, skip the expensive on-chain get_dy call.
+            let optimistic_output = best_expected_output.saturating_add(
+                best_expected_output.saturating_mul(U256::from(self.max_quote_deviation_bps))
                     / U256::from(10_000u32),
             );
-            if self.apply_slippage(optimistic_output) < order.buy.amount {
+            let optimistic_slippage_bps = routes
+                .iter()
+                .map(|route| self.order_slippage_bps(order, route, depth))
+                .min()
+                .unwrap_or_default();
+            let min_output = self.apply_slippage(optimistic_output, optimistic_slippage_bps);
+            if min_output < order.buy.amount {
                 return Err(SolveError::InsufficientOutput {
-                    min_output: self.apply_slippage(optimistic_output),
+                    min_output,
                     required: order.buy.amount,
                 });
             }
 
-            let verify = tokio::time::timeout(
-                ONCHAIN_VERIFY_TIMEOUT,
-                self.verify_quote_onchain(&route, order.sell.amount),
-            );
-
-            let (onchain_result, (fetched_price, price_fetch_ms)) =
-                tokio::join!(verify, price_fetch);
-            let onchain_output = onchain_result
-                .map_err(|_| {
-                    SolveError::OnchainVerification(format!(
-                        "verification timed out after {}ms",
-                        ONCHAIN_VERIFY_TIMEOUT.as_millis()
-                    ))
-                })??;
-
-            // Check deviation between API and on-chain quote
-            let deviation_bps =
-                self.calculate_deviation_bps(route.expected_output, onchain_output);
-            if deviation_bps > self.max_quote_deviation_bps {
-                return Err(SolveError::QuoteDeviation {
-                    api_output: route.expected_output,
-                    onchain_output,
-                    deviation_bps,
-                });
-            }
+            let (selected, (fetched_price, price_fetch_ms)) =
+                tokio::join!(self.select_route(routes, order.sell.amount), price_fetch);
+            let (route, onchain_output) = selected?;
 
+            let slippage_bps = self.order_slippage_bps(order, &route, depth);
+            let full = exact_out::Point {
+                input: order.sell.amount,
+                output: onchain_output,
+            };
+            let swap = match order.side {
+                order::Side::Sell => full,
+                order::Side::Buy => self.exact_out(&route, order, full, slippage_bps).await,
+            };
             (
                 route,
-                self.apply_slippage(onchain_output),
+                swap,
+                slippage_bps,
                 fetched_price,
                 route_ms,
                 price_fetch_ms,
             )
         };
 
+        tracing::debug!(slippage_bps, "slippage tolerance");
+        let min_output = self.apply_slippage(swap.output, slippage_bps);
         if min_output < order.buy.amount {
             return Err(SolveError::InsufficientOutput {
                 min_output,
@@ -635,19 +1395,20 @@ impl Inner {
             });
         }
 
-        // 4. Build solution with custom interaction
-        let interaction = interactions::build_exchange_interaction(
+        // 4. Build solution with custom interaction, calling the pool directly
+        // if that is cheaper than the Router
+        let (interaction, estimated_gas) = self.swap_interaction(
             &route,
-            order.sell.token,
-            order.sell.amount,
-            order.buy.token,
-            min_output,
-            self.settlement_contract,
+            eth::Asset {
+                token: order.sell.token,
+                amount: swap.input,
+            },
+            eth::Asset {
+                token: order.buy.token,
+                amount: min_output,
+            },
         );
 
-        // 5. Calculate gas estimate
-        let estimated_gas = eth::Gas(U256::from(350_000)) + self.solution_gas_offset;
-
         // 6. Calculate fee based on gas
         let sell_token_price = match tokens.reference_price(&order.sell.token) {
             Some(price) => price,
@@ -662,45 +1423,342 @@ impl Inner {
             .ok_or(SolveError::FeeCalculation)?;
 
         // 8. Build the solution
-        // For sell orders: input is the full sell amount, output is slippage-adjusted.
-        // For buy orders: output is the exact desired buy amount. Input must be
-        // sell_amount minus fee, because into_solution() adds the surplus fee back
-        // to the sell side (input + fee must not exceed order.sell.amount).
-        let (input_amount, output_amount) = match order.side {
-            order::Side::Sell => (order.sell.amount, min_output),
-            order::Side::Buy => (
-                order
-                    .sell
-                    .amount
-                    .checked_sub(fee_in_sell_token)
-                    .ok_or(SolveError::FeeCalculation)?,
-                order.buy.amount,
-            ),
+        let (solution, output_amount) = single_solution(
+            order,
+            swap.input,
+            min_output,
+            vec![solution::Interaction::Custom(interaction)],
+            estimated_gas,
+            fee_in_sell_token,
+        )?;
+        Ok((solution, output_amount, route_ms, price_fetch_ms))
+    }
+
+    /// Finds the preferred route for selling `amount` of `sell` for `buy`
+    /// without verifying candidates on-chain. See [`Self::routes`].
+    async fn route(
+        &self,
+        sell: eth::TokenAddress,
+        buy: eth::TokenAddress,
+        amount: eth::U256,
+        sell_decimals: u8,
+        buy_decimals: u8,
+    ) -> Result<api::Route, SolveError> {
+        preferred(
+            self.routes(sell, buy, amount, sell_decimals, buy_decimals)
+                .await?,
+        )
+    }
+
+    /// Finds candidate routes for selling `amount` of `sell` for `buy`, in
+    /// order of preference, skipping routes through pools of recently
+    /// reverted settlements. The returned list is never empty.
+    async fn routes(
+        &self,
+        sell: eth::TokenAddress,
+        buy: eth::TokenAddress,
+        amount: eth::U256,
+        sell_decimals: u8,
+        buy_decimals: u8,
+    ) -> Result<Routes, SolveError> {
+        let mut routes = self
+            .candidate_routes(sell, buy, amount, sell_decimals, buy_decimals)
+            .await?;
+        routes.retain(|route| !self.feedback.is_blocked(route));
+        if routes.is_empty() {
+            return Err(SolveError::Api(api::Error::InvalidRoute(
+                "all routes trade through blocklisted pools".to_string(),
+            )));
+        }
+        Ok(routes)
+    }
+
+    /// Finds candidate routes for selling `amount` of `sell` for `buy`, in
+    /// order of preference. The returned list is never empty.
+    ///
+    /// Pairs involving the LP token of a configured pool are priced locally
+    /// from on-chain pool state. The Curve Router API is then only queried as
+    /// a cross-check with a shorter timeout, and its routes are preferred only
+    /// when one quotes more output than the allowed quote deviation. Otherwise
+    /// the local route comes first and the API routes follow as fallbacks.
+    /// All other pairs are routed by the API.
+    async fn candidate_routes(
+        &self,
+        sell: eth::TokenAddress,
+        buy: eth::TokenAddress,
+        amount: eth::U256,
+        sell_decimals: u8,
+        buy_decimals: u8,
+    ) -> Result<Routes, SolveError> {
+        let request = route_cache::Request {
+            sell,
+            buy,
+            amount,
+            sell_decimals,
+            buy_decimals,
         };
+        let api_routes = |timeout: Duration| self.api_routes(request, timeout);
 
-        let single = solution::Single {
-            order: order.clone(),
-            input: eth::Asset {
-                token: order.sell.token,
-                amount: input_amount,
-            },
-            output: eth::Asset {
-                token: order.buy.token,
-                amount: output_amount,
-            },
-            interactions: vec![solution::Interaction::Custom(interaction)],
-            gas: estimated_gas,
-            wrappers: order.wrappers.clone(),
+        let Some(metadata) = self.pool(sell).or_else(|| self.pool(buy)) else {
+            return api_routes(ROUTE_REQUEST_TIMEOUT).await;
         };
 
-        let solution = single
-            .into_solution(eth::SellTokenAmount(fee_in_sell_token))
-            .ok_or(SolveError::SolutionConstruction)?;
-        Ok((solution, output_amount, route_ms, price_fetch_ms))
+        let (local, remote) = tokio::join!(
+            self.local_route(&metadata, sell, buy, amount),
+            api_routes(API_CROSS_CHECK_TIMEOUT),
+        );
+        match (local, remote) {
+            (Ok(local), Ok(remote)) => {
+                let api_output = remote
+                    .iter()
+                    .map(|route| route.expected_output)
+                    .max()
+                    .unwrap_or_default();
+                let deviation_bps = self.calculate_deviation_bps(local.expected_output, api_output);
+                tracing::debug!(
+                    local_output = %local.expected_output,
+                    api_output = %api_output,
+                    deviation_bps,
+                    "cross-checked local Curve quote"
+                );
+                if api_output > local.expected_output
+                    && deviation_bps > self.max_quote_deviation_bps
+                {
+                    Ok(remote)
+                } else {
+                    Ok(Routes::local(local).chain(remote))
+                }
+            }
+            (Ok(local), Err(err)) => {
+                tracing::debug!(?err, "Curve API cross-check unavailable, using local quote");
+                Ok(Routes::local(local))
+            }
+            (Err(err), remote) => {
+                tracing::debug!(?err, "local Curve quote failed, using Curve API");
+                remote
+            }
+        }
+    }
+
+    /// Returns the Router API routes for `request`. The routes cached for the
+    /// same pair and amount bucket are reused if they can still be quoted
+    /// on-chain, which saves the round trip to the API. Reused routes come
+    /// with their on-chain quotes, so they don't need to be verified again.
+    async fn api_routes(
+        &self,
+        request: route_cache::Request,
+        timeout: Duration,
+    ) -> Result<Routes, SolveError> {
+        // Cache lookups are archived, so that replays reuse the routes that
+        // were cached when the auction was recorded.
+        let key = request.key();
+        let cached = archive::exchange(
+            "route-cache",
+            serde_json::json!({
+                "sell": request.sell.0,
+                "buy": request.buy.0,
+                "amount": request.amount,
+            }),
+            async { Ok::<_, std::convert::Infallible>(self.route_cache.get(&key)) },
+        )
+        .await
+        .ok()
+        .flatten();
+        if let Some(routes) = cached {
+            let requoted =
+                tokio::time::timeout(ONCHAIN_VERIFY_TIMEOUT, self.requote(routes, request.amount))
+                    .await;
+            match requoted {
+                Ok(Ok(routes)) => {
+                    metrics::route_cache("hit");
+                    return Ok(Routes::verified(routes));
+                }
+                Ok(Err(err)) => {
+                    tracing::debug!(?err, "failed to requote cached Curve routes");
+                    self.route_cache.remove(&key);
+                }
+                Err(_) => tracing::debug!("requoting cached Curve routes timed out"),
+            }
+        }
+        metrics::route_cache("miss");
+
+        let routes = self.fetch_api_routes(&request, timeout).await?;
+        self.route_cache.insert(request, routes.clone());
+        Ok(Routes::unverified(routes))
+    }
+
+    /// Requests routes from the Curve Router API, bypassing the cache.
+    async fn fetch_api_routes(
+        &self,
+        request: &route_cache::Request,
+        timeout: Duration,
+    ) -> Result<Vec<api::Route>, SolveError> {
+        self.api_client
+            .get_routes(
+                self.chain_id,
+                request.sell.0,
+                request.buy.0,
+                request.amount,
+                request.sell_decimals,
+                request.buy_decimals,
+                timeout,
+            )
+            .await
+            .map_err(SolveError::Api)
+    }
+
+    /// Replaces the expected outputs of cached routes with their on-chain
+    /// quotes for selling `amount`, dropping routes that revert.
+    async fn requote(
+        &self,
+        routes: Vec<api::Route>,
+        amount: eth::U256,
+    ) -> Result<Vec<api::Route>, SolveError> {
+        let outputs = self.quote_routes_onchain(&routes, amount).await?;
+        let routes = routes
+            .into_iter()
+            .zip(outputs)
+            .filter_map(|(route, output)| {
+                Some(api::Route {
+                    expected_output: output?,
+                    ..route
+                })
+            })
+            .collect::<Vec<_>>();
+        if routes.is_empty() {
+            return Err(SolveError::OnchainVerification(
+                "all cached routes reverted".to_string(),
+            ));
+        }
+        Ok(routes)
     }
 
-    /// Verifies the quote on-chain by calling Router.get_dy().
-    async fn verify_quote_onchain(
+    /// Returns the pool minting `lp_token` if it can be priced locally.
+    /// Configured pools take precedence over discovered ones.
+    fn pool(&self, lp_token: eth::TokenAddress) -> Option<pool::Metadata> {
+        self.pools
+            .get(&lp_token)
+            .cloned()
+            .or_else(|| self.registry.as_ref()?.pool(&lp_token))
+    }
+
+    /// Returns the native price of a token without a reference price. LP
+    /// tokens of known pools are priced on-chain, falling back to the Curve
+    /// Price API for other tokens or if that fails.
+    async fn native_price(
+        &self,
+        token: eth::TokenAddress,
+        tokens: &auction::Tokens,
+    ) -> Option<eth::U256> {
+        if let Some(metadata) = self.pool(token) {
+            match self.onchain_lp_price(&metadata, tokens).await {
+                Ok(Some(price)) => return Some(price),
+                Ok(None) => tracing::debug!(?token, "no coin prices to price LP token on-chain"),
+                Err(err) => tracing::debug!(?token, ?err, "failed to price LP token on-chain"),
+            }
+        }
+        self.price_client
+            .get_eth_price(&self.price_api_chain, token.0, PRICE_FETCH_TIMEOUT)
+            .await
+            .ok()
+    }
+
+    /// Prices the LP token of a pool from its on-chain state at the current
+    /// block and the reference prices of its coins.
+    async fn onchain_lp_price(
+        &self,
+        metadata: &pool::Metadata,
+        tokens: &auction::Tokens,
+    ) -> Result<Option<eth::U256>, boundary::curve::pool::Error> {
+        let block = self
+            .provider
+            .get_block_number()
+            .await
+            .map_err(|e| boundary::curve::pool::Error::Rpc(e.to_string()))?;
+        let pricing = match self.lp_prices.get(&metadata.lp_token, block) {
+            Some(pricing) => pricing,
+            None => {
+                let pricing = Arc::new(self.pool_fetcher.pricing(metadata, block).await?);
+                self.lp_prices
+                    .insert(metadata.lp_token, block, pricing.clone());
+                pricing
+            }
+        };
+
+        Ok(pricing.native_price(|coin| {
+            let coin = if coin.0 == liquidity::NATIVE_ETH {
+                eth::TokenAddress(self.weth.0)
+            } else {
+                *coin
+            };
+            match tokens.reference_price(&coin) {
+                Some(price) => Some(price.0.0),
+                None => (coin.0 == self.weth.0).then(|| U256::from(10u64.pow(18))),
+            }
+        }))
+    }
+
+    /// Prices a single hop route through the specified pool from its current
+    /// on-chain state.
+    async fn local_route(
+        &self,
+        metadata: &pool::Metadata,
+        sell: eth::TokenAddress,
+        buy: eth::TokenAddress,
+        amount: eth::U256,
+    ) -> Result<api::Route, SolveError> {
+        let pool = tokio::time::timeout(POOL_FETCH_TIMEOUT, self.pool_fetcher.fetch(metadata))
+            .await
+            .map_err(|_| {
+                SolveError::LocalQuote(format!(
+                    "pool state fetch timed out after {}ms",
+                    POOL_FETCH_TIMEOUT.as_millis()
+                ))
+            })?
+            .map_err(|e| SolveError::LocalQuote(e.to_string()))?;
+
+        pool.route(sell, buy, amount).ok_or_else(|| {
+            SolveError::LocalQuote(format!(
+                "pool {:?} cannot price {:?} -> {:?}",
+                metadata.address, sell, buy
+            ))
+        })
+    }
+
+    /// Finds the smallest sell amount for which `route` still covers the buy
+    /// amount of `order` after `slippage_bps`, quoting candidate amounts
+    /// on-chain.
+    /// Falls back to the best amount found so far (at worst `full`, the full
+    /// sell amount) when the search runs out of time.
+    async fn exact_out(
+        &self,
+        route: &api::Route,
+        order: &Order,
+        full: exact_out::Point,
+        slippage_bps: u32,
+    ) -> exact_out::Point {
+        let deadline = tokio::time::Instant::now() + EXACT_OUT_SEARCH_TIMEOUT;
+        let target = self.required_output(order.buy.amount, slippage_bps);
+        let swap = exact_out::min_input(target, full, move |amount| async move {
+            tokio::time::timeout_at(deadline, self.quote_onchain(route, amount))
+                .await
+                .ok()?
+                .ok()
+        })
+        .await
+        .unwrap_or(full);
+
+        tracing::debug!(
+            sell_amount = %swap.input,
+            max_sell_amount = %full.input,
+            output = %swap.output,
+            "exact-out search"
+        );
+        swap
+    }
+
+    /// Quotes a single route on-chain by calling Router.get_dy().
+    async fn quote_onchain(
         &self,
         route: &api::Route,
         amount: eth::U256,
@@ -708,7 +1766,7 @@ impl Inner {
         let calldata = router::encode_get_dy(route, amount);
 
         let tx = TransactionRequest::default()
-            .to(router::ROUTER_ADDRESS)
+            .to(self.router)
             .input(calldata.into());
 
         let result = self
@@ -721,6 +1779,146 @@ impl Inner {
             .map_err(|e| SolveError::OnchainVerification(e.to_string()))
     }
 
+    /// Picks the candidate route with the largest on-chain output for selling
+    /// `amount`, and returns it together with that output.
+    ///
+    /// Routes requoted from the route cache already carry their on-chain
+    /// outputs, so they are only quoted again alongside routes that weren't.
+    /// Their API quotes are gone, so they aren't checked for deviating from
+    /// them either.
+    async fn select_route(
+        &self,
+        mut routes: Routes,
+        amount: eth::U256,
+    ) -> Result<(api::Route, eth::U256), SolveError> {
+        let (rank, onchain_output) = if routes.verified.iter().all(|verified| *verified) {
+            let outputs = routes
+                .iter()
+                .map(|route| Some(route.expected_output))
+                .collect::<Vec<_>>();
+            best_output(&outputs).ok_or_else(|| {
+                SolveError::OnchainVerification("no candidate routes".to_string())
+            })?
+        } else {
+            tokio::time::timeout(
+                ONCHAIN_VERIFY_TIMEOUT,
+                self.verify_routes_onchain(&routes.routes, amount),
+            )
+            .await
+            .map_err(|_| {
+                SolveError::OnchainVerification(format!(
+                    "verification timed out after {}ms",
+                    ONCHAIN_VERIFY_TIMEOUT.as_millis()
+                ))
+            })??
+        };
+
+        let candidates = routes.len();
+        let (source, source_rank) = routes.source_rank(rank);
+        let verified = routes.verified[rank];
+        let route = routes.routes.swap_remove(rank);
+        tracing::debug!(
+            rank,
+            candidates,
+            source = source.label(),
+            expected_output = %route.expected_output,
+            onchain_output = %onchain_output,
+            "selected Curve route"
+        );
+        metrics::route_selected(source.label(), source_rank);
+
+        // Check deviation between API and on-chain quote
+        if !verified {
+            let deviation_bps = self.calculate_deviation_bps(route.expected_output, onchain_output);
+            metrics::quote_deviation(deviation_bps);
+            if deviation_bps > self.max_quote_deviation_bps {
+                return Err(SolveError::QuoteDeviation {
+                    api_output: route.expected_output,
+                    onchain_output,
+                    deviation_bps,
+                });
+            }
+        }
+        Ok((route, onchain_output))
+    }
+
+    async fn verify_routes_onchain(
+        &self,
+        routes: &[api::Route],
+        amount: eth::U256,
+    ) -> Result<(usize, eth::U256), SolveError> {
+        let outputs = self.quote_routes_onchain(routes, amount).await?;
+        best_output(&outputs).ok_or_else(|| {
+            SolveError::OnchainVerification("all candidate routes reverted".to_string())
+        })
+    }
+
+    /// Quotes routes on-chain by calling Router.get_dy() for all of them in a
+    /// single multicall. Routes that revert have no output.
+    async fn quote_routes_onchain(
+        &self,
+        routes: &[api::Route],
+        amount: eth::U256,
+    ) -> Result<Vec<Option<eth::U256>>, SolveError> {
+        let calldata = router::encode_get_dy_batch(self.router, routes, amount);
+
+        let tx = TransactionRequest::default()
+            .to(router::MULTICALL_ADDRESS)
+            .input(calldata.into());
+
+        let result = self
+            .provider
+            .call(tx)
+            .await
+            .map_err(|e| SolveError::OnchainVerification(e.to_string()))?;
+
+        router::decode_get_dy_batch_result(&result)
+            .map_err(|e| SolveError::OnchainVerification(e.to_string()))
+    }
+
+    /// Builds the interaction executing `route` together with its gas
+    /// estimate, which depends on the shape of the route.
+    ///
+    /// Single hop LP mints and burns are sent to the pool directly when that
+    /// is cheaper than going through the Router. The pool executes the same
+    /// operation the Router would, so quotes from `get_dy` remain valid.
+    fn swap_interaction(
+        &self,
+        route: &api::Route,
+        sell: eth::Asset,
+        buy: eth::Asset,
+    ) -> (solution::CustomInteraction, eth::Gas) {
+        let router_gas = self.gas.estimate(route);
+        let direct = liquidity::Call::from_route(route)
+            .filter(|call| call.gas() < router_gas)
+            .and_then(|call| {
+                let interaction = interactions::build_pool_interaction(
+                    &call,
+                    sell.token,
+                    sell.amount,
+                    buy.token,
+                    buy.amount,
+                )?;
+                Some((interaction, call.gas()))
+            });
+        let (interaction, gas) = direct.unwrap_or_else(|| {
+            let interaction = interactions::build_exchange_interaction(
+                self.router,
+                route,
+                sell.token,
+                sell.amount,
+                buy.token,
+                buy.amount,
+                self.settlement_contract,
+            );
+            (interaction, router_gas)
+        });
+        (
+            interaction,
+            eth::Gas(U256::from(gas)) + self.solution_gas_offset,
+        )
+    }
+
     /// Calculates the deviation between two values in basis points.
     fn calculate_deviation_bps(&self, a: eth::U256, b: eth::U256) -> u32 {
         if a.is_zero() || b.is_zero() {
@@ -732,17 +1930,93 @@ impl Inner {
         bps.try_into().unwrap_or(u32::MAX)
     }
 
-    /// Applies slippage buffer to the output amount.
-    fn apply_slippage(&self, amount: eth::U256) -> eth::U256 {
+    /// Returns the supply of the LP token traded for sizing the slippage
+    /// tolerance, reusing recently fetched supplies.
+    async fn depth(
+        &self,
+        sell: eth::TokenAddress,
+        buy: eth::TokenAddress,
+    ) -> Option<slippage::Depth> {
+        let lp_token = if self.lp_tokens.accepts(&sell.0) {
+            sell
+        } else {
+            buy
+        };
+        let supply = match self.slippage.supply(lp_token) {
+            Some(supply) => supply,
+            None => {
+                let supply = tokio::time::timeout(
+                    SUPPLY_FETCH_TIMEOUT,
+                    self.pool_fetcher.total_supply(lp_token),
+                )
+                .await;
+                match supply {
+                    Ok(Ok(supply)) => {
+                        self.slippage.record_supply(lp_token, supply);
+                        supply
+                    }
+                    Ok(Err(err)) => {
+                        tracing::debug!(?lp_token, ?err, "failed to fetch LP token supply");
+                        return None;
+                    }
+                    Err(_) => {
+                        tracing::debug!(?lp_token, "fetching LP token supply timed out");
+                        return None;
+                    }
+                }
+            }
+        };
+        Some(slippage::Depth { lp_token, supply })
+    }
+
+    /// The slippage tolerance of a trade, widened for tokens whose solutions
+    /// recently failed simulation.
+    fn slippage_bps(&self, trade: &slippage::Trade) -> u32 {
+        let extra = self
+            .feedback
+            .extra_slippage_bps(trade.sell)
+            .max(self.feedback.extra_slippage_bps(trade.buy));
+        self.slippage
+            .tolerance_bps(trade)
+            .saturating_add(extra)
+            .min(10_000 - 1)
+    }
+
+    /// The slippage tolerance for filling `order` through `route`.
+    fn order_slippage_bps(
+        &self,
+        order: &Order,
+        route: &api::Route,
+        depth: Option<slippage::Depth>,
+    ) -> u32 {
+        self.slippage_bps(&slippage::Trade {
+            sell: order.sell.token,
+            buy: order.buy.token,
+            input: order.sell.amount,
+            route,
+            depth,
+        })
+    }
+
+    /// Applies a slippage tolerance to the output amount.
+    fn apply_slippage(&self, amount: eth::U256, slippage_bps: u32) -> eth::U256 {
         // min_output = amount * (10000 - slippage_bps) / 10000
-        let multiplier = U256::from(10_000 - self.slippage_bps);
+        let multiplier = U256::from(10_000 - slippage_bps);
         amount.saturating_mul(multiplier) / U256::from(10_000)
     }
+
+    /// Returns the smallest output that still covers `amount` after slippage,
+    /// i.e. the inverse of [`Self::apply_slippage`].
+    fn required_output(&self, amount: eth::U256, slippage_bps: u32) -> eth::U256 {
+        let divisor = U256::from(10_000 - slippage_bps);
+        amount.saturating_mul(U256::from(10_000)).div_ceil(divisor)
+    }
 }
 
 #[derive(Debug)]
 pub enum SolveError {
     Api(api::Error),
+    LocalQuote(String),
     OnchainVerification(String),
     QuoteDeviation {
         api_output: eth::U256,
@@ -758,10 +2032,27 @@ pub enum SolveError {
     SolutionConstruction,
 }
 
+impl SolveError {
+    /// A short label of the error kind, for metrics.
+    fn reason(&self) -> &'static str {
+        match self {
+            SolveError::Api(_) => "api",
+            SolveError::LocalQuote(_) => "local_quote",
+            SolveError::OnchainVerification(_) => "onchain_verification",
+            SolveError::QuoteDeviation { .. } => "quote_deviation",
+            SolveError::InsufficientOutput { .. } => "insufficient_output",
+            SolveError::NoPriceForSellToken => "no_price_for_sell_token",
+            SolveError::FeeCalculation => "fee_calculation",
+            SolveError::SolutionConstruction => "solution_construction",
+        }
+    }
+}
+
 impl fmt::Display for SolveError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             SolveError::Api(e) => write!(f, "Curve API error: {}", e),
+            SolveError::LocalQuote(msg) => write!(f, "local Curve quote failed: {}", msg),
             SolveError::OnchainVerification(msg) => {
                 write!(f, "on-chain verification failed: {}", msg)
             }
@@ -799,14 +2090,107 @@ impl From<api::Error> for SolveError {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use {super::*, alloy::primitives::address};
+
+    const WETH: eth::Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+    const WETH_TOKEN: eth::TokenAddress = eth::TokenAddress(WETH);
+    const LP_TOKEN: eth::TokenAddress =
+        eth::TokenAddress(address!("ecb0f0d68c19bdaadaebe24f6752a4db34e2c2cb"));
+
+    fn trade(
+        sell: eth::TokenAddress,
+        buy: eth::TokenAddress,
+        route: &api::Route,
+    ) -> slippage::Trade<'_> {
+        slippage::Trade {
+            sell,
+            buy,
+            input: U256::from(10_000u64),
+            route,
+            depth: None,
+        }
+    }
+
+    /// A single hop route burning LP tokens in a CryptoSwap pool.
+    fn crypto_route() -> api::Route {
+        let mut swap_params = [[0; 5]; 5];
+        swap_params[0] = [0, 1, 6, 30, 3];
+        api::Route {
+            route: [eth::Address::ZERO; 11],
+            swap_params,
+            pools: [eth::Address::ZERO; 5],
+            expected_output: U256::ZERO,
+        }
+    }
 
     #[tokio::test]
     async fn slippage_100bps() {
         let inner = test_inner(100, 500);
+        let route = crypto_route();
+        let slippage_bps = inner.slippage_bps(&trade(LP_TOKEN, WETH_TOKEN, &route));
         let amount = U256::from(10_000u64);
         // 1% slippage: 10000 * 9900 / 10000 = 9900
-        assert_eq!(inner.apply_slippage(amount), U256::from(9_900u64));
+        assert_eq!(
+            inner.apply_slippage(amount, slippage_bps),
+            U256::from(9_900u64)
+        );
+    }
+
+    #[tokio::test]
+    async fn simulation_failures_widen_slippage() {
+        let inner = test_inner(100, 500);
+        let route = crypto_route();
+        inner.feedback.record_failure(&[LP_TOKEN]);
+        inner.feedback.record_failure(&[LP_TOKEN]);
+        assert!(inner.slippage_bps(&trade(LP_TOKEN, WETH_TOKEN, &route)) > 100);
+        assert!(inner.slippage_bps(&trade(WETH_TOKEN, LP_TOKEN, &route)) > 100);
+    }
+
+    #[tokio::test]
+    async fn required_output_covers_slippage() {
+        let inner = test_inner(100, 500);
+        // 9900 * 10000 / 9900 = 10000, rounded up for inexact divisions
+        assert_eq!(
+            inner.required_output(U256::from(9_900u64), 100),
+            U256::from(10_000u64)
+        );
+        let required = inner.required_output(U256::from(1_234u64), 100);
+        assert!(inner.apply_slippage(required, 100) >= U256::from(1_234u64));
+        assert!(inner.apply_slippage(required - U256::ONE, 100) < U256::from(1_234u64));
+    }
+
+    #[tokio::test]
+    async fn requoted_routes_are_not_verified_again() {
+        // The node is unreachable, so any verification would fail.
+        let inner = test_inner(100, 0);
+        let route = |output: u64| api::Route {
+            expected_output: U256::from(output),
+            ..crypto_route()
+        };
+        let (route, onchain_output) = inner
+            .select_route(
+                Routes::verified(vec![route(100), route(120)]),
+                U256::from(1_000u64),
+            )
+            .await
+            .unwrap();
+        assert_eq!(route.expected_output, U256::from(120u64));
+        assert_eq!(onchain_output, U256::from(120u64));
+
+        let routes = Routes::verified(vec![route(100)]).chain(Routes::unverified(vec![route(120)]));
+        assert!(matches!(
+            inner.select_route(routes, U256::from(1_000u64)).await,
+            Err(SolveError::OnchainVerification(_))
+        ));
+    }
+
+    #[test]
+    fn routes_are_ranked_within_their_source() {
+        let routes = Routes::local(crypto_route())
+            .chain(Routes::unverified(vec![crypto_route(), crypto_route()]));
+        assert_eq!(routes.source_rank(0), (RouteSource::Local, 0));
+        assert_eq!(routes.source_rank(1), (RouteSource::Api, 0));
+        assert_eq!(routes.source_rank(2), (RouteSource::Api, 1));
     }
 
     #[tokio::test]
@@ -931,6 +2315,7 @@ mod tests {
 
     #[test]
     fn test_native_price_probe_detection() {
+        let weth = eth::WethAddress(WETH);
         let probe_order = Order {
             uid: order::Uid([0u8; 56]),
             sell: eth::Asset {
@@ -949,52 +2334,170 @@ mod tests {
         };
 
         // All four conditions met → true
-        assert!(is_native_price_probe(&probe_order, true));
+        assert!(is_native_price_probe(&probe_order, true, weth));
 
         // Different buy_amount still matches (not part of predicate)
         let mut o = probe_order.clone();
         o.buy.amount = U256::from(200_000_000_000_000_000u128);
-        assert!(is_native_price_probe(&o, true));
+        assert!(is_native_price_probe(&o, true, weth));
 
         // is_quote = false → false
-        assert!(!is_native_price_probe(&probe_order, false));
+        assert!(!is_native_price_probe(&probe_order, false, weth));
 
         // Wrong sell_amount → false
         let mut o = probe_order.clone();
         o.sell.amount = U256::from(1_000_000u64);
-        assert!(!is_native_price_probe(&o, true));
+        assert!(!is_native_price_probe(&o, true, weth));
 
         // Wrong buy_token (not WETH) → false
         let mut o = probe_order.clone();
         o.buy.token = eth::TokenAddress(address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"));
-        assert!(!is_native_price_probe(&o, true));
+        assert!(!is_native_price_probe(&o, true, weth));
 
         // Wrong side (Sell) → false
         let mut o = probe_order.clone();
         o.side = order::Side::Sell;
-        assert!(!is_native_price_probe(&o, true));
+        assert!(!is_native_price_probe(&o, true, weth));
+    }
+
+    #[test]
+    fn test_partial_order_never_relaxes_limit_price() {
+        let order = Order {
+            uid: order::Uid([0u8; 56]),
+            sell: eth::Asset {
+                token: eth::TokenAddress(address!("ecb0f0d68c19bdaadaebe24f6752a4db34e2c2cb")),
+                amount: U256::from(1_001u64),
+            },
+            buy: eth::Asset {
+                token: eth::TokenAddress(WETH),
+                amount: U256::from(333u64),
+            },
+            side: order::Side::Sell,
+            class: order::Class::Limit,
+            partially_fillable: true,
+            flashloan_hint: None,
+            wrappers: vec![],
+        };
+
+        let partial = partial_order(&order, U256::from(3), U256::from(8)).unwrap();
+        // 1001 * 3 / 8 = 375.375 rounds down, 333 * 3 / 8 = 124.875 rounds up
+        assert_eq!(partial.sell.amount, U256::from(375u64));
+        assert_eq!(partial.buy.amount, U256::from(125u64));
+        assert!(partial.sell.amount * order.buy.amount <= order.sell.amount * partial.buy.amount);
+
+        assert!(partial_order(&order, U256::ZERO, U256::from(8)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_partial_fill_bisection_converges() {
+        // Fills of up to 11 / 16 are covered.
+        let mut steps = 0;
+        let numerator = bisect_fill(5, None, |numerator| {
+            steps += 1;
+            async move { numerator <= U256::from(11) }
+        })
+        .await;
+        assert_eq!(numerator, U256::from(11));
+        assert_eq!(steps, 4);
+    }
+
+    #[tokio::test]
+    async fn test_partial_fill_bisection_stops_at_the_deadline() {
+        let step = Duration::from_millis(40);
+        let deadline = tokio::time::Instant::now() + step * 5 / 2;
+        let mut steps = 0;
+        let numerator = bisect_fill(MAX_PARTIAL_ATTEMPTS, Some(deadline), |_| {
+            steps += 1;
+            async move {
+                tokio::time::sleep(step).await;
+                true
+            }
+        })
+        .await;
+        // The third step wouldn't finish in time, so the search stops after
+        // two, keeping the fill they found.
+        assert_eq!(steps, 2);
+        assert_eq!(numerator, U256::from(3) << (MAX_PARTIAL_ATTEMPTS - 3));
+        assert!(tokio::time::Instant::now() < deadline);
+
+        // Steps running into the deadline are abandoned.
+        let deadline = tokio::time::Instant::now() + step;
+        let numerator = bisect_fill(MAX_PARTIAL_ATTEMPTS, Some(deadline), |_| async move {
+            tokio::time::sleep(step * 10).await;
+            true
+        })
+        .await;
+        assert_eq!(numerator, U256::ZERO);
+        assert!(tokio::time::Instant::now() < deadline + step);
+    }
+
+    #[test]
+    fn test_best_output_picks_largest_surviving_route() {
+        let out = |v: u64| Some(U256::from(v));
+        assert_eq!(
+            best_output(&[out(100), None, out(120), out(120)]),
+            Some((2, U256::from(120)))
+        );
+        assert_eq!(best_output(&[None, out(1)]), Some((1, U256::from(1))));
+        assert_eq!(best_output(&[None, None]), None);
+    }
+
+    #[test]
+    fn test_token_filter() {
+        let listed = address!("f939E0A03FB07F59A73314E73794Be0E57ac1b4E");
+        let other = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+
+        let filter = TokenFilter::new(Some(vec![listed]), None, TokenFilter::LpTokens);
+        assert!(filter.accepts(&listed));
+        assert!(!filter.accepts(&other));
+
+        let filter = TokenFilter::new(None, None, TokenFilter::LpTokens);
+        assert!(filter.accepts(&listed));
+        assert!(filter.accepts(&other));
     }
 
     /// Helper to build a minimal Inner for testing pure methods.
     /// Uses dummy URLs that will never be called.
     fn test_inner(slippage_bps: u32, max_quote_deviation_bps: u32) -> Inner {
+        let provider = ethrpc::web3(
+            Default::default(),
+            Default::default(),
+            &"http://localhost:1".parse().unwrap(),
+            "test",
+        )
+        .alloy;
         Inner {
             chain_id: 1,
-            lp_tokens: None,
-            allowed_buy_tokens: None,
-            api_client: api::Client::new("http://localhost:1".parse().unwrap()),
-            price_client: price_api::Client::new("http://localhost:1".parse().unwrap()),
-            provider: ethrpc::web3(
-                Default::default(),
-                Default::default(),
-                &"http://localhost:1".parse().unwrap(),
-                "test",
-            )
-            .alloy,
-            slippage_bps,
+            router: router::deployment(chain::Chain::Mainnet).unwrap(),
+            weth: eth::WethAddress(WETH),
+            price_api_chain: "ethereum".to_string(),
+            lp_tokens: TokenFilter::Any,
+            allowed_buy_tokens: TokenFilter::Any,
+            api_client: api::Client::new("http://localhost:1".parse().unwrap(), vec![]),
+            price_client: price_api::Client::new(
+                "http://localhost:1".parse().unwrap(),
+                vec![],
+                eth::WethAddress(WETH),
+            ),
+            pool_fetcher: boundary::curve::pool::Fetcher::new(provider.clone()),
+            lp_prices: lp_price::Cache::default(),
+            provider,
+            slippage: slippage::Slippage::new(slippage::Config {
+                default_bps: slippage_bps,
+                stable_bps: slippage_bps,
+                max_bps: 10_000 - 1,
+                overrides: HashMap::new(),
+            }),
             max_quote_deviation_bps,
             solution_gas_offset: eth::SignedGas::default(),
+            max_partial_attempts: 5,
             settlement_contract: eth::Address::default(),
+            pools: HashMap::new(),
+            registry: None,
+            buffer_budgets: HashMap::new(),
+            gas: gas::Estimator::default(),
+            feedback: feedback::Feedback::default(),
+            route_cache: route_cache::Cache::default(),
         }
     }
 }
@@ -0,0 +1,657 @@
+//! Coincidence of wants between curve-lp orders of the same auction.
+//!
+//! Sell orders trading the same pair of tokens, in either direction, are
+//! settled together in a single solution with uniform clearing prices. Orders
+//! on opposite sides are netted against each other so that only the residual
+//! of the larger side is routed through Curve, and orders on the same side
+//! share that route. This saves both price impact and gas.
+
+use {
+    super::{EXCHANGE_GAS, Inner, SolveError, slippage},
+    crate::domain::{
+        auction,
+        curve::api,
+        eth,
+        order::{self, Order},
+        solution::{self, Solution},
+    },
+    alloy::primitives::U256,
+    std::collections::HashMap,
+};
+
+/// Minimum number of orders on a pair worth settling together.
+const MIN_BATCH_SIZE: usize = 2;
+/// Maximum number of times the clearing price is recomputed after dropping
+/// orders whose limit price it violates, or after charging the orders fees
+/// for the gas the solution actually uses.
+const MAX_CLEARING_ROUNDS: usize = 3;
+
+/// Splits orders into groups that can be batched, one per token pair, and the
+/// remaining orders that have to be solved individually.
+pub(super) fn partition(
+    orders: Vec<(usize, Order)>,
+) -> (Vec<Vec<(usize, Order)>>, Vec<(usize, Order)>) {
+    let mut groups = HashMap::<_, Vec<_>>::new();
+    let mut singles = Vec::new();
+    for (i, order) in orders {
+        if eligible(&order) {
+            groups.entry(pair(&order)).or_default().push((i, order));
+        } else {
+            singles.push((i, order));
+        }
+    }
+
+    let mut batches = Vec::new();
+    for group in groups.into_values() {
+        if group.len() >= MIN_BATCH_SIZE {
+            batches.push(group);
+        } else {
+            singles.extend(group);
+        }
+    }
+    (batches, singles)
+}
+
+/// Whether an order can be settled as part of a batch. Buy orders need an
+/// exact-out search of their own, and wrappers and flashloans apply to a
+/// whole solution, so those orders are solved individually.
+pub(super) fn eligible(order: &Order) -> bool {
+    order.side == order::Side::Sell && order.wrappers.is_empty() && order.flashloan_hint.is_none()
+}
+
+/// The unordered token pair traded by an order.
+fn pair(order: &Order) -> (eth::TokenAddress, eth::TokenAddress) {
+    let (sell, buy) = (order.sell.token, order.buy.token);
+    if sell < buy { (sell, buy) } else { (buy, sell) }
+}
+
+/// An order taking part in a batch.
+pub(super) struct Leg {
+    pub index: usize,
+    pub order: Order,
+    pub fee: solution::Fee,
+    /// The amount of sell tokens traded, i.e. excluding the fee.
+    pub executed: U256,
+}
+
+impl Leg {
+    /// Charges the order its share of the settlement gas. Gives the order back
+    /// if it is a limit order whose fee cannot be priced, or if the fee
+    /// exceeds its sell amount.
+    fn new(
+        index: usize,
+        order: Order,
+        tokens: &auction::Tokens,
+        gas_cost: eth::Ether,
+    ) -> Result<Self, (usize, Order)> {
+        let fee = if order.solver_determines_fee() {
+            let fee = tokens
+                .reference_price(&order.sell.token)
+                .and_then(|price| price.ether_value(gas_cost));
+            match fee {
+                Some(fee) => solution::Fee::Surplus(eth::SellTokenAmount(fee)),
+                None => return Err((index, order)),
+            }
+        } else {
+            solution::Fee::Protocol
+        };
+        match order
+            .sell
+            .amount
+            .checked_sub(fee.surplus().unwrap_or_default())
+        {
+            Some(executed) if !executed.is_zero() => Ok(Self {
+                index,
+                order,
+                fee,
+                executed,
+            }),
+            _ => Err((index, order)),
+        }
+    }
+}
+
+/// Charges each order an equal share of `gas`. Returns the orders that can
+/// pay their share and the ones that can't. Shares are only right if all
+/// orders can pay them.
+pub(super) fn charge(
+    orders: impl IntoIterator<Item = (usize, Order)>,
+    tokens: &auction::Tokens,
+    gas: eth::Gas,
+    gas_price: &auction::GasPrice,
+) -> (Vec<Leg>, Vec<(usize, Order)>) {
+    let orders = orders.into_iter().collect::<Vec<_>>();
+    let gas_cost =
+        eth::Ether(gas.0.saturating_mul(gas_price.0.0) / U256::from(orders.len().max(1)));
+    let mut legs = Vec::new();
+    let mut excluded = Vec::new();
+    for (index, order) in orders {
+        match Leg::new(index, order, tokens, gas_cost) {
+            Ok(leg) => legs.push(leg),
+            Err(order) => excluded.push(order),
+        }
+    }
+    (legs, excluded)
+}
+
+/// A price of `num / den` buy tokens per sell token.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Price {
+    num: U256,
+    den: U256,
+}
+
+impl Price {
+    /// Whether `self` is at least as good as `other` for the seller.
+    fn at_least(&self, other: &Price) -> bool {
+        self.num.saturating_mul(other.den) >= other.num.saturating_mul(self.den)
+    }
+}
+
+/// The part of the larger side of a batch that is routed through Curve.
+struct Residual {
+    route: api::Route,
+    sell: eth::Asset,
+    /// The slippage-adjusted output of the route.
+    buy: eth::Asset,
+}
+
+/// Uniform clearing prices for a batch.
+struct Clearing {
+    prices: [(eth::TokenAddress, U256); 2],
+    residual: Option<Residual>,
+}
+
+impl Clearing {
+    fn price(&self, token: eth::TokenAddress) -> U256 {
+        self.prices
+            .iter()
+            .find(|(t, _)| *t == token)
+            .map(|(_, price)| *price)
+            .unwrap_or_default()
+    }
+
+    /// The amount of buy tokens a leg receives at the clearing prices.
+    fn output(&self, leg: &Leg) -> Option<U256> {
+        leg.executed
+            .checked_mul(self.price(leg.order.sell.token))?
+            .checked_div(self.price(leg.order.buy.token))
+    }
+
+    /// Whether a leg's limit price is respected.
+    fn satisfies(&self, leg: &Leg) -> bool {
+        self.output(leg)
+            .is_some_and(|output| output >= leg.order.buy.amount)
+    }
+
+    /// Whether the tokens paid out to every leg are covered by the tokens the
+    /// other legs and the residual route bring in.
+    fn is_balanced(&self, legs: &[Leg]) -> bool {
+        let mut balances = HashMap::<eth::TokenAddress, (U256, U256)>::new();
+        for leg in legs {
+            let Some(output) = self.output(leg) else {
+                return false;
+            };
+            let sold = balances.entry(leg.order.sell.token).or_default();
+            sold.0 = sold.0.saturating_add(leg.executed);
+            let bought = balances.entry(leg.order.buy.token).or_default();
+            bought.1 = bought.1.saturating_add(output);
+        }
+        if let Some(residual) = &self.residual {
+            let sold = balances.entry(residual.sell.token).or_default();
+            sold.1 = sold.1.saturating_add(residual.sell.amount);
+            let bought = balances.entry(residual.buy.token).or_default();
+            bought.0 = bought.0.saturating_add(residual.buy.amount);
+        }
+        balances.values().all(|(received, paid)| received >= paid)
+    }
+}
+
+/// Returns the amount of the larger side, selling `sum` in total, that has to
+/// be routed when the `opposite` side is cleared against it at `price`.
+/// Rounds up, as routing more than needed is always safe.
+fn residual_amount(sum: U256, opposite: U256, price: Price) -> U256 {
+    let matched = opposite.saturating_mul(price.den) / price.num;
+    sum.saturating_sub(matched)
+}
+
+impl Inner {
+    /// Settles orders trading the same token pair in a single solution.
+    ///
+    /// Returns the solution, if any, together with the orders it could not
+    /// include, which should be solved individually.
+    pub(super) async fn solve_batch(
+        &self,
+        orders: Vec<(usize, Order)>,
+        tokens: &auction::Tokens,
+        gas_price: &auction::GasPrice,
+    ) -> (Option<Solution>, Vec<(usize, Order)>) {
+        // The gas of the solution depends on the residual route, which is
+        // only known once the batch is cleared. Fees start out from a typical
+        // exchange and are charged again if the solution uses a different
+        // amount of gas or is shared by fewer orders, so that they always
+        // match the gas the solution declares.
+        let mut gas = eth::Gas(U256::from(EXCHANGE_GAS)) + self.solution_gas_offset;
+        let mut sharing = orders.len();
+        let (mut legs, mut excluded) = charge(orders, tokens, gas, gas_price);
+
+        for _ in 0..MAX_CLEARING_ROUNDS {
+            if legs.len() < MIN_BATCH_SIZE {
+                break;
+            }
+
+            let clearing = match self.clear(&legs, tokens).await {
+                Ok(clearing) => clearing,
+                Err(err) => {
+                    tracing::debug!(?err, orders = legs.len(), "failed to clear batch");
+                    break;
+                }
+            };
+
+            let (kept, dropped): (Vec<_>, Vec<_>) =
+                legs.into_iter().partition(|leg| clearing.satisfies(leg));
+            legs = kept;
+            if !dropped.is_empty() {
+                excluded.extend(dropped.into_iter().map(|leg| (leg.index, leg.order)));
+                continue;
+            }
+            if !clearing.is_balanced(&legs) {
+                tracing::debug!(orders = legs.len(), "batch clearing is not balanced");
+                break;
+            }
+
+            let (interactions, solution_gas) = self.batch_interactions(&clearing);
+            if solution_gas.0 != gas.0 || legs.len() != sharing {
+                gas = solution_gas;
+                sharing = legs.len();
+                let (charged, uncharged) = charge(
+                    legs.into_iter().map(|leg| (leg.index, leg.order)),
+                    tokens,
+                    gas,
+                    gas_price,
+                );
+                legs = charged;
+                excluded.extend(uncharged);
+                continue;
+            }
+            match self.batch_solution(clearing, interactions, &legs, gas) {
+                Some(solution) => {
+                    tracing::debug!(
+                        orders = legs.len(),
+                        excluded = excluded.len(),
+                        "batched orders"
+                    );
+                    return (Some(solution), excluded);
+                }
+                None => break,
+            }
+        }
+
+        excluded.extend(legs.into_iter().map(|leg| (leg.index, leg.order)));
+        (None, excluded)
+    }
+
+    /// Computes uniform clearing prices for the legs, which all trade the same
+    /// token pair.
+    async fn clear(&self, legs: &[Leg], tokens: &auction::Tokens) -> Result<Clearing, SolveError> {
+        let (a, b) = pair(&legs[0].order);
+        let total = |token| {
+            legs.iter()
+                .filter(|leg| leg.order.sell.token == token)
+                .map(|leg| leg.executed)
+                .fold(U256::ZERO, U256::saturating_add)
+        };
+        let (sum_a, sum_b) = (total(a), total(b));
+
+        // Price each side as if it were routed entirely. This is the worst
+        // price either side can get, so it bounds the residual from above.
+        let (out_a, out_b) = tokio::join!(
+            self.quoted_output(a, b, sum_a, tokens),
+            self.quoted_output(b, a, sum_b, tokens),
+        );
+
+        let excess = match (out_a, out_b) {
+            (Ok(out_a), _) if out_a > sum_b => Some((a, b, sum_a, sum_b, out_a)),
+            (_, Ok(out_b)) if out_b > sum_a => Some((b, a, sum_b, sum_a, out_b)),
+            (Ok(_), Ok(_)) => None,
+            (Err(err), _) | (_, Err(err)) => return Err(err),
+        };
+
+        let Some((sell, buy, sum, opposite, full_output)) = excess else {
+            // Both sides cover each other at their worst prices, so they can
+            // be cleared entirely against each other.
+            return Ok(Clearing {
+                prices: [(a, sum_b), (b, sum_a)],
+                residual: None,
+            });
+        };
+
+        // Route the residual at the worst price, and then try once more at
+        // the better price the smaller residual actually gets. Output is
+        // concave, so a price is feasible when the route for the residual it
+        // implies quotes at least that price.
+        let worst = Price {
+            num: full_output,
+            den: sum,
+        };
+        let amount = residual_amount(sum, opposite, worst);
+        let (route, output) = self.verified_route(sell, buy, amount, tokens).await?;
+        let price = Price {
+            num: output,
+            den: amount,
+        };
+        if !price.at_least(&worst) {
+            return Err(SolveError::InsufficientOutput {
+                min_output: output,
+                required: amount.saturating_mul(worst.num) / worst.den,
+            });
+        }
+
+        let refined_amount = residual_amount(sum, opposite, price);
+        let (price, amount, route, output) = if refined_amount == amount {
+            (price, amount, route, output)
+        } else {
+            match self.verified_route(sell, buy, refined_amount, tokens).await {
+                Ok((refined_route, refined_output))
+                    if Price {
+                        num: refined_output,
+                        den: refined_amount,
+                    }
+                    .at_least(&price) =>
+                {
+                    (price, refined_amount, refined_route, refined_output)
+                }
+                result => {
+                    if let Err(err) = result {
+                        tracing::debug!(?err, "failed to refine batch clearing price");
+                    }
+                    (worst, amount, route, output)
+                }
+            }
+        };
+
+        Ok(Clearing {
+            prices: [(sell, price.num), (buy, price.den)],
+            residual: Some(Residual {
+                route,
+                sell: eth::Asset {
+                    token: sell,
+                    amount,
+                },
+                buy: eth::Asset {
+                    token: buy,
+                    amount: output,
+                },
+            }),
+        })
+    }
+
+    /// The slippage-adjusted output of the best quoted route, without
+    /// on-chain verification.
+    async fn quoted_output(
+        &self,
+        sell: eth::TokenAddress,
+        buy: eth::TokenAddress,
+        amount: U256,
+        tokens: &auction::Tokens,
+    ) -> Result<U256, SolveError> {
+        if amount.is_zero() {
+            return Ok(U256::ZERO);
+        }
+        let decimals = |token| tokens.get(&token).and_then(|t| t.decimals).unwrap_or(18);
+        let (routes, depth) = tokio::join!(
+            self.routes(sell, buy, amount, decimals(sell), decimals(buy)),
+            self.depth(sell, buy),
+        );
+        let output = routes?
+            .iter()
+            .map(|route| {
+                let slippage_bps = self.slippage_bps(&slippage::Trade {
+                    sell,
+                    buy,
+                    input: amount,
+                    route,
+                    depth,
+                });
+                self.apply_slippage(route.expected_output, slippage_bps)
+            })
+            .max()
+            .unwrap_or_default();
+        Ok(output)
+    }
+
+    /// Finds the best route for `amount` after verifying the candidates
+    /// on-chain. Returns it with its slippage-adjusted on-chain output.
+    async fn verified_route(
+        &self,
+        sell: eth::TokenAddress,
+        buy: eth::TokenAddress,
+        amount: U256,
+        tokens: &auction::Tokens,
+    ) -> Result<(api::Route, U256), SolveError> {
+        let decimals = |token| tokens.get(&token).and_then(|t| t.decimals).unwrap_or(18);
+        let (routes, depth) = tokio::join!(
+            self.routes(sell, buy, amount, decimals(sell), decimals(buy)),
+            self.depth(sell, buy),
+        );
+        let (route, onchain_output) = self.select_route(routes?, amount).await?;
+        let slippage_bps = self.slippage_bps(&slippage::Trade {
+            sell,
+            buy,
+            input: amount,
+            route: &route,
+            depth,
+        });
+        Ok((route, self.apply_slippage(onchain_output, slippage_bps)))
+    }
+
+    /// The interactions settling a batch, i.e. routing its residual if there
+    /// is one, together with the gas of the whole solution.
+    fn batch_interactions(&self, clearing: &Clearing) -> (Vec<solution::Interaction>, eth::Gas) {
+        match &clearing.residual {
+            Some(residual) => {
+                let (interaction, gas) =
+                    self.swap_interaction(&residual.route, residual.sell, residual.buy);
+                (vec![solution::Interaction::Custom(interaction)], gas)
+            }
+            None => (vec![], eth::Gas(U256::ZERO) + self.solution_gas_offset),
+        }
+    }
+
+    fn batch_solution(
+        &self,
+        clearing: Clearing,
+        interactions: Vec<solution::Interaction>,
+        legs: &[Leg],
+        gas: eth::Gas,
+    ) -> Option<Solution> {
+        let trades = legs
+            .iter()
+            .map(|leg| {
+                solution::Fulfillment::new(leg.order.clone(), leg.executed, leg.fee)
+                    .map(solution::Trade::Fulfillment)
+            })
+            .collect::<Option<_>>()?;
+
+        Some(Solution {
+            id: solution::Id(legs.iter().map(|leg| leg.index).min()? as u64),
+            prices: solution::ClearingPrices::new(clearing.prices),
+            trades,
+            interactions,
+            gas: Some(gas),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloy::primitives::Address};
+
+    fn token(byte: u8) -> eth::TokenAddress {
+        eth::TokenAddress(Address::repeat_byte(byte))
+    }
+
+    fn order(sell: u8, buy: u8, sell_amount: u64, buy_amount: u64) -> Order {
+        Order {
+            uid: order::Uid([sell; 56]),
+            sell: eth::Asset {
+                token: token(sell),
+                amount: U256::from(sell_amount),
+            },
+            buy: eth::Asset {
+                token: token(buy),
+                amount: U256::from(buy_amount),
+            },
+            side: order::Side::Sell,
+            class: order::Class::Market,
+            partially_fillable: false,
+            flashloan_hint: None,
+            wrappers: vec![],
+        }
+    }
+
+    fn leg(index: usize, order: Order) -> Leg {
+        Leg {
+            index,
+            executed: order.sell.amount,
+            order,
+            fee: solution::Fee::Protocol,
+        }
+    }
+
+    #[test]
+    fn partitions_by_pair() {
+        let mut buy = order(1, 2, 100, 100);
+        buy.side = order::Side::Buy;
+        let orders = vec![
+            (0, order(1, 2, 100, 100)),
+            (1, order(2, 1, 100, 100)),
+            (2, order(1, 3, 100, 100)),
+            (3, buy),
+        ];
+
+        let (batches, singles) = partition(orders);
+        assert_eq!(batches.len(), 1);
+        let mut batched: Vec<_> = batches[0].iter().map(|(i, _)| *i).collect();
+        batched.sort();
+        assert_eq!(batched, vec![0, 1]);
+        let mut singles: Vec<_> = singles.iter().map(|(i, _)| *i).collect();
+        singles.sort();
+        assert_eq!(singles, vec![2, 3]);
+    }
+
+    #[test]
+    fn residual_nets_opposite_side() {
+        // 1000 A against 300 B at 0.5 B per A: 600 A are matched.
+        let price = Price {
+            num: U256::from(1),
+            den: U256::from(2),
+        };
+        assert_eq!(
+            residual_amount(U256::from(1_000), U256::from(300), price),
+            U256::from(400)
+        );
+        assert_eq!(
+            residual_amount(U256::from(500), U256::from(300), price),
+            U256::ZERO
+        );
+    }
+
+    #[test]
+    fn clearing_respects_limits_and_balances() {
+        let (a, b) = (token(1), token(2));
+        let legs = vec![
+            leg(0, order(1, 2, 1_000, 450)),
+            leg(1, order(2, 1, 300, 500)),
+        ];
+        // 400 A are routed for 196 B; A sellers get 0.49 B per A.
+        let clearing = Clearing {
+            prices: [(a, U256::from(49)), (b, U256::from(100))],
+            residual: Some(Residual {
+                route: api::Route {
+                    route: [Address::ZERO; 11],
+                    swap_params: [[0; 5]; 5],
+                    pools: [Address::ZERO; 5],
+                    expected_output: U256::from(200),
+                },
+                sell: eth::Asset {
+                    token: a,
+                    amount: U256::from(400),
+                },
+                buy: eth::Asset {
+                    token: b,
+                    amount: U256::from(196),
+                },
+            }),
+        };
+
+        assert_eq!(clearing.output(&legs[0]), Some(U256::from(490)));
+        assert_eq!(clearing.output(&legs[1]), Some(U256::from(612)));
+        assert!(legs.iter().all(|leg| clearing.satisfies(leg)));
+        // B: 300 + 196 in, 490 out. A: 1000 in, 612 + 400 out.
+        assert!(!clearing.is_balanced(&legs));
+
+        let clearing = Clearing {
+            prices: [(a, U256::from(49)), (b, U256::from(100))],
+            residual: Some(Residual {
+                sell: eth::Asset {
+                    token: a,
+                    amount: U256::from(388),
+                },
+                ..clearing.residual.unwrap()
+            }),
+        };
+        assert!(clearing.is_balanced(&legs));
+    }
+
+    #[test]
+    fn charges_equal_shares_of_gas() {
+        // Token 1 trades one to one with ETH.
+        let tokens = auction::Tokens(
+            [(
+                token(1),
+                auction::Token {
+                    decimals: Some(18),
+                    symbol: None,
+                    reference_price: Some(auction::Price(eth::Ether(U256::from(10_u128.pow(18))))),
+                    available_balance: U256::ZERO,
+                    trusted: false,
+                },
+            )]
+            .into(),
+        );
+        let limit = |sell_amount| Order {
+            class: order::Class::Limit,
+            ..order(1, 2, sell_amount, 1)
+        };
+        let orders = vec![(0, limit(1_000)), (1, limit(500)), (2, limit(400))];
+        let gas_price = auction::GasPrice(eth::Ether(U256::from(1)));
+
+        // 1500 gas at 1 wei are shared by three orders. Only the first one
+        // is left with anything to trade after paying its share of 500 wei.
+        let (legs, excluded) = charge(orders, &tokens, eth::Gas(U256::from(1_500)), &gas_price);
+        assert_eq!(
+            legs.iter()
+                .map(|leg| (leg.index, leg.executed))
+                .collect::<Vec<_>>(),
+            [(0, U256::from(500))]
+        );
+        assert_eq!(excluded.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [1, 2]);
+    }
+
+    #[test]
+    fn price_comparison() {
+        let half = Price {
+            num: U256::from(1),
+            den: U256::from(2),
+        };
+        let third = Price {
+            num: U256::from(1),
+            den: U256::from(3),
+        };
+        assert!(half.at_least(&third));
+        assert!(!third.at_least(&half));
+        assert!(half.at_least(&half));
+    }
+}
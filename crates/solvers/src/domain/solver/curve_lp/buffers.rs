@@ -0,0 +1,300 @@
+//! Use of the settlement contract's token buffers by curve-lp solutions.
+//!
+//! Interactions whose inputs are trusted tokens and whose outputs the
+//! settlement contract holds are internalized, like the baseline solver does.
+//! Solutions of a single order with a single interaction whose outputs also
+//! fit the buffer budget configured for their tokens are instead filled from
+//! the buffers directly, dropping the interaction altogether and charging only
+//! for the gas that is left. Buffers are tracked across all solutions of an
+//! auction so that merging them cannot overdraw the settlement contract or a
+//! budget.
+
+use {
+    super::Inner,
+    crate::domain::{
+        auction,
+        eth,
+        solution::{self, Solution},
+    },
+    std::{collections::HashMap, sync::Mutex},
+};
+
+/// The buffers left for the solutions of an auction.
+pub(super) struct Buffers(Mutex<HashMap<eth::TokenAddress, Buffer>>);
+
+#[derive(Clone, Copy)]
+struct Buffer {
+    /// The balance of the settlement contract.
+    available: eth::U256,
+    /// What is left of the budget for direct fills, `None` if the token can't
+    /// be used for them.
+    budget: Option<eth::U256>,
+}
+
+/// How a solution uses the buffers of a token.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Use {
+    Internalize,
+    Fill,
+}
+
+impl Buffers {
+    pub fn new(tokens: &auction::Tokens, budgets: &HashMap<eth::TokenAddress, eth::U256>) -> Self {
+        let buffers = tokens
+            .0
+            .iter()
+            .filter(|(_, token)| !token.available_balance.is_zero())
+            .map(|(address, token)| {
+                let buffer = Buffer {
+                    available: token.available_balance,
+                    budget: budgets.get(address).copied(),
+                };
+                (*address, buffer)
+            })
+            .collect();
+        Self(Mutex::new(buffers))
+    }
+
+    /// Reserves buffers for all of `assets`, or for none of them if they
+    /// don't suffice.
+    fn reserve(&self, assets: &[eth::Asset], use_: Use) -> bool {
+        let Ok(mut buffers) = self.0.lock() else {
+            return false;
+        };
+        let mut reserved = HashMap::new();
+        for asset in assets {
+            let Some(buffer) = reserved.get(&asset.token).or(buffers.get(&asset.token)) else {
+                return false;
+            };
+            let Some(available) = buffer.available.checked_sub(asset.amount) else {
+                return false;
+            };
+            let budget = match (use_, buffer.budget) {
+                (Use::Internalize, budget) => budget,
+                (Use::Fill, Some(budget)) => match budget.checked_sub(asset.amount) {
+                    Some(budget) => Some(budget),
+                    None => return false,
+                },
+                (Use::Fill, None) => return false,
+            };
+            reserved.insert(asset.token, Buffer { available, budget });
+        }
+        buffers.extend(reserved);
+        true
+    }
+}
+
+impl Inner {
+    /// Lets a solution use the settlement contract's buffers where possible,
+    /// see the module documentation.
+    pub(super) fn use_buffers(
+        &self,
+        mut solution: Solution,
+        buffers: &Buffers,
+        tokens: &auction::Tokens,
+    ) -> Solution {
+        let trusted = |assets: &[eth::Asset]| {
+            assets.iter().all(|asset| {
+                matches!(
+                    tokens.get(&asset.token),
+                    Some(auction::Token { trusted: true, .. })
+                )
+            })
+        };
+
+        if let Some(filled) = fill(&solution, self.solution_gas_offset)
+            && trusted(&filled.inputs)
+            && buffers.reserve(&filled.outputs, Use::Fill)
+        {
+            tracing::debug!(
+                solution_id = solution.id.0,
+                "filling solution from settlement buffers"
+            );
+            return filled.solution;
+        }
+
+        for interaction in &mut solution.interactions {
+            let solution::Interaction::Custom(interaction) = interaction else {
+                continue;
+            };
+            if interaction.inputs.is_empty()
+                || interaction.outputs.is_empty()
+                || !trusted(&interaction.inputs)
+            {
+                continue;
+            }
+            if buffers.reserve(&interaction.outputs, Use::Internalize) {
+                interaction.internalize = true;
+            }
+        }
+        solution
+    }
+}
+
+/// A solution filled from the buffers instead of swapping on Curve.
+struct Filled {
+    solution: Solution,
+    /// The tokens the buffers receive.
+    inputs: Vec<eth::Asset>,
+    /// The tokens the buffers pay out.
+    outputs: Vec<eth::Asset>,
+}
+
+/// Fills the order of a single order solution swapping through a single
+/// interaction from the buffers instead. Only the `gas` of the settlement
+/// without the interaction is declared, and the surplus fee, which is linear
+/// in the declared gas, is scaled down accordingly.
+fn fill(solution: &Solution, gas: eth::Gas) -> Option<Filled> {
+    let ([solution::Interaction::Custom(interaction)], [solution::Trade::Fulfillment(fulfillment)]) =
+        (solution.interactions.as_slice(), solution.trades.as_slice())
+    else {
+        return None;
+    };
+    let (input, min_output) = match (&interaction.inputs[..], &interaction.outputs[..]) {
+        ([input], [output]) => (input.amount, output.amount),
+        _ => return None,
+    };
+    let fee = match (fulfillment.surplus_fee(), solution.gas) {
+        (Some(fee), Some(routed)) => fee.amount.checked_mul(gas.0)?.checked_div(routed.0)?,
+        _ => eth::U256::ZERO,
+    };
+    let (filled, _) =
+        super::single_solution(fulfillment.order(), input, min_output, Vec::new(), gas, fee)
+            .ok()?;
+    Some(Filled {
+        solution: filled.with_id(solution.id),
+        inputs: interaction.inputs.clone(),
+        outputs: interaction.outputs.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::domain::order::{self, Order},
+        alloy::primitives::Address,
+    };
+
+    fn token(byte: u8) -> eth::TokenAddress {
+        eth::TokenAddress(Address::repeat_byte(byte))
+    }
+
+    fn asset(byte: u8, amount: u64) -> eth::Asset {
+        eth::Asset {
+            token: token(byte),
+            amount: eth::U256::from(amount),
+        }
+    }
+
+    fn buffers(balances: &[(u8, u64)], budgets: &[(u8, u64)]) -> Buffers {
+        let tokens = auction::Tokens(
+            balances
+                .iter()
+                .map(|(byte, balance)| {
+                    let token = auction::Token {
+                        decimals: Some(18),
+                        symbol: None,
+                        reference_price: None,
+                        available_balance: eth::U256::from(*balance),
+                        trusted: true,
+                    };
+                    (self::token(*byte), token)
+                })
+                .collect(),
+        );
+        let budgets = budgets
+            .iter()
+            .map(|(byte, budget)| (token(*byte), eth::U256::from(*budget)))
+            .collect();
+        Buffers::new(&tokens, &budgets)
+    }
+
+    #[test]
+    fn internalizations_share_the_available_balance() {
+        let buffers = buffers(&[(1, 100)], &[]);
+        assert!(buffers.reserve(&[asset(1, 60)], Use::Internalize));
+        assert!(!buffers.reserve(&[asset(1, 60)], Use::Internalize));
+        assert!(buffers.reserve(&[asset(1, 40)], Use::Internalize));
+        assert!(!buffers.reserve(&[asset(2, 1)], Use::Internalize));
+    }
+
+    #[test]
+    fn fills_are_limited_by_the_budget() {
+        let buffers = buffers(&[(1, 100), (2, 100)], &[(1, 50)]);
+        // Tokens without a budget can't be used for fills.
+        assert!(!buffers.reserve(&[asset(2, 10)], Use::Fill));
+        assert!(buffers.reserve(&[asset(1, 30)], Use::Fill));
+        assert!(!buffers.reserve(&[asset(1, 30)], Use::Fill));
+        // Internalizations don't count against the budget, but fills are
+        // still bounded by the balance.
+        assert!(buffers.reserve(&[asset(1, 60)], Use::Internalize));
+        assert!(!buffers.reserve(&[asset(1, 20)], Use::Fill));
+        assert!(buffers.reserve(&[asset(1, 10)], Use::Fill));
+    }
+
+    #[test]
+    fn reservations_are_all_or_nothing() {
+        let buffers = buffers(&[(1, 100), (2, 100)], &[]);
+        assert!(!buffers.reserve(
+            &[asset(1, 50), asset(2, 50), asset(1, 60)],
+            Use::Internalize
+        ));
+        assert!(buffers.reserve(&[asset(1, 100), asset(2, 100)], Use::Internalize));
+    }
+
+    #[test]
+    fn fills_charge_for_the_declared_gas() {
+        let order = Order {
+            uid: order::Uid([0u8; 56]),
+            sell: asset(1, 1_000_000),
+            buy: asset(2, 400_000),
+            side: order::Side::Sell,
+            class: order::Class::Limit,
+            partially_fillable: false,
+            flashloan_hint: None,
+            wrappers: vec![],
+        };
+        let interaction = solution::CustomInteraction {
+            target: Address::repeat_byte(3),
+            value: eth::Ether(eth::U256::ZERO),
+            calldata: Vec::new(),
+            internalize: false,
+            inputs: vec![asset(1, 1_000_000)],
+            outputs: vec![asset(2, 500_000)],
+            allowances: Vec::new(),
+        };
+        let (routed, _) = super::super::single_solution(
+            &order,
+            eth::U256::from(1_000_000),
+            eth::U256::from(500_000),
+            vec![solution::Interaction::Custom(interaction)],
+            eth::Gas(eth::U256::from(200_000)),
+            eth::U256::from(1_000),
+        )
+        .unwrap();
+
+        let filled = fill(&routed, eth::Gas(eth::U256::from(50_000))).unwrap();
+        assert_eq!(filled.inputs[0].amount, eth::U256::from(1_000_000));
+        assert_eq!(filled.outputs[0].amount, eth::U256::from(500_000));
+
+        let solution = filled.solution;
+        assert!(solution.interactions.is_empty());
+        assert_eq!(solution.gas.unwrap().0, eth::U256::from(50_000));
+        let [solution::Trade::Fulfillment(fulfillment)] = solution.trades.as_slice() else {
+            panic!("expected a single fulfillment");
+        };
+        // A quarter of the gas is declared, so a quarter of the fee is charged.
+        assert_eq!(
+            fulfillment.surplus_fee().unwrap().amount,
+            eth::U256::from(250)
+        );
+        // The clearing prices pay out the swap output for the sell amount
+        // without the fee.
+        assert_eq!(
+            solution.prices.0[&token(1)],
+            eth::U256::from(999_750u64 * 500_000 / 1_000_000)
+        );
+        assert_eq!(solution.prices.0[&token(2)], eth::U256::from(999_750));
+    }
+}
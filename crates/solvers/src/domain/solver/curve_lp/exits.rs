@@ -0,0 +1,322 @@
+//! Balanced exits of curve-lp orders selling the same LP token.
+//!
+//! Sell orders of the LP token of a locally priced pool that together buy
+//! every coin of the pool can be settled with a single balanced
+//! `remove_liquidity`. It pays out all coins in proportion to the pool's
+//! balances without the fee of single coin exits, and costs the gas of one
+//! pool call for all orders. Each coin is shared among the orders buying it at
+//! a uniform clearing price. Exits are proposed in addition to the orders' own
+//! solutions, so they only win when they are better for the orders.
+
+use {
+    super::{Inner, POOL_FETCH_TIMEOUT, batch, slippage},
+    crate::{
+        boundary::curve::{interactions, liquidity},
+        domain::{
+            auction,
+            eth,
+            order::Order,
+            solution::{self, Solution},
+        },
+    },
+    alloy::primitives::U256,
+    std::collections::{HashMap, HashSet},
+};
+
+/// Maximum number of times an exit is cleared again after dropping orders
+/// whose limit price it violates.
+const MAX_CLEARING_ROUNDS: usize = 3;
+
+/// Groups the orders that may exit a pool together by the token they sell.
+/// Only groups buying at least two different tokens are returned.
+pub(super) fn partition(orders: &[(usize, Order)]) -> Vec<Vec<(usize, Order)>> {
+    let mut groups = HashMap::<_, Vec<_>>::new();
+    for (i, order) in orders {
+        if batch::eligible(order) {
+            groups
+                .entry(order.sell.token)
+                .or_default()
+                .push((*i, order.clone()));
+        }
+    }
+    groups
+        .into_values()
+        .filter(|group| {
+            let coins = group
+                .iter()
+                .map(|(_, order)| order.buy.token)
+                .collect::<HashSet<_>>();
+            coins.len() >= 2
+        })
+        .collect()
+}
+
+/// Uniform clearing prices paying each of the `outputs` out to the legs
+/// buying it, in proportion to the LP tokens they sell.
+fn prices(
+    lp_token: eth::TokenAddress,
+    legs: &[batch::Leg],
+    outputs: &[eth::Asset],
+) -> Option<HashMap<eth::TokenAddress, U256>> {
+    // Scaling the LP token price up keeps the rounding of coin prices small.
+    let scale = outputs
+        .iter()
+        .map(|output| output.amount)
+        .max()?
+        .checked_mul(U256::from(10u64.pow(18)))?;
+    let mut prices = HashMap::from([(lp_token, scale)]);
+    for output in outputs {
+        let sold = legs
+            .iter()
+            .filter(|leg| leg.order.buy.token == output.token)
+            .map(|leg| leg.executed)
+            .fold(U256::ZERO, U256::saturating_add);
+        if sold.is_zero() || output.amount.is_zero() {
+            return None;
+        }
+        // Rounding up never pays out more of a coin than the exit receives.
+        let price = sold.checked_mul(scale)?.div_ceil(output.amount);
+        prices.insert(output.token, price);
+    }
+    Some(prices)
+}
+
+/// The amount of buy tokens a leg receives at the clearing prices.
+fn output(prices: &HashMap<eth::TokenAddress, U256>, leg: &batch::Leg) -> Option<U256> {
+    leg.executed
+        .checked_mul(*prices.get(&leg.order.sell.token)?)?
+        .checked_div(*prices.get(&leg.order.buy.token)?)
+}
+
+impl Inner {
+    /// Settles orders selling the LP token of a pool for its coins with a
+    /// balanced exit, see the module documentation.
+    pub(super) async fn solve_exit(
+        &self,
+        id: solution::Id,
+        orders: Vec<(usize, Order)>,
+        tokens: &auction::Tokens,
+        gas_price: &auction::GasPrice,
+    ) -> Option<Solution> {
+        let lp_token = orders.first()?.1.sell.token;
+        let metadata = self.pool(lp_token)?;
+        let pool = match tokio::time::timeout(
+            POOL_FETCH_TIMEOUT,
+            self.pool_fetcher.fetch(&metadata),
+        )
+        .await
+        {
+            Ok(Ok(pool)) => pool,
+            Ok(Err(err)) => {
+                tracing::debug!(?err, pool = ?metadata.address, "failed to fetch pool to exit");
+                return None;
+            }
+            Err(_) => {
+                tracing::debug!(pool = ?metadata.address, "fetching pool to exit timed out");
+                return None;
+            }
+        };
+        if pool
+            .coins
+            .iter()
+            .any(|coin| coin.0 == liquidity::NATIVE_ETH)
+        {
+            return None;
+        }
+
+        let gas = eth::Gas(U256::from(liquidity::remove_liquidity_gas(
+            metadata.kind,
+            pool.coins.len(),
+        ))) + self.solution_gas_offset;
+        let orders = orders
+            .into_iter()
+            .filter(|(_, order)| pool.coins.contains(&order.buy.token));
+        let (mut legs, _) = batch::charge(orders, tokens, gas, gas_price);
+
+        for _ in 0..MAX_CLEARING_ROUNDS {
+            // All coins are paid out, so each of them needs a buyer.
+            if !pool
+                .coins
+                .iter()
+                .all(|coin| legs.iter().any(|leg| leg.order.buy.token == *coin))
+            {
+                return None;
+            }
+
+            let burn = legs
+                .iter()
+                .map(|leg| leg.executed)
+                .fold(U256::ZERO, U256::saturating_add);
+            let depth = slippage::Depth {
+                lp_token,
+                supply: pool.total_supply(),
+            };
+            let outputs = pool
+                .coins
+                .iter()
+                .zip(pool.remove_liquidity(burn)?)
+                .map(|(coin, amount)| {
+                    let route = pool.route(lp_token, *coin, burn)?;
+                    let slippage_bps = self.slippage_bps(&slippage::Trade {
+                        sell: lp_token,
+                        buy: *coin,
+                        input: burn,
+                        route: &route,
+                        depth: Some(depth),
+                    });
+                    Some(eth::Asset {
+                        token: *coin,
+                        amount: self.apply_slippage(amount, slippage_bps),
+                    })
+                })
+                .collect::<Option<Vec<_>>>()?;
+            let prices = prices(lp_token, &legs, &outputs)?;
+
+            let sharing = legs.len();
+            legs.retain(|leg| output(&prices, leg).is_some_and(|out| out >= leg.order.buy.amount));
+            if legs.len() != sharing {
+                // Fewer orders share the gas of the exit.
+                (legs, _) = batch::charge(
+                    legs.into_iter().map(|leg| (leg.index, leg.order)),
+                    tokens,
+                    gas,
+                    gas_price,
+                );
+                continue;
+            }
+
+            let interaction =
+                interactions::build_remove_liquidity_interaction(&metadata, burn, outputs)?;
+            let trades = legs
+                .iter()
+                .map(|leg| {
+                    solution::Fulfillment::new(leg.order.clone(), leg.executed, leg.fee)
+                        .map(solution::Trade::Fulfillment)
+                })
+                .collect::<Option<_>>()?;
+            tracing::debug!(pool = ?metadata.address, orders = legs.len(), "balanced exit");
+            return Some(Solution {
+                id,
+                prices: solution::ClearingPrices::new(prices),
+                trades,
+                interactions: vec![solution::Interaction::Custom(interaction)],
+                gas: Some(gas),
+                ..Default::default()
+            });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::domain::order,
+        alloy::primitives::Address,
+    };
+
+    fn token(byte: u8) -> eth::TokenAddress {
+        eth::TokenAddress(Address::repeat_byte(byte))
+    }
+
+    fn asset(byte: u8, amount: u64) -> eth::Asset {
+        eth::Asset {
+            token: token(byte),
+            amount: U256::from(amount),
+        }
+    }
+
+    fn order(sell: u8, buy: u8, sell_amount: u64, buy_amount: u64) -> Order {
+        Order {
+            uid: order::Uid([buy; 56]),
+            sell: eth::Asset {
+                token: token(sell),
+                amount: U256::from(sell_amount),
+            },
+            buy: eth::Asset {
+                token: token(buy),
+                amount: U256::from(buy_amount),
+            },
+            side: order::Side::Sell,
+            class: order::Class::Market,
+            partially_fillable: false,
+            flashloan_hint: None,
+            wrappers: vec![],
+        }
+    }
+
+    fn leg(index: usize, order: Order) -> batch::Leg {
+        batch::Leg {
+            index,
+            executed: order.sell.amount,
+            order,
+            fee: solution::Fee::Protocol,
+        }
+    }
+
+    #[test]
+    fn partitions_by_sold_token() {
+        let orders = vec![
+            (0, order(1, 2, 100, 100)),
+            (1, order(1, 3, 100, 100)),
+            (2, order(1, 3, 100, 100)),
+            (3, order(4, 2, 100, 100)),
+            (4, order(4, 2, 100, 100)),
+        ];
+
+        let groups = partition(&orders);
+        assert_eq!(groups.len(), 1);
+        let mut grouped: Vec<_> = groups[0].iter().map(|(i, _)| *i).collect();
+        grouped.sort();
+        assert_eq!(grouped, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn shares_coins_in_proportion_to_lp_sold() {
+        let lp = token(1);
+        let legs = vec![
+            leg(0, order(1, 2, 300, 200)),
+            leg(1, order(1, 2, 100, 50)),
+            leg(2, order(1, 3, 600, 1_300)),
+        ];
+        // Burning 1000 LP pays out 500 of coin 2 and 1200 of coin 3.
+        let outputs = [asset(2, 500), asset(3, 1_200)];
+
+        let clearing = prices(lp, &legs, &outputs).unwrap();
+        let paid = legs
+            .iter()
+            .map(|leg| output(&clearing, leg).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(paid, [U256::from(375), U256::from(125), U256::from(1_200)]);
+        // The first two orders get their limit, the third doesn't.
+        let satisfied = legs
+            .iter()
+            .zip(&paid)
+            .map(|(leg, paid)| *paid >= leg.order.buy.amount)
+            .collect::<Vec<_>>();
+        assert_eq!(satisfied, [true, true, false]);
+
+        // Coins without buyers can't be paid out.
+        assert!(prices(lp, &legs[..2], &outputs).is_none());
+    }
+
+    #[test]
+    fn never_pays_out_more_than_the_exit_receives() {
+        let lp = token(1);
+        let legs = vec![
+            leg(0, order(1, 2, 1, 0)),
+            leg(1, order(1, 2, 1, 0)),
+            leg(2, order(1, 3, 1, 0)),
+        ];
+        let outputs = [asset(2, 7), asset(3, 5)];
+
+        let clearing = prices(lp, &legs, &outputs).unwrap();
+        let paid = legs
+            .iter()
+            .map(|leg| output(&clearing, leg).unwrap())
+            .collect::<Vec<_>>();
+        assert!(paid[0] + paid[1] <= U256::from(7));
+        assert!(paid[2] <= U256::from(5));
+    }
+}
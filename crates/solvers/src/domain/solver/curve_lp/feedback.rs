@@ -0,0 +1,363 @@
+//! Learning from the outcome of curve-lp solutions.
+//!
+//! Notifications only identify a solution by its auction and solution ID, so
+//! the pools and tokens used by recent solutions are remembered. Pools of
+//! settlements that reverted are blocklisted for a while, and tokens whose
+//! solutions repeatedly fail simulation are traded with a wider slippage
+//! tolerance until they settle successfully again.
+
+use {
+    crate::{
+        boundary::curve::router,
+        domain::{
+            curve::api,
+            eth,
+            notification,
+            solution::{self, Solution},
+        },
+    },
+    std::{
+        collections::{HashMap, VecDeque},
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// Number of recent solutions remembered for notifications.
+const MAX_TRACKED_SOLUTIONS: usize = 1_024;
+/// How long pools of reverted settlements are excluded from routing.
+const POOL_BLOCKLIST_DURATION: Duration = Duration::from_secs(30 * 60);
+/// Simulation failures older than this are forgotten.
+const FAILURE_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// Number of simulation failures of a token before its slippage is widened.
+const FAILURES_BEFORE_WIDENING: u32 = 2;
+/// Slippage added per simulation failure beyond the threshold.
+const SLIPPAGE_STEP_BPS: u32 = 25;
+/// Upper bound on the slippage added for a token.
+const MAX_EXTRA_SLIPPAGE_BPS: u32 = 100;
+
+/// What a solution traded through.
+#[derive(Clone, Debug, Default)]
+pub(super) struct Used {
+    /// Router routes of the solution's exchanges.
+    pub routes: Vec<api::Route>,
+    /// Pools traded through, either by the routes or called directly.
+    pub pools: Vec<eth::Address>,
+    /// LP and pool tokens traded by interactions that aren't internalized.
+    pub tokens: Vec<eth::TokenAddress>,
+    /// The gas estimate of the solution, `None` for merged solutions.
+    pub gas: Option<eth::Gas>,
+    /// Whether some interactions are internalized, so that the gas the
+    /// settlement used depends on whether the driver executed them.
+    pub internalized: bool,
+}
+
+impl Used {
+    /// Extracts the routes, pools and tokens of a solution's interactions.
+    /// The quote token most pairs share, WETH, is left out of the tokens so
+    /// that failures of one pair don't widen the slippage of all others.
+    pub fn of(solution: &Solution, router: eth::Address, weth: eth::WethAddress) -> Self {
+        let mut used = Self {
+            gas: solution.gas,
+            ..Default::default()
+        };
+        for interaction in &solution.interactions {
+            let solution::Interaction::Custom(interaction) = interaction else {
+                continue;
+            };
+            if interaction.target == router {
+                if let Some(route) = router::decode_exchange(&interaction.calldata) {
+                    used.pools.extend(route.pools());
+                    used.routes.push(route);
+                }
+            } else {
+                used.pools.push(interaction.target);
+            }
+            if interaction.internalize {
+                used.internalized = true;
+                continue;
+            }
+            used.tokens.extend(
+                interaction
+                    .inputs
+                    .iter()
+                    .chain(&interaction.outputs)
+                    .map(|asset| asset.token)
+                    .filter(|token| token.0 != weth.0),
+            );
+        }
+        used.pools.sort();
+        used.pools.dedup();
+        used.tokens.sort();
+        used.tokens.dedup();
+        used
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Feedback {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    solutions: HashMap<(i64, u64), Used>,
+    /// Insertion order of `solutions`, for evicting the oldest.
+    order: VecDeque<(i64, u64)>,
+    blocked_pools: HashMap<eth::Address, Instant>,
+    failures: HashMap<eth::TokenAddress, Failures>,
+}
+
+struct Failures {
+    count: u32,
+    last: Instant,
+}
+
+impl Feedback {
+    /// Remembers what a solution of an auction traded through.
+    pub fn record(&self, auction: i64, solution: solution::Id, used: Used) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let key = (auction, solution.0);
+        if state.solutions.insert(key, used).is_none() {
+            state.order.push_back(key);
+        }
+        while state.order.len() > MAX_TRACKED_SOLUTIONS {
+            if let Some(oldest) = state.order.pop_front() {
+                state.solutions.remove(&oldest);
+            }
+        }
+    }
+
+    /// Returns what the solution a notification is about traded through.
+    /// Merged solutions combine everything their parts used.
+    pub fn used(&self, notification: &notification::Notification) -> Option<Used> {
+        let crate::domain::auction::Id::Solve(auction) = notification.auction_id else {
+            return None;
+        };
+        let (ids, merged) = match notification.solution_id.as_ref()? {
+            notification::Id::Single(id) => (vec![id.0], false),
+            notification::Id::Merged(ids) => (ids.clone(), true),
+        };
+        let state = self.state.lock().ok()?;
+        let parts = ids
+            .iter()
+            .filter_map(|id| state.solutions.get(&(auction, *id)))
+            .collect::<Vec<_>>();
+        match parts.as_slice() {
+            [] => None,
+            // The settlement of merged solutions executes all of their parts,
+            // so its gas can't be attributed to any one of them.
+            [used] if !merged => Some((*used).clone()),
+            _ => Some(Used {
+                routes: parts.iter().flat_map(|used| used.routes.clone()).collect(),
+                pools: parts.iter().flat_map(|used| used.pools.clone()).collect(),
+                tokens: parts.iter().flat_map(|used| used.tokens.clone()).collect(),
+                gas: None,
+                internalized: parts.iter().any(|used| used.internalized),
+            }),
+        }
+    }
+
+    /// Excludes the pools from routing for a while.
+    pub fn block_pools(&self, pools: &[eth::Address]) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let until = Instant::now() + POOL_BLOCKLIST_DURATION;
+        for pool in pools {
+            state.blocked_pools.insert(*pool, until);
+        }
+    }
+
+    /// Whether the route trades through a blocklisted pool.
+    pub fn is_blocked(&self, route: &api::Route) -> bool {
+        let Ok(mut state) = self.state.lock() else {
+            return false;
+        };
+        let now = Instant::now();
+        state.blocked_pools.retain(|_, until| *until > now);
+        route
+            .pools()
+            .any(|pool| state.blocked_pools.contains_key(&pool))
+    }
+
+    /// Counts a simulation failure against the tokens.
+    pub fn record_failure(&self, tokens: &[eth::TokenAddress]) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        for token in tokens {
+            let failures = state.failures.entry(*token).or_insert(Failures {
+                count: 0,
+                last: now,
+            });
+            if now.duration_since(failures.last) > FAILURE_WINDOW {
+                failures.count = 0;
+            }
+            failures.count = failures.count.saturating_add(1);
+            failures.last = now;
+        }
+    }
+
+    /// Forgets the simulation failures of the tokens.
+    pub fn clear_failures(&self, tokens: &[eth::TokenAddress]) {
+        if let Ok(mut state) = self.state.lock() {
+            for token in tokens {
+                state.failures.remove(token);
+            }
+        }
+    }
+
+    /// The slippage added to the configured tolerance for trading `token`.
+    pub fn extra_slippage_bps(&self, token: eth::TokenAddress) -> u32 {
+        let Ok(state) = self.state.lock() else {
+            return 0;
+        };
+        let Some(failures) = state.failures.get(&token) else {
+            return 0;
+        };
+        if failures.last.elapsed() > FAILURE_WINDOW {
+            return 0;
+        }
+        failures
+            .count
+            .saturating_sub(FAILURES_BEFORE_WIDENING - 1)
+            .saturating_mul(SLIPPAGE_STEP_BPS)
+            .min(MAX_EXTRA_SLIPPAGE_BPS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloy::primitives::Address};
+
+    fn notification(auction: i64, solution: u64) -> notification::Notification {
+        notification::Notification {
+            auction_id: crate::domain::auction::Id::Solve(auction),
+            solution_id: Some(notification::Id::Single(solution::Id(solution))),
+            kind: notification::Kind::Timeout,
+        }
+    }
+
+    fn route(pool: Address) -> api::Route {
+        let mut route = api::Route {
+            route: [Address::ZERO; 11],
+            swap_params: [[0; 5]; 5],
+            pools: [Address::ZERO; 5],
+            expected_output: eth::U256::ZERO,
+        };
+        route.route[1] = pool;
+        route.swap_params[0] = [0, 1, 6, 10, 2];
+        route
+    }
+
+    #[test]
+    fn remembers_recent_solutions() {
+        let feedback = Feedback::default();
+        let pool = Address::repeat_byte(1);
+        feedback.record(
+            7,
+            solution::Id(0),
+            Used {
+                pools: vec![pool],
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(feedback.used(&notification(7, 0)).unwrap().pools, [pool]);
+        assert!(feedback.used(&notification(7, 1)).is_none());
+        assert!(feedback.used(&notification(8, 0)).is_none());
+
+        for id in 1..=MAX_TRACKED_SOLUTIONS as u64 {
+            feedback.record(7, solution::Id(id), Used::default());
+        }
+        assert!(feedback.used(&notification(7, 0)).is_none());
+    }
+
+    #[test]
+    fn uses_tokens_of_executed_interactions_except_weth() {
+        let weth = eth::WethAddress(Address::repeat_byte(0xee));
+        let token = |byte| eth::TokenAddress(Address::repeat_byte(byte));
+        let interaction = |pool, sell, buy, internalize| {
+            solution::Interaction::Custom(solution::CustomInteraction {
+                target: Address::repeat_byte(pool),
+                value: eth::Ether(eth::U256::ZERO),
+                calldata: Vec::new(),
+                internalize,
+                inputs: vec![eth::Asset {
+                    token: token(sell),
+                    amount: eth::U256::ONE,
+                }],
+                outputs: vec![eth::Asset {
+                    token: token(buy),
+                    amount: eth::U256::ONE,
+                }],
+                allowances: Vec::new(),
+            })
+        };
+        let solution = Solution {
+            interactions: vec![interaction(1, 3, 0xee, false), interaction(2, 4, 5, true)],
+            ..Default::default()
+        };
+
+        let used = Used::of(&solution, Address::ZERO, weth);
+        assert_eq!(
+            used.pools,
+            [Address::repeat_byte(1), Address::repeat_byte(2)]
+        );
+        assert_eq!(used.tokens, [token(3)]);
+        assert!(used.internalized);
+    }
+
+    #[test]
+    fn blocks_pools_of_reverted_routes() {
+        let feedback = Feedback::default();
+        let pool = Address::repeat_byte(1);
+        assert!(!feedback.is_blocked(&route(pool)));
+
+        feedback.block_pools(&[pool]);
+        assert!(feedback.is_blocked(&route(pool)));
+        assert!(!feedback.is_blocked(&route(Address::repeat_byte(2))));
+    }
+
+    #[test]
+    fn merged_solutions_have_no_gas_estimate() {
+        let feedback = Feedback::default();
+        let used = || Used {
+            gas: Some(eth::Gas(eth::U256::from(100_000))),
+            ..Default::default()
+        };
+        feedback.record(7, solution::Id(0), used());
+        feedback.record(7, solution::Id(1), used());
+
+        assert!(feedback.used(&notification(7, 0)).unwrap().gas.is_some());
+        let merged = |ids: Vec<u64>| notification::Notification {
+            solution_id: Some(notification::Id::Merged(ids)),
+            ..notification(7, 0)
+        };
+        assert!(feedback.used(&merged(vec![0, 1])).unwrap().gas.is_none());
+        // Also when only one of the parts is remembered.
+        assert!(feedback.used(&merged(vec![0, 2])).unwrap().gas.is_none());
+    }
+
+    #[test]
+    fn widens_slippage_after_repeated_failures() {
+        let feedback = Feedback::default();
+        let token = eth::TokenAddress(Address::repeat_byte(1));
+
+        feedback.record_failure(&[token]);
+        assert_eq!(feedback.extra_slippage_bps(token), 0);
+        feedback.record_failure(&[token]);
+        assert_eq!(feedback.extra_slippage_bps(token), SLIPPAGE_STEP_BPS);
+        for _ in 0..10 {
+            feedback.record_failure(&[token]);
+        }
+        assert_eq!(feedback.extra_slippage_bps(token), MAX_EXTRA_SLIPPAGE_BPS);
+
+        feedback.clear_failures(&[token]);
+        assert_eq!(feedback.extra_slippage_bps(token), 0);
+    }
+}
@@ -0,0 +1,233 @@
+//! Caching of Curve Router API routes across auctions.
+//!
+//! The same LP token orders re-appear auction after auction, so routes are
+//! cached by token pair and sell amount bucket. Only the shape of a cached
+//! route is reused: its output is quoted on-chain again before use. Entries
+//! are invalidated when the state of one of the pools they trade through
+//! changes, since that may make a different route preferable, and once they
+//! reach a maximum age.
+
+use {
+    crate::domain::{curve::api, eth},
+    std::{
+        collections::{HashMap, HashSet},
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// How long the shape of a route is reused before asking the API again.
+const MAX_AGE: Duration = Duration::from_secs(10 * 60);
+/// Upper bound on the number of cached entries.
+const MAX_ENTRIES: usize = 4_096;
+
+/// Identifies the routes for selling an amount of `sell` for `buy`.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(super) struct Key {
+    sell: eth::TokenAddress,
+    buy: eth::TokenAddress,
+    /// The number of bits of the sell amount, so that amounts within a
+    /// factor of two of each other share their routes.
+    bucket: usize,
+}
+
+/// A Router API request, which is repeated to warm its cache entry.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Request {
+    pub sell: eth::TokenAddress,
+    pub buy: eth::TokenAddress,
+    pub amount: eth::U256,
+    pub sell_decimals: u8,
+    pub buy_decimals: u8,
+}
+
+impl Request {
+    pub fn key(&self) -> Key {
+        Key {
+            sell: self.sell,
+            buy: self.buy,
+            bucket: self.amount.bit_len(),
+        }
+    }
+}
+
+struct Entry {
+    request: Request,
+    routes: Vec<api::Route>,
+    fetched: Instant,
+}
+
+impl Entry {
+    fn is_expired(&self) -> bool {
+        self.fetched.elapsed() >= MAX_AGE
+    }
+
+    fn trades_through(&self, pools: &HashSet<eth::Address>) -> bool {
+        self.routes
+            .iter()
+            .flat_map(api::Route::pools)
+            .any(|pool| pools.contains(&pool))
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Cache {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    entries: HashMap<Key, Entry>,
+    /// The block up to which pool events were checked.
+    block: Option<u64>,
+}
+
+impl Cache {
+    /// Returns the cached routes for `key`, unless they expired.
+    pub fn get(&self, key: &Key) -> Option<Vec<api::Route>> {
+        let state = self.state.lock().ok()?;
+        let entry = state.entries.get(key)?;
+        (!entry.is_expired()).then(|| entry.routes.clone())
+    }
+
+    /// Caches the routes returned for `request`, evicting the oldest entry
+    /// if the cache is full.
+    pub fn insert(&self, request: Request, routes: Vec<api::Route>) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let key = request.key();
+        if !state.entries.contains_key(&key) && state.entries.len() >= MAX_ENTRIES {
+            let oldest = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.fetched)
+                .map(|(key, _)| *key);
+            if let Some(oldest) = oldest {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.entries.insert(
+            key,
+            Entry {
+                request,
+                routes,
+                fetched: Instant::now(),
+            },
+        );
+    }
+
+    pub fn remove(&self, key: &Key) {
+        if let Ok(mut state) = self.state.lock() {
+            state.entries.remove(key);
+        }
+    }
+
+    /// The pools traded through by cached routes.
+    pub fn pools(&self) -> HashSet<eth::Address> {
+        let Ok(state) = self.state.lock() else {
+            return HashSet::new();
+        };
+        state
+            .entries
+            .values()
+            .flat_map(|entry| entry.routes.iter().flat_map(api::Route::pools))
+            .collect()
+    }
+
+    /// Records that pool events are checked up to `block`, returning the
+    /// block they were previously checked up to.
+    pub fn advance(&self, block: u64) -> Option<u64> {
+        let mut state = self.state.lock().ok()?;
+        let previous = state.block;
+        state.block = Some(previous.map_or(block, |previous| previous.max(block)));
+        previous
+    }
+
+    /// Drops the entries trading through any of the `changed` pools as well
+    /// as expired entries, returning the requests they were fetched for.
+    pub fn invalidate(&self, changed: &HashSet<eth::Address>) -> Vec<Request> {
+        self.drop_where(|entry| entry.is_expired() || entry.trades_through(changed))
+    }
+
+    /// Drops all entries, returning the requests they were fetched for.
+    pub fn clear(&self) -> Vec<Request> {
+        self.drop_where(|_| true)
+    }
+
+    fn drop_where(&self, predicate: impl Fn(&Entry) -> bool) -> Vec<Request> {
+        let Ok(mut state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let mut dropped = Vec::new();
+        state.entries.retain(|_, entry| {
+            let drop = predicate(entry);
+            if drop {
+                dropped.push(entry.request);
+            }
+            !drop
+        });
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloy::primitives::Address};
+
+    fn request(amount: u64) -> Request {
+        Request {
+            sell: eth::TokenAddress(Address::repeat_byte(1)),
+            buy: eth::TokenAddress(Address::repeat_byte(2)),
+            amount: eth::U256::from(amount),
+            sell_decimals: 18,
+            buy_decimals: 18,
+        }
+    }
+
+    fn route(pool: Address) -> api::Route {
+        let mut route = [Address::ZERO; 11];
+        route[0] = Address::repeat_byte(1);
+        route[1] = pool;
+        route[2] = Address::repeat_byte(2);
+        let mut swap_params = [[0; 5]; 5];
+        swap_params[0] = [0, 1, 1, 1, 2];
+        api::Route {
+            route,
+            swap_params,
+            pools: [Address::ZERO; 5],
+            expected_output: eth::U256::from(1_000),
+        }
+    }
+
+    #[test]
+    fn amounts_share_routes_within_a_bucket() {
+        assert_eq!(request(1_024).key(), request(2_047).key());
+        assert_ne!(request(1_024).key(), request(2_048).key());
+        assert_ne!(request(1_024).key(), request(1_023).key());
+    }
+
+    #[test]
+    fn invalidates_entries_of_changed_pools() {
+        let cache = Cache::default();
+        let (pool, other) = (Address::repeat_byte(0xaa), Address::repeat_byte(0xbb));
+        cache.insert(request(1_000), vec![route(pool)]);
+        cache.insert(request(1_000_000), vec![route(other)]);
+        assert_eq!(cache.pools(), HashSet::from([pool, other]));
+
+        let dropped = cache.invalidate(&HashSet::from([pool]));
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].amount, eth::U256::from(1_000));
+        assert!(cache.get(&request(1_000).key()).is_none());
+        assert!(cache.get(&request(1_000_000).key()).is_some());
+    }
+
+    #[test]
+    fn advances_monotonically() {
+        let cache = Cache::default();
+        assert_eq!(cache.advance(10), None);
+        assert_eq!(cache.advance(12), Some(10));
+        assert_eq!(cache.advance(11), Some(12));
+        assert_eq!(cache.advance(13), Some(12));
+    }
+}
@@ -0,0 +1,304 @@
+//! Per-trade slippage tolerances of curve-lp.
+//!
+//! The tolerance of a trade starts from a base depending on the pools it
+//! trades through, since StableSwap pools hold coins of about the same value
+//! and their prices move less than those of CryptoSwap pools between quoting
+//! and settling. Trades then get a buffer for the share of their LP token's
+//! supply they mint or burn, as other trades against the pool move its price
+//! by more the larger the trade is, and a buffer for how much the price of
+//! the pair moved between recent auctions. Tokens with a configured override
+//! are traded with exactly that tolerance instead.
+
+use {
+    crate::domain::{
+        curve::{api, pool},
+        eth,
+    },
+    std::{
+        collections::{HashMap, VecDeque},
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// The share of the LP token supply traded that is added to the tolerance,
+/// in percent.
+const DEPTH_PCT: u32 = 10;
+/// Number of standard deviations of the pair's price moves between auctions
+/// added to the tolerance.
+const VOLATILITY_SIGMAS: f64 = 2.0;
+/// Reference prices observed earlier than this are forgotten.
+const PRICE_WINDOW: Duration = Duration::from_secs(30 * 60);
+/// Upper bound on the number of auctions whose reference prices are kept.
+const MAX_SNAPSHOTS: usize = 256;
+/// Minimum number of price moves of a pair to estimate its volatility.
+const MIN_RETURNS: usize = 5;
+/// How long the supply of an LP token is reused.
+const SUPPLY_MAX_AGE: Duration = Duration::from_secs(60);
+
+pub(super) struct Config {
+    /// Base tolerance of routes through CryptoSwap or unknown pools.
+    pub default_bps: u32,
+    /// Base tolerance of routes only trading through StableSwap pools.
+    pub stable_bps: u32,
+    /// Upper bound on the tolerance widened for trade size and volatility.
+    pub max_bps: u32,
+    /// Fixed tolerances of trades buying or selling these tokens.
+    pub overrides: HashMap<eth::TokenAddress, u32>,
+}
+
+/// A trade through a route whose tolerance is computed.
+pub(super) struct Trade<'a> {
+    pub sell: eth::TokenAddress,
+    pub buy: eth::TokenAddress,
+    /// The amount of sell tokens traded.
+    pub input: eth::U256,
+    pub route: &'a api::Route,
+    /// The supply of the LP token traded, if known.
+    pub depth: Option<Depth>,
+}
+
+/// The supply of the LP token minted or burned by a trade.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Depth {
+    pub lp_token: eth::TokenAddress,
+    pub supply: eth::U256,
+}
+
+pub(super) struct Slippage {
+    config: Config,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// Reference prices of recent auctions, oldest first.
+    snapshots: VecDeque<Snapshot>,
+    supplies: HashMap<eth::TokenAddress, (Instant, eth::U256)>,
+}
+
+struct Snapshot {
+    at: Instant,
+    prices: HashMap<eth::TokenAddress, f64>,
+}
+
+impl Slippage {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            state: Default::default(),
+        }
+    }
+
+    /// Remembers the reference prices of an auction.
+    pub fn observe(&self, prices: impl IntoIterator<Item = (eth::TokenAddress, eth::U256)>) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        let now = Instant::now();
+        while state
+            .snapshots
+            .front()
+            .is_some_and(|snapshot| now.duration_since(snapshot.at) > PRICE_WINDOW)
+            || state.snapshots.len() >= MAX_SNAPSHOTS
+        {
+            state.snapshots.pop_front();
+        }
+        let prices = prices
+            .into_iter()
+            .map(|(token, price)| (token, f64::from(price)))
+            .filter(|(_, price)| price.is_normal())
+            .collect::<HashMap<_, _>>();
+        if !prices.is_empty() {
+            state.snapshots.push_back(Snapshot { at: now, prices });
+        }
+    }
+
+    /// The supply of an LP token, if it was recorded recently.
+    pub fn supply(&self, lp_token: eth::TokenAddress) -> Option<eth::U256> {
+        let state = self.state.lock().ok()?;
+        let (at, supply) = state.supplies.get(&lp_token)?;
+        (at.elapsed() < SUPPLY_MAX_AGE).then_some(*supply)
+    }
+
+    pub fn record_supply(&self, lp_token: eth::TokenAddress, supply: eth::U256) {
+        if let Ok(mut state) = self.state.lock() {
+            state.supplies.insert(lp_token, (Instant::now(), supply));
+        }
+    }
+
+    /// The slippage tolerance of a trade in basis points.
+    pub fn tolerance_bps(&self, trade: &Trade) -> u32 {
+        let overrides = &self.config.overrides;
+        let fixed = overrides
+            .get(&trade.sell)
+            .max(overrides.get(&trade.buy))
+            .copied();
+        if let Some(bps) = fixed {
+            return bps;
+        }
+
+        let base = if is_stable(trade.route) {
+            self.config.stable_bps
+        } else {
+            self.config.default_bps
+        };
+        let depth = trade.depth.map_or(0, |depth| {
+            share_bps(trade, depth).saturating_mul(DEPTH_PCT) / 100
+        });
+        let volatility = self.volatility_bps(trade.sell, trade.buy);
+        // The bound only limits the widening, never the base.
+        base.saturating_add(depth)
+            .saturating_add(volatility)
+            .min(self.config.max_bps.max(base))
+    }
+
+    /// Standard deviations of the price of `sell` in `buy` between recent
+    /// auctions, in basis points. Zero until enough prices were observed.
+    fn volatility_bps(&self, sell: eth::TokenAddress, buy: eth::TokenAddress) -> u32 {
+        let Ok(state) = self.state.lock() else {
+            return 0;
+        };
+        let ratios = state
+            .snapshots
+            .iter()
+            .filter_map(|snapshot| Some(snapshot.prices.get(&sell)? / snapshot.prices.get(&buy)?))
+            .collect::<Vec<_>>();
+        let returns = ratios
+            .windows(2)
+            .map(|pair| (pair[1] / pair[0]).ln())
+            .collect::<Vec<_>>();
+        if returns.len() < MIN_RETURNS {
+            return 0;
+        }
+
+        let n = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.);
+        // Float to integer casts saturate.
+        (variance.sqrt() * VOLATILITY_SIGMAS * 10_000.).ceil() as u32
+    }
+}
+
+/// Whether every hop of the route trades through a StableSwap pool.
+fn is_stable(route: &api::Route) -> bool {
+    route
+        .swap_params
+        .iter()
+        .take_while(|params| params[2] != 0)
+        .all(|params| {
+            matches!(
+                pool::Kind::from_router_pool_type(params[3]),
+                Some(pool::Kind::StableSwap | pool::Kind::StableSwapNg)
+            )
+        })
+}
+
+/// The share of the LP token supply minted or burned by the trade, in basis
+/// points.
+fn share_bps(trade: &Trade, depth: Depth) -> u32 {
+    if depth.supply.is_zero() {
+        return 0;
+    }
+    let amount = if depth.lp_token == trade.sell {
+        trade.input
+    } else {
+        trade.route.expected_output
+    };
+    (amount.saturating_mul(eth::U256::from(10_000)) / depth.supply)
+        .try_into()
+        .unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, alloy::primitives::Address};
+
+    const LP: eth::TokenAddress = eth::TokenAddress(Address::repeat_byte(1));
+    const COIN: eth::TokenAddress = eth::TokenAddress(Address::repeat_byte(2));
+
+    fn slippage(overrides: HashMap<eth::TokenAddress, u32>) -> Slippage {
+        Slippage::new(Config {
+            default_bps: 100,
+            stable_bps: 20,
+            max_bps: 300,
+            overrides,
+        })
+    }
+
+    fn route(pool_type: u64, expected_output: u64) -> api::Route {
+        let mut swap_params = [[0; 5]; 5];
+        swap_params[0] = [0, 1, 6, pool_type, 2];
+        api::Route {
+            route: [Address::ZERO; 11],
+            swap_params,
+            pools: [Address::ZERO; 5],
+            expected_output: eth::U256::from(expected_output),
+        }
+    }
+
+    fn trade(route: &api::Route, supply: Option<u64>) -> Trade<'_> {
+        Trade {
+            sell: LP,
+            buy: COIN,
+            input: eth::U256::from(1_000),
+            route,
+            depth: supply.map(|supply| Depth {
+                lp_token: LP,
+                supply: eth::U256::from(supply),
+            }),
+        }
+    }
+
+    #[test]
+    fn bases_tolerance_on_pool_kind() {
+        let slippage = slippage(HashMap::new());
+        let (stable, crypto, unknown) = (route(10, 0), route(30, 0), route(99, 0));
+        assert_eq!(slippage.tolerance_bps(&trade(&stable, None)), 20);
+        assert_eq!(slippage.tolerance_bps(&trade(&crypto, None)), 100);
+        assert_eq!(slippage.tolerance_bps(&trade(&unknown, None)), 100);
+
+        let slippage = self::slippage(HashMap::from([(COIN, 5)]));
+        assert_eq!(slippage.tolerance_bps(&trade(&crypto, None)), 5);
+    }
+
+    #[test]
+    fn widens_tolerance_with_trade_size() {
+        let slippage = slippage(HashMap::new());
+        let stable = route(1, 0);
+        // Burning 10% of the supply adds 1%.
+        assert_eq!(slippage.tolerance_bps(&trade(&stable, Some(10_000))), 120);
+        // Burning all of it is capped.
+        assert_eq!(slippage.tolerance_bps(&trade(&stable, Some(1_000))), 300);
+
+        // Mints are sized by their output.
+        let mint = route(1, 500);
+        let trade = Trade {
+            sell: COIN,
+            buy: LP,
+            ..trade(&mint, Some(10_000))
+        };
+        assert_eq!(slippage.tolerance_bps(&trade), 70);
+    }
+
+    #[test]
+    fn widens_tolerance_with_volatility() {
+        let slippage = slippage(HashMap::new());
+        let stable = route(1, 0);
+        let price = |wei: u64| eth::U256::from(wei);
+
+        // Both tokens moving together keep the pair stable.
+        for wei in [100, 110, 90, 120, 80, 100] {
+            slippage.observe([(LP, price(wei * 2)), (COIN, price(wei))]);
+        }
+        assert_eq!(slippage.tolerance_bps(&trade(&stable, None)), 20);
+
+        // Moves of 1% in alternating directions.
+        let slippage = self::slippage(HashMap::new());
+        for wei in [1_000, 1_010, 1_000, 1_010, 1_000, 1_010] {
+            slippage.observe([(LP, price(wei)), (COIN, price(1_000))]);
+        }
+        let tolerance = slippage.tolerance_bps(&trade(&stable, None));
+        assert!((200..=260).contains(&tolerance), "{tolerance}");
+    }
+}
@@ -1,11 +1,15 @@
 //! Solver implementations.
 
 mod baseline;
+pub mod composite;
 pub mod curve_lp;
 
 pub use baseline::{Config, Request, Route, Segment};
 
-use crate::domain::{auction, solution};
+use crate::{
+    domain::{auction, curve::upstream, notification, solution},
+    infra::metrics,
+};
 
 /// A solver that can handle auctions.
 pub enum Solver {
@@ -13,6 +17,8 @@ pub enum Solver {
     Baseline(baseline::Solver),
     /// Curve LP token solver.
     CurveLp(curve_lp::Solver),
+    /// Solver running several other solvers on the same auction.
+    Composite(composite::Solver),
 }
 
 impl Solver {
@@ -26,11 +32,44 @@ impl Solver {
         Self::CurveLp(curve_lp::Solver::new(config).await)
     }
 
+    /// Creates a new composite solver.
+    pub async fn new_composite(config: composite::Config) -> Self {
+        Self::Composite(composite::Solver::new(config).await)
+    }
+
     /// Solves the auction.
+    ///
+    /// The metrics of the auction are recorded here rather than by the
+    /// engines, so that the sub-solvers of a composite solver don't record
+    /// them once each.
     pub async fn solve(&self, auction: auction::Auction) -> Vec<solution::Solution> {
-        match self {
+        metrics::solve(&auction);
+        let deadline = auction.deadline.clone();
+        let solutions = match self {
             Solver::Baseline(solver) => solver.solve(auction).await,
             Solver::CurveLp(solver) => solver.solve(auction).await,
+            Solver::Composite(solver) => solver.solve(auction).await,
+        };
+        metrics::solved(&deadline, &solutions);
+        solutions
+    }
+
+    /// The upstream API mirrors whose circuit breakers currently reject
+    /// requests.
+    pub fn open_breakers(&self) -> Vec<upstream::OpenBreaker> {
+        match self {
+            Solver::Baseline(_) => vec![],
+            Solver::CurveLp(solver) => solver.open_breakers(),
+            Solver::Composite(solver) => solver.open_breakers(),
+        }
+    }
+
+    /// Informs the solver about the outcome of one of its solutions.
+    pub fn notify(&self, notification: notification::Notification) {
+        match self {
+            Solver::Baseline(_) => {}
+            Solver::CurveLp(solver) => solver.notify(notification),
+            Solver::Composite(solver) => solver.notify(notification),
         }
     }
 }
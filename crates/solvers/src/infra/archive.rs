@@ -0,0 +1,311 @@
+//! Record-and-replay archive of solved auctions.
+//!
+//! When recording, every `/solve` request is appended to a JSONL archive
+//! together with the upstream HTTP and RPC responses the solver received
+//! while solving it and the solutions it returned. Upstream calls are
+//! attributed to the auction through a task-local [`Session`], which the
+//! Curve API clients and the [`rpc`] transport consult for every request.
+//! When replaying, the same session serves the recorded responses instead of
+//! reaching out to the network.
+
+pub mod rpc;
+
+use {
+    serde::{Deserialize, Serialize, de::DeserializeOwned},
+    serde_json::Value,
+    std::{
+        fmt,
+        fs::OpenOptions,
+        future::Future,
+        io::{self, BufWriter, Write},
+        path::Path,
+        sync::{Arc, Mutex, mpsc},
+    },
+};
+
+tokio::task_local! {
+    static SESSION: Arc<Session>;
+}
+
+/// A JSONL file solved auctions are appended to.
+pub struct Archive {
+    /// Sends records to the thread writing them to the file, so that the
+    /// `/solve` handler never blocks on file I/O.
+    writer: mpsc::Sender<Record>,
+}
+
+/// A solved auction, as stored in one line of the archive.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Record {
+    /// When the auction was received, to replay it with the same time budget.
+    pub received_at: chrono::DateTime<chrono::Utc>,
+    pub auction: solvers_dto::auction::Auction,
+    /// The upstream responses received while solving, in request order.
+    pub exchanges: Vec<Exchange>,
+    pub solutions: solvers_dto::solution::Solutions,
+}
+
+/// An upstream request and the response it received.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Exchange {
+    /// The upstream service, e.g. "curve-api" or "node".
+    pub service: String,
+    pub request: Value,
+    /// The response, or the error the request failed with.
+    pub response: Result<Value, String>,
+}
+
+/// A plain HTTP response.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+impl Archive {
+    /// Opens the archive at `path` for appending, creating it if needed.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (writer, records) = mpsc::channel::<Record>();
+        std::thread::spawn(move || {
+            let mut file = BufWriter::new(file);
+            for record in records {
+                let result = serde_json::to_string(&record)
+                    .map_err(io::Error::other)
+                    .and_then(|line| {
+                        writeln!(file, "{line}")?;
+                        file.flush()
+                    });
+                if let Err(err) = result {
+                    tracing::warn!(?err, "failed to archive auction");
+                }
+            }
+        });
+        Ok(Self { writer })
+    }
+
+    /// Reads all records of the archive at `path`.
+    pub fn load(path: &Path) -> io::Result<Vec<Record>> {
+        std::fs::read_to_string(path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(io::Error::other))
+            .collect()
+    }
+
+    /// Appends a record to the archive in the background.
+    pub fn append(&self, record: Record) {
+        if self.writer.send(record).is_err() {
+            tracing::warn!("archive writer stopped, failed to archive auction");
+        }
+    }
+}
+
+/// The upstream exchanges of a single auction, either being recorded or
+/// replayed.
+pub struct Session {
+    replay: bool,
+    /// The exchanges, and for replays whether each has been served already.
+    exchanges: Mutex<Vec<(Exchange, bool)>>,
+}
+
+impl Session {
+    /// A session recording upstream exchanges.
+    pub fn record() -> Arc<Self> {
+        Arc::new(Self {
+            replay: false,
+            exchanges: Default::default(),
+        })
+    }
+
+    /// A session serving recorded exchanges instead of sending requests.
+    pub fn replay(exchanges: Vec<Exchange>) -> Arc<Self> {
+        Arc::new(Self {
+            replay: true,
+            exchanges: Mutex::new(
+                exchanges
+                    .into_iter()
+                    .map(|exchange| (exchange, false))
+                    .collect(),
+            ),
+        })
+    }
+
+    /// The exchanges recorded so far.
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.exchanges
+            .lock()
+            .map(|exchanges| {
+                exchanges
+                    .iter()
+                    .map(|(exchange, _)| exchange.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn push(&self, exchange: Exchange) {
+        if let Ok(mut exchanges) = self.exchanges.lock() {
+            exchanges.push((exchange, false));
+        }
+    }
+
+    /// Returns the recorded response to a request. Identical requests are
+    /// served their recorded responses in order, repeating the last one once
+    /// all of them were served.
+    fn replayed(&self, service: &str, request: &Value) -> Option<Result<Value, String>> {
+        let mut exchanges = self.exchanges.lock().ok()?;
+        let mut matching = exchanges
+            .iter_mut()
+            .filter(|(exchange, _)| exchange.service == service && exchange.request == *request)
+            .peekable();
+        let mut last = None;
+        while let Some((exchange, served)) = matching.next() {
+            if !*served || matching.peek().is_none() {
+                *served = true;
+                last = Some(exchange.response.clone());
+                break;
+            }
+        }
+        last
+    }
+}
+
+/// Runs `future` within `session`, if any.
+pub async fn scope<F: Future>(session: Option<Arc<Session>>, future: F) -> F::Output {
+    match session {
+        Some(session) => SESSION.scope(session, future).await,
+        None => future.await,
+    }
+}
+
+/// Runs `future` within the session of the current task, if any. Futures
+/// spawned onto other tasks need this to stay part of the session.
+pub fn inherit<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    scope(current(), future)
+}
+
+fn current() -> Option<Arc<Session>> {
+    SESSION.try_with(Arc::clone).ok()
+}
+
+/// Sends an upstream request with `fetch`, recording its response if the
+/// current session is recorded. In replayed sessions the recorded response
+/// is returned instead, without sending the request.
+pub async fn exchange<T, E>(
+    service: &str,
+    request: Value,
+    fetch: impl Future<Output = Result<T, E>>,
+) -> Result<T, Error>
+where
+    T: Serialize + DeserializeOwned,
+    E: fmt::Display,
+{
+    let Some(session) = current() else {
+        return fetch.await.map_err(|err| Error::Upstream(err.to_string()));
+    };
+
+    if session.replay {
+        return match session.replayed(service, &request) {
+            Some(Ok(response)) => serde_json::from_value(response)
+                .map_err(|err| Error::NotRecorded(format!("invalid {service} response: {err}"))),
+            Some(Err(err)) => Err(Error::Upstream(err)),
+            None => Err(Error::NotRecorded(format!("{service} request {request}"))),
+        };
+    }
+
+    let result = fetch.await.map_err(|err| err.to_string());
+    session.push(Exchange {
+        service: service.to_owned(),
+        request,
+        response: result
+            .as_ref()
+            .map_err(Clone::clone)
+            .and_then(|response| serde_json::to_value(response).map_err(|err| err.to_string())),
+    });
+    result.map_err(Error::Upstream)
+}
+
+/// Sends a GET request, see [`exchange`].
+pub async fn get(service: &str, http: &reqwest::Client, url: &str) -> Result<HttpResponse, Error> {
+    exchange(service, Value::String(url.to_owned()), async {
+        let response = http.get(url).send().await?;
+        let status = response.status().as_u16();
+        let body = response.text().await?;
+        Ok::<_, reqwest::Error>(HttpResponse { status, body })
+    })
+    .await
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The upstream request failed.
+    Upstream(String),
+    /// A replayed request has no recorded response.
+    NotRecorded(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Upstream(msg) => write!(f, "{}", msg),
+            Error::NotRecorded(msg) => write!(f, "not recorded: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, serde_json::json};
+
+    async fn fetch(session: Arc<Session>, url: &str, upstream: u64) -> Result<u64, Error> {
+        scope(
+            Some(session),
+            exchange("test", json!(url), async move { Ok::<_, Error>(upstream) }),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn replays_recorded_exchanges() {
+        let recording = Session::record();
+        assert_eq!(fetch(recording.clone(), "a", 1).await.unwrap(), 1);
+        assert_eq!(fetch(recording.clone(), "a", 2).await.unwrap(), 2);
+        assert_eq!(fetch(recording.clone(), "b", 3).await.unwrap(), 3);
+
+        let replay = Session::replay(recording.exchanges());
+        // Upstream isn't reached, and identical requests are served in order.
+        assert_eq!(fetch(replay.clone(), "b", 0).await.unwrap(), 3);
+        assert_eq!(fetch(replay.clone(), "a", 0).await.unwrap(), 1);
+        assert_eq!(fetch(replay.clone(), "a", 0).await.unwrap(), 2);
+        assert_eq!(fetch(replay.clone(), "a", 0).await.unwrap(), 2);
+        assert!(matches!(
+            fetch(replay, "c", 0).await,
+            Err(Error::NotRecorded(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn sessions_are_inherited_by_spawned_tasks() {
+        let recording = Session::record();
+        scope(Some(recording.clone()), async {
+            tokio::spawn(inherit(exchange("test", json!("a"), async {
+                Ok::<_, Error>(1)
+            })))
+            .await
+            .unwrap()
+            .unwrap();
+        })
+        .await;
+        assert_eq!(recording.exchanges().len(), 1);
+    }
+}
@@ -0,0 +1,144 @@
+//! Recording and replaying of JSON-RPC requests.
+
+use {
+    super::Session,
+    alloy::{
+        providers::{DynProvider, Provider, ProviderBuilder},
+        rpc::{
+            client::RpcClient,
+            json_rpc::{Id, RequestPacket, Response, ResponsePacket, ResponsePayload},
+        },
+        transports::{TransportError, TransportErrorKind},
+    },
+    ethrpc::alloy::RpcClientRandomIdExt,
+    serde_json::{Value, json},
+    std::{
+        pin::Pin,
+        sync::Arc,
+        task::{Context, Poll},
+    },
+    tower::Service,
+};
+
+/// The service name of recorded JSON-RPC requests.
+const SERVICE: &str = "node";
+
+/// Wraps the transport of `provider` so that its requests take part in the
+/// archive session of the calling task.
+pub fn archived(provider: DynProvider) -> DynProvider {
+    let is_local = provider.client().is_local();
+    let transport = Archived {
+        inner: provider.client().transport().clone(),
+    };
+    let client = RpcClient::with_random_id(transport, is_local);
+    ProviderBuilder::new().connect_client(client).erased()
+}
+
+#[derive(Clone, Debug)]
+struct Archived<S> {
+    inner: S,
+}
+
+impl<S> Service<RequestPacket> for Archived<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError>,
+    S::Future: Send + 'static,
+{
+    type Error = TransportError;
+    type Future = Pin<Box<dyn Future<Output = Result<ResponsePacket, TransportError>> + Send>>;
+    type Response = ResponsePacket;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: RequestPacket) -> Self::Future {
+        let Some(session) = super::current() else {
+            return Box::pin(self.inner.call(req));
+        };
+        // Request IDs differ between runs, so requests are identified by
+        // their method and parameters only.
+        let requests = req
+            .requests()
+            .iter()
+            .map(|request| {
+                let key = json!({ "method": request.method(), "params": request.params() });
+                (request.id().clone(), key)
+            })
+            .collect::<Vec<_>>();
+        let batch = matches!(req, RequestPacket::Batch(_));
+
+        if session.replay {
+            let responses = requests
+                .into_iter()
+                .map(|(id, key)| replay(&session, id, &key))
+                .collect::<Result<Vec<_>, _>>();
+            return Box::pin(async move {
+                let mut responses = responses?;
+                Ok(match (batch, responses.pop()) {
+                    (false, Some(response)) => ResponsePacket::Single(response),
+                    (_, last) => {
+                        responses.extend(last);
+                        ResponsePacket::Batch(responses)
+                    }
+                })
+            });
+        }
+
+        let fut = self.inner.call(req);
+        Box::pin(async move {
+            let result = fut.await;
+            record(&session, &requests, &result);
+            result
+        })
+    }
+}
+
+/// Builds the response to a request from the session's recording.
+fn replay(session: &Session, id: Id, key: &Value) -> Result<Response, TransportError> {
+    let payload = session
+        .replayed(SERVICE, key)
+        .ok_or_else(|| TransportErrorKind::custom_str(&format!("not recorded: {key}")))?
+        .map_err(|err| TransportErrorKind::custom_str(&err))?;
+    let mut response = json!({ "jsonrpc": "2.0", "id": id });
+    if let (Some(response), Value::Object(payload)) = (response.as_object_mut(), payload) {
+        response.extend(payload);
+    }
+    serde_json::from_value(response).map_err(|err| TransportErrorKind::custom_str(&err.to_string()))
+}
+
+/// Records the responses to the requests of a packet.
+fn record(
+    session: &Arc<Session>,
+    requests: &[(Id, Value)],
+    result: &Result<ResponsePacket, TransportError>,
+) {
+    let responses = match result {
+        Ok(ResponsePacket::Single(response)) => std::slice::from_ref(response),
+        Ok(ResponsePacket::Batch(responses)) => responses.as_slice(),
+        Err(err) => {
+            for (_, key) in requests {
+                session.push(super::Exchange {
+                    service: SERVICE.to_owned(),
+                    request: key.clone(),
+                    response: Err(err.to_string()),
+                });
+            }
+            return;
+        }
+    };
+    for (id, key) in requests {
+        let Some(response) = responses.iter().find(|response| response.id == *id) else {
+            continue;
+        };
+        let payload = match &response.payload {
+            ResponsePayload::Success(result) => json!({ "result": result }),
+            ResponsePayload::Failure(error) => json!({ "error": error }),
+        };
+        session.push(super::Exchange {
+            service: SERVICE.to_owned(),
+            request: key.clone(),
+            response: Ok(payload),
+        });
+    }
+}
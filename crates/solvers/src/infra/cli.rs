@@ -25,6 +25,11 @@ pub struct Args {
     #[arg(long, env, default_value = "127.0.0.1:7872")]
     pub addr: SocketAddr,
 
+    /// Append every solved auction, the upstream responses received while
+    /// solving it and the returned solutions to this JSONL archive.
+    #[arg(long, env)]
+    pub record: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -45,4 +50,23 @@ pub enum Command {
         #[clap(long, env)]
         config: PathBuf,
     },
+    /// run several solver engines on each auction and combine their
+    /// solutions
+    Composite {
+        #[clap(long, env)]
+        config: PathBuf,
+    },
+    /// re-run recorded auctions offline and diff the solutions
+    Replay {
+        /// The JSONL archive written with `--record`.
+        #[clap(long, env)]
+        archive: PathBuf,
+        /// The ID of the auction to replay. Omit to replay all recorded
+        /// auctions.
+        #[clap(long)]
+        auction: Option<i64>,
+        /// The solver engine to replay the auctions with.
+        #[command(subcommand)]
+        solver: Box<Command>,
+    },
 }
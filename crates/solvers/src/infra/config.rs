@@ -1,3 +1,4 @@
+pub mod composite;
 pub mod curve_lp;
 
 use {
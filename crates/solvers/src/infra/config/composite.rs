@@ -0,0 +1,72 @@
+//! Configuration for the composite solver.
+
+use {
+    crate::{domain::solver::composite, infra::config},
+    serde::Deserialize,
+    std::path::{Path, PathBuf},
+    tokio::fs,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct Config {
+    /// The solvers each auction is fanned out to.
+    solvers: Vec<SolverConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct SolverConfig {
+    /// The name identifying the solver in logs.
+    name: String,
+
+    /// The solver engine to run.
+    engine: Engine,
+
+    /// Path to the solver's configuration file, relative to this file.
+    config: PathBuf,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Engine {
+    Baseline,
+    CurveLp,
+}
+
+/// Load the composite solver configuration, and the configurations of all of
+/// its solvers, from TOML files.
+///
+/// # Panics
+///
+/// This method panics if any config is invalid or on I/O errors.
+pub async fn load(path: &Path) -> composite::Config {
+    let data = fs::read_to_string(path)
+        .await
+        .unwrap_or_else(|e| panic!("I/O error while reading {path:?}: {e:?}"));
+    let config = config::unwrap_or_log(toml::de::from_str::<Config>(&data), &path);
+    assert!(
+        !config.solvers.is_empty(),
+        "invalid configuration: `solvers` must not be empty"
+    );
+
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let mut solvers = Vec::with_capacity(config.solvers.len());
+    for solver in config.solvers {
+        let path = dir.join(&solver.config);
+        let engine = match solver.engine {
+            Engine::Baseline => composite::Engine::Baseline(config::load(&path).await),
+            Engine::CurveLp => composite::Engine::CurveLp(
+                config::curve_lp::load(&path)
+                    .await
+                    .unwrap_or_else(|err| panic!("invalid configuration at {path:?}: {err}")),
+            ),
+        };
+        solvers.push(composite::SubSolver {
+            name: solver.name,
+            engine,
+        });
+    }
+
+    composite::Config { solvers }
+}
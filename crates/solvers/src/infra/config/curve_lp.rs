@@ -1,26 +1,57 @@
 //! Configuration for the Curve LP solver.
 
 use {
-    crate::domain::{eth, solver::curve_lp},
+    crate::{
+        boundary::curve::{registry, router},
+        domain::{
+            curve::{pool, price_api},
+            eth,
+            solver::curve_lp,
+        },
+        infra::contracts,
+    },
+    chain::Chain,
     reqwest::Url,
     serde::Deserialize,
     shared::price_estimation::gas::SETTLEMENT_OVERHEAD,
-    std::path::Path,
+    std::{
+        collections::HashMap,
+        fmt,
+        path::{Path, PathBuf},
+    },
     tokio::fs,
 };
 
 #[derive(Deserialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 struct Config {
-    /// Chain ID (1 for mainnet).
+    /// Chain ID (1 for mainnet). Determines the defaults of `router`, `weth`
+    /// and `price-api-chain`.
     chain_id: u64,
 
-    /// Whitelisted LP tokens that this solver handles.
+    /// Curve Router contract address. Defaults to the Curve Router NG
+    /// deployment of the configured chain.
+    #[serde(default)]
+    router: Option<eth::Address>,
+
+    /// Wrapped native token address. Defaults to the canonical WETH contract
+    /// of the configured chain.
+    #[serde(default)]
+    weth: Option<eth::Address>,
+
+    /// The name of the chain in the Curve Price API (e.g. "ethereum",
+    /// "xdai"). Defaults to the name of the configured chain.
+    #[serde(default)]
+    price_api_chain: Option<String>,
+
+    /// Whitelisted LP tokens that this solver handles. Overrides the LP
+    /// tokens discovered by the `registry`.
     /// Omit to accept any sell token.
     #[serde(default)]
     lp_tokens: Option<Vec<eth::Address>>,
 
-    /// Allowed buy tokens (crvUSD + pool underlyings).
+    /// Allowed buy tokens (crvUSD + pool underlyings). Overrides the pool
+    /// coins discovered by the `registry`.
     /// Omit to accept any buy token.
     #[serde(default)]
     allowed_buy_tokens: Option<Vec<eth::Address>>,
@@ -28,16 +59,43 @@ struct Config {
     /// Curve Router API URL.
     curve_api_url: Url,
 
+    /// Mirrors of the Curve Router API, in order of preference. Requests fail
+    /// over to them while `curve-api-url` is unavailable.
+    #[serde(default)]
+    curve_api_mirrors: Vec<Url>,
+
     /// Curve Price API URL.
     curve_price_api_url: Url,
 
+    /// Mirrors of the Curve Price API, in order of preference. Requests fail
+    /// over to them while `curve-price-api-url` is unavailable.
+    #[serde(default)]
+    curve_price_api_mirrors: Vec<Url>,
+
     /// Node URL for on-chain verification.
     node_url: Url,
 
-    /// Slippage buffer in basis points (e.g., 100 = 1%).
+    /// Base slippage tolerance of routes through CryptoSwap pools in basis
+    /// points (e.g., 100 = 1%).
     #[serde(default = "default_slippage_bps")]
     slippage_bps: u32,
 
+    /// Base slippage tolerance of routes only trading through StableSwap
+    /// pools in basis points. Defaults to `slippage-bps`.
+    #[serde(default)]
+    stable_slippage_bps: Option<u32>,
+
+    /// Upper bound on the slippage tolerance after widening it for the share
+    /// of the LP token supply traded and the recent volatility of the pair,
+    /// in basis points.
+    #[serde(default = "default_max_slippage_bps")]
+    max_slippage_bps: u32,
+
+    /// Fixed slippage tolerances in basis points of trades buying or selling
+    /// these tokens, replacing the dynamic tolerance.
+    #[serde(default)]
+    slippage_overrides: HashMap<eth::Address, u32>,
+
     /// Maximum deviation between API quote and on-chain get_dy (basis points).
     #[serde(default = "default_max_quote_deviation_bps")]
     max_quote_deviation_bps: u32,
@@ -46,18 +104,108 @@ struct Config {
     #[serde(default = "default_gas_offset")]
     solution_gas_offset: i64,
 
+    /// The maximum number of fill amounts tried for partially fillable
+    /// orders that cannot be filled completely, including the full amount.
+    /// Set to 1 to disable partial fills.
+    #[serde(default = "default_max_partial_attempts")]
+    max_partial_attempts: usize,
+
     /// Settlement contract address.
     settlement_contract: eth::Address,
+
+    /// Curve pools whose LP tokens are priced locally from on-chain state,
+    /// using the Curve Router API only as a cross-check.
+    #[serde(default)]
+    pools: Vec<PoolConfig>,
+
+    /// Discover pools, their LP tokens and coins from the on-chain Curve
+    /// factories and MetaRegistry. Omit to disable discovery.
+    #[serde(default)]
+    registry: Option<RegistryConfig>,
+
+    /// The amount of each token that may be paid out of the settlement
+    /// contract's buffers per auction to fill orders directly, without
+    /// trading on Curve. Tokens without a budget are only used to internalize
+    /// interactions.
+    #[serde(default)]
+    buffer_budgets: HashMap<eth::Address, eth::U256>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct RegistryConfig {
+    /// File the discovered pools are persisted to. Omit to re-index on every
+    /// start.
+    #[serde(default)]
+    path: Option<PathBuf>,
+
+    /// Import the pools listed in the MetaRegistry, which includes pools that
+    /// predate the indexed factories.
+    #[serde(default = "default_bootstrap")]
+    bootstrap: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct PoolConfig {
+    /// Pool contract address.
+    address: eth::Address,
+
+    /// The pool flavour, which determines the invariant used for pricing.
+    kind: PoolKind,
+
+    /// LP token address. Defaults to the pool address, which is the case for
+    /// all NG pools.
+    #[serde(default)]
+    lp_token: Option<eth::Address>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+enum PoolKind {
+    #[serde(rename = "stableswap")]
+    StableSwap,
+    #[serde(rename = "stableswap-ng")]
+    StableSwapNg,
+    #[serde(rename = "twocrypto")]
+    TwoCrypto,
+    #[serde(rename = "twocrypto-ng")]
+    TwoCryptoNg,
+    #[serde(rename = "tricrypto-ng")]
+    TricryptoNg,
+}
+
+impl From<PoolKind> for pool::Kind {
+    fn from(kind: PoolKind) -> Self {
+        match kind {
+            PoolKind::StableSwap => Self::StableSwap,
+            PoolKind::StableSwapNg => Self::StableSwapNg,
+            PoolKind::TwoCrypto => Self::TwoCrypto,
+            PoolKind::TwoCryptoNg => Self::TwoCryptoNg,
+            PoolKind::TricryptoNg => Self::TricryptoNg,
+        }
+    }
+}
+
+fn default_bootstrap() -> bool {
+    true
 }
 
 fn default_slippage_bps() -> u32 {
     100 // 1%
 }
 
+fn default_max_slippage_bps() -> u32 {
+    500 // 5%
+}
+
 fn default_max_quote_deviation_bps() -> u32 {
     50 // 0.5%
 }
 
+fn default_max_partial_attempts() -> usize {
+    5
+}
+
 fn default_gas_offset() -> i64 {
     SETTLEMENT_OVERHEAD.try_into().unwrap()
 }
@@ -66,8 +214,9 @@ fn default_gas_offset() -> i64 {
 ///
 /// # Panics
 ///
-/// This method panics if the config is invalid or on I/O errors.
-pub async fn load(path: &Path) -> curve_lp::Config {
+/// This method panics on I/O errors and if the file can't be parsed. Settings
+/// that are missing and can't be defaulted are returned as an error.
+pub async fn load(path: &Path) -> Result<curve_lp::Config, Error> {
     let data = fs::read_to_string(path)
         .await
         .unwrap_or_else(|e| panic!("I/O error while reading {path:?}: {e:?}"));
@@ -83,16 +232,145 @@ pub async fn load(path: &Path) -> curve_lp::Config {
         }
     });
 
-    curve_lp::Config {
-        chain_id: config.chain_id,
+    // The chain is only needed for defaults, so chains this repository doesn't
+    // know are fine as long as everything is configured explicitly.
+    let chain_id = config.chain_id;
+    let chain = || Chain::try_from(chain_id).map_err(|_| Error::UnknownChain(chain_id));
+    let router = match config.router {
+        Some(router) => router,
+        None => {
+            let chain = chain()?;
+            router::deployment(chain).ok_or(Error::NoRouter(chain.name()))?
+        }
+    };
+    let weth = match config.weth {
+        Some(weth) => eth::WethAddress(weth),
+        None => contracts::Contracts::for_chain(chain()?).weth,
+    };
+    let price_api_chain = match config.price_api_chain {
+        Some(price_api_chain) => price_api_chain,
+        None => {
+            let chain = chain()?;
+            price_api::chain_name(chain)
+                .ok_or(Error::NoPriceApiChain(chain.name()))?
+                .to_string()
+        }
+    };
+
+    Ok(curve_lp::Config {
+        chain_id,
+        router,
+        weth,
+        price_api_chain,
         lp_tokens: config.lp_tokens,
         allowed_buy_tokens: config.allowed_buy_tokens,
         curve_api_url: config.curve_api_url,
+        curve_api_mirrors: config.curve_api_mirrors,
         curve_price_api_url: config.curve_price_api_url,
+        curve_price_api_mirrors: config.curve_price_api_mirrors,
+        registry: config.registry.map(|registry| registry::Config {
+            chain_id,
+            path: registry.path,
+            bootstrap: registry.bootstrap,
+        }),
         node_url: config.node_url,
         slippage_bps: config.slippage_bps,
+        stable_slippage_bps: config.stable_slippage_bps.unwrap_or(config.slippage_bps),
+        max_slippage_bps: config.max_slippage_bps,
+        slippage_overrides: config
+            .slippage_overrides
+            .into_iter()
+            .map(|(token, bps)| (eth::TokenAddress(token), bps))
+            .collect(),
         max_quote_deviation_bps: config.max_quote_deviation_bps,
         solution_gas_offset: config.solution_gas_offset.into(),
+        max_partial_attempts: config.max_partial_attempts,
         settlement_contract: config.settlement_contract,
+        pools: config
+            .pools
+            .into_iter()
+            .map(|pool| pool::Metadata {
+                address: pool.address,
+                lp_token: eth::TokenAddress(pool.lp_token.unwrap_or(pool.address)),
+                kind: pool.kind.into(),
+            })
+            .collect(),
+        buffer_budgets: config
+            .buffer_budgets
+            .into_iter()
+            .map(|(token, budget)| (eth::TokenAddress(token), budget))
+            .collect(),
+        offline: false,
+    })
+}
+
+/// A configuration setting that is missing and has no default.
+#[derive(Debug)]
+pub enum Error {
+    /// The chain is unknown, so nothing can be defaulted.
+    UnknownChain(u64),
+    /// There is no known Curve Router on the chain.
+    NoRouter(&'static str),
+    /// The chain is not known to the Curve Price API.
+    NoPriceApiChain(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnknownChain(chain_id) => write!(
+                f,
+                "unknown chain ID {chain_id}, `router`, `weth` and `price-api-chain` must be set"
+            ),
+            Error::NoRouter(chain) => {
+                write!(f, "no known Curve Router on {chain}, `router` must be set")
+            }
+            Error::NoPriceApiChain(chain) => write!(
+                f,
+                "{chain} is not known to the Curve Price API, `price-api-chain` must be set"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::io::Write};
+
+    async fn load_toml(toml: &str) -> Result<curve_lp::Config, Error> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+                curve-api-url = "http://localhost:1/router"
+                curve-price-api-url = "http://localhost:1/prices/"
+                node-url = "http://localhost:1/node"
+                settlement-contract = "0x9008D19f58AAbD9eD0D60971565AA8510560ab41"
+                {toml}
+            "#
+        )
+        .unwrap();
+        load(file.path()).await
+    }
+
+    #[tokio::test]
+    async fn unknown_chains_need_explicit_settings() {
+        let err = load_toml("chain-id = 424242").await.unwrap_err();
+        assert!(matches!(err, Error::UnknownChain(424242)));
+
+        let config = load_toml(
+            r#"
+                chain-id = 424242
+                router = "0x0000000000000000000000000000000000000001"
+                weth = "0x0000000000000000000000000000000000000002"
+                price-api-chain = "somechain"
+            "#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(config.chain_id, 424242);
+        assert_eq!(config.price_api_chain, "somechain");
     }
 }
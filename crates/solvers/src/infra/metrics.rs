@@ -1,4 +1,4 @@
-use crate::domain::{auction, solution};
+use crate::domain::{auction, eth, solution};
 
 /// Metrics for the solver engine.
 #[derive(Debug, Clone, prometheus_metric_storage::MetricStorage)]
@@ -18,6 +18,38 @@ struct Metrics {
 
     /// The number of solutions that were found.
     solutions: prometheus::IntCounter,
+
+    /// The rank of the candidate route that yielded the most output when
+    /// verified on-chain, among the routes of its source: `local` for routes
+    /// priced from pool state, `api` for routes in the order proposed by the
+    /// router.
+    #[metric(labels("source", "rank"))]
+    winning_route_rank: prometheus::IntCounterVec,
+
+    /// Orders that were not attempted, by rejection reason.
+    #[metric(labels("reason"))]
+    order_rejections: prometheus::IntCounterVec,
+
+    /// The latency of upstream API requests in seconds.
+    #[metric(labels("api"), buckets(0.05, 0.1, 0.25, 0.5, 0.75, 1, 1.5, 2, 3, 5))]
+    api_request_seconds: prometheus::HistogramVec,
+
+    /// The deviation between the API quote of a route and its on-chain
+    /// `get_dy` output in basis points.
+    #[metric(buckets(0, 1, 5, 10, 25, 50, 100, 200, 500, 1000))]
+    quote_deviation_bps: prometheus::Histogram,
+
+    /// Native price probes, by outcome.
+    #[metric(labels("outcome"))]
+    native_price_probes: prometheus::IntCounterVec,
+
+    /// Solutions per LP token, for auctions and quotes.
+    #[metric(labels("token", "kind"))]
+    lp_token_solutions: prometheus::IntCounterVec,
+
+    /// Router API route lookups, by whether cached routes were reused.
+    #[metric(labels("result"))]
+    route_cache_lookups: prometheus::IntCounterVec,
 }
 
 /// Setup the metrics registry.
@@ -42,6 +74,54 @@ pub fn solved(deadline: &auction::Deadline, solutions: &[solution::Solution]) {
     get().solutions.inc_by(solutions.len() as u64);
 }
 
+pub fn route_selected(source: &str, rank: usize) {
+    get()
+        .winning_route_rank
+        .with_label_values(&[source, &rank.to_string()])
+        .inc();
+}
+
+pub fn solve_error(reason: &str) {
+    get().solve_errors.with_label_values(&[reason]).inc();
+}
+
+pub fn order_rejected(reason: &str) {
+    get().order_rejections.with_label_values(&[reason]).inc();
+}
+
+pub fn api_request(api: &str, elapsed: std::time::Duration) {
+    get()
+        .api_request_seconds
+        .with_label_values(&[api])
+        .observe(elapsed.as_secs_f64());
+}
+
+pub fn quote_deviation(deviation_bps: u32) {
+    get().quote_deviation_bps.observe(f64::from(deviation_bps));
+}
+
+pub fn native_price_probe(outcome: &str) {
+    get()
+        .native_price_probes
+        .with_label_values(&[outcome])
+        .inc();
+}
+
+pub fn lp_token_solution(token: &eth::TokenAddress, is_quote: bool) {
+    let kind = if is_quote { "quote" } else { "auction" };
+    get()
+        .lp_token_solutions
+        .with_label_values(&[&format!("{:?}", token.0), kind])
+        .inc();
+}
+
+pub fn route_cache(result: &str) {
+    get()
+        .route_cache_lookups
+        .with_label_values(&[result])
+        .inc();
+}
+
 /// Get the metrics instance.
 fn get() -> &'static Metrics {
     Metrics::instance(observe::metrics::get_storage_registry())
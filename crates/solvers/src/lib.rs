@@ -6,6 +6,7 @@ mod api;
 mod boundary;
 mod domain;
 mod infra;
+mod replay;
 mod run;
 #[cfg(test)]
 mod tests;
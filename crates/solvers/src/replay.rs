@@ -0,0 +1,73 @@
+//! Offline replay of auctions recorded with `--record`.
+//!
+//! Every recorded auction is solved again against the upstream responses
+//! recorded with it, and the new solutions are compared to the recorded ones.
+//! Requests that were not recorded fail, as if the upstream was unavailable.
+//! Solvers are built without any background work reaching out to the network,
+//! so replays run offline.
+
+use {
+    crate::{
+        domain::solver::Solver,
+        infra::archive::{Archive, Session},
+    },
+    shared::json,
+    std::path::Path,
+};
+
+/// How many replayed auctions had their solutions change.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Summary {
+    pub replayed: usize,
+    pub changed: usize,
+}
+
+/// Replays the auctions of the archive at `path`, or only the one with the
+/// specified ID, and prints how their solutions changed.
+pub async fn run(path: &Path, auction: Option<i64>, solver: Solver) -> Summary {
+    let records = Archive::load(path)
+        .unwrap_or_else(|e| panic!("I/O error while reading archive {path:?}: {e:?}"));
+
+    let (mut replayed, mut changed) = (0, 0);
+    for record in records
+        .into_iter()
+        .filter(|record| auction.is_none_or(|id| record.auction.id == Some(id)))
+    {
+        let id = record
+            .auction
+            .id
+            .map_or_else(|| "quote".to_owned(), |id| id.to_string());
+
+        // Give the solver the time budget it had when the auction was
+        // recorded.
+        let mut auction = record.auction;
+        auction.deadline = chrono::Utc::now() + (auction.deadline - record.received_at);
+
+        let session = Session::replay(record.exchanges);
+        let solutions = match crate::api::solve_auction(&solver, auction, Some(session)).await {
+            Ok(solutions) => solutions,
+            Err(err) => {
+                println!("auction {id}: invalid auction: {err}");
+                continue;
+            }
+        };
+
+        let differences = json::diff(
+            &serde_json::to_value(&record.solutions).unwrap_or_default(),
+            &serde_json::to_value(&solutions).unwrap_or_default(),
+        );
+        replayed += 1;
+        if differences.is_empty() {
+            println!("auction {id}: solutions unchanged");
+        } else {
+            changed += 1;
+            println!("auction {id}: solutions changed");
+            for difference in differences {
+                println!("  {difference}");
+            }
+        }
+    }
+
+    println!("replayed {replayed} auctions, {changed} with changed solutions");
+    Summary { replayed, changed }
+}
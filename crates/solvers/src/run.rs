@@ -2,8 +2,8 @@
 use tokio::signal::unix::{self, SignalKind};
 use {
     crate::{
-        domain::solver,
-        infra::{cli, config},
+        domain::solver::{self, composite},
+        infra::{archive::Archive, cli, config},
     },
     clap::Parser,
     std::net::SocketAddr,
@@ -39,24 +39,57 @@ async fn run_with(args: cli::Args, bind: Option<oneshot::Sender<SocketAddr>>) {
 
     tracing::info!(%commit_hash, "running solver engine with {args:#?}");
 
-    let solver = match args.command {
+    if let cli::Command::Replay {
+        archive,
+        auction,
+        solver,
+    } = args.command
+    {
+        crate::replay::run(&archive, auction, self::solver(*solver, true).await).await;
+        return;
+    }
+
+    let archive = args.record.map(|path| {
+        Archive::create(&path)
+            .unwrap_or_else(|e| panic!("I/O error while opening archive {path:?}: {e:?}"))
+    });
+
+    crate::api::Api {
+        addr: args.addr,
+        solver: self::solver(args.command, false).await,
+        archive,
+    }
+    .serve(bind, shutdown_signal())
+    .await
+    .unwrap();
+}
+
+/// Creates the solver engine of `command`. Replays run the solver `offline`,
+/// without any background work reaching out to the network.
+async fn solver(command: cli::Command, offline: bool) -> solver::Solver {
+    match command {
         cli::Command::Baseline { config } => {
             let config = config::load(&config).await;
             solver::Solver::new(config).await
         }
         cli::Command::CurveLp { config } => {
-            let config = config::curve_lp::load(&config).await;
+            let mut config = config::curve_lp::load(&config)
+                .await
+                .unwrap_or_else(|err| panic!("invalid configuration: {err}"));
+            config.offline = offline;
             solver::Solver::new_curve_lp(config).await
         }
-    };
-
-    crate::api::Api {
-        addr: args.addr,
-        solver,
+        cli::Command::Composite { config } => {
+            let mut config = config::composite::load(&config).await;
+            for solver in &mut config.solvers {
+                if let composite::Engine::CurveLp(config) = &mut solver.engine {
+                    config.offline = offline;
+                }
+            }
+            solver::Solver::new_composite(config).await
+        }
+        cli::Command::Replay { .. } => panic!("cannot replay with a replay"),
     }
-    .serve(bind, shutdown_signal())
-    .await
-    .unwrap();
 }
 
 #[cfg(unix)]
@@ -8,7 +8,8 @@
 //! ```
 //!
 //! Make sure to set up a valid config file at `configs/local/curve-lp.local.toml`
-//! before running these tests.
+//! before running these tests. The `curve_lp_hermetic` cases cover the same
+//! flows against scripted Curve APIs and node responses instead.
 
 use {crate::tests, serde_json::json, std::time::Duration};
 
@@ -0,0 +1,345 @@
+//! Curve LP solver end-to-end tests against the scripted Curve APIs and node
+//! of [`tests::curve`], which run without network access.
+
+use {
+    crate::tests::{
+        self,
+        curve::{self, Curve},
+    },
+    alloy::primitives::{Address, U256, address},
+    serde_json::{Value, json},
+    std::time::{Duration, Instant},
+};
+
+const LP: Address = address!("f5f5B97624542D72A9E06f04804Bf81baA15e2B4");
+const CRVUSD: Address = address!("f939E0A03FB07F59A73314E73794Be0E57ac1b4E");
+const WETH: Address = address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2");
+const POOL: Address = address!("1111111111111111111111111111111111111111");
+
+/// Native price probes sell this many tokens, 2^144.
+const NATIVE_PRICE_SELL_SENTINEL: &str = "22300745198530623141535718272648361505980416";
+
+fn ether(amount: u64) -> U256 {
+    U256::from(amount) * U256::from(10u64.pow(18))
+}
+
+async fn solver_engine(curve: &Curve) -> tests::SolverEngine {
+    tests::SolverEngine::new("curvelp", tests::Config::String(curve.config(""))).await
+}
+
+/// An auction with a single order. `id` is `null` for quotes.
+fn auction(
+    id: Value,
+    kind: &str,
+    sell: (Address, &str),
+    buy: (Address, &str),
+    deadline: &str,
+) -> Value {
+    json!({
+        "id": id,
+        "tokens": {
+            (sell.0.to_string()): {
+                "decimals": 18,
+                "symbol": "SELL",
+                "availableBalance": "0",
+                "trusted": true
+            },
+            (buy.0.to_string()): {
+                "decimals": 18,
+                "symbol": "BUY",
+                "availableBalance": "0",
+                "trusted": true
+            }
+        },
+        "orders": [
+            {
+                "uid": "0x0101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101010101",
+                "sellToken": sell.0,
+                "buyToken": buy.0,
+                "sellAmount": sell.1,
+                "fullSellAmount": sell.1,
+                "buyAmount": buy.1,
+                "fullBuyAmount": buy.1,
+                "feePolicies": [],
+                "validTo": 0,
+                "kind": kind,
+                "owner": "0x5b1e2c2762667331bc91648052f646d1b0d35984",
+                "partiallyFillable": false,
+                "preInteractions": [],
+                "postInteractions": [],
+                "sellTokenSource": "erc20",
+                "buyTokenDestination": "erc20",
+                "class": "market",
+                "appData": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "signingScheme": "presign",
+                "signature": "0x"
+            }
+        ],
+        "liquidity": [],
+        "effectiveGasPrice": "15000000000",
+        "deadline": deadline,
+        "surplusCapturingJitOrderOwners": []
+    })
+}
+
+/// Returns the only interaction of the only solution.
+fn interaction(solutions: &Value) -> &Value {
+    let solutions = solutions["solutions"].as_array().unwrap();
+    assert_eq!(solutions.len(), 1);
+    let interactions = solutions[0]["interactions"].as_array().unwrap();
+    assert_eq!(interactions.len(), 1);
+    &interactions[0]
+}
+
+fn target(interaction: &Value) -> Address {
+    interaction["target"].as_str().unwrap().parse().unwrap()
+}
+
+#[tokio::test]
+async fn quotes_from_api_without_onchain_verification() {
+    let curve = Curve::new().await;
+    curve.route(curve::Route::exchange(LP, CRVUSD, POOL, ether(2_000)));
+    // Without a reference price the fee is priced with the Price API.
+    curve.price(LP, 2_000.);
+    curve.price(WETH, 4_000.);
+    let engine = solver_engine(&curve).await;
+
+    let solutions = engine
+        .solve(auction(
+            Value::Null,
+            "sell",
+            (LP, "1000000000000000000"),
+            (CRVUSD, "1"),
+            "2099-01-01T00:00:00.000Z",
+        ))
+        .await;
+
+    let interaction = interaction(&solutions);
+    assert_eq!(target(interaction), curve::router());
+    // The API quote minus the default 1% slippage.
+    assert_eq!(
+        interaction["outputs"][0]["amount"],
+        ether(1_980).to_string()
+    );
+    assert_eq!(curve.get_dy_calls(), 0);
+}
+
+#[tokio::test]
+async fn native_price_probe() {
+    let curve = Curve::new().await;
+    curve.route(curve::Route::exchange(WETH, LP, POOL, ether(2)));
+    curve.route(curve::Route::exchange(
+        LP,
+        WETH,
+        POOL,
+        ether(1) / U256::from(2),
+    ));
+    let engine = solver_engine(&curve).await;
+
+    let solutions = engine
+        .solve(auction(
+            Value::Null,
+            "buy",
+            (LP, NATIVE_PRICE_SELL_SENTINEL),
+            (WETH, "1000000000000000000"),
+            "2099-01-01T00:00:00.000Z",
+        ))
+        .await;
+
+    // 1 WETH buys 2 LP tokens, which are padded by 5% to sell for the
+    // requested 1 WETH.
+    let interaction = interaction(&solutions);
+    assert_eq!(interaction["inputs"][0]["amount"], "2100000000000000000");
+    assert_eq!(curve.get_dy_calls(), 0);
+}
+
+#[tokio::test]
+async fn verifies_routes_onchain() {
+    let curve = Curve::new().await;
+    curve.route(curve::Route::exchange(LP, CRVUSD, POOL, ether(2_000)));
+    curve.price(LP, 2_000.);
+    curve.price(WETH, 4_000.);
+    let engine = solver_engine(&curve).await;
+
+    let solutions = engine
+        .solve(auction(
+            json!("1"),
+            "sell",
+            (LP, "1000000000000000000"),
+            (CRVUSD, "1"),
+            "2099-01-01T00:00:00.000Z",
+        ))
+        .await;
+
+    let interaction = interaction(&solutions);
+    assert_eq!(target(interaction), curve::router());
+    assert_eq!(
+        interaction["outputs"][0]["amount"],
+        ether(1_980).to_string()
+    );
+    assert!(curve.get_dy_calls() > 0);
+}
+
+#[tokio::test]
+async fn rejects_quotes_deviating_from_onchain() {
+    let curve = Curve::new().await;
+    curve.route(curve::Route {
+        onchain_rate: Some(ether(1_900)),
+        ..curve::Route::exchange(LP, CRVUSD, POOL, ether(2_000))
+    });
+    curve.price(LP, 2_000.);
+    curve.price(WETH, 4_000.);
+    let engine = solver_engine(&curve).await;
+
+    let solutions = engine
+        .solve(auction(
+            json!("1"),
+            "sell",
+            (LP, "1000000000000000000"),
+            (CRVUSD, "1"),
+            "2099-01-01T00:00:00.000Z",
+        ))
+        .await;
+
+    assert_eq!(solutions["solutions"], json!([]));
+    assert!(curve.get_dy_calls() > 0);
+}
+
+#[tokio::test]
+async fn gives_up_on_slow_routes_at_the_deadline() {
+    let curve = Curve::new().await;
+    curve.route(curve::Route::exchange(LP, CRVUSD, POOL, ether(2_000)));
+    curve.delay_routes(Duration::from_secs(30));
+    let engine = solver_engine(&curve).await;
+
+    let start = Instant::now();
+    let deadline = chrono::Utc::now() + chrono::Duration::seconds(2);
+    let solutions = engine
+        .solve(auction(
+            json!("1"),
+            "sell",
+            (LP, "1000000000000000000"),
+            (CRVUSD, "1"),
+            &deadline.to_rfc3339(),
+        ))
+        .await;
+
+    assert_eq!(solutions["solutions"], json!([]));
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_eq!(curve.get_dy_calls(), 0);
+}
+
+#[tokio::test]
+async fn reuses_cached_routes_across_auctions() {
+    let curve = Curve::new().await;
+    curve.route(curve::Route::exchange(LP, CRVUSD, POOL, ether(2_000)));
+    curve.price(LP, 2_000.);
+    curve.price(WETH, 4_000.);
+    let engine = solver_engine(&curve).await;
+
+    let solutions = engine
+        .solve(auction(
+            json!("1"),
+            "sell",
+            (LP, "1000000000000000000"),
+            (CRVUSD, "1"),
+            "2099-01-01T00:00:00.000Z",
+        ))
+        .await;
+    assert_eq!(target(interaction(&solutions)), curve::router());
+
+    // The Router API no longer answers in time, but the route of the previous
+    // auction is reused for a sell amount in the same bucket and requoted
+    // on-chain.
+    curve.delay_routes(Duration::from_secs(30));
+    let deadline = chrono::Utc::now() + chrono::Duration::seconds(2);
+    let solutions = engine
+        .solve(auction(
+            json!("2"),
+            "sell",
+            (LP, "1500000000000000000"),
+            (CRVUSD, "1"),
+            &deadline.to_rfc3339(),
+        ))
+        .await;
+
+    let interaction = interaction(&solutions);
+    assert_eq!(target(interaction), curve::router());
+    assert_eq!(
+        interaction["outputs"][0]["amount"],
+        ether(2_970).to_string()
+    );
+}
+
+#[tokio::test]
+async fn replays_recorded_auctions_offline() {
+    let curve = Curve::new().await;
+    curve.route(curve::Route::exchange(LP, CRVUSD, POOL, ether(2_000)));
+    curve.price(LP, 2_000.);
+    curve.price(WETH, 4_000.);
+    let config_file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(config_file.path(), curve.config("")).unwrap();
+    let archive = tempfile::NamedTempFile::new().unwrap();
+    let engine = tests::SolverEngine::recording(
+        "curvelp",
+        tests::Config::File(config_file.path().to_owned()),
+        archive.path(),
+    )
+    .await;
+
+    // The second auction is solved with the route cached for the first one.
+    engine
+        .solve(auction(
+            json!("1"),
+            "sell",
+            (LP, "1000000000000000000"),
+            (CRVUSD, "1"),
+            "2099-01-01T00:00:00.000Z",
+        ))
+        .await;
+    curve.delay_routes(Duration::from_secs(30));
+    let deadline = chrono::Utc::now() + chrono::Duration::seconds(2);
+    engine
+        .solve(auction(
+            json!("2"),
+            "sell",
+            (LP, "1500000000000000000"),
+            (CRVUSD, "1"),
+            &deadline.to_rfc3339(),
+        ))
+        .await;
+    drop(engine);
+
+    // Auctions are archived in the background.
+    let archived = || {
+        std::fs::read_to_string(archive.path())
+            .unwrap()
+            .lines()
+            .count()
+    };
+    let start = Instant::now();
+    while archived() < 2 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "auctions not archived"
+        );
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let requests = curve.requests();
+    let mut config = crate::infra::config::curve_lp::load(config_file.path())
+        .await
+        .unwrap();
+    config.offline = true;
+    let solver = crate::domain::solver::Solver::new_curve_lp(config).await;
+    let summary = crate::replay::run(archive.path(), None, solver).await;
+
+    assert_eq!(
+        summary,
+        crate::replay::Summary {
+            replayed: 2,
+            changed: 0,
+        }
+    );
+    assert_eq!(curve.requests(), requests);
+}
@@ -2,7 +2,9 @@
 
 mod bal_liquidity;
 mod buy_order_rounding;
+mod composite;
 mod curve_lp;
+mod curve_lp_hermetic;
 mod direct_swap;
 mod internalization;
 mod limit_order_quoting;
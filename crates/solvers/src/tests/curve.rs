@@ -0,0 +1,375 @@
+//! Hermetic stand-ins for the services the Curve LP solver depends on.
+//!
+//! A single HTTP server fakes the Curve Router API, the Curve Price API and
+//! the `eth_call`s the solver sends to its node to quote routes with the
+//! Router's `get_dy`, either directly or batched through Multicall3, and to
+//! read the state of pools. All of them answer from routes, prices and pools
+//! scripted by the test.
+
+use {
+    crate::boundary::curve::router::{self, ICurveRouter, IMulticall3},
+    alloy::{
+        primitives::{Address, Bytes, U256},
+        sol,
+        sol_types::SolCall,
+    },
+    serde::Deserialize,
+    serde_json::{Value, json},
+    std::{
+        collections::HashMap,
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+        time::Duration,
+    },
+};
+
+sol! {
+    interface IPool {
+        function coins(uint256 i) external view returns (address);
+        function balances(uint256 i) external view returns (uint256);
+        function fee() external view returns (uint256);
+        function A() external view returns (uint256);
+        function A_precise() external view returns (uint256);
+        function totalSupply() external view returns (uint256);
+    }
+
+    interface IToken {
+        function decimals() external view returns (uint8);
+    }
+}
+
+/// The Router of the fake chain, which is the mainnet deployment.
+pub fn router() -> Address {
+    router::deployment(chain::Chain::Mainnet).unwrap()
+}
+
+/// A scripted single hop route.
+#[derive(Clone, Debug)]
+pub struct Route {
+    pub sell: Address,
+    pub buy: Address,
+    pub pool: Address,
+    /// Router swap parameters: `[i, j, swap_type, pool_type, n_coins]`.
+    pub swap_params: [u64; 5],
+    /// Output quoted by the Router API per 1e18 units of input.
+    pub rate: U256,
+    /// Output quoted by `get_dy` per 1e18 units of input, `None` if `get_dy`
+    /// reverts.
+    pub onchain_rate: Option<U256>,
+}
+
+impl Route {
+    /// A StableSwap `exchange` through `pool` quoting `rate` both from the API
+    /// and on-chain.
+    pub fn exchange(sell: Address, buy: Address, pool: Address, rate: U256) -> Self {
+        Self {
+            sell,
+            buy,
+            pool,
+            swap_params: [0, 1, 1, 1, 2],
+            rate,
+            onchain_rate: Some(rate),
+        }
+    }
+}
+
+/// A scripted plain StableSwap pool that is its own LP token. Its coins have
+/// 18 decimals.
+#[derive(Clone, Debug)]
+pub struct Pool {
+    pub address: Address,
+    pub coins: Vec<Address>,
+    pub balances: Vec<U256>,
+    /// The amplification coefficient as returned by `A()`.
+    pub amp: U256,
+    pub fee: U256,
+    pub total_supply: U256,
+    /// Whether the pool exposes `A_precise()`, which legacy pools don't.
+    pub precise: bool,
+}
+
+#[derive(Default)]
+struct State {
+    routes: HashMap<(Address, Address), Route>,
+    pools: HashMap<Address, Pool>,
+    prices: HashMap<Address, f64>,
+    route_delay: Duration,
+    get_dy_calls: usize,
+    requests: usize,
+}
+
+type Shared = Arc<Mutex<State>>;
+
+/// Fake Curve APIs and node, see the module documentation.
+pub struct Curve {
+    addr: SocketAddr,
+    state: Shared,
+}
+
+impl Curve {
+    /// Starts the fake services without any routes or prices.
+    pub async fn new() -> Self {
+        let state = Shared::default();
+        let app = axum::Router::new()
+            .route("/router", axum::routing::get(routes))
+            .route(
+                "/prices/v1/usd_price/:chain/:token",
+                axum::routing::get(usd_price),
+            )
+            .route("/node", axum::routing::post(node))
+            .with_state(state.clone());
+        let server =
+            axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(app.into_make_service());
+        let addr = server.local_addr();
+        tokio::spawn(async move { server.await.unwrap() });
+        Self { addr, state }
+    }
+
+    /// Scripts the route returned for the route's token pair.
+    pub fn route(&self, route: Route) {
+        let mut state = self.state.lock().unwrap();
+        state.routes.insert((route.sell, route.buy), route);
+    }
+
+    /// Scripts the state of a pool.
+    pub fn pool(&self, pool: Pool) {
+        let mut state = self.state.lock().unwrap();
+        state.pools.insert(pool.address, pool);
+    }
+
+    /// Scripts the USD price of a token.
+    pub fn price(&self, token: Address, usd: f64) {
+        self.state.lock().unwrap().prices.insert(token, usd);
+    }
+
+    /// Delays all Router API responses.
+    pub fn delay_routes(&self, delay: Duration) {
+        self.state.lock().unwrap().route_delay = delay;
+    }
+
+    /// The number of `get_dy` calls quoted on-chain so far.
+    pub fn get_dy_calls(&self) -> usize {
+        self.state.lock().unwrap().get_dy_calls
+    }
+
+    /// The number of requests any of the fake services received so far.
+    pub fn requests(&self) -> usize {
+        self.state.lock().unwrap().requests
+    }
+
+    /// The URL of the fake node.
+    pub fn node_url(&self) -> reqwest::Url {
+        format!("http://{}/node", self.addr).parse().unwrap()
+    }
+
+    /// A Curve LP solver configuration pointing to the fake services,
+    /// followed by `extra` configuration lines.
+    pub fn config(&self, extra: &str) -> String {
+        format!(
+            r#"
+                chain-id = 1
+                curve-api-url = "http://{addr}/router"
+                curve-price-api-url = "http://{addr}/prices/"
+                node-url = "http://{addr}/node"
+                settlement-contract = "0x9008D19f58AAbD9eD0D60971565AA8510560ab41"
+                {extra}
+            "#,
+            addr = self.addr,
+        )
+    }
+}
+
+/// Applies a rate per 1e18 units of input to `amount`.
+fn output(amount: U256, rate: U256) -> U256 {
+    amount.saturating_mul(rate) / U256::from(10u64.pow(18))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RouteQuery {
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+}
+
+async fn routes(
+    axum::extract::State(state): axum::extract::State<Shared>,
+    axum::extract::Query(query): axum::extract::Query<RouteQuery>,
+) -> axum::response::Json<Value> {
+    let (route, delay) = {
+        let mut state = state.lock().unwrap();
+        state.requests += 1;
+        let route = state
+            .routes
+            .get(&(query.token_in, query.token_out))
+            .cloned();
+        (route, state.route_delay)
+    };
+    tokio::time::sleep(delay).await;
+
+    let options = route
+        .map(|route| {
+            json!({
+                "amountOut": [output(query.amount_in, route.rate).to_string()],
+                "route": [{
+                    "tokenIn": [route.sell],
+                    "tokenOut": [route.buy],
+                    "args": {
+                        "poolId": "fake",
+                        "swapAddress": route.pool,
+                        "swapParams": route.swap_params,
+                        "poolAddress": Address::ZERO,
+                    },
+                }],
+            })
+        })
+        .into_iter()
+        .collect::<Vec<_>>();
+    axum::response::Json(json!(options))
+}
+
+async fn usd_price(
+    axum::extract::State(state): axum::extract::State<Shared>,
+    axum::extract::Path((_, token)): axum::extract::Path<(String, Address)>,
+) -> Result<axum::response::Json<Value>, axum::http::StatusCode> {
+    let price = {
+        let mut state = state.lock().unwrap();
+        state.requests += 1;
+        state.prices.get(&token).copied()
+    };
+    let price = price.ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    Ok(axum::response::Json(
+        json!({ "data": { "usd_price": price } }),
+    ))
+}
+
+/// Answers a JSON-RPC request or batch of requests.
+async fn node(
+    axum::extract::State(state): axum::extract::State<Shared>,
+    axum::extract::Json(request): axum::extract::Json<Value>,
+) -> axum::response::Json<Value> {
+    state.lock().unwrap().requests += 1;
+    let response = match request {
+        Value::Array(requests) => Value::Array(
+            requests
+                .iter()
+                .map(|request| rpc(&state, request))
+                .collect(),
+        ),
+        request => rpc(&state, &request),
+    };
+    axum::response::Json(response)
+}
+
+#[derive(Deserialize)]
+struct CallRequest {
+    to: Address,
+    #[serde(default)]
+    input: Option<Bytes>,
+    #[serde(default)]
+    data: Option<Bytes>,
+}
+
+fn rpc(state: &Shared, request: &Value) -> Value {
+    let id = request["id"].clone();
+    let result = match request["method"].as_str() {
+        Some("eth_chainId") => Ok(json!("0x1")),
+        Some("eth_blockNumber") => Ok(json!("0x1")),
+        Some("eth_call") => serde_json::from_value::<CallRequest>(request["params"][0].clone())
+            .map_err(|err| json!({ "code": -32602, "message": err.to_string() }))
+            .and_then(|call| {
+                let input = call.input.or(call.data).unwrap_or_default();
+                eth_call(state, call.to, &input)
+                    .map(|output| json!(Bytes::from(output)))
+                    .ok_or_else(|| json!({ "code": 3, "message": "execution reverted" }))
+            }),
+        method => Err(json!({
+            "code": -32601,
+            "message": format!("method {method:?} not supported by the fake node"),
+        })),
+    };
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+}
+
+/// Executes a call to the Router, to Multicall3 or to a scripted pool or its
+/// coins, returning `None` if it reverts.
+fn eth_call(state: &Shared, to: Address, input: &[u8]) -> Option<Vec<u8>> {
+    if to == router() {
+        return get_dy(state, input);
+    }
+    if scripted(state, to) {
+        return pool_call(state, to, input);
+    }
+    if to != router::MULTICALL_ADDRESS {
+        return None;
+    }
+    let call = IMulticall3::aggregate3Call::abi_decode(input).ok()?;
+    let results = call
+        .calls
+        .iter()
+        .map(|call| {
+            let output = (call.target == router())
+                .then(|| get_dy(state, &call.callData))
+                .flatten();
+            if output.is_none() && !call.allowFailure {
+                return None;
+            }
+            Some(IMulticall3::Call3Result {
+                success: output.is_some(),
+                returnData: output.unwrap_or_default().into(),
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(IMulticall3::aggregate3Call::abi_encode_returns(&results))
+}
+
+/// Quotes a scripted route with the Router's `get_dy`.
+fn get_dy(state: &Shared, input: &[u8]) -> Option<Vec<u8>> {
+    let call = ICurveRouter::get_dyCall::abi_decode(input).ok()?;
+    let mut state = state.lock().unwrap();
+    state.get_dy_calls += 1;
+    let route = state.routes.get(&(call._route[0], call._route[2]))?;
+    if call._route[1] != route.pool {
+        return None;
+    }
+    let output = output(call._amount, route.onchain_rate?);
+    Some(ICurveRouter::get_dyCall::abi_encode_returns(&output))
+}
+
+/// Whether `to` is a scripted pool or one of its coins.
+fn scripted(state: &Shared, to: Address) -> bool {
+    let state = state.lock().unwrap();
+    state
+        .pools
+        .values()
+        .any(|pool| pool.address == to || pool.coins.contains(&to))
+}
+
+/// Answers a call to a scripted pool or to one of its coins.
+fn pool_call(state: &Shared, to: Address, input: &[u8]) -> Option<Vec<u8>> {
+    if IToken::decimalsCall::abi_decode(input).is_ok() {
+        return Some(IToken::decimalsCall::abi_encode_returns(&18));
+    }
+    let state = state.lock().unwrap();
+    let pool = state.pools.get(&to)?;
+    let index = |i: U256| usize::try_from(i).ok();
+    let output = if let Ok(call) = IPool::coinsCall::abi_decode(input) {
+        IPool::coinsCall::abi_encode_returns(pool.coins.get(index(call.i)?)?)
+    } else if let Ok(call) = IPool::balancesCall::abi_decode(input) {
+        IPool::balancesCall::abi_encode_returns(pool.balances.get(index(call.i)?)?)
+    } else if IPool::feeCall::abi_decode(input).is_ok() {
+        IPool::feeCall::abi_encode_returns(&pool.fee)
+    } else if IPool::ACall::abi_decode(input).is_ok() {
+        IPool::ACall::abi_encode_returns(&pool.amp)
+    } else if IPool::A_preciseCall::abi_decode(input).is_ok() && pool.precise {
+        IPool::A_preciseCall::abi_encode_returns(&(pool.amp * U256::from(100)))
+    } else if IPool::totalSupplyCall::abi_decode(input).is_ok() {
+        IPool::totalSupplyCall::abi_encode_returns(&pool.total_supply)
+    } else {
+        return None;
+    };
+    Some(output)
+}
@@ -6,11 +6,15 @@
 
 use {
     reqwest::Url,
-    std::{io::Write, path::PathBuf},
+    std::{
+        io::Write,
+        path::{Path, PathBuf},
+    },
     tokio::{sync::oneshot, task::JoinHandle},
 };
 
 mod cases;
+pub mod curve;
 
 /// A solver engine handle for E2E testing.
 pub struct SolverEngine {
@@ -30,14 +34,25 @@ impl SolverEngine {
     /// Creates a new solver engine handle for the specified command
     /// configuration.
     pub async fn new(command: &str, config: Config) -> Self {
+        Self::with_args(command, config, &[]).await
+    }
+
+    /// Creates a new solver engine handle recording solved auctions to the
+    /// archive at `path`.
+    pub async fn recording(command: &str, config: Config, path: &Path) -> Self {
+        Self::with_args(command, config, &[format!("--record={}", path.display())]).await
+    }
+
+    async fn with_args(command: &str, config: Config, extra: &[String]) -> Self {
         let (bind, bind_receiver) = oneshot::channel();
 
         let mut args = vec![
             "/test/solvers/path".to_owned(),
             "--addr=0.0.0.0:0".to_owned(),
             "--log=solvers=trace".to_owned(),
-            command.to_owned(),
         ];
+        args.extend_from_slice(extra);
+        args.push(command.to_owned());
         let tempfile = match config {
             Config::None => None,
             Config::File(path) => {